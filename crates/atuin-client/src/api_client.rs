@@ -14,9 +14,12 @@ use atuin_common::{
 };
 use atuin_common::{
     api::{
-        AddHistoryRequest, ChangePasswordRequest, CountResponse, DeleteHistoryRequest,
-        ErrorResponse, LoginRequest, LoginResponse, MeResponse, RegisterResponse,
-        SendVerificationResponse, StatusResponse, SyncHistoryResponse, VerificationTokenRequest,
+        AddHistoryRequest, AddTeamMemberRequest, ChangePasswordRequest, CountResponse,
+        CreateTeamRequest, CreateTokenRequest, CreateTokenResponse, DeleteHistoryRequest,
+        ErrorResponse, LoginRequest, LoginResponse, MeResponse, OidcDeviceResponse,
+        OidcTokenRequest, OidcTokenResponse, RegisterResponse, ResetPasswordRequest,
+        SendVerificationResponse, SessionsResponse, StatusResponse, SyncHistoryResponse,
+        TeamMembersResponse, TeamsResponse, TokenScope, TokensResponse, VerificationTokenRequest,
         VerificationTokenResponse,
     },
     record::RecordStatus,
@@ -26,13 +29,20 @@ use semver::Version;
 use time::OffsetDateTime;
 use time::format_description::well_known::Rfc3339;
 
-use crate::{history::History, sync::hash_str, utils::get_host_user};
+use crate::{
+    history::History,
+    sync::hash_str,
+    utils::{get_host_user, get_hostname},
+};
 
 static APP_USER_AGENT: &str = concat!("atuin/", env!("CARGO_PKG_VERSION"),);
 
 pub struct Client<'a> {
     sync_addr: &'a str,
     client: reqwest::Client,
+
+    /// Sync the record store of this team, rather than our own
+    team: Option<String>,
 }
 
 fn make_url(address: &str, path: &str) -> Result<String> {
@@ -59,12 +69,20 @@ pub async fn register(
     username: &str,
     email: &str,
     password: &str,
+    invite: Option<&str>,
 ) -> Result<RegisterResponse> {
     let mut map = HashMap::new();
     map.insert("username", username);
     map.insert("email", email);
     map.insert("password", password);
 
+    if let Some(invite) = invite {
+        map.insert("invite", invite);
+    }
+
+    let device = get_hostname();
+    map.insert("device", &device);
+
     let url = make_url(address, &format!("/user/{username}"))?;
     let resp = reqwest::get(url).await?;
 
@@ -91,7 +109,9 @@ pub async fn register(
     Ok(session)
 }
 
-pub async fn login(address: &str, req: LoginRequest) -> Result<LoginResponse> {
+pub async fn login(address: &str, mut req: LoginRequest) -> Result<LoginResponse> {
+    req.device.get_or_insert_with(get_hostname);
+
     let url = make_url(address, "/login")?;
     let client = reqwest::Client::new();
 
@@ -111,6 +131,68 @@ pub async fn login(address: &str, req: LoginRequest) -> Result<LoginResponse> {
     Ok(session)
 }
 
+/// Start a single sign-on login with the server's identity provider
+pub async fn oidc_device(address: &str) -> Result<OidcDeviceResponse> {
+    let url = make_url(address, "/api/v0/login/oidc/device")?;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(url)
+        .header(USER_AGENT, APP_USER_AGENT)
+        .send()
+        .await?;
+    let resp = handle_resp_error(resp).await?;
+
+    if !ensure_version(&resp)? {
+        bail!("Could not login due to version mismatch");
+    }
+
+    Ok(resp.json().await?)
+}
+
+pub async fn oidc_token(
+    address: &str,
+    device_code: String,
+    device: Option<String>,
+    invite: Option<String>,
+) -> Result<OidcTokenResponse> {
+    let url = make_url(address, "/api/v0/login/oidc/token")?;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(url)
+        .header(USER_AGENT, APP_USER_AGENT)
+        .json(&OidcTokenRequest {
+            device_code,
+            device: Some(device.unwrap_or_else(get_hostname)),
+            invite,
+        })
+        .send()
+        .await?;
+    let resp = handle_resp_error(resp).await?;
+
+    Ok(resp.json().await?)
+}
+
+pub async fn reset_password(address: &str, req: ResetPasswordRequest) -> Result<()> {
+    let url = make_url(address, "/api/v0/account/password/reset")?;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(url)
+        .header(USER_AGENT, APP_USER_AGENT)
+        .json(&req)
+        .send()
+        .await?;
+    let resp = handle_resp_error(resp).await?;
+
+    if !ensure_version(&resp)? {
+        bail!("Could not reset password due to version mismatch");
+    }
+
+    Ok(())
+}
+
 #[cfg(feature = "check-update")]
 pub async fn latest_version() -> Result<Version> {
     use atuin_common::api::IndexResponse;
@@ -170,6 +252,18 @@ async fn handle_resp_error(resp: Response) -> Result<Response> {
         bail!("Rate limited; please wait before doing that again");
     }
 
+    if status == StatusCode::INSUFFICIENT_STORAGE {
+        let reason = resp
+            .json::<ErrorResponse>()
+            .await
+            .map(|e| e.reason.into_owned())
+            .unwrap_or_else(|_| String::from("storage quota exceeded"));
+
+        bail!(
+            "The sync server is not accepting any more records from you: {reason}.\nAsk the host of your sync server to raise your quota"
+        );
+    }
+
     if !status.is_success() {
         if let Ok(error) = resp.json::<ErrorResponse>().await {
             let reason = error.reason;
@@ -212,9 +306,30 @@ impl<'a> Client<'a> {
                 .connect_timeout(Duration::new(connect_timeout, 0))
                 .timeout(Duration::new(timeout, 0))
                 .build()?,
+            team: None,
         })
     }
 
+    /// Point the record methods at a team's store
+    pub fn with_team(mut self, team: &str) -> Self {
+        self.team = Some(team.to_string());
+        self
+    }
+
+    pub fn team(&self) -> Option<&str> {
+        self.team.as_deref()
+    }
+
+    fn record_url(&self, path: &str) -> Result<String> {
+        match &self.team {
+            Some(team) => make_url(
+                self.sync_addr,
+                &format!("/api/v0/teams/{team}/record{path}"),
+            ),
+            None => make_url(self.sync_addr, &format!("/api/v0/record{path}")),
+        }
+    }
+
     pub async fn count(&self) -> Result<i64> {
         let url = make_url(self.sync_addr, "/sync/count")?;
         let url = Url::parse(url.as_str())?;
@@ -263,6 +378,126 @@ impl<'a> Client<'a> {
         Ok(status)
     }
 
+    pub async fn sessions(&self) -> Result<SessionsResponse> {
+        let url = make_url(self.sync_addr, "/api/v0/account/sessions")?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.get(url).send().await?;
+        let resp = handle_resp_error(resp).await?;
+
+        Ok(resp.json().await?)
+    }
+
+    pub async fn revoke_session(&self, id: i64) -> Result<()> {
+        let url = make_url(self.sync_addr, &format!("/api/v0/account/sessions/{id}"))?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.delete(url).send().await?;
+        handle_resp_error(resp).await?;
+
+        Ok(())
+    }
+
+    pub async fn create_token(
+        &self,
+        name: &str,
+        scopes: Vec<TokenScope>,
+    ) -> Result<CreateTokenResponse> {
+        let url = make_url(self.sync_addr, "/api/v0/account/tokens")?;
+        let url = Url::parse(url.as_str())?;
+
+        let req = CreateTokenRequest {
+            name: name.to_string(),
+            scopes,
+        };
+
+        let resp = self.client.post(url).json(&req).send().await?;
+        let resp = handle_resp_error(resp).await?;
+
+        Ok(resp.json().await?)
+    }
+
+    pub async fn tokens(&self) -> Result<TokensResponse> {
+        let url = make_url(self.sync_addr, "/api/v0/account/tokens")?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.get(url).send().await?;
+        let resp = handle_resp_error(resp).await?;
+
+        Ok(resp.json().await?)
+    }
+
+    pub async fn revoke_token(&self, id: i64) -> Result<()> {
+        let url = make_url(self.sync_addr, &format!("/api/v0/account/tokens/{id}"))?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.delete(url).send().await?;
+        handle_resp_error(resp).await?;
+
+        Ok(())
+    }
+
+    pub async fn create_team(&self, name: &str) -> Result<()> {
+        let url = make_url(self.sync_addr, "/api/v0/teams")?;
+        let url = Url::parse(url.as_str())?;
+
+        let req = CreateTeamRequest {
+            name: name.to_string(),
+        };
+
+        let resp = self.client.post(url).json(&req).send().await?;
+        handle_resp_error(resp).await?;
+
+        Ok(())
+    }
+
+    pub async fn teams(&self) -> Result<TeamsResponse> {
+        let url = make_url(self.sync_addr, "/api/v0/teams")?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.get(url).send().await?;
+        let resp = handle_resp_error(resp).await?;
+
+        Ok(resp.json().await?)
+    }
+
+    pub async fn team_members(&self, team: &str) -> Result<TeamMembersResponse> {
+        let url = make_url(self.sync_addr, &format!("/api/v0/teams/{team}/members"))?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.get(url).send().await?;
+        let resp = handle_resp_error(resp).await?;
+
+        Ok(resp.json().await?)
+    }
+
+    pub async fn add_team_member(&self, team: &str, username: &str) -> Result<()> {
+        let url = make_url(self.sync_addr, &format!("/api/v0/teams/{team}/members"))?;
+        let url = Url::parse(url.as_str())?;
+
+        let req = AddTeamMemberRequest {
+            username: username.to_string(),
+        };
+
+        let resp = self.client.post(url).json(&req).send().await?;
+        handle_resp_error(resp).await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_team_member(&self, team: &str, username: &str) -> Result<()> {
+        let url = make_url(
+            self.sync_addr,
+            &format!("/api/v0/teams/{team}/members/{username}"),
+        )?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.delete(url).send().await?;
+        handle_resp_error(resp).await?;
+
+        Ok(())
+    }
+
     pub async fn get_history(
         &self,
         sync_ts: OffsetDateTime,
@@ -328,7 +563,7 @@ impl<'a> Client<'a> {
     }
 
     pub async fn post_records(&self, records: &[Record<EncryptedData>]) -> Result<()> {
-        let url = make_url(self.sync_addr, "/api/v0/record")?;
+        let url = self.record_url("")?;
         let url = Url::parse(url.as_str())?;
 
         debug!("uploading {} records to {url}", records.len());
@@ -353,13 +588,13 @@ impl<'a> Client<'a> {
             start
         );
 
-        let url = make_url(
-            self.sync_addr,
-            &format!(
-                "/api/v0/record/next?host={}&tag={}&count={}&start={}",
-                host.0, tag, count, start
-            ),
-        )?;
+        let url = self.record_url(&format!(
+            "/next?host={}&tag={}&count={}&start={}",
+            host.0,
+            urlencoding::encode(&tag),
+            count,
+            start
+        ))?;
 
         let url = Url::parse(url.as_str())?;
 
@@ -372,7 +607,7 @@ impl<'a> Client<'a> {
     }
 
     pub async fn record_status(&self) -> Result<RecordStatus> {
-        let url = make_url(self.sync_addr, "/api/v0/record")?;
+        let url = self.record_url("")?;
         let url = Url::parse(url.as_str())?;
 
         let resp = self.client.get(url).send().await?;
@@ -1,7 +1,8 @@
 use std::{
     borrow::Cow,
+    collections::HashMap,
     env,
-    path::{Path, PathBuf},
+    path::{MAIN_SEPARATOR, Path, PathBuf},
     str::FromStr,
     time::Duration,
 };
@@ -22,7 +23,7 @@ use sqlx::{
 use time::OffsetDateTime;
 
 use crate::{
-    history::{HistoryId, HistoryStats},
+    history::{CommandStats, CommandUsage, HistoryId, HistoryStats},
     utils::get_host_user,
 };
 
@@ -40,6 +41,43 @@ pub struct Context {
     pub git_root: Option<PathBuf>,
 }
 
+impl Context {
+    /// The git repository we are in, without a trailing separator
+    fn workspace(&self) -> Option<&str> {
+        self.git_root
+            .as_ref()
+            .and_then(|root| root.to_str())
+            .map(|root| root.trim_end_matches(['/', '\\']))
+    }
+}
+
+/// A condition matching commands run in `dir` or anywhere under it. A LIKE prefix match would
+/// also take in directories next to it with longer names, eg /srv/app-old for /srv/app.
+fn in_dir_tree(dir: &str) -> String {
+    let dir = dir.trim_end_matches(['/', '\\']);
+    let prefix = quote(format!("{dir}{MAIN_SEPARATOR}"));
+
+    format!(
+        "(cwd = {} or substr(cwd, 1, length({prefix})) = {prefix})",
+        quote(dir)
+    )
+}
+
+/// Only keep the history the filter mode lets through, in searches and the like
+fn filter_by(sql: &mut SqlBuilder, filter: FilterMode, context: &Context) {
+    let workspace = context.workspace().unwrap_or(&context.cwd);
+
+    match filter {
+        FilterMode::Global => sql,
+        FilterMode::Host => {
+            sql.and_where_eq("lower(hostname)", quote(context.hostname.to_lowercase()))
+        }
+        FilterMode::Session => sql.and_where_eq("session", quote(&context.session)),
+        FilterMode::Directory => sql.and_where_eq("cwd", quote(&context.cwd)),
+        FilterMode::Workspace => sql.and_where(in_dir_tree(workspace)),
+    };
+}
+
 #[derive(Default, Clone)]
 pub struct OptFilters {
     pub exit: Option<i64>,
@@ -121,6 +159,50 @@ pub trait Database: Send + Sync + 'static {
     async fn stats(&self, h: &History) -> Result<HistoryStats>;
 
     async fn get_dups(&self, before: i64, dupkeep: u32) -> Result<Vec<History>>;
+
+    /// Every directory a command was run in, with how many were
+    async fn directories(&self) -> Result<Vec<(String, i64)>>;
+
+    /// How each of these commands has been used, counted against the context we are in
+    async fn usage(
+        &self,
+        commands: &[String],
+        context: &Context,
+    ) -> Result<HashMap<String, CommandUsage>>;
+
+    /// The commands that were run right after this one in the same session, with how often each
+    /// was. Where the command itself was run is counted against the context we are in, and the
+    /// failures are those of the command that followed.
+    async fn following(
+        &self,
+        command: &str,
+        context: &Context,
+    ) -> Result<Vec<(String, CommandUsage)>>;
+
+    /// How each of these commands has been run, of the runs the filter mode lets through
+    async fn command_stats(
+        &self,
+        commands: &[String],
+        filter: FilterMode,
+        context: &Context,
+    ) -> Result<Vec<CommandStats>>;
+
+    /// The latest runs of a command that the filter mode lets through, newest first
+    async fn runs(
+        &self,
+        command: &str,
+        filter: FilterMode,
+        context: &Context,
+        limit: i64,
+    ) -> Result<Vec<History>>;
+
+    /// The last run of each of these commands that the filter mode lets through, if any
+    async fn latest_runs(
+        &self,
+        commands: &[String],
+        filter: FilterMode,
+        context: &Context,
+    ) -> Result<Vec<History>>;
 }
 
 // Intended for use on a developer machine and not a sync server.
@@ -310,11 +392,7 @@ impl Database for Sqlite {
             query.and_where_is_null("deleted_at");
         }
 
-        let git_root = if let Some(git_root) = context.git_root.clone() {
-            git_root.to_str().unwrap_or("/").to_string()
-        } else {
-            context.cwd.clone()
-        };
+        let workspace = context.workspace().unwrap_or(&context.cwd);
 
         for filter in filters {
             match filter {
@@ -322,7 +400,7 @@ impl Database for Sqlite {
                 FilterMode::Host => query.and_where_eq("hostname", quote(&context.hostname)),
                 FilterMode::Session => query.and_where_eq("session", quote(&context.session)),
                 FilterMode::Directory => query.and_where_eq("cwd", quote(&context.cwd)),
-                FilterMode::Workspace => query.and_where_like_left("cwd", &git_root),
+                FilterMode::Workspace => query.and_where(in_dir_tree(workspace)),
             };
         }
 
@@ -431,21 +509,7 @@ impl Database for Sqlite {
             sql.order_desc("timestamp");
         }
 
-        let git_root = if let Some(git_root) = context.git_root.clone() {
-            git_root.to_str().unwrap_or("/").to_string()
-        } else {
-            context.cwd.clone()
-        };
-
-        match filter {
-            FilterMode::Global => &mut sql,
-            FilterMode::Host => {
-                sql.and_where_eq("lower(hostname)", quote(context.hostname.to_lowercase()))
-            }
-            FilterMode::Session => sql.and_where_eq("session", quote(&context.session)),
-            FilterMode::Directory => sql.and_where_eq("cwd", quote(&context.cwd)),
-            FilterMode::Workspace => sql.and_where_like_left("cwd", git_root),
-        };
+        filter_by(&mut sql, filter, context);
 
         let orig_query = query;
 
@@ -670,6 +734,13 @@ impl Database for Sqlite {
             .order_by("timestamp", false)
             .limit(1);
 
+        let mut session = SqlBuilder::select_from("history");
+        session
+            .field("*")
+            .and_where("session = ?1")
+            .and_where_is_null("deleted_at")
+            .order_by("timestamp", false);
+
         let mut total = SqlBuilder::select_from("history");
         total.field("count(1)").and_where("command = ?1");
 
@@ -708,6 +779,7 @@ impl Database for Sqlite {
 
         let prev = prev.sql().expect("issue in stats previous query");
         let next = next.sql().expect("issue in stats next query");
+        let session = session.sql().expect("issue in stats session query");
         let total = total.sql().expect("issue in stats average query");
         let average = average.sql().expect("issue in stats previous query");
         let exits = exits.sql().expect("issue in stats exits query");
@@ -730,6 +802,12 @@ impl Database for Sqlite {
             .fetch_optional(&self.pool)
             .await?;
 
+        let session = sqlx::query(&session)
+            .bind(&h.session)
+            .map(Self::query_history)
+            .fetch_all(&self.pool)
+            .await?;
+
         let total: (i64,) = sqlx::query_as(&total)
             .bind(&h.command)
             .fetch_one(&self.pool)
@@ -768,6 +846,7 @@ impl Database for Sqlite {
             exits,
             day_of_week,
             duration_over_time,
+            session,
         })
     }
 
@@ -790,6 +869,262 @@ impl Database for Sqlite {
 
         Ok(res)
     }
+
+    async fn directories(&self) -> Result<Vec<(String, i64)>> {
+        let res = sqlx::query_as(
+            "select cwd, count(1) from history where deleted_at is null group by cwd order by cwd",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(res)
+    }
+
+    async fn usage(
+        &self,
+        commands: &[String],
+        context: &Context,
+    ) -> Result<HashMap<String, CommandUsage>> {
+        // keep well under sqlite's limit on the number of parameters
+        const CHUNK: usize = 500;
+
+        let git_root = context.workspace();
+
+        let mut usage = HashMap::with_capacity(commands.len());
+
+        for chunk in commands.chunks(CHUNK) {
+            let query = format!(
+                "select command, count(1), sum(cwd = ?1),
+                    ifnull(sum(cwd = ?2 or substr(cwd, 1, length(?3)) = ?3), 0),
+                    sum(exit != 0 and duration != -1)
+                from history
+                where deleted_at is null and command in ({})
+                group by command",
+                (4..4 + chunk.len()).map(|i| format!("?{i}")).join(", ")
+            );
+
+            let mut query = sqlx::query_as::<_, (String, i64, i64, i64, i64)>(&query)
+                .bind(&context.cwd)
+                .bind(git_root)
+                .bind(git_root.map(|root| format!("{root}{MAIN_SEPARATOR}")));
+            for command in chunk {
+                query = query.bind(command);
+            }
+
+            for (command, count, here, workspace, failures) in query.fetch_all(&self.pool).await? {
+                usage.insert(
+                    command,
+                    CommandUsage {
+                        count,
+                        here,
+                        workspace,
+                        failures,
+                    },
+                );
+            }
+        }
+
+        Ok(usage)
+    }
+
+    async fn following(
+        &self,
+        command: &str,
+        context: &Context,
+    ) -> Result<Vec<(String, CommandUsage)>> {
+        let git_root = context.workspace();
+
+        // only the sessions the command was run in need ordering
+        let res = sqlx::query_as::<_, (String, i64, i64, i64, i64)>(
+            "with ordered as (
+                select command, cwd,
+                    lead(command) over session_order as next,
+                    lead(exit) over session_order as next_exit,
+                    lead(duration) over session_order as next_duration
+                from history
+                where deleted_at is null
+                    and session in (select session from history where command = ?1)
+                window session_order as (partition by session order by timestamp)
+            )
+            select next, count(1), sum(cwd = ?2),
+                ifnull(sum(cwd = ?3 or substr(cwd, 1, length(?4)) = ?4), 0),
+                sum(next_exit != 0 and next_duration != -1)
+            from ordered
+            where command = ?1 and next is not null
+            group by next",
+        )
+        .bind(command)
+        .bind(&context.cwd)
+        .bind(git_root)
+        .bind(git_root.map(|root| format!("{root}{MAIN_SEPARATOR}")))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(res
+            .into_iter()
+            .map(|(next, count, here, workspace, failures)| {
+                (
+                    next,
+                    CommandUsage {
+                        count,
+                        here,
+                        workspace,
+                        failures,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    async fn command_stats(
+        &self,
+        commands: &[String],
+        filter: FilterMode,
+        context: &Context,
+    ) -> Result<Vec<CommandStats>> {
+        if commands.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let runs_of = |fields: &[&str]| {
+            let mut sql = SqlBuilder::select_from("history");
+            sql.fields(fields)
+                .and_where_is_null("deleted_at")
+                .and_where_in_quoted("command", commands);
+            filter_by(&mut sql, filter, context);
+            sql
+        };
+
+        let mut sql = runs_of(&[
+            "command",
+            "count(1)",
+            "sum(duration != -1)",
+            "sum(duration != -1 and exit = 0)",
+            "max(timestamp)",
+        ]);
+        sql.group_by("command");
+        let query = sql
+            .sql()
+            .expect("bug in command stats query. please report");
+
+        let mut stats: HashMap<String, CommandStats> =
+            sqlx::query_as::<_, (String, i64, i64, i64, i64)>(&query)
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|(command, count, finished, succeeded, last_run)| {
+                    let stats = CommandStats {
+                        command: command.clone(),
+                        count,
+                        finished,
+                        succeeded,
+                        last_run: OffsetDateTime::from_unix_timestamp_nanos(i128::from(last_run))
+                            .unwrap_or(OffsetDateTime::UNIX_EPOCH),
+                        median_duration: None,
+                        hosts: Vec::new(),
+                    };
+                    (command, stats)
+                })
+                .collect();
+
+        // the median is the middle one of the finished runs, in order of how long they took
+        let mut sql = runs_of(&[
+            "command",
+            "duration",
+            "row_number() over (partition by command order by duration) as n",
+            "count(1) over (partition by command) as finished",
+        ]);
+        sql.and_where_ne("duration", -1);
+        let query = format!(
+            "select command, duration from {} where n = finished / 2 + 1",
+            sql.subquery_as("ranked")
+                .expect("bug in command median query. please report")
+        );
+
+        for (command, duration) in sqlx::query_as::<_, (String, i64)>(&query)
+            .fetch_all(&self.pool)
+            .await?
+        {
+            if let Some(stats) = stats.get_mut(&command) {
+                stats.median_duration = Some(duration);
+            }
+        }
+
+        // hostnames are stored as host:user
+        let mut sql = runs_of(&[
+            "command",
+            "substr(hostname, 1, instr(hostname || ':', ':') - 1) as host",
+            "count(1) as runs",
+        ]);
+        sql.group_by("command").group_by("host").order_desc("runs");
+        let query = sql
+            .sql()
+            .expect("bug in command hosts query. please report");
+
+        for (command, host, runs) in sqlx::query_as::<_, (String, String, i64)>(&query)
+            .fetch_all(&self.pool)
+            .await?
+        {
+            if let Some(stats) = stats.get_mut(&command) {
+                stats.hosts.push((host, runs));
+            }
+        }
+
+        Ok(stats.into_values().collect())
+    }
+
+    async fn runs(
+        &self,
+        command: &str,
+        filter: FilterMode,
+        context: &Context,
+        limit: i64,
+    ) -> Result<Vec<History>> {
+        let mut sql = SqlBuilder::select_from("history");
+        sql.field("*")
+            .and_where_is_null("deleted_at")
+            .and_where_eq("command", quote(command))
+            .order_desc("timestamp")
+            .limit(limit);
+        filter_by(&mut sql, filter, context);
+
+        let query = sql.sql().expect("bug in runs query. please report");
+
+        let res = sqlx::query(&query)
+            .map(Self::query_history)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(res)
+    }
+
+    async fn latest_runs(
+        &self,
+        commands: &[String],
+        filter: FilterMode,
+        context: &Context,
+    ) -> Result<Vec<History>> {
+        if commands.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // with max(), sqlite takes the other columns from the row that has it
+        let mut sql = SqlBuilder::select_from("history");
+        sql.fields(&["*", "max(timestamp)"])
+            .and_where_is_null("deleted_at")
+            .and_where_in_quoted("command", commands)
+            .group_by("command");
+        filter_by(&mut sql, filter, context);
+
+        let query = sql.sql().expect("bug in latest runs query. please report");
+
+        let res = sqlx::query(&query)
+            .map(Self::query_history)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(res)
+    }
 }
 
 trait SqlBuilderExt {
@@ -1141,6 +1476,375 @@ mod test {
             .unwrap();
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stats_session() {
+        let context = Context {
+            hostname: "booop".to_string(),
+            session: "beep boop".to_string(),
+            cwd: "/home/ellie".to_string(),
+            host_id: "test-host".to_string(),
+            git_root: None,
+        };
+
+        let mut db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+        for cmd in ["cd /var/log", "tail -f syslog", "systemctl restart nginx"] {
+            new_history_item(&mut db, cmd).await.unwrap();
+        }
+
+        let history = db.list(&[], &context, None, false, false).await.unwrap();
+        let tail = history
+            .iter()
+            .find(|h| h.command == "tail -f syslog")
+            .unwrap();
+
+        let stats = db.stats(tail).await.unwrap();
+        let session: Vec<&str> = stats.session.iter().map(|h| h.command.as_str()).collect();
+
+        assert_eq!(
+            session,
+            ["cd /var/log", "tail -f syslog", "systemctl restart nginx"]
+        );
+        assert_eq!(stats.previous.unwrap().command, "cd /var/log");
+        assert_eq!(stats.next.unwrap().command, "systemctl restart nginx");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_workspace_filter() {
+        let context = Context {
+            hostname: "booop".to_string(),
+            session: "beep boop".to_string(),
+            cwd: "/srv/app/src".to_string(),
+            host_id: "test-host".to_string(),
+            git_root: Some(PathBuf::from("/srv/app/")),
+        };
+
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+        for (cmd, cwd) in [
+            ("make", "/srv/app"),
+            ("make test", "/srv/app/src"),
+            ("make clean", "/srv/app-old"),
+            ("make install", "/srv/App"),
+        ] {
+            let h: History = History::capture()
+                .timestamp(OffsetDateTime::now_utc())
+                .command(cmd)
+                .cwd(cwd)
+                .build()
+                .into();
+            db.save(&h).await.unwrap();
+        }
+
+        let listed = db
+            .list(&[FilterMode::Workspace], &context, None, false, false)
+            .await
+            .unwrap();
+        let mut commands: Vec<&str> = listed.iter().map(|h| h.command.as_str()).collect();
+        commands.sort_unstable();
+        assert_eq!(commands, ["make", "make test"]);
+
+        let found = db
+            .search(
+                SearchMode::FullText,
+                FilterMode::Workspace,
+                &context,
+                "make",
+                OptFilters::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_usage() {
+        let context = Context {
+            hostname: "booop".to_string(),
+            session: "beep boop".to_string(),
+            cwd: "/home/ellie/src/atuin".to_string(),
+            host_id: "test-host".to_string(),
+            git_root: Some(PathBuf::from("/home/ellie/src/atuin")),
+        };
+
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+        for (cmd, cwd, exit) in [
+            ("cargo test", "/home/ellie/src/atuin", 0),
+            ("cargo test", "/home/ellie/src/atuin/crates", 101),
+            ("cargo test", "/home/ellie/src/blog", 0),
+            // LIKE would ignore the case
+            ("cargo test", "/home/ellie/src/ATUIN/crates", 0),
+            ("ls", "/home/ellie", 0),
+        ] {
+            let mut h: History = History::capture()
+                .timestamp(OffsetDateTime::now_utc())
+                .command(cmd)
+                .cwd(cwd)
+                .build()
+                .into();
+            h.exit = exit;
+            h.duration = 1;
+            db.save(&h).await.unwrap();
+        }
+
+        let usage = db
+            .usage(&["cargo test".to_string(), "vim".to_string()], &context)
+            .await
+            .unwrap();
+
+        assert_eq!(usage.len(), 1);
+        assert_eq!(
+            usage["cargo test"],
+            CommandUsage {
+                count: 4,
+                here: 1,
+                workspace: 2,
+                failures: 1,
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_following() {
+        let context = Context {
+            hostname: "booop".to_string(),
+            session: "beep boop".to_string(),
+            cwd: "/home/ellie/src/atuin".to_string(),
+            host_id: "test-host".to_string(),
+            git_root: None,
+        };
+
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let start = OffsetDateTime::now_utc() - time::Duration::hours(1);
+        for (i, (session, cmd, cwd)) in [
+            ("one", "git add -p", "/home/ellie/src/atuin"),
+            ("two", "git add -p", "/home/ellie/src/blog"),
+            ("one", "git commit -v", "/home/ellie/src/atuin"),
+            ("two", "git commit -v", "/home/ellie/src/blog"),
+            ("one", "git add -p", "/home/ellie/src/atuin"),
+            ("one", "git stash", "/home/ellie/src/atuin"),
+            ("two", "git add -p", "/home/ellie/src/blog"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut h: History = History::capture()
+                .timestamp(start + time::Duration::seconds(i64::try_from(i).unwrap()))
+                .command(cmd)
+                .cwd(cwd)
+                .build()
+                .into();
+            h.session = session.to_string();
+            h.exit = 0;
+            h.duration = 1;
+            db.save(&h).await.unwrap();
+        }
+
+        let mut following = db.following("git add -p", &context).await.unwrap();
+        following.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            following,
+            [
+                (
+                    "git commit -v".to_string(),
+                    CommandUsage {
+                        count: 2,
+                        here: 1,
+                        workspace: 0,
+                        failures: 0,
+                    }
+                ),
+                (
+                    "git stash".to_string(),
+                    CommandUsage {
+                        count: 1,
+                        here: 1,
+                        workspace: 0,
+                        failures: 0,
+                    }
+                ),
+            ]
+        );
+    }
+
+    fn stats_context() -> Context {
+        Context {
+            hostname: "laptop:ellie".to_string(),
+            session: "beep boop".to_string(),
+            cwd: "/srv".to_string(),
+            host_id: "test-host".to_string(),
+            git_root: None,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_runs() {
+        let context = stats_context();
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        for (i, (cmd, cwd)) in [
+            ("cargo test", "/srv"),
+            ("ls", "/srv"),
+            ("cargo test", "/tmp"),
+            ("cargo test", "/srv"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let h: History = History::capture()
+                .timestamp(now - Duration::from_secs(i as u64))
+                .command(cmd)
+                .cwd(cwd)
+                .build()
+                .into();
+            db.save(&h).await.unwrap();
+        }
+
+        let timestamps = |runs: Vec<History>| -> Vec<OffsetDateTime> {
+            runs.into_iter().map(|h| h.timestamp).collect()
+        };
+
+        let runs = db
+            .runs("cargo test", FilterMode::Global, &context, 2)
+            .await
+            .unwrap();
+        assert_eq!(timestamps(runs), [now, now - Duration::from_secs(2)]);
+
+        let runs = db
+            .runs("cargo test", FilterMode::Directory, &context, 10)
+            .await
+            .unwrap();
+        assert_eq!(timestamps(runs), [now, now - Duration::from_secs(3)]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_command_stats() {
+        let context = stats_context();
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        for (i, (cmd, host, cwd, exit, seconds)) in [
+            ("cargo test", "laptop", "/srv", 0, 30),
+            ("ls", "laptop", "/srv", 0, 1),
+            ("cargo test", "server", "/srv", 101, 10),
+            ("cargo test", "laptop", "/srv", 0, 20),
+            ("cargo test", "laptop", "/tmp", 0, 40),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut h: History = History::capture()
+                .timestamp(now - Duration::from_secs(i as u64))
+                .command(cmd)
+                .cwd(cwd)
+                .build()
+                .into();
+            h.hostname = format!("{host}:ellie");
+            h.exit = exit;
+            h.duration = Duration::from_secs(seconds).as_nanos() as i64;
+            db.save(&h).await.unwrap();
+        }
+
+        // a run that hasn't finished counts, but not towards success or the median
+        let mut running: History = History::capture()
+            .timestamp(now - Duration::from_secs(10))
+            .command("cargo test")
+            .cwd("/srv")
+            .build()
+            .into();
+        running.hostname = "laptop:ellie".to_string();
+        running.duration = -1;
+        db.save(&running).await.unwrap();
+
+        let commands = ["cargo test".to_string(), "vim".to_string()];
+        let stats = db
+            .command_stats(&commands, FilterMode::Directory, &context)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stats,
+            [CommandStats {
+                command: "cargo test".to_string(),
+                count: 4,
+                finished: 3,
+                succeeded: 2,
+                last_run: now,
+                median_duration: Some(Duration::from_secs(20).as_nanos() as i64),
+                hosts: vec![("laptop".to_string(), 3), ("server".to_string(), 1)],
+            }]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_latest_runs() {
+        let context = Context {
+            hostname: "booop".to_string(),
+            session: "beep boop".to_string(),
+            cwd: "/srv".to_string(),
+            host_id: "test-host".to_string(),
+            git_root: None,
+        };
+
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        for (i, (cmd, cwd)) in [
+            ("make", "/srv"),
+            ("make", "/tmp"),
+            ("ls", "/tmp"),
+            ("make", "/srv"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let h: History = History::capture()
+                .timestamp(now - Duration::from_secs(i as u64))
+                .command(cmd)
+                .cwd(cwd)
+                .build()
+                .into();
+            db.save(&h).await.unwrap();
+        }
+
+        let commands = ["make".to_string(), "ls".to_string()];
+
+        let mut latest = db
+            .latest_runs(&commands, FilterMode::Global, &context)
+            .await
+            .unwrap();
+        latest.sort_by(|a, b| a.command.cmp(&b.command));
+        assert_eq!(
+            latest
+                .iter()
+                .map(|h| (h.command.as_str(), h.timestamp))
+                .collect::<Vec<_>>(),
+            [("ls", now - Duration::from_secs(2)), ("make", now),]
+        );
+
+        // ls was never run here
+        let latest = db
+            .latest_runs(&commands, FilterMode::Directory, &context)
+            .await
+            .unwrap();
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].cwd, "/srv");
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_search_bench_dupes() {
         let context = Context {
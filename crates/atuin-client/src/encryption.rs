@@ -8,7 +8,7 @@
 // clients must share the secret in order to be able to sync, as it is needed
 // to decrypt
 
-use std::{io::prelude::*, path::PathBuf};
+use std::{collections::HashMap, io::prelude::*, path::PathBuf};
 
 use base64::prelude::{BASE64_STANDARD, Engine};
 pub use crypto_secretbox::Key;
@@ -22,7 +22,7 @@ use rmp::{Marker, decode::Bytes};
 use serde::{Deserialize, Serialize};
 use time::{OffsetDateTime, format_description::well_known::Rfc3339, macros::format_description};
 
-use crate::{history::History, settings::Settings};
+use crate::{history::History, settings::Settings, team};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EncryptedHistory {
@@ -67,6 +67,136 @@ pub fn load_key(settings: &Settings) -> Result<Key> {
     Ok(key)
 }
 
+fn tag_key_path(settings: &Settings, tag: &str) -> Result<PathBuf> {
+    // The tag becomes a file name
+    if tag.is_empty() || tag.starts_with('.') || tag.contains(['/', '\\']) {
+        bail!("{tag:?} is not a valid record tag");
+    }
+
+    Ok(PathBuf::from(&settings.tag_keys_path).join(tag))
+}
+
+/// Whether records with this tag are encrypted with a key of their own, rather than the main key
+pub fn has_tag_key(settings: &Settings, tag: &str) -> Result<bool> {
+    Ok(tag_key_path(settings, tag)?.exists())
+}
+
+/// Loads the key for records with the given tag. Tags use the main key, unless they have been
+/// given their own with `atuin store rekey --tag`. A team's tags use the team's key.
+pub fn load_tag_key(settings: &Settings, tag: &str) -> Result<Key> {
+    let path = tag_key_path(settings, tag)?;
+
+    if path.exists() {
+        let key = fs::read_to_string(&path)?;
+        decode_key(key).wrap_err_with(|| format!("could not load the key for {tag} records"))
+    } else if let Some(team) = team::team(tag) {
+        load_team_key(settings, team)
+    } else {
+        load_key(settings)
+    }
+}
+
+/// Loads the key shared by the members of a team. Never falls back to the main key - anything
+/// encrypted with that would be unreadable to the rest of the team.
+pub fn load_team_key(settings: &Settings, team: &str) -> Result<Key> {
+    let path = tag_key_path(settings, &team::key_name(team))?;
+
+    if !path.exists() {
+        bail!(
+            "you don't have the key for team {team}. Ask a member for it, and run `atuin team join {team}`"
+        );
+    }
+
+    let key = fs::read_to_string(&path)?;
+    decode_key(key).wrap_err_with(|| format!("could not load the key for team {team}"))
+}
+
+pub fn save_team_key(settings: &Settings, team: &str, encoded: &str) -> Result<()> {
+    save_tag_key(settings, &team::key_name(team), encoded)
+}
+
+/// Forget a team's key, so we stop syncing with it
+pub fn delete_team_key(settings: &Settings, team: &str) -> Result<()> {
+    let path = tag_key_path(settings, &team::key_name(team))?;
+
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Give the tag its own key, or replace the one it has. Does not re-encrypt anything!
+pub fn save_tag_key(settings: &Settings, tag: &str, encoded: &str) -> Result<()> {
+    let path = tag_key_path(settings, tag)?;
+
+    fs::create_dir_all(&settings.tag_keys_path)?;
+    fs::write(path, encoded)?;
+
+    Ok(())
+}
+
+/// The key for every record tag, for working over the whole record store at once
+#[derive(Debug, Clone)]
+pub struct Keys {
+    main: [u8; 32],
+    tags: HashMap<String, [u8; 32]>,
+}
+
+impl Keys {
+    /// Only the main key, for every tag
+    pub fn new(main: [u8; 32]) -> Self {
+        Self {
+            main,
+            tags: HashMap::new(),
+        }
+    }
+
+    pub fn load(settings: &Settings) -> Result<Self> {
+        let mut keys = Self::new(load_key(settings)?.into());
+
+        let dir = PathBuf::from(&settings.tag_keys_path);
+        if !dir.exists() {
+            return Ok(keys);
+        }
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+
+            // Skip anything that can't be a tag, like editor swap files
+            let Some(tag) = entry.file_name().to_str().map(String::from) else {
+                continue;
+            };
+
+            if tag.starts_with('.') || !entry.file_type()?.is_file() {
+                continue;
+            }
+
+            let key = load_tag_key(settings, &tag)?;
+            keys.tags.insert(tag, key.into());
+        }
+
+        Ok(keys)
+    }
+
+    pub fn get(&self, tag: &str) -> &[u8; 32] {
+        self.tags
+            .get(tag)
+            .or_else(|| team::team(tag).and_then(|team| self.tags.get(&team::key_name(team))))
+            .unwrap_or(&self.main)
+    }
+
+    pub fn with_main(mut self, main: [u8; 32]) -> Self {
+        self.main = main;
+        self
+    }
+
+    pub fn with_tag(mut self, tag: &str, key: [u8; 32]) -> Self {
+        self.tags.insert(tag.to_string(), key);
+        self
+    }
+}
+
 pub fn encode_key(key: &Key) -> Result<String> {
     let mut buf = vec![];
     rmp::encode::write_array_len(&mut buf, key.len() as u32)
@@ -271,7 +401,31 @@ mod test {
 
     use crate::history::History;
 
-    use super::{decode, decrypt, encode, encrypt};
+    use super::{Keys, decode, decrypt, encode, encrypt};
+
+    #[test]
+    fn tags_fall_back_to_the_main_key() {
+        let keys = Keys::new([1; 32]).with_tag("script", [2; 32]);
+
+        assert_eq!(keys.get("history"), &[1; 32]);
+        assert_eq!(keys.get("script"), &[2; 32]);
+
+        let keys = keys.with_main([3; 32]);
+
+        assert_eq!(keys.get("history"), &[3; 32]);
+        assert_eq!(keys.get("script"), &[2; 32]);
+    }
+
+    #[test]
+    fn team_tags_use_the_team_key() {
+        let keys = Keys::new([1; 32])
+            .with_tag("@ops", [2; 32])
+            .with_tag("kv@ops", [3; 32]);
+
+        assert_eq!(keys.get("script@ops"), &[2; 32]);
+        assert_eq!(keys.get("kv@ops"), &[3; 32]);
+        assert_eq!(keys.get("script"), &[1; 32]);
+    }
 
     #[test]
     fn test_encrypt_decrypt() {
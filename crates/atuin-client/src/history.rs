@@ -90,6 +90,46 @@ pub struct HistoryStats {
     pub day_of_week: Vec<(String, i64)>,
 
     pub duration_over_time: Vec<(String, i64)>,
+
+    /// Every command in the session, oldest first
+    pub session: Vec<History>,
+}
+
+/// How a command has been used, counted against where we are now
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandUsage {
+    /// How many times it was run
+    pub count: i64,
+
+    /// How many of those were in the current directory
+    pub here: i64,
+
+    /// How many were in the current git repository
+    pub workspace: i64,
+
+    /// How many failed
+    pub failures: i64,
+}
+
+/// How a command has been run, summed up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandStats {
+    pub command: String,
+
+    /// How many times it was run
+    pub count: i64,
+
+    /// Runs that have finished, and those of them that succeeded
+    pub finished: i64,
+    pub succeeded: i64,
+
+    pub last_run: OffsetDateTime,
+
+    /// Of the finished runs, in nanoseconds
+    pub median_duration: Option<i64>,
+
+    /// Hosts it was run on, with how many times, most first
+    pub hosts: Vec<(String, i64)>,
 }
 
 impl History {
@@ -180,6 +180,20 @@ impl HistoryStore {
         self.push_record(record).await
     }
 
+    /// Delete many entries at once - one deletion record each, in a single transaction - and
+    /// remove them from the history database
+    pub async fn delete_many(&self, database: &dyn Database, ids: &[HistoryId]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        self.push_batch(ids.iter().cloned().map(HistoryRecord::Delete))
+            .await?;
+        database.delete_rows(ids).await?;
+
+        Ok(())
+    }
+
     pub async fn push(&self, history: History) -> Result<(RecordId, RecordIdx)> {
         // TODO(ellie): move the history store to its own file
         // it's tiny rn so fine as is
@@ -340,12 +354,20 @@ impl HistoryStore {
 
 #[cfg(test)]
 mod tests {
-    use atuin_common::record::DecryptedData;
-    use time::macros::datetime;
+    use atuin_common::{
+        record::{DecryptedData, HostId},
+        utils::uuid_v7,
+    };
+    use time::{OffsetDateTime, macros::datetime};
 
-    use crate::history::{HISTORY_VERSION, store::HistoryRecord};
+    use crate::{
+        database::{Database, Sqlite},
+        history::{HISTORY_VERSION, store::HistoryRecord},
+        record::sqlite_store::SqliteStore,
+        settings::test_local_timeout,
+    };
 
-    use super::History;
+    use super::{History, HistoryStore};
 
     #[test]
     fn test_serialize_deserialize_create() {
@@ -408,4 +430,45 @@ mod tests {
                 .expect("failed to deserialize HistoryRecord");
         assert_eq!(deserialized, record);
     }
+
+    #[tokio::test]
+    async fn delete_many() {
+        let store = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let history_store = HistoryStore::new(store, HostId(uuid_v7()), [0; 32]);
+
+        let mut ids = Vec::new();
+        for command in ["export TOKEN=hunter2", "curl -H 'Token: hunter2'", "ls"] {
+            let history: History = History::capture()
+                .timestamp(OffsetDateTime::now_utc())
+                .command(command)
+                .cwd("/home/ellie")
+                .build()
+                .into();
+
+            ids.push(history.id.clone());
+            history_store.push(history).await.unwrap();
+        }
+        history_store.build(&db).await.unwrap();
+
+        history_store.delete_many(&db, &ids[..2]).await.unwrap();
+
+        assert_eq!(db.history_count(true).await.unwrap(), 1);
+
+        let deleted: Vec<_> = history_store
+            .history()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|record| match record {
+                HistoryRecord::Delete(id) => Some(id),
+                HistoryRecord::Create(_) => None,
+            })
+            .collect();
+        assert_eq!(deleted, ids[..2]);
+    }
 }
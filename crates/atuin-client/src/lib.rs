@@ -20,6 +20,7 @@ pub mod record;
 pub mod register;
 pub mod secrets;
 pub mod settings;
+pub mod team;
 pub mod theme;
 
 mod utils;
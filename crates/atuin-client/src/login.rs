@@ -1,13 +1,14 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
-use atuin_common::api::LoginRequest;
+use atuin_common::api::{LoginRequest, OidcDeviceResponse, OidcTokenResponse};
 use eyre::{Context, Result, bail};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
+use tokio::time::Instant;
 
 use crate::{
     api_client,
-    encryption::{Key, decode_key, encode_key, load_key},
+    encryption::{Key, Keys, decode_key, encode_key, load_key},
     record::{sqlite_store::SqliteStore, store::Store},
     settings::Settings,
 };
@@ -72,7 +73,11 @@ pub async fn login(
         if new_key != current_key {
             println!("\nRe-encrypting local store with new key");
 
-            store.re_encrypt(&current_key, &new_key).await?;
+            // Tags with keys of their own are left alone
+            let keys = Keys::load(settings)?;
+            store
+                .re_encrypt(&keys, &keys.clone().with_main(new_key))
+                .await?;
 
             println!("Writing new key");
             let mut file = File::create(key_path).await?;
@@ -82,7 +87,11 @@ pub async fn login(
 
     let session = api_client::login(
         settings.sync_address.as_str(),
-        LoginRequest { username, password },
+        LoginRequest {
+            username,
+            password,
+            device: None,
+        },
     )
     .await?;
 
@@ -92,3 +101,46 @@ pub async fn login(
 
     Ok(session.session)
 }
+
+/// Log in with the sync server's single sign-on provider. `prompt` is called once, with the code
+/// the user needs to enter and where to enter it. Returns the session token and username.
+/// The session is named `device`, or the hostname if not set. `invite` is only needed to create
+/// an account on a server that is not open for registration.
+pub async fn sso(
+    settings: &Settings,
+    device: Option<String>,
+    invite: Option<String>,
+    prompt: impl FnOnce(&OidcDeviceResponse),
+) -> Result<(String, String)> {
+    let address = settings.sync_address.as_str();
+
+    let code = api_client::oidc_device(address).await?;
+    prompt(&code);
+
+    let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+    let mut interval = Duration::from_secs(code.interval.max(1));
+
+    while Instant::now() < deadline {
+        tokio::time::sleep(interval).await;
+
+        let token = api_client::oidc_token(
+            address,
+            code.device_code.clone(),
+            device.clone(),
+            invite.clone(),
+        );
+
+        match token.await? {
+            OidcTokenResponse::Pending => {}
+
+            // As the spec asks of us
+            OidcTokenResponse::SlowDown => interval += Duration::from_secs(5),
+
+            OidcTokenResponse::Complete { session, username } => {
+                return Ok((session, username));
+            }
+        }
+    }
+
+    bail!("timed out waiting for you to log in, please try again")
+}
@@ -131,7 +131,7 @@ impl PASETO_V4 {
 
         ensure!(
             current_kid == kid,
-            "attempting to decrypt with incorrect key. currently using {current_kid}, expecting {kid}"
+            "this record was encrypted with a different key ({kid}) to the one in use ({current_kid}). Every machine you sync must use the same key - see `atuin key`"
         );
 
         // decrypt the random key
@@ -4,3 +4,6 @@ pub mod store;
 
 #[cfg(feature = "sync")]
 pub mod sync;
+
+#[cfg(feature = "sync")]
+pub mod transport;
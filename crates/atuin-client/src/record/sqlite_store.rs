@@ -22,6 +22,7 @@ use uuid::Uuid;
 
 use super::encryption::PASETO_V4;
 use super::store::Store;
+use crate::encryption::Keys;
 
 #[derive(Debug, Clone)]
 pub struct SqliteStore {
@@ -167,6 +168,42 @@ impl Store for SqliteStore {
         Ok(())
     }
 
+    async fn truncate(&self, host: HostId, tag: &str, idx: RecordIdx) -> Result<u64> {
+        let res = sqlx::query("delete from store where idx >= ?1 and host = ?2 and tag = ?3")
+            .bind(idx as i64)
+            .bind(host.0.as_hyphenated().to_string())
+            .bind(tag)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    async fn replace_tail(
+        &self,
+        host: HostId,
+        tag: &str,
+        idx: RecordIdx,
+        records: &[Record<EncryptedData>],
+    ) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let res = sqlx::query("delete from store where idx >= ?1 and host = ?2 and tag = ?3")
+            .bind(idx as i64)
+            .bind(host.0.as_hyphenated().to_string())
+            .bind(tag)
+            .execute(&mut *tx)
+            .await?;
+
+        for record in records {
+            Self::save_raw(&mut tx, record).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(res.rows_affected())
+    }
+
     async fn last(&self, host: HostId, tag: &str) -> Result<Option<Record<EncryptedData>>> {
         let res =
             sqlx::query("select * from store where host=?1 and tag=?2 order by idx desc limit 1")
@@ -297,7 +334,7 @@ impl Store for SqliteStore {
 
     /// Reencrypt every single item in this store with a new key
     /// Be careful - this may mess with sync.
-    async fn re_encrypt(&self, old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<()> {
+    async fn re_encrypt(&self, old_keys: &Keys, new_keys: &Keys) -> Result<()> {
         // Load all the records
         // In memory like some of the other code here
         // This will never be called in a hot loop, and only under the following circumstances
@@ -309,7 +346,27 @@ impl Store for SqliteStore {
 
         let re_encrypted = all
             .into_iter()
-            .map(|record| record.re_encrypt::<PASETO_V4>(old_key, new_key))
+            .map(|record| {
+                let old_key = old_keys.get(&record.tag);
+                let new_key = new_keys.get(&record.tag);
+
+                if old_key == new_key {
+                    return Ok(record);
+                }
+
+                // Records downloaded from a machine that already uses the new key, after we
+                // synced with the wrong one, can stay as they are
+                record
+                    .clone()
+                    .re_encrypt::<PASETO_V4>(old_key, new_key)
+                    .or_else(|e| {
+                        if record.clone().decrypt::<PASETO_V4>(new_key).is_ok() {
+                            Ok(record)
+                        } else {
+                            Err(e)
+                        }
+                    })
+            })
             .collect::<Result<Vec<_>>>()?;
 
         // next up, we delete all the old data and reinsert the new stuff
@@ -334,13 +391,16 @@ impl Store for SqliteStore {
         Ok(())
     }
 
-    /// Verify that every record in this store can be decrypted with the current key
+    /// Verify that every record in this store can be decrypted with the key for its tag
     /// Someday maybe also check each tag/record can be deserialized, but not for now.
-    async fn verify(&self, key: &[u8; 32]) -> Result<()> {
+    async fn verify(&self, keys: &Keys) -> Result<()> {
         let all = self.load_all().await?;
 
         all.into_iter()
-            .map(|record| record.decrypt::<PASETO_V4>(key))
+            .map(|record| {
+                let key = keys.get(&record.tag);
+                record.decrypt::<PASETO_V4>(key)
+            })
             .collect::<Result<Vec<_>>>()?;
 
         Ok(())
@@ -348,11 +408,11 @@ impl Store for SqliteStore {
 
     /// Verify that every record in this store can be decrypted with the current key
     /// Someday maybe also check each tag/record can be deserialized, but not for now.
-    async fn purge(&self, key: &[u8; 32]) -> Result<()> {
+    async fn purge(&self, keys: &Keys) -> Result<()> {
         let all = self.load_all().await?;
 
         for record in all.iter() {
-            match record.clone().decrypt::<PASETO_V4>(key) {
+            match record.clone().decrypt::<PASETO_V4>(keys.get(&record.tag)) {
                 Ok(_) => continue,
                 Err(_) => {
                     println!(
@@ -377,7 +437,7 @@ mod tests {
     };
 
     use crate::{
-        encryption::generate_encoded_key,
+        encryption::{Keys, generate_encoded_key},
         record::{encryption::PASETO_V4, store::Store},
         settings::test_local_timeout,
     };
@@ -552,6 +612,59 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn truncate() {
+        let db = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+
+        let mut tail = test_record();
+        let other = test_record();
+        db.push(&tail).await.unwrap();
+        db.push(&other).await.unwrap();
+
+        for _ in 1..10 {
+            tail = tail.append(vec![1, 2, 3, 4]).encrypt::<PASETO_V4>(&[0; 32]);
+            db.push(&tail).await.unwrap();
+        }
+
+        let deleted = db.truncate(tail.host.id, &tail.tag, 4).await.unwrap();
+        assert_eq!(deleted, 6);
+
+        assert_eq!(db.len(tail.host.id, &tail.tag).await.unwrap(), 4);
+        assert_eq!(
+            db.last(tail.host.id, &tail.tag).await.unwrap().unwrap().idx,
+            3
+        );
+
+        // other stores are left alone
+        assert_eq!(db.len(other.host.id, &other.tag).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn replace_tail() {
+        let db = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+
+        let mut tail = test_record();
+        db.push(&tail).await.unwrap();
+        for _ in 1..10 {
+            tail = tail.append(vec![1, 2, 3, 4]).encrypt::<PASETO_V4>(&[0; 32]);
+            db.push(&tail).await.unwrap();
+        }
+
+        let moved = test_record();
+        let deleted = db
+            .replace_tail(tail.host.id, &tail.tag, 4, std::slice::from_ref(&moved))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 6);
+
+        assert_eq!(db.len(tail.host.id, &tail.tag).await.unwrap(), 4);
+        assert_eq!(db.get(moved.id).await.unwrap(), moved);
+    }
+
     #[tokio::test]
     async fn append_a_big_bunch() {
         let db = SqliteStore::new(":memory:", test_local_timeout())
@@ -618,7 +731,7 @@ mod tests {
 
         let (new_key, _) = generate_encoded_key().unwrap();
         store
-            .re_encrypt(&key.into(), &new_key.into())
+            .re_encrypt(&Keys::new(key.into()), &Keys::new(new_key.into()))
             .await
             .expect("failed to re-encrypt store");
 
@@ -639,4 +752,87 @@ mod tests {
 
         assert_eq!(store.len(host_id, "test").await.unwrap(), 10);
     }
+
+    #[tokio::test]
+    async fn re_encrypt_one_tag() {
+        let store = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let data = vec![0u8, 1u8, 2u8, 3u8];
+        let host_id = HostId(uuid_v7());
+
+        let keys = Keys::new([1; 32]);
+
+        for tag in ["history", "script"] {
+            let record = Record::builder()
+                .host(Host::new(host_id))
+                .version(String::from("test"))
+                .tag(String::from(tag))
+                .idx(0)
+                .data(DecryptedData(data.clone()))
+                .build()
+                .encrypt::<PASETO_V4>(keys.get(tag));
+
+            store.push(&record).await.unwrap();
+        }
+
+        // give only the scripts a key of their own
+        let new_keys = keys.clone().with_tag("script", [2; 32]);
+
+        store.re_encrypt(&keys, &new_keys).await.unwrap();
+        store.verify(&new_keys).await.unwrap();
+        assert!(store.verify(&keys).await.is_err());
+
+        let history = store.all_tagged("history").await.unwrap();
+        history[0].clone().decrypt::<PASETO_V4>(&[1; 32]).unwrap();
+
+        let scripts = store.all_tagged("script").await.unwrap();
+        assert!(scripts[0].clone().decrypt::<PASETO_V4>(&[1; 32]).is_err());
+        scripts[0].clone().decrypt::<PASETO_V4>(&[2; 32]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn re_encrypt_a_mixed_store() {
+        // Our own records are under the wrong key, the ones we downloaded under the right one
+        let store = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+
+        for key in [[1; 32], [2; 32]] {
+            let record = Record::builder()
+                .host(Host::new(HostId(uuid_v7())))
+                .version(String::from("test"))
+                .tag(String::from("history"))
+                .idx(0)
+                .data(DecryptedData(vec![0, 1, 2, 3]))
+                .build()
+                .encrypt::<PASETO_V4>(&key);
+
+            store.push(&record).await.unwrap();
+        }
+
+        store
+            .re_encrypt(&Keys::new([1; 32]), &Keys::new([2; 32]))
+            .await
+            .unwrap();
+        store.verify(&Keys::new([2; 32])).await.unwrap();
+
+        // A record neither key can read is still an error
+        let record = Record::builder()
+            .host(Host::new(HostId(uuid_v7())))
+            .version(String::from("test"))
+            .tag(String::from("history"))
+            .idx(0)
+            .data(DecryptedData(vec![0, 1, 2, 3]))
+            .build()
+            .encrypt::<PASETO_V4>(&[3; 32]);
+        store.push(&record).await.unwrap();
+
+        assert!(
+            store
+                .re_encrypt(&Keys::new([2; 32]), &Keys::new([4; 32]))
+                .await
+                .is_err()
+        );
+    }
 }
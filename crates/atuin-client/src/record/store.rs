@@ -3,6 +3,8 @@ use eyre::Result;
 
 use atuin_common::record::{EncryptedData, HostId, Record, RecordId, RecordIdx, RecordStatus};
 
+use crate::encryption::Keys;
+
 /// A record store stores records
 /// In more detail - we tend to need to process this into _another_ format to actually query it.
 /// As is, the record store is intended as the source of truth for arbitrary data, which could
@@ -25,6 +27,18 @@ pub trait Store {
     async fn delete(&self, id: RecordId) -> Result<()>;
     async fn delete_all(&self) -> Result<()>;
 
+    /// Delete every record for the given host and tag, from the given index onwards
+    async fn truncate(&self, host: HostId, tag: &str, idx: RecordIdx) -> Result<u64>;
+
+    /// Truncate a store as above, and push these records in its place - all or nothing
+    async fn replace_tail(
+        &self,
+        host: HostId,
+        tag: &str,
+        idx: RecordIdx,
+        records: &[Record<EncryptedData>],
+    ) -> Result<u64>;
+
     async fn len_all(&self) -> Result<u64>;
     async fn len(&self, host: HostId, tag: &str) -> Result<u64>;
     async fn len_tag(&self, tag: &str) -> Result<u64>;
@@ -32,9 +46,10 @@ pub trait Store {
     async fn last(&self, host: HostId, tag: &str) -> Result<Option<Record<EncryptedData>>>;
     async fn first(&self, host: HostId, tag: &str) -> Result<Option<Record<EncryptedData>>>;
 
-    async fn re_encrypt(&self, old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<()>;
-    async fn verify(&self, key: &[u8; 32]) -> Result<()>;
-    async fn purge(&self, key: &[u8; 32]) -> Result<()>;
+    /// Re-encrypt every record whose key differs between the old and new keys
+    async fn re_encrypt(&self, old_keys: &Keys, new_keys: &Keys) -> Result<()>;
+    async fn verify(&self, keys: &Keys) -> Result<()>;
+    async fn purge(&self, keys: &Keys) -> Result<()>;
 
     /// Get the next `limit` records, after and including the given index
     async fn next(
@@ -1,14 +1,26 @@
 // do a sync :O
-use std::{cmp::Ordering, fmt::Write};
+use std::cmp::Ordering;
 
 use eyre::Result;
 use thiserror::Error;
 
-use super::store::Store;
-use crate::{api_client::Client, settings::Settings};
+use super::{
+    store::Store,
+    transport::{self, Transport},
+};
+use crate::{encryption::Keys, settings::Settings, team};
 
 use atuin_common::record::{Diff, HostId, RecordId, RecordIdx, RecordStatus};
-use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+
+pub use checkpoint::Checkpoint;
+pub use divergence::{Divergence, find_divergence};
+pub use key::check_key;
+pub use progress::{Direction, NoProgress, ProgressBars, SyncEvent, SyncProgress, Transfer};
+
+mod checkpoint;
+mod divergence;
+mod key;
+mod progress;
 
 #[derive(Error, Debug)]
 pub enum SyncError {
@@ -26,6 +38,17 @@ pub enum SyncError {
 
     #[error("a request to the sync server failed: {msg:?}")]
     RemoteRequestError { msg: String },
+
+    #[error(
+        "the local record store has diverged from the remote ({}). Another machine is using this host ID - was it cloned, or restored from a backup? Run `atuin store repair` to fix it",
+        .divergences.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    Diverged { divergences: Vec<Divergence> },
+
+    #[error(
+        "none of the records synced by your other machines can be decrypted with the key on this one. Was it logged in with a new key, rather than theirs?"
+    )]
+    WrongKey,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -53,32 +76,45 @@ pub async fn diff(
     settings: &Settings,
     store: &impl Store,
 ) -> Result<(Vec<Diff>, RecordStatus), SyncError> {
-    let client = Client::new(
-        &settings.sync_address,
-        settings
-            .session_token()
-            .map_err(|e| SyncError::RemoteRequestError { msg: e.to_string() })?
-            .as_str(),
-        settings.network_connect_timeout,
-        settings.network_timeout,
-    )
-    .map_err(|e| SyncError::OperationalError { msg: e.to_string() })?;
+    let transport = transport::from_settings(settings)
+        .map_err(|e| SyncError::OperationalError { msg: e.to_string() })?;
+
+    diff_with(transport.as_ref(), store).await
+}
 
+pub async fn diff_with(
+    transport: &dyn Transport,
+    store: &impl Store,
+) -> Result<(Vec<Diff>, RecordStatus), SyncError> {
     let local_index = store
         .status()
         .await
         .map_err(|e| SyncError::LocalStoreError { msg: e.to_string() })?;
 
-    let remote_index = client
+    let remote_index = transport
         .record_status()
         .await
         .map_err(|e| SyncError::RemoteRequestError { msg: e.to_string() })?;
 
+    let local_index = synced_by(transport, local_index);
+    let remote_index = synced_by(transport, remote_index);
+
     let diff = local_index.diff(&remote_index);
 
     Ok((diff, remote_index))
 }
 
+/// Only the tags that the transport syncs
+fn synced_by(transport: &dyn Transport, mut status: RecordStatus) -> RecordStatus {
+    for tags in status.hosts.values_mut() {
+        tags.retain(|tag, _| transport.syncs_tag(tag));
+    }
+
+    status.hosts.retain(|_, tags| !tags.is_empty());
+
+    status
+}
+
 // Take a diff, along with a local store, and resolve it into a set of operations.
 // With the store as context, we can determine if a tail exists locally or not and therefore if it needs uploading or download.
 // In theory this could be done as a part of the diffing stage, but it's easier to reason
@@ -158,34 +194,30 @@ pub async fn operations(
 
 async fn sync_upload(
     store: &impl Store,
-    client: &Client<'_>,
+    transport: &dyn Transport,
+    progress: &dyn SyncProgress,
     host: HostId,
     tag: String,
     local: RecordIdx,
     remote: Option<RecordIdx>,
 ) -> Result<i64, SyncError> {
-    let remote = remote.unwrap_or(0);
-    let expected = local - remote;
     let upload_page_size = 100;
-    let mut progress = 0;
-
-    let pb = ProgressBar::new(expected);
-    pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {human_pos}/{human_len} ({eta})")
-        .unwrap()
-        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-        .progress_chars("#>-"));
-
-    println!(
-        "Uploading {} records to {}/{}",
-        expected,
-        host.0.as_simple(),
-        tag
-    );
-
-    // preload with the first entry if remote does not know of this store
-    loop {
+    let mut next = remote.map_or(0, |r| r + 1);
+
+    let mut transfer = Transfer {
+        direction: Direction::Upload,
+        host,
+        tag: tag.clone(),
+        done: 0,
+        total: (local + 1).saturating_sub(next),
+        bytes: 0,
+    };
+
+    progress.event(&SyncEvent::Started(transfer.clone()));
+
+    while next <= local {
         let page = store
-            .next(host, tag.as_str(), remote + progress, upload_page_size)
+            .next(host, tag.as_str(), next, upload_page_size)
             .await
             .map_err(|e| {
                 error!("failed to read upload page: {e:?}");
@@ -193,75 +225,93 @@ async fn sync_upload(
                 SyncError::LocalStoreError { msg: e.to_string() }
             })?;
 
-        client.post_records(&page).await.map_err(|e| {
+        let Some(last) = page.last() else {
+            warn!("local store has no records for {host:?}/{tag} at {next}");
+            break;
+        };
+        next = last.idx + 1;
+
+        transport.post_records(&page).await.map_err(|e| {
             error!("failed to post records: {e:?}");
 
             SyncError::RemoteRequestError { msg: e.to_string() }
         })?;
 
-        pb.set_position(progress);
-        progress += page.len() as u64;
-
-        if progress >= expected {
-            break;
-        }
+        transfer.add_page(&page);
+        progress.event(&SyncEvent::Progress(transfer.clone()));
     }
 
-    pb.finish_with_message("Uploaded records");
+    progress.event(&SyncEvent::Finished(transfer.clone()));
 
-    Ok(progress as i64)
+    Ok(transfer.done as i64)
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn sync_download(
     store: &impl Store,
-    client: &Client<'_>,
+    transport: &dyn Transport,
+    progress: &dyn SyncProgress,
+    checkpoint: &Checkpoint,
     host: HostId,
     tag: String,
     local: Option<RecordIdx>,
     remote: RecordIdx,
 ) -> Result<Vec<RecordId>, SyncError> {
-    let local = local.unwrap_or(0);
-    let expected = remote - local;
     let download_page_size = 100;
-    let mut progress = 0;
+    let mut next = local.map_or(0, |l| l + 1);
     let mut ret = Vec::new();
 
-    println!(
-        "Downloading {} records from {}/{}",
-        expected,
-        host.0.as_simple(),
-        tag
-    );
-
-    let pb = ProgressBar::new(expected);
-    pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {human_pos}/{human_len} ({eta})")
-        .unwrap()
-        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-        .progress_chars("#>-"));
-
-    // preload with the first entry if remote does not know of this store
-    loop {
-        let page = client
-            .next_records(host, tag.clone(), local + progress, download_page_size)
+    let mut transfer = Transfer {
+        direction: Direction::Download,
+        host,
+        tag: tag.clone(),
+        done: 0,
+        total: (remote + 1).saturating_sub(next),
+        bytes: 0,
+    };
+
+    match checkpoint.begin(host, &tag, next, remote) {
+        // Every page we stored before the interruption is already in the local store, so we
+        // carry on from there. Report against the original download.
+        Some(previous) if previous.first < next => {
+            transfer.done = next - previous.first;
+            transfer.total = (remote + 1).saturating_sub(previous.first);
+
+            progress.event(&SyncEvent::Resumed(transfer.clone()));
+        }
+
+        _ => progress.event(&SyncEvent::Started(transfer.clone())),
+    }
+
+    while next <= remote {
+        let page = transport
+            .next_records(host, tag.clone(), next, download_page_size)
             .await
             .map_err(|e| SyncError::RemoteRequestError { msg: e.to_string() })?;
 
+        // The remote claimed to have more than it is willing to give us. Don't spin forever.
+        let Some(last) = page.last() else {
+            warn!("remote returned no records for {host:?}/{tag} at {next}");
+            break;
+        };
+        let last = last.idx;
+
+        // A page is stored in a single transaction. If we are interrupted, the next sync picks up
+        // from the last complete page.
         store
             .push_batch(page.iter())
             .await
             .map_err(|e| SyncError::LocalStoreError { msg: e.to_string() })?;
 
+        next = last + 1;
         ret.extend(page.iter().map(|f| f.id));
 
-        pb.set_position(progress);
-        progress += page.len() as u64;
-
-        if progress >= expected {
-            break;
-        }
+        transfer.add_page(&page);
+        progress.event(&SyncEvent::Progress(transfer.clone()));
     }
 
-    pb.finish_with_message("Downloaded records");
+    checkpoint.finish(host, &tag);
+    progress.event(&SyncEvent::Finished(transfer));
 
     Ok(ret)
 }
@@ -271,17 +321,26 @@ pub async fn sync_remote(
     local_store: &impl Store,
     settings: &Settings,
 ) -> Result<(i64, Vec<RecordId>), SyncError> {
-    let client = Client::new(
-        &settings.sync_address,
-        settings
-            .session_token()
-            .map_err(|e| SyncError::RemoteRequestError { msg: e.to_string() })?
-            .as_str(),
-        settings.network_connect_timeout,
-        settings.network_timeout,
+    let transport = transport::from_settings(settings)
+        .map_err(|e| SyncError::OperationalError { msg: e.to_string() })?;
+
+    sync_remote_with(
+        operations,
+        local_store,
+        transport.as_ref(),
+        &ProgressBars::new(),
+        &Checkpoint::in_data_dir(),
     )
-    .expect("failed to create client");
+    .await
+}
 
+pub async fn sync_remote_with(
+    operations: Vec<Operation>,
+    local_store: &impl Store,
+    transport: &dyn Transport,
+    progress: &dyn SyncProgress,
+    checkpoint: &Checkpoint,
+) -> Result<(i64, Vec<RecordId>), SyncError> {
     let mut uploaded = 0;
     let mut downloaded = Vec::new();
 
@@ -293,7 +352,10 @@ pub async fn sync_remote(
                 tag,
                 local,
                 remote,
-            } => uploaded += sync_upload(local_store, &client, host, tag, local, remote).await?,
+            } => {
+                uploaded +=
+                    sync_upload(local_store, transport, progress, host, tag, local, remote).await?
+            }
 
             Operation::Download {
                 host,
@@ -301,7 +363,17 @@ pub async fn sync_remote(
                 local,
                 remote,
             } => {
-                let mut d = sync_download(local_store, &client, host, tag, local, remote).await?;
+                let mut d = sync_download(
+                    local_store,
+                    transport,
+                    progress,
+                    checkpoint,
+                    host,
+                    tag,
+                    local,
+                    remote,
+                )
+                .await?;
                 downloaded.append(&mut d)
             }
 
@@ -316,24 +388,136 @@ pub async fn sync(
     settings: &Settings,
     store: &impl Store,
 ) -> Result<(i64, Vec<RecordId>), SyncError> {
-    let (diff, _) = diff(settings, store).await?;
+    let transport = transport::from_settings(settings)
+        .map_err(|e| SyncError::OperationalError { msg: e.to_string() })?;
+
+    let host = Settings::host_id().expect("failed to get host_id");
+    let progress = ProgressBars::new();
+    let checkpoint = Checkpoint::in_data_dir();
+
+    let keys =
+        Keys::load(settings).map_err(|e| SyncError::OperationalError { msg: e.to_string() })?;
+
+    // Don't upload anything the other machines won't be able to read
+    check_key(store, transport.as_ref(), &keys, host).await?;
+
+    let (mut uploaded, mut downloaded) =
+        sync_with(transport.as_ref(), store, host, &progress, &checkpoint).await?;
+
+    let (up, mut down) = sync_teams(settings, store, &keys, host, &progress, &checkpoint).await?;
+    uploaded += up;
+    downloaded.append(&mut down);
+
+    Ok((uploaded, downloaded))
+}
+
+/// Sync the store of every team we have joined. Teams that fail are logged and skipped.
+pub async fn sync_teams(
+    settings: &Settings,
+    store: &impl Store,
+    keys: &Keys,
+    host: HostId,
+    progress: &dyn SyncProgress,
+    checkpoint: &Checkpoint,
+) -> Result<(i64, Vec<RecordId>), SyncError> {
+    let mut uploaded = 0;
+    let mut downloaded = Vec::new();
+
+    // Teams only exist on the sync server
+    if settings.sync.directory.is_some() {
+        return Ok((uploaded, downloaded));
+    }
+
+    let teams =
+        team::joined(settings).map_err(|e| SyncError::OperationalError { msg: e.to_string() })?;
+
+    // One team being unreachable, or having removed us, shouldn't stop everything else syncing
+    for team in teams {
+        let res = async {
+            let client = transport::for_team(settings, &team)
+                .map_err(|e| SyncError::OperationalError { msg: e.to_string() })?;
+
+            check_key(store, &client, keys, host).await?;
+            sync_with(&client, store, host, progress, checkpoint).await
+        }
+        .await;
+
+        match res {
+            Ok((up, mut down)) => {
+                uploaded += up;
+                downloaded.append(&mut down);
+            }
+            Err(e) => warn!("failed to sync team {team}: {e}"),
+        }
+    }
+
+    Ok((uploaded, downloaded))
+}
+
+/// Sync the local store with the given transport, reporting progress as we go
+///
+/// Refuses to sync if the stores belonging to `host` have diverged from the remote.
+pub async fn sync_with(
+    transport: &dyn Transport,
+    store: &impl Store,
+    host: HostId,
+    progress: &dyn SyncProgress,
+    checkpoint: &Checkpoint,
+) -> Result<(i64, Vec<RecordId>), SyncError> {
+    let local_index = store
+        .status()
+        .await
+        .map_err(|e| SyncError::LocalStoreError { msg: e.to_string() })?;
+
+    let remote_index = transport
+        .record_status()
+        .await
+        .map_err(|e| SyncError::RemoteRequestError { msg: e.to_string() })?;
+
+    let local_index = synced_by(transport, local_index);
+    let remote_index = synced_by(transport, remote_index);
+
+    // A divergence only does harm once records move, and none move for the stores both sides
+    // have the same length of. Checking those too would cost a request each, every sync.
+    let mut moving = RecordStatus::new();
+    for (h, tags) in &local_index.hosts {
+        for (tag, idx) in tags {
+            if remote_index.get(*h, tag.clone()) != Some(*idx) {
+                moving.set_raw(*h, tag.clone(), *idx);
+            }
+        }
+    }
+
+    let divergences = find_divergence(store, transport, &moving, &remote_index, Some(host)).await?;
+
+    if !divergences.is_empty() {
+        return Err(SyncError::Diverged { divergences });
+    }
+
+    let diff = local_index.diff(&remote_index);
     let operations = operations(diff, store).await?;
-    let (uploaded, downloaded) = sync_remote(operations, store, settings).await?;
+    let (uploaded, downloaded) =
+        sync_remote_with(operations, store, transport, progress, checkpoint).await?;
 
     Ok((uploaded, downloaded))
 }
 
 #[cfg(test)]
 mod tests {
-    use atuin_common::record::{Diff, EncryptedData, HostId, Record};
+    use atuin_common::record::{DecryptedData, Diff, EncryptedData, HostId, Record};
     use pretty_assertions::assert_eq;
 
     use crate::{
+        encryption::Keys,
         record::{
             encryption::PASETO_V4,
             sqlite_store::SqliteStore,
             store::Store,
-            sync::{self, Operation},
+            sync::{
+                self, Checkpoint, Direction, Divergence, NoProgress, Operation, SyncError,
+                SyncEvent, SyncProgress, Transfer,
+            },
+            transport::{DirectoryTransport, Transport},
         },
         settings::test_local_timeout,
     };
@@ -353,6 +537,37 @@ mod tests {
             .build()
     }
 
+    // A single store with n records
+    fn test_chain(n: u64) -> Vec<Record<EncryptedData>> {
+        let first = test_record();
+
+        (0..n)
+            .map(|idx| Record {
+                id: atuin_common::record::RecordId(atuin_common::utils::uuid_v7()),
+                idx,
+                data: EncryptedData {
+                    data: format!("data {idx}"),
+                    content_encryption_key: String::new(),
+                },
+                ..first.clone()
+            })
+            .collect()
+    }
+
+    // The host of the machine doing the sync, when it is not the one that wrote the records
+    fn other_host() -> HostId {
+        HostId(atuin_common::utils::uuid_v7())
+    }
+
+    #[derive(Default)]
+    struct Events(std::sync::Mutex<Vec<SyncEvent>>);
+
+    impl SyncProgress for Events {
+        fn event(&self, event: &SyncEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
     // Take a list of local records, and a list of remote records.
     // Return the local database, and a diff of local/remote, ready to build
     // ops
@@ -613,4 +828,297 @@ mod tests {
 
         assert_eq!(result_ops, operations);
     }
+
+    #[tokio::test]
+    async fn sync_via_directory() {
+        // Two machines that never talk to each other, only to a shared directory
+
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path());
+
+        let first = test_record();
+        let second = first.append(vec![1, 2, 3]).encrypt::<PASETO_V4>(&[0; 32]);
+
+        let machine_a = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let machine_b = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+
+        machine_a.push(&first).await.unwrap();
+        machine_a.push(&second).await.unwrap();
+
+        let (uploaded, downloaded) = sync::sync_with(
+            &transport,
+            &machine_a,
+            first.host.id,
+            &NoProgress,
+            &Checkpoint::disabled(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(uploaded, 2);
+        assert!(downloaded.is_empty());
+
+        let (uploaded, downloaded) = sync::sync_with(
+            &transport,
+            &machine_b,
+            other_host(),
+            &NoProgress,
+            &Checkpoint::disabled(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(uploaded, 0);
+        assert_eq!(downloaded, vec![first.id, second.id]);
+
+        assert_eq!(
+            machine_a.status().await.unwrap().hosts,
+            machine_b.status().await.unwrap().hosts
+        );
+    }
+
+    #[tokio::test]
+    async fn download_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path());
+        let records = test_chain(250);
+        transport.post_records(&records).await.unwrap();
+
+        let local = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let events = Events::default();
+
+        sync::sync_with(
+            &transport,
+            &local,
+            other_host(),
+            &events,
+            &Checkpoint::disabled(),
+        )
+        .await
+        .unwrap();
+
+        let events = events.0.into_inner().unwrap();
+        let done = events
+            .iter()
+            .map(|e| match e {
+                SyncEvent::Started(t) => ("started", t.done, t.total),
+                SyncEvent::Resumed(t) => ("resumed", t.done, t.total),
+                SyncEvent::Progress(t) => ("progress", t.done, t.total),
+                SyncEvent::Finished(t) => ("finished", t.done, t.total),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            done,
+            vec![
+                ("started", 0, 250),
+                ("progress", 100, 250),
+                ("progress", 200, 250),
+                ("progress", 250, 250),
+                ("finished", 250, 250),
+            ]
+        );
+
+        let SyncEvent::Finished(finished) = events.last().unwrap() else {
+            panic!("expected the last event to be finished");
+        };
+        assert_eq!(finished.direction, Direction::Download);
+        assert!(finished.bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn resume_interrupted_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path().join("remote"));
+        let records = test_chain(250);
+        let host = records[0].host.id;
+        let tag = records[0].tag.clone();
+        transport.post_records(&records).await.unwrap();
+
+        // A previous sync started downloading everything, but only stored the first page
+        let checkpoint = Checkpoint::new(dir.path().join("checkpoint.json"));
+        checkpoint.begin(host, &tag, 0, 249);
+
+        let local = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        local.push_batch(records[..100].iter()).await.unwrap();
+
+        let events = Events::default();
+        let (_, downloaded) =
+            sync::sync_with(&transport, &local, other_host(), &events, &checkpoint)
+                .await
+                .unwrap();
+
+        assert_eq!(
+            downloaded,
+            records[100..].iter().map(|r| r.id).collect::<Vec<_>>()
+        );
+
+        let events = events.0.into_inner().unwrap();
+        assert_eq!(
+            events[0],
+            SyncEvent::Resumed(Transfer {
+                direction: Direction::Download,
+                host,
+                tag: tag.clone(),
+                done: 100,
+                total: 250,
+                bytes: 0,
+            })
+        );
+        assert!(matches!(events.last(), Some(SyncEvent::Finished(t)) if t.done == 250));
+
+        // All done, so the next sync is not a resume
+        assert!(!dir.path().join("checkpoint.json").exists());
+        assert_eq!(checkpoint.begin(host, &tag, 250, 300), None);
+    }
+
+    #[tokio::test]
+    async fn detect_divergence() {
+        // Two machines share a host ID. They agree on the first two records, then each writes
+        // their own.
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path());
+
+        let original = test_chain(5);
+        let host = original[0].host.id;
+        let tag = original[0].tag.clone();
+
+        let mut clone = original.clone();
+        for r in clone[2..].iter_mut() {
+            r.id = atuin_common::record::RecordId(atuin_common::utils::uuid_v7());
+        }
+
+        let machine_a = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let machine_b = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+
+        machine_a.push_batch(original.iter()).await.unwrap();
+        machine_b.push_batch(clone[..4].iter()).await.unwrap();
+
+        sync::sync_with(
+            &transport,
+            &machine_a,
+            host,
+            &NoProgress,
+            &Checkpoint::disabled(),
+        )
+        .await
+        .unwrap();
+
+        let err = sync::sync_with(
+            &transport,
+            &machine_b,
+            host,
+            &NoProgress,
+            &Checkpoint::disabled(),
+        )
+        .await
+        .unwrap_err();
+
+        let SyncError::Diverged { divergences } = err else {
+            panic!("expected divergence, got {err:?}");
+        };
+
+        assert_eq!(
+            divergences,
+            vec![Divergence {
+                host,
+                tag,
+                idx: 2,
+                local: Some(clone[2].id),
+                remote: Some(original[2].id),
+            }]
+        );
+
+        // Nothing was uploaded on top of the other chain
+        assert_eq!(
+            transport.record_status().await.unwrap().hosts,
+            machine_a.status().await.unwrap().hosts
+        );
+
+        // Another machine with its own host ID is happy to sync
+        let machine_c = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let (_, downloaded) = sync::sync_with(
+            &transport,
+            &machine_c,
+            other_host(),
+            &NoProgress,
+            &Checkpoint::disabled(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(downloaded.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn check_key_catches_the_wrong_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path());
+
+        let record = Record::builder()
+            .host(atuin_common::record::Host::new(other_host()))
+            .version("v1".into())
+            .tag("history".into())
+            .data(DecryptedData(vec![1, 2, 3]))
+            .idx(0)
+            .build()
+            .encrypt::<PASETO_V4>(&[1; 32]);
+
+        transport.post_records(&[record]).await.unwrap();
+
+        let store = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let host = other_host();
+
+        // Nothing local yet, so the remote is sampled
+        assert!(matches!(
+            sync::check_key(&store, &transport, &Keys::new([2; 32]), host).await,
+            Err(SyncError::WrongKey)
+        ));
+        sync::check_key(&store, &transport, &Keys::new([1; 32]), host)
+            .await
+            .unwrap();
+
+        sync::sync_with(
+            &transport,
+            &store,
+            host,
+            &NoProgress,
+            &Checkpoint::disabled(),
+        )
+        .await
+        .unwrap();
+
+        // And now the local copy is
+        assert!(matches!(
+            sync::check_key(&store, &transport, &Keys::new([2; 32]), host).await,
+            Err(SyncError::WrongKey)
+        ));
+
+        // Nobody else has written anything, so any key will do
+        let empty = tempfile::tempdir().unwrap();
+        let store = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        sync::check_key(
+            &store,
+            &DirectoryTransport::new(empty.path()),
+            &Keys::new([2; 32]),
+            host,
+        )
+        .await
+        .unwrap();
+    }
 }
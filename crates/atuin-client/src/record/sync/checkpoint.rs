@@ -0,0 +1,116 @@
+// Downloads are committed to the local store a page at a time, so an interrupted sync always
+// resumes from the last page it stored - the local store is the source of truth for where we are.
+// The checkpoint only remembers which downloads were in flight, and where they started, so that
+// we can tell the user we are resuming and report progress against the original total.
+//
+// This is best effort. If the checkpoint can't be read or written, sync carries on regardless.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use atuin_common::record::{HostId, RecordIdx};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::SYNC_CHECKPOINT_FILENAME;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InFlight {
+    /// The first idx the original download started from
+    pub first: RecordIdx,
+
+    /// The idx the download is working towards
+    pub target: RecordIdx,
+}
+
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    path: Option<PathBuf>,
+}
+
+impl Checkpoint {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// Don't keep track of anything. Every download starts fresh.
+    pub fn disabled() -> Self {
+        Self { path: None }
+    }
+
+    pub fn in_data_dir() -> Self {
+        Self::new(atuin_common::utils::data_dir().join(SYNC_CHECKPOINT_FILENAME))
+    }
+
+    fn key(host: HostId, tag: &str) -> String {
+        format!("{}/{}", host.0.as_simple(), tag)
+    }
+
+    fn load(&self) -> HashMap<String, InFlight> {
+        let Some(path) = &self.path else {
+            return HashMap::new();
+        };
+
+        fs_err::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, downloads: &HashMap<String, InFlight>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let res = if downloads.is_empty() {
+            fs_err::remove_file(path).or_else(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            })
+        } else {
+            serde_json::to_vec(downloads)
+                .map_err(std::io::Error::other)
+                .and_then(|data| fs_err::write(path, data))
+        };
+
+        if let Err(e) = res {
+            warn!("failed to save sync checkpoint: {e}");
+        }
+    }
+
+    /// Record the start of a download. If a previous download of this store never finished,
+    /// return it.
+    pub fn begin(
+        &self,
+        host: HostId,
+        tag: &str,
+        first: RecordIdx,
+        target: RecordIdx,
+    ) -> Option<InFlight> {
+        let mut downloads = self.load();
+        let key = Self::key(host, tag);
+
+        let previous = downloads.get(&key).copied();
+
+        let current = match previous {
+            Some(p) => InFlight {
+                first: p.first.min(first),
+                target: p.target.max(target),
+            },
+            None => InFlight { first, target },
+        };
+
+        downloads.insert(key, current);
+        self.save(&downloads);
+
+        previous
+    }
+
+    pub fn finish(&self, host: HostId, tag: &str) {
+        let mut downloads = self.load();
+
+        if downloads.remove(&Self::key(host, tag)).is_some() {
+            self.save(&downloads);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// Two machines that share a host ID (a cloned VM image, a restored backup) will both write records
+// with the same (host, tag, idx). The record status only knows about the tail idx of each store, so
+// a diff can't tell the chains apart - it happily uploads one on top of the other.
+//
+// To catch it, we compare the record IDs at the last idx both sides have. If they differ, the
+// chains have diverged, and we binary search for the first idx at which they do.
+
+use std::fmt;
+
+use atuin_common::record::{HostId, RecordId, RecordIdx, RecordStatus};
+
+use super::SyncError;
+use crate::record::{store::Store, transport::Transport};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub host: HostId,
+    pub tag: String,
+
+    /// The first idx at which the local and remote chains hold different records
+    pub idx: RecordIdx,
+
+    pub local: Option<RecordId>,
+    pub remote: Option<RecordId>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} from idx {}",
+            self.host.0.as_simple(),
+            self.tag,
+            self.idx
+        )
+    }
+}
+
+async fn local_id(
+    store: &impl Store,
+    host: HostId,
+    tag: &str,
+    idx: RecordIdx,
+) -> Result<Option<RecordId>, SyncError> {
+    let record = store
+        .idx(host, tag, idx)
+        .await
+        .map_err(|e| SyncError::LocalStoreError { msg: e.to_string() })?;
+
+    Ok(record.map(|r| r.id))
+}
+
+async fn remote_id(
+    transport: &dyn Transport,
+    host: HostId,
+    tag: &str,
+    idx: RecordIdx,
+) -> Result<Option<RecordId>, SyncError> {
+    let page = transport
+        .next_records(host, tag.to_string(), idx, 1)
+        .await
+        .map_err(|e| SyncError::RemoteRequestError { msg: e.to_string() })?;
+
+    Ok(page.into_iter().find(|r| r.idx == idx).map(|r| r.id))
+}
+
+/// Check every store both sides know about for divergent chains. Pass a host to only check the
+/// stores belonging to it - each check costs at least one request to the remote.
+pub async fn find_divergence(
+    store: &impl Store,
+    transport: &dyn Transport,
+    local: &RecordStatus,
+    remote: &RecordStatus,
+    host: Option<HostId>,
+) -> Result<Vec<Divergence>, SyncError> {
+    let mut ret = Vec::new();
+
+    for (h, tags) in local.hosts.iter() {
+        if host.is_some_and(|host| host != *h) {
+            continue;
+        }
+
+        for (tag, local_idx) in tags.iter() {
+            let Some(remote_idx) = remote.get(*h, tag.clone()) else {
+                continue;
+            };
+
+            let common = remote_idx.min(*local_idx);
+
+            let local_tail = local_id(store, *h, tag, common).await?;
+            let remote_tail = remote_id(transport, *h, tag, common).await?;
+
+            if local_tail == remote_tail {
+                continue;
+            }
+
+            // The chains differ at `common`. Find the first idx they differ at, assuming that
+            // once they have diverged they stay that way.
+            let (mut lo, mut hi) = (0, common);
+            let (mut local_first, mut remote_first) = (local_tail, remote_tail);
+
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+
+                let l = local_id(store, *h, tag, mid).await?;
+                let r = remote_id(transport, *h, tag, mid).await?;
+
+                if l == r {
+                    lo = mid + 1;
+                } else {
+                    hi = mid;
+                    (local_first, remote_first) = (l, r);
+                }
+            }
+
+            warn!(
+                "record store for {h:?}/{tag} has diverged from the remote at idx {lo}: local {local_first:?}, remote {remote_first:?}"
+            );
+
+            ret.push(Divergence {
+                host: *h,
+                tag: tag.clone(),
+                idx: lo,
+                local: local_first,
+                remote: remote_first,
+            });
+        }
+    }
+
+    ret.sort_by(|a, b| (a.host, &a.tag).cmp(&(b.host, &b.tag)));
+
+    Ok(ret)
+}
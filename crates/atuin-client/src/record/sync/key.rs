@@ -0,0 +1,110 @@
+// Logging in on a second machine with a fresh key, rather than the one the first machine uses, is
+// an easy mistake to make. Left alone, the new machine uploads records nobody else can read, and
+// every record it downloads fails to decrypt with an error about key IDs.
+//
+// So before syncing, we check that we can read at least some of what other hosts have written.
+// Once we have downloaded anything from another host the check is local, and cheap. Until then,
+// we sample the first record of a few of the remote's stores.
+
+use atuin_common::record::{EncryptedData, HostId, Record, RecordStatus};
+
+use super::SyncError;
+use crate::{
+    encryption::Keys,
+    record::{encryption::PASETO_V4, store::Store, transport::Transport},
+};
+
+const MAX_SAMPLES: usize = 8;
+
+/// The stores written by other hosts, that this transport syncs
+fn other_stores(
+    status: &RecordStatus,
+    transport: &dyn Transport,
+    host: HostId,
+) -> Vec<(HostId, String)> {
+    let mut stores = status
+        .hosts
+        .iter()
+        .filter(|(h, _)| **h != host)
+        .flat_map(|(h, tags)| tags.keys().map(|tag| (*h, tag.clone())))
+        .filter(|(_, tag)| transport.syncs_tag(tag))
+        .collect::<Vec<_>>();
+
+    // Stable, so we sample the same stores every time
+    stores.sort_by_key(|(h, tag)| (*h, tag.clone()));
+    stores.truncate(MAX_SAMPLES);
+
+    stores
+}
+
+async fn samples(
+    store: &impl Store,
+    transport: &dyn Transport,
+    host: HostId,
+) -> Result<Vec<Record<EncryptedData>>, SyncError> {
+    let local = store
+        .status()
+        .await
+        .map_err(|e| SyncError::LocalStoreError { msg: e.to_string() })?;
+
+    let stores = other_stores(&local, transport, host);
+
+    if !stores.is_empty() {
+        let mut samples = Vec::with_capacity(stores.len());
+
+        for (h, tag) in stores {
+            let record = store
+                .first(h, &tag)
+                .await
+                .map_err(|e| SyncError::LocalStoreError { msg: e.to_string() })?;
+
+            samples.extend(record);
+        }
+
+        return Ok(samples);
+    }
+
+    let remote = transport
+        .record_status()
+        .await
+        .map_err(|e| SyncError::RemoteRequestError { msg: e.to_string() })?;
+
+    let mut samples = Vec::new();
+
+    for (h, tag) in other_stores(&remote, transport, host) {
+        let page = transport
+            .next_records(h, tag, 0, 1)
+            .await
+            .map_err(|e| SyncError::RemoteRequestError { msg: e.to_string() })?;
+
+        samples.extend(page);
+    }
+
+    Ok(samples)
+}
+
+/// Make sure our keys can read what other hosts have synced. Fails with `SyncError::WrongKey` if
+/// there is something to read, and none of it decrypts.
+pub async fn check_key(
+    store: &impl Store,
+    transport: &dyn Transport,
+    keys: &Keys,
+    host: HostId,
+) -> Result<(), SyncError> {
+    let samples = samples(store, transport, host).await?;
+
+    if samples.is_empty() {
+        return Ok(());
+    }
+
+    let readable = samples.into_iter().any(|record| {
+        let key = *keys.get(&record.tag);
+        record.decrypt::<PASETO_V4>(&key).is_ok()
+    });
+
+    if readable {
+        Ok(())
+    } else {
+        Err(SyncError::WrongKey)
+    }
+}
@@ -0,0 +1,134 @@
+// Sync reports what it is doing as a stream of events, one transfer (host, tag) at a time. The CLI
+// turns them into progress bars, the daemon into logs.
+
+use std::{fmt::Write, sync::Mutex};
+
+use atuin_common::record::{EncryptedData, HostId, Record};
+use indicatif::{HumanBytes, ProgressBar, ProgressState, ProgressStyle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Upload,
+    Download,
+}
+
+/// How far along the transfer of a single (host, tag) store is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub direction: Direction,
+    pub host: HostId,
+    pub tag: String,
+
+    /// Records transferred so far. When resuming, this includes those transferred before the
+    /// interruption.
+    pub done: u64,
+    pub total: u64,
+
+    /// Size of the encrypted record data transferred by this sync
+    pub bytes: u64,
+}
+
+impl Transfer {
+    pub(crate) fn add_page(&mut self, page: &[Record<EncryptedData>]) {
+        self.done += page.len() as u64;
+        self.bytes += page
+            .iter()
+            .map(|r| (r.data.data.len() + r.data.content_encryption_key.len()) as u64)
+            .sum::<u64>();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncEvent {
+    Started(Transfer),
+
+    /// A download that was interrupted by a previous sync is being picked up where it left off
+    Resumed(Transfer),
+
+    Progress(Transfer),
+    Finished(Transfer),
+}
+
+pub trait SyncProgress: Send + Sync {
+    fn event(&self, event: &SyncEvent);
+}
+
+/// Ignore all progress
+pub struct NoProgress;
+
+impl SyncProgress for NoProgress {
+    fn event(&self, _: &SyncEvent) {}
+}
+
+/// Print a progress bar for each transfer
+#[derive(Default)]
+pub struct ProgressBars {
+    bar: Mutex<Option<ProgressBar>>,
+}
+
+impl ProgressBars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn bar(total: u64) -> ProgressBar {
+        let pb = ProgressBar::new(total);
+        pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {human_pos}/{human_len} {msg} ({eta})")
+            .unwrap()
+            .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+            .progress_chars("#>-"));
+
+        pb
+    }
+}
+
+impl SyncProgress for ProgressBars {
+    fn event(&self, event: &SyncEvent) {
+        let mut bar = self.bar.lock().expect("progress bar lock poisoned");
+
+        match event {
+            SyncEvent::Started(t) | SyncEvent::Resumed(t) => {
+                if matches!(event, SyncEvent::Resumed(_)) {
+                    println!(
+                        "Resuming interrupted download from {}/{}, {} of {} records already downloaded",
+                        t.host.0.as_simple(),
+                        t.tag,
+                        t.done,
+                        t.total
+                    );
+                } else {
+                    let (verb, preposition) = match t.direction {
+                        Direction::Upload => ("Uploading", "to"),
+                        Direction::Download => ("Downloading", "from"),
+                    };
+
+                    println!(
+                        "{verb} {} records {preposition} {}/{}",
+                        t.total,
+                        t.host.0.as_simple(),
+                        t.tag
+                    );
+                }
+
+                let pb = Self::bar(t.total);
+                pb.set_position(t.done);
+                pb.reset_eta();
+                *bar = Some(pb);
+            }
+
+            SyncEvent::Progress(t) => {
+                if let Some(pb) = bar.as_ref() {
+                    pb.set_position(t.done);
+                    pb.set_message(HumanBytes(t.bytes).to_string());
+                }
+            }
+
+            SyncEvent::Finished(t) => {
+                if let Some(pb) = bar.take() {
+                    pb.set_position(t.done);
+                    pb.finish_with_message(HumanBytes(t.bytes).to_string());
+                }
+            }
+        }
+    }
+}
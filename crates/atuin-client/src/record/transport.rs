@@ -0,0 +1,112 @@
+// A transport is anything we can sync the record store with. Usually that's the sync server, but
+// it could just as well be a directory on a shared filesystem.
+// Records are always encrypted before they reach a transport, so a transport never needs to know
+// about keys.
+
+use async_trait::async_trait;
+use eyre::{Result, bail};
+
+use atuin_common::record::{EncryptedData, HostId, Record, RecordIdx, RecordStatus};
+
+use crate::{api_client::Client, settings::Settings, team};
+
+pub mod directory;
+
+pub use directory::DirectoryTransport;
+
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Get the status (host -> tag -> tail idx) of the remote store
+    async fn record_status(&self) -> Result<RecordStatus>;
+
+    /// Upload a page of records. They should all be for the same host and tag, and in order.
+    async fn post_records(&self, records: &[Record<EncryptedData>]) -> Result<()>;
+
+    /// Get the next `count` records, after and including the given index
+    async fn next_records(
+        &self,
+        host: HostId,
+        tag: String,
+        start: RecordIdx,
+        count: u64,
+    ) -> Result<Vec<Record<EncryptedData>>>;
+
+    /// Delete every record held by the remote
+    async fn delete_store(&self) -> Result<()>;
+
+    /// Whether records with this tag belong on the remote. Team records are only ever synced
+    /// with their team.
+    fn syncs_tag(&self, tag: &str) -> bool {
+        team::team(tag).is_none()
+    }
+}
+
+#[async_trait]
+impl Transport for Client<'_> {
+    async fn record_status(&self) -> Result<RecordStatus> {
+        Client::record_status(self).await
+    }
+
+    async fn post_records(&self, records: &[Record<EncryptedData>]) -> Result<()> {
+        Client::post_records(self, records).await
+    }
+
+    async fn next_records(
+        &self,
+        host: HostId,
+        tag: String,
+        start: RecordIdx,
+        count: u64,
+    ) -> Result<Vec<Record<EncryptedData>>> {
+        Client::next_records(self, host, tag, start, count).await
+    }
+
+    async fn delete_store(&self) -> Result<()> {
+        if let Some(team) = self.team() {
+            bail!("the store of team {team} can't be deleted");
+        }
+
+        Client::delete_store(self).await
+    }
+
+    fn syncs_tag(&self, tag: &str) -> bool {
+        team::team(tag) == self.team()
+    }
+}
+
+/// Build the transport configured in the settings. If `sync.directory` is set we sync with that
+/// directory, otherwise with the sync server.
+pub fn from_settings(settings: &Settings) -> Result<Box<dyn Transport + '_>> {
+    from_settings_with_timeout(settings, settings.network_timeout)
+}
+
+/// As `from_settings`, but with a custom network timeout. Only relevant for the sync server.
+pub fn from_settings_with_timeout(
+    settings: &Settings,
+    timeout: u64,
+) -> Result<Box<dyn Transport + '_>> {
+    if let Some(dir) = &settings.sync.directory {
+        return Ok(Box::new(DirectoryTransport::new(dir)));
+    }
+
+    let client = Client::new(
+        &settings.sync_address,
+        settings.session_token()?.as_str(),
+        settings.network_connect_timeout,
+        timeout,
+    )?;
+
+    Ok(Box::new(client))
+}
+
+/// The transport for a team's store on the sync server
+pub fn for_team<'a>(settings: &'a Settings, team: &str) -> Result<Client<'a>> {
+    let client = Client::new(
+        &settings.sync_address,
+        settings.session_token()?.as_str(),
+        settings.network_connect_timeout,
+        settings.network_timeout,
+    )?;
+
+    Ok(client.with_team(team))
+}
@@ -0,0 +1,364 @@
+// Sync records via a plain directory - eg an NFS mount, a Syncthing folder, or a USB stick.
+// No server is involved, so anyone who can see the directory can see the (encrypted) records.
+//
+// The layout is
+//
+//   <root>/<host id>/<tag>/<chunk>.json
+//
+// where each chunk file holds up to CHUNK_SIZE records, ordered by idx. Chunk n holds the records
+// with idx in [n * CHUNK_SIZE, (n + 1) * CHUNK_SIZE). A host only ever appends to its own
+// directory, so several machines can share the same root without stepping on each other.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use async_trait::async_trait;
+use eyre::{Result, WrapErr, bail};
+use itertools::Itertools;
+use tokio::fs;
+use uuid::Uuid;
+
+use atuin_common::record::{EncryptedData, HostId, Record, RecordIdx, RecordStatus};
+
+use super::Transport;
+
+pub const CHUNK_SIZE: u64 = 1000;
+const CHUNK_EXTENSION: &str = "json";
+
+#[derive(Debug, Clone)]
+pub struct DirectoryTransport {
+    root: PathBuf,
+}
+
+impl DirectoryTransport {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn tag_dir(&self, host: HostId, tag: &str) -> PathBuf {
+        self.root
+            .join(host.0.as_simple().to_string())
+            .join(urlencoding::encode(tag).as_ref())
+    }
+
+    fn chunk_path(&self, host: HostId, tag: &str, chunk: u64) -> PathBuf {
+        self.tag_dir(host, tag)
+            .join(format!("{chunk:010}.{CHUNK_EXTENSION}"))
+    }
+
+    async fn read_chunk(path: &Path) -> Result<Vec<Record<EncryptedData>>> {
+        let data = match fs::read(path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).wrap_err_with(|| format!("failed to read chunk {path:?}")),
+        };
+
+        serde_json::from_slice(&data).wrap_err_with(|| format!("failed to parse chunk {path:?}"))
+    }
+
+    // Write to a temporary file first, and then rename over the top. Whatever is syncing the
+    // directory should then only ever see complete chunks.
+    async fn write_chunk(path: &Path, records: &[Record<EncryptedData>]) -> Result<()> {
+        let dir = path.parent().expect("chunk path always has a parent");
+        fs::create_dir_all(dir).await?;
+
+        let file_name = path
+            .file_name()
+            .expect("chunk path always has a file name")
+            .to_string_lossy();
+        let tmp = dir.join(format!(".{file_name}.{}.tmp", Uuid::new_v4().as_simple()));
+
+        fs::write(&tmp, serde_json::to_vec(records)?).await?;
+        fs::rename(&tmp, path)
+            .await
+            .wrap_err_with(|| format!("failed to write chunk {path:?}"))?;
+
+        Ok(())
+    }
+
+    // The chunk numbers present in a tag directory, in ascending order
+    async fn chunks(dir: &Path) -> Result<Vec<u64>> {
+        let mut entries = match fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut chunks = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+
+            if path.extension().and_then(|e| e.to_str()) != Some(CHUNK_EXTENSION) {
+                continue;
+            }
+
+            if let Some(chunk) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                chunks.push(chunk);
+            }
+        }
+
+        chunks.sort_unstable();
+
+        Ok(chunks)
+    }
+
+    // Subdirectories of a directory, along with their name. Anything else is ignored.
+    async fn subdirs(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let mut entries = match fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut dirs = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_dir() {
+                continue;
+            }
+
+            if let Some(name) = entry.file_name().to_str() {
+                dirs.push((name.to_string(), entry.path()));
+            }
+        }
+
+        Ok(dirs)
+    }
+}
+
+#[async_trait]
+impl Transport for DirectoryTransport {
+    async fn record_status(&self) -> Result<RecordStatus> {
+        let mut status = RecordStatus::new();
+
+        for (host, host_dir) in Self::subdirs(&self.root).await? {
+            let Ok(host) = Uuid::from_str(&host) else {
+                debug!("skipping unknown directory {host_dir:?} in sync directory");
+                continue;
+            };
+
+            for (tag, tag_dir) in Self::subdirs(&host_dir).await? {
+                let tag = urlencoding::decode(&tag)?.into_owned();
+
+                let Some(last) = Self::chunks(&tag_dir).await?.pop() else {
+                    continue;
+                };
+
+                let records =
+                    Self::read_chunk(&tag_dir.join(format!("{last:010}.{CHUNK_EXTENSION}")))
+                        .await?;
+
+                if let Some(tail) = records.last() {
+                    status.set_raw(HostId(host), tag, tail.idx);
+                }
+            }
+        }
+
+        Ok(status)
+    }
+
+    async fn post_records(&self, records: &[Record<EncryptedData>]) -> Result<()> {
+        let groups = records
+            .iter()
+            .into_group_map_by(|r| (r.host.id, r.tag.clone(), r.idx / CHUNK_SIZE));
+
+        for ((host, tag, chunk), records) in groups {
+            let path = self.chunk_path(host, &tag, chunk);
+
+            let mut existing: BTreeMap<RecordIdx, Record<EncryptedData>> = Self::read_chunk(&path)
+                .await?
+                .into_iter()
+                .map(|r| (r.idx, r))
+                .collect();
+
+            for record in records {
+                match existing.get(&record.idx) {
+                    // Already have it. Uploads are retried, so this is fine.
+                    Some(r) if r.id == record.id => continue,
+
+                    Some(r) => bail!(
+                        "record {}/{}/{} already exists in the sync directory with a different id ({} != {})",
+                        host.0.as_simple(),
+                        tag,
+                        record.idx,
+                        r.id.0.as_hyphenated(),
+                        record.id.0.as_hyphenated()
+                    ),
+
+                    None => {
+                        existing.insert(record.idx, record.clone());
+                    }
+                }
+            }
+
+            let records = existing.into_values().collect::<Vec<_>>();
+            Self::write_chunk(&path, &records).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn next_records(
+        &self,
+        host: HostId,
+        tag: String,
+        start: RecordIdx,
+        count: u64,
+    ) -> Result<Vec<Record<EncryptedData>>> {
+        let mut ret = Vec::new();
+        let mut chunk = start / CHUNK_SIZE;
+
+        while (ret.len() as u64) < count {
+            let records = Self::read_chunk(&self.chunk_path(host, &tag, chunk)).await?;
+
+            if records.is_empty() {
+                break;
+            }
+
+            ret.extend(
+                records
+                    .into_iter()
+                    .filter(|r| r.idx >= start)
+                    .take((count as usize).saturating_sub(ret.len())),
+            );
+
+            chunk += 1;
+        }
+
+        Ok(ret)
+    }
+
+    async fn delete_store(&self) -> Result<()> {
+        // Only remove what we put there. The root itself may well be a mount point.
+        for (host, host_dir) in Self::subdirs(&self.root).await? {
+            if Uuid::from_str(&host).is_ok() {
+                fs::remove_dir_all(host_dir).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_common::record::{self, EncryptedData, Host, HostId, Record};
+    use pretty_assertions::assert_eq;
+
+    use super::{CHUNK_SIZE, DirectoryTransport};
+    use crate::record::transport::Transport;
+
+    fn test_records(n: u64) -> Vec<Record<EncryptedData>> {
+        record::test_records(&Host::new(HostId(atuin_common::utils::uuid_v7())), n)
+    }
+
+    #[tokio::test]
+    async fn empty_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path().join("does-not-exist"));
+
+        let status = transport.record_status().await.unwrap();
+
+        assert!(status.hosts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn push_and_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path());
+        let records = test_records(CHUNK_SIZE + 10);
+
+        for page in records.chunks(100) {
+            transport.post_records(page).await.unwrap();
+        }
+
+        let status = transport.record_status().await.unwrap();
+        let host = records[0].host.id;
+
+        assert_eq!(
+            status.get(host, "history".to_string()),
+            Some(CHUNK_SIZE + 9)
+        );
+    }
+
+    #[tokio::test]
+    async fn next_across_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path());
+        let records = test_records(CHUNK_SIZE + 10);
+        let host = records[0].host.id;
+
+        transport.post_records(&records).await.unwrap();
+
+        let page = transport
+            .next_records(host, "history".to_string(), CHUNK_SIZE - 5, 100)
+            .await
+            .unwrap();
+
+        assert_eq!(page, records[(CHUNK_SIZE - 5) as usize..].to_vec());
+
+        let page = transport
+            .next_records(host, "history".to_string(), 0, 10)
+            .await
+            .unwrap();
+
+        assert_eq!(page, records[..10].to_vec());
+    }
+
+    #[tokio::test]
+    async fn reupload_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path());
+        let records = test_records(10);
+        let host = records[0].host.id;
+
+        transport.post_records(&records).await.unwrap();
+        transport.post_records(&records[5..]).await.unwrap();
+
+        let page = transport
+            .next_records(host, "history".to_string(), 0, 100)
+            .await
+            .unwrap();
+
+        assert_eq!(page, records);
+    }
+
+    #[tokio::test]
+    async fn conflicting_upload_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path());
+        let records = test_records(1);
+
+        transport.post_records(&records).await.unwrap();
+
+        let mut conflict = records[0].clone();
+        conflict.id = atuin_common::record::RecordId(atuin_common::utils::uuid_v7());
+
+        assert!(transport.post_records(&[conflict]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn delete_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = DirectoryTransport::new(dir.path());
+
+        transport.post_records(&test_records(10)).await.unwrap();
+        std::fs::write(dir.path().join("README"), "not ours").unwrap();
+
+        transport.delete_store().await.unwrap();
+
+        let status = transport.record_status().await.unwrap();
+        assert!(status.hosts.is_empty());
+        assert!(dir.path().join("README").exists());
+    }
+}
@@ -9,9 +9,16 @@ pub async fn register(
     username: String,
     email: String,
     password: String,
+    invite: Option<String>,
 ) -> Result<String> {
-    let session =
-        api_client::register(settings.sync_address.as_str(), &username, &email, &password).await?;
+    let session = api_client::register(
+        settings.sync_address.as_str(),
+        &username,
+        &email,
+        &password,
+        invite.as_deref(),
+    )
+    .await?;
 
     let path = settings.session_path.as_str();
     let mut file = File::create(path).await?;
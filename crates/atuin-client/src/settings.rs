@@ -27,6 +27,7 @@ pub const LAST_SYNC_FILENAME: &str = "last_sync_time";
 pub const LAST_VERSION_CHECK_FILENAME: &str = "last_version_check_time";
 pub const LATEST_VERSION_FILENAME: &str = "latest_version";
 pub const HOST_ID_FILENAME: &str = "host_id";
+pub const SYNC_CHECKPOINT_FILENAME: &str = "sync_checkpoint.json";
 static EXAMPLE_CONFIG: &str = include_str!("../config.toml");
 
 mod dotfiles;
@@ -330,6 +331,11 @@ impl Default for Stats {
 #[derive(Clone, Debug, Deserialize, Default, Serialize)]
 pub struct Sync {
     pub records: bool,
+
+    /// Sync records with this directory rather than the sync server. Useful for shared filesystems,
+    /// or syncing with tools like Syncthing.
+    #[serde(default)]
+    pub directory: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Default, Serialize)]
@@ -340,6 +346,36 @@ pub struct Keys {
     pub prefix: String,
 }
 
+/// Changes to the keybindings of the interactive search, as action name to the key chords that
+/// trigger it. Actions that are not listed keep their default keys.
+#[derive(Clone, Debug, Deserialize, Default, Serialize)]
+pub struct Keymap {
+    #[serde(default)]
+    pub emacs: HashMap<String, Vec<String>>,
+
+    #[serde(default)]
+    pub vim_normal: HashMap<String, Vec<String>>,
+
+    #[serde(default)]
+    pub vim_insert: HashMap<String, Vec<String>>,
+
+    /// Keys in the inspector tab, whatever the keymap mode
+    #[serde(default)]
+    pub inspector: HashMap<String, Vec<String>>,
+
+    /// Keys in the directories tab, whatever the keymap mode
+    #[serde(default)]
+    pub directories: HashMap<String, Vec<String>>,
+
+    /// Keys in the commands tab, whatever the keymap mode
+    #[serde(default)]
+    pub commands: HashMap<String, Vec<String>>,
+
+    /// Keys pressed after the prefix (ctrl-a by default), as in screen or tmux
+    #[serde(default)]
+    pub prefix: HashMap<String, Vec<String>>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Preview {
     pub strategy: PreviewStrategy,
@@ -383,6 +419,37 @@ pub struct Search {
     pub filters: Vec<FilterMode>,
 }
 
+/// How search results are ranked when frecency is enabled. Each weight scales one part of the
+/// score; set it to 0 to leave that part out.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Frecency {
+    /// Rank results by frecency, rather than by how recently they were run
+    pub enabled: bool,
+
+    /// How long it takes for the recency of a command to halve, eg "1w"
+    pub half_life: String,
+
+    pub recency: f64,
+    pub frequency: f64,
+
+    /// Boost for commands that were run in the current directory
+    pub directory: f64,
+
+    /// Boost for commands that were run in the current git repository
+    pub workspace: f64,
+
+    /// How much to hold a command's failures against it, from 0 (not at all) to 1, where a
+    /// command that always failed scores nothing
+    pub failure: f64,
+}
+
+impl Frecency {
+    pub fn half_life(&self) -> Result<std::time::Duration> {
+        parse_duration(&self.half_life)
+            .map_err(|e| eyre!("invalid frecency.half_life {:?}: {e}", self.half_life))
+    }
+}
+
 impl Default for Preview {
     fn default() -> Self {
         Self {
@@ -413,6 +480,20 @@ impl Default for Daemon {
     }
 }
 
+impl Default for Frecency {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            half_life: "1w".to_string(),
+            recency: 1.0,
+            frequency: 1.0,
+            directory: 0.5,
+            workspace: 0.25,
+            failure: 0.5,
+        }
+    }
+}
+
 impl Default for Search {
     fn default() -> Self {
         Self {
@@ -455,6 +536,7 @@ pub struct Settings {
     pub db_path: String,
     pub record_store_path: String,
     pub key_path: String,
+    pub tag_keys_path: String,
     pub session_path: String,
     pub search_mode: SearchMode,
     pub filter_mode: Option<FilterMode>,
@@ -465,6 +547,8 @@ pub struct Settings {
     pub inline_height_shell_up_key_binding: Option<u16>,
     pub invert: bool,
     pub show_preview: bool,
+    pub show_details: bool,
+    pub syntax_highlighting: bool,
     pub max_preview_height: u16,
     pub show_help: bool,
     pub show_tabs: bool,
@@ -495,6 +579,7 @@ pub struct Settings {
     pub local_timeout: f64,
     pub enter_accept: bool,
     pub smart_sort: bool,
+    pub predict_next: bool,
     pub command_chaining: bool,
 
     #[serde(default)]
@@ -506,6 +591,9 @@ pub struct Settings {
     #[serde(default)]
     pub keys: Keys,
 
+    #[serde(default)]
+    pub keymap: Keymap,
+
     #[serde(default)]
     pub preview: Preview,
 
@@ -518,6 +606,9 @@ pub struct Settings {
     #[serde(default)]
     pub search: Search,
 
+    #[serde(default)]
+    pub frecency: Frecency,
+
     #[serde(default)]
     pub theme: Theme,
 
@@ -617,8 +708,13 @@ impl Settings {
         Some(HostId(uuid))
     }
 
+    /// Replace the host ID of this machine. Records written from now on belong to the new host.
+    pub fn set_host_id(id: HostId) -> Result<()> {
+        Settings::save_to_data_dir(HOST_ID_FILENAME, id.0.as_simple().to_string().as_ref())
+    }
+
     pub fn should_sync(&self) -> Result<bool> {
-        if !self.auto_sync || !PathBuf::from(self.session_path.as_str()).exists() {
+        if !self.auto_sync || !self.can_sync() {
             return Ok(false);
         }
 
@@ -641,6 +737,12 @@ impl Settings {
         PathBuf::from(session_path).exists()
     }
 
+    /// Whether we have somewhere to sync to - either we are logged in to a sync server, or we are
+    /// syncing records via a directory.
+    pub fn can_sync(&self) -> bool {
+        self.logged_in() || (self.sync.records && self.sync.directory.is_some())
+    }
+
     pub fn session_token(&self) -> Result<String> {
         if !self.logged_in() {
             return Err(eyre!("Tried to load session; not logged in"));
@@ -747,6 +849,7 @@ impl Settings {
         let socket_path = atuin_common::utils::runtime_dir().join("atuin.sock");
 
         let key_path = data_dir.join("key");
+        let tag_keys_path = data_dir.join("keys");
         let session_path = data_dir.join("session");
 
         Ok(Config::builder()
@@ -754,6 +857,7 @@ impl Settings {
             .set_default("db_path", db_path.to_str())?
             .set_default("record_store_path", record_store_path.to_str())?
             .set_default("key_path", key_path.to_str())?
+            .set_default("tag_keys_path", tag_keys_path.to_str())?
             .set_default("session_path", session_path.to_str())?
             .set_default("dialect", "us")?
             .set_default("timezone", "local")?
@@ -766,6 +870,8 @@ impl Settings {
             .set_default("style", "compact")?
             .set_default("inline_height", 40)?
             .set_default("show_preview", true)?
+            .set_default("show_details", false)?
+            .set_default("syntax_highlighting", true)?
             .set_default("preview.strategy", "auto")?
             .set_default("max_preview_height", 4)?
             .set_default("show_help", true)?
@@ -801,6 +907,7 @@ impl Settings {
             .set_default("keymap_mode_shell", "auto")?
             .set_default("keymap_cursor", HashMap::<String, String>::new())?
             .set_default("smart_sort", false)?
+            .set_default("predict_next", false)?
             .set_default("command_chaining", false)?
             .set_default("store_failed", true)?
             .set_default("daemon.sync_frequency", 300)?
@@ -808,6 +915,13 @@ impl Settings {
             .set_default("daemon.socket_path", socket_path.to_str())?
             .set_default("daemon.systemd_socket", false)?
             .set_default("daemon.tcp_port", 8889)?
+            .set_default("frecency.enabled", false)?
+            .set_default("frecency.half_life", "1w")?
+            .set_default("frecency.recency", 1.0)?
+            .set_default("frecency.frequency", 1.0)?
+            .set_default("frecency.directory", 0.5)?
+            .set_default("frecency.workspace", 0.25)?
+            .set_default("frecency.failure", 0.5)?
             .set_default("kv.db_path", kv_path.to_str())?
             .set_default("scripts.db_path", scripts_path.to_str())?
             .set_default(
@@ -873,7 +987,9 @@ impl Settings {
         settings.db_path = Self::expand_path(settings.db_path)?;
         settings.record_store_path = Self::expand_path(settings.record_store_path)?;
         settings.key_path = Self::expand_path(settings.key_path)?;
+        settings.tag_keys_path = Self::expand_path(settings.tag_keys_path)?;
         settings.session_path = Self::expand_path(settings.session_path)?;
+        settings.sync.directory = settings.sync.directory.map(Self::expand_path).transpose()?;
 
         Ok(settings)
     }
@@ -0,0 +1,127 @@
+// Teams share a record store on the sync server. A team's records live in the local store
+// alongside our own, tagged `<tag>@<team>`, and are encrypted with the team's key rather than
+// ours. Members pass the key around out of band, so the server never sees it.
+//
+// Having the team's key is what makes us a member locally - only teams we have a key for are
+// synced, and built into their own kv and script databases.
+
+use std::path::{Path, PathBuf};
+
+use eyre::{Result, bail};
+use fs_err as fs;
+
+use crate::settings::Settings;
+
+pub const SEPARATOR: char = '@';
+
+const MAX_NAME_LEN: usize = 24;
+
+/// The tag a team's records of the given kind are stored under
+pub fn tag(tag: &str, team: &str) -> String {
+    format!("{tag}{SEPARATOR}{team}")
+}
+
+/// The team a record tag belongs to, if any
+pub fn team(tag: &str) -> Option<&str> {
+    tag.split_once(SEPARATOR).map(|(_, team)| team)
+}
+
+/// The name the team's key is kept under, next to the tag keys
+pub fn key_name(team: &str) -> String {
+    format!("{SEPARATOR}{team}")
+}
+
+/// Team names have to be valid as part of a tag, a file name and a URL
+pub fn validate(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    if !valid {
+        bail!(
+            "{name:?} is not a valid team name: use at most {MAX_NAME_LEN} lowercase letters, digits and hyphens"
+        );
+    }
+
+    Ok(())
+}
+
+/// The teams we have a key for, by name
+pub fn joined(settings: &Settings) -> Result<Vec<String>> {
+    let dir = PathBuf::from(&settings.tag_keys_path);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut teams = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+
+        let Some(name) = entry.file_name().to_str().map(String::from) else {
+            continue;
+        };
+
+        let Some(team) = name.strip_prefix(SEPARATOR) else {
+            continue;
+        };
+
+        if validate(team).is_ok() && entry.file_type()?.is_file() {
+            teams.push(team.to_string());
+        }
+    }
+
+    teams.sort();
+
+    Ok(teams)
+}
+
+/// Each team gets its own copy of a database, next to ours. `kv.db` becomes `kv@team.db`.
+pub fn db_path(path: &str, team: &str) -> String {
+    let path = Path::new(path);
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}.{}", tag(&stem, team), ext.to_string_lossy()),
+        None => tag(&stem, team),
+    };
+
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{db_path, tag, team, validate};
+
+    #[test]
+    fn team_tags() {
+        assert_eq!(tag("kv", "ops"), "kv@ops");
+        assert_eq!(team("kv@ops"), Some("ops"));
+        assert_eq!(team("kv"), None);
+    }
+
+    #[test]
+    fn team_names() {
+        assert!(validate("ops").is_ok());
+        assert!(validate("platform-2").is_ok());
+
+        assert!(validate("").is_err());
+        assert!(validate("Ops").is_err());
+        assert!(validate("ops/../x").is_err());
+        assert!(validate(&"a".repeat(25)).is_err());
+    }
+
+    #[test]
+    fn team_databases() {
+        assert_eq!(db_path("/data/kv.db", "ops"), "/data/kv@ops.db");
+        assert_eq!(db_path("/data/scripts", "ops"), "/data/scripts@ops");
+    }
+}
@@ -23,6 +23,13 @@ pub struct RegisterRequest {
     pub email: String,
     pub username: String,
     pub password: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+
+    /// Required when the server is not open for registrations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,10 +65,188 @@ pub struct ChangePasswordRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChangePasswordResponse {}
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub username: String,
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetPasswordResponse {}
+
+/// The start of a single sign-on login. Show the user the verification URI and code, then poll
+/// with the device code.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcDeviceResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: Option<String>,
+
+    /// Seconds until the device code expires
+    pub expires_in: u64,
+
+    /// Seconds to wait between polls
+    pub interval: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcTokenRequest {
+    pub device_code: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+
+    /// Needed to create an account on servers that are not open for registration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum OidcTokenResponse {
+    /// The user has not finished logging in yet
+    Pending,
+
+    /// Polling too often. Wait longer between polls.
+    SlowDown,
+
+    Complete {
+        session: String,
+        username: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: i64,
+    pub name: Option<String>,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub created_at: Option<OffsetDateTime>,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+
+    /// This is the session making the request
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+/// What an API token is allowed to do. Sessions can do everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenScope {
+    /// Fetch the account's username, and its record store status
+    ReadStatus,
+
+    /// Download records
+    ReadRecords,
+
+    /// Upload records
+    WriteRecords,
+}
+
+impl TokenScope {
+    pub const ALL: [TokenScope; 3] = [
+        TokenScope::ReadStatus,
+        TokenScope::ReadRecords,
+        TokenScope::WriteRecords,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::ReadStatus => "read-status",
+            TokenScope::ReadRecords => "read-records",
+            TokenScope::WriteRecords => "write-records",
+        }
+    }
+}
+
+impl std::fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for TokenScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TokenScope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| {
+                let valid = TokenScope::ALL.map(|scope| scope.as_str()).join(", ");
+                format!("unknown scope {s}, expected one of: {valid}")
+            })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTokenResponse {
+    pub id: i64,
+
+    /// The only time the token is shown
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokensResponse {
+    pub tokens: Vec<TokenResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTeamRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TeamsResponse {
+    /// The teams the user is a member of
+    pub teams: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TeamMembersResponse {
+    /// Usernames
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddTeamMemberRequest {
+    pub username: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
+
+    /// A name for the new session, so the user can tell their sessions apart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -278,6 +278,26 @@ impl Record<EncryptedData> {
     }
 }
 
+/// A host's worth of records with made up data, numbered from zero, for tests of whatever
+/// stores or moves them
+#[cfg(feature = "test-utils")]
+pub fn test_records(host: &Host, n: u64) -> Vec<Record<EncryptedData>> {
+    (0..n)
+        .map(|idx| {
+            Record::builder()
+                .host(host.clone())
+                .version("v1".into())
+                .tag("history".into())
+                .data(EncryptedData {
+                    data: format!("data {idx}"),
+                    content_encryption_key: String::new(),
+                })
+                .idx(idx)
+                .build()
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::record::{Host, HostId};
@@ -4,7 +4,9 @@ use serde::Serialize;
 use sysinfo::{Process, System, get_current_pid};
 use thiserror::Error;
 
-#[derive(PartialEq)]
+use crate::utils;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Shell {
     Sh,
     Bash,
@@ -62,6 +64,21 @@ impl Shell {
         Shell::from_string(shell.to_string())
     }
 
+    /// The shell whose integration ran atuin, if it said so. Unlike `current`, this is cheap.
+    pub fn from_env() -> Shell {
+        if utils::is_zsh() {
+            Shell::Zsh
+        } else if utils::is_bash() {
+            Shell::Bash
+        } else if utils::is_fish() {
+            Shell::Fish
+        } else if utils::is_xonsh() {
+            Shell::Xonsh
+        } else {
+            Shell::Unknown
+        }
+    }
+
     pub fn config_file(&self) -> Option<std::path::PathBuf> {
         let mut path = if let Some(base) = directories::BaseDirs::new() {
             base.home_dir().to_owned()
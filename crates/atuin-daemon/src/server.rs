@@ -1,13 +1,14 @@
 use eyre::WrapErr;
 
-use atuin_client::encryption;
-use atuin_client::history::store::HistoryStore;
+use atuin_client::encryption::Keys;
+use atuin_client::history::{HISTORY_TAG, store::HistoryStore};
 use atuin_client::record::sqlite_store::SqliteStore;
 use atuin_client::settings::Settings;
 #[cfg(unix)]
 use std::path::PathBuf;
 use std::sync::Arc;
 use time::OffsetDateTime;
+use tokio::sync::RwLock;
 use tracing::{Level, instrument};
 
 use atuin_client::database::{Database, Sqlite as HistoryDatabase};
@@ -27,12 +28,13 @@ pub struct HistoryService {
     // A store for WIP history
     // This is history that has not yet been completed, aka a command that's current running.
     running: Arc<DashMap<HistoryId, History>>,
-    store: HistoryStore,
+    // Replaced by the sync worker when the history key changes, eg after `atuin sync` recovers it
+    store: Arc<RwLock<HistoryStore>>,
     history_db: HistoryDatabase,
 }
 
 impl HistoryService {
-    pub fn new(store: HistoryStore, history_db: HistoryDatabase) -> Self {
+    pub fn new(store: Arc<RwLock<HistoryStore>>, history_db: HistoryDatabase) -> Self {
         Self {
             running: Arc::new(DashMap::new()),
             store,
@@ -114,10 +116,11 @@ impl HistorySvc for HistoryService {
                 "end history"
             );
 
-            let (id, idx) =
-                self.store.push(history).await.map_err(|e| {
-                    Status::internal(format!("failed to push record to store: {e:?}"))
-                })?;
+            let store = self.store.read().await.clone();
+            let (id, idx) = store
+                .push(history)
+                .await
+                .map_err(|e| Status::internal(format!("failed to push record to store: {e:?}")))?;
 
             let reply = EndHistoryReply {
                 id: id.0.to_string(),
@@ -250,12 +253,14 @@ pub async fn listen(
     store: SqliteStore,
     history_db: HistoryDatabase,
 ) -> Result<()> {
-    let encryption_key: [u8; 32] = encryption::load_key(&settings)
-        .context("could not load encryption key")?
-        .into();
+    let keys = Keys::load(&settings).context("could not load encryption key")?;
 
     let host_id = Settings::host_id().expect("failed to get host_id");
-    let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
+    let history_store = Arc::new(RwLock::new(HistoryStore::new(
+        store.clone(),
+        host_id,
+        *keys.get(HISTORY_TAG),
+    )));
 
     let history = HistoryService::new(history_store.clone(), history_db.clone());
 
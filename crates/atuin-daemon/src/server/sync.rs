@@ -1,29 +1,189 @@
+use std::sync::Arc;
+
 use eyre::Result;
 use rand::Rng;
-use tokio::time::{self, MissedTickBehavior};
+use tokio::{
+    sync::RwLock,
+    time::{self, MissedTickBehavior},
+};
 
 use atuin_client::database::Sqlite as HistoryDatabase;
 use atuin_client::{
-    encryption,
-    history::store::HistoryStore,
-    record::{sqlite_store::SqliteStore, sync},
+    encryption::Keys,
+    history::{HISTORY_TAG, store::HistoryStore},
+    record::{
+        sqlite_store::SqliteStore,
+        sync::{self, Checkpoint, SyncError, SyncEvent, SyncProgress},
+        transport,
+    },
     settings::Settings,
+    team,
 };
+use atuin_common::record::{HostId, RecordId};
+
+use atuin_dotfiles::store::{
+    AliasStore, CONFIG_SHELL_ALIAS_TAG,
+    var::{DOTFILES_VAR_TAG, VarStore},
+};
+use atuin_kv::store::{KvStore, record::KV_TAG};
+use atuin_scripts::store::{ScriptStore, script::SCRIPT_TAG};
+
+/// Report sync progress in the daemon log
+struct TracingProgress;
+
+impl SyncProgress for TracingProgress {
+    fn event(&self, event: &SyncEvent) {
+        match event {
+            SyncEvent::Started(t) => tracing::info!(
+                direction = ?t.direction,
+                host = %t.host.0.as_simple(),
+                tag = t.tag,
+                total = t.total,
+                "sync transfer started"
+            ),
+            SyncEvent::Resumed(t) => tracing::info!(
+                direction = ?t.direction,
+                host = %t.host.0.as_simple(),
+                tag = t.tag,
+                done = t.done,
+                total = t.total,
+                "resuming interrupted sync transfer"
+            ),
+            SyncEvent::Progress(t) => tracing::debug!(
+                direction = ?t.direction,
+                host = %t.host.0.as_simple(),
+                tag = t.tag,
+                done = t.done,
+                total = t.total,
+                bytes = t.bytes,
+                "sync transfer progress"
+            ),
+            SyncEvent::Finished(t) => tracing::info!(
+                direction = ?t.direction,
+                host = %t.host.0.as_simple(),
+                tag = t.tag,
+                done = t.done,
+                bytes = t.bytes,
+                "sync transfer finished"
+            ),
+        }
+    }
+}
+
+/// Sync our own store, then those of the teams we have joined
+async fn sync_all(
+    settings: &Settings,
+    store: &SqliteStore,
+    keys: &Keys,
+    host_id: HostId,
+) -> Result<(i64, Vec<RecordId>), SyncError> {
+    let transport = transport::from_settings(settings)
+        .map_err(|e| SyncError::OperationalError { msg: e.to_string() })?;
+
+    // `atuin sync` can walk the user through fixing a wrong key, we can only refuse
+    sync::check_key(store, transport.as_ref(), keys, host_id).await?;
+
+    let checkpoint = Checkpoint::in_data_dir();
+
+    let (mut uploaded, mut downloaded) = sync::sync_with(
+        transport.as_ref(),
+        store,
+        host_id,
+        &TracingProgress,
+        &checkpoint,
+    )
+    .await?;
+
+    let (up, mut down) = sync::sync_teams(
+        settings,
+        store,
+        keys,
+        host_id,
+        &TracingProgress,
+        &checkpoint,
+    )
+    .await?;
+    uploaded += up;
+    downloaded.append(&mut down);
+
+    Ok((uploaded, downloaded))
+}
+
+/// Rebuild the kv and script databases of every team we have joined, as `atuin sync` does
+async fn build_teams(
+    settings: &Settings,
+    store: &SqliteStore,
+    keys: &Keys,
+    host_id: HostId,
+) -> Result<()> {
+    for name in team::joined(settings)? {
+        let kv_db =
+            atuin_kv::database::Database::new(team::db_path(&settings.kv.db_path, &name), 1.0)
+                .await?;
+
+        KvStore::new(
+            store.clone(),
+            kv_db,
+            host_id,
+            *keys.get(&team::tag(KV_TAG, &name)),
+        )
+        .with_team(&name)
+        .build()
+        .await?;
 
-use atuin_dotfiles::store::{AliasStore, var::VarStore};
+        let script_db = atuin_scripts::database::Database::new(
+            team::db_path(&settings.scripts.db_path, &name),
+            1.0,
+        )
+        .await?;
+
+        ScriptStore::new(
+            store.clone(),
+            host_id,
+            *keys.get(&team::tag(SCRIPT_TAG, &name)),
+        )
+        .with_team(&name)
+        .build(script_db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Bring the local databases up to date with what was downloaded
+async fn build_all(
+    settings: &Settings,
+    store: &SqliteStore,
+    history_store: &HistoryStore,
+    history_db: &HistoryDatabase,
+    keys: &Keys,
+    host_id: HostId,
+    downloaded: &[RecordId],
+) -> Result<()> {
+    history_store
+        .incremental_build(history_db, downloaded)
+        .await?;
+
+    AliasStore::new(store.clone(), host_id, *keys.get(CONFIG_SHELL_ALIAS_TAG))
+        .build()
+        .await?;
+    VarStore::new(store.clone(), host_id, *keys.get(DOTFILES_VAR_TAG))
+        .build()
+        .await?;
+    build_teams(settings, store, keys, host_id).await?;
+
+    Ok(())
+}
 
 pub async fn worker(
     settings: Settings,
     store: SqliteStore,
-    history_store: HistoryStore,
+    shared_history_store: Arc<RwLock<HistoryStore>>,
     history_db: HistoryDatabase,
 ) -> Result<()> {
     tracing::info!("booting sync worker");
 
-    let encryption_key: [u8; 32] = encryption::load_key(&settings)?.into();
     let host_id = Settings::host_id().expect("failed to get host_id");
-    let alias_store = AliasStore::new(store.clone(), host_id, encryption_key);
-    let var_store = VarStore::new(store.clone(), host_id, encryption_key);
 
     // Don't backoff by more than 30 mins (with a random jitter of up to 1 min)
     let max_interval: f64 = 60.0 * 30.0 + rand::thread_rng().gen_range(0.0..60.0);
@@ -38,12 +198,46 @@ pub async fn worker(
         ticker.tick().await;
         tracing::info!("sync worker tick");
 
-        if !settings.logged_in() {
+        if !settings.can_sync() {
             tracing::debug!("not logged in, skipping sync tick");
             continue;
         }
 
-        let res = sync::sync(&settings, &store).await;
+        // Load the keys on every tick, so a key recovered with `atuin sync` is used without
+        // restarting the daemon
+        let keys = match Keys::load(&settings) {
+            Ok(keys) => keys,
+            Err(e) => {
+                tracing::error!("failed to load encryption keys: {e}");
+                continue;
+            }
+        };
+
+        // New history is pushed by the history service, which must use the same key
+        let history_store = HistoryStore::new(store.clone(), host_id, *keys.get(HISTORY_TAG));
+        *shared_history_store.write().await = history_store.clone();
+
+        let res = match sync_all(&settings, &store, &keys, host_id).await {
+            Ok((uploaded, downloaded)) => {
+                tracing::info!(
+                    uploaded = ?uploaded,
+                    downloaded = ?downloaded,
+                    "sync complete"
+                );
+
+                build_all(
+                    &settings,
+                    &store,
+                    &history_store,
+                    &history_db,
+                    &keys,
+                    host_id,
+                    &downloaded,
+                )
+                .await
+            }
+            Err(e) => Err(e.into()),
+        };
 
         if let Err(e) = res {
             tracing::error!("sync tick failed with {e}");
@@ -61,21 +255,6 @@ pub async fn worker(
 
             tracing::error!("backing off, next sync tick in {new_interval}");
         } else {
-            let (uploaded, downloaded) = res.unwrap();
-
-            tracing::info!(
-                uploaded = ?uploaded,
-                downloaded = ?downloaded,
-                "sync complete"
-            );
-
-            history_store
-                .incremental_build(&history_db, &downloaded)
-                .await?;
-
-            alias_store.build().await?;
-            var_store.build().await?;
-
             // Reset backoff on success
             if ticker.period().as_secs() != settings.daemon.sync_frequency {
                 ticker = time::interval(time::Duration::from_secs(settings.daemon.sync_frequency));
@@ -14,7 +14,7 @@ use atuin_client::record::store::Store;
 use crate::shell::Var;
 
 const DOTFILES_VAR_VERSION: &str = "v0";
-const DOTFILES_VAR_TAG: &str = "dotfiles-var";
+pub const DOTFILES_VAR_TAG: &str = "dotfiles-var";
 const DOTFILES_VAR_LEN: usize = 20000; // 20kb max total len, way more than should be needed.
 
 #[derive(Debug, Clone, PartialEq, Eq)]
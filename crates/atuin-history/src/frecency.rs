@@ -0,0 +1,211 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use atuin_client::{
+    history::{CommandUsage, History},
+    settings::Frecency,
+};
+use time::OffsetDateTime;
+
+use crate::sort::match_score;
+
+/// How a result scored, and what the score is made of. The weighted parts are added up, scaled
+/// by how well the command matches the query, and cut by its failures:
+///
+/// total = matched * (recency + frequency + directory + workspace) * (1 - failure)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Score {
+    pub total: f64,
+    pub matched: f64,
+    pub recency: f64,
+    pub frequency: f64,
+    pub directory: f64,
+    pub workspace: f64,
+
+    /// The share of the score lost to failures
+    pub failure: f64,
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>7.3} = match {:.2} * (recency {:.3} + frequency {:.3} + directory {:.2} + workspace {:.2}) * (1 - failure {:.2})",
+            self.total,
+            self.matched,
+            self.recency,
+            self.frequency,
+            self.directory,
+            self.workspace,
+            self.failure
+        )
+    }
+}
+
+pub struct Scorer<'a> {
+    weights: &'a Frecency,
+    half_life: f64,
+    now: OffsetDateTime,
+    usage: &'a HashMap<String, CommandUsage>,
+
+    /// The most any of the commands was run, which frequency is measured against
+    max_count: i64,
+}
+
+impl<'a> Scorer<'a> {
+    pub fn new(
+        weights: &'a Frecency,
+        half_life: Duration,
+        usage: &'a HashMap<String, CommandUsage>,
+        now: OffsetDateTime,
+    ) -> Self {
+        Self {
+            weights,
+            half_life: half_life.as_secs_f64().max(1.0),
+            now,
+            usage,
+            max_count: usage.values().map(|u| u.count).max().unwrap_or(0),
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn score(&self, query: &str, h: &History) -> Score {
+        let usage = self.usage.get(&h.command).cloned().unwrap_or_default();
+        let flag = |n: i64| if n > 0 { 1.0 } else { 0.0 };
+
+        // commands from the future are as recent as can be
+        let age = (self.now - h.timestamp).as_seconds_f64().max(0.0);
+        let recency = self.weights.recency * 0.5_f64.powf(age / self.half_life);
+
+        // the tenth run counts for less than the second
+        let frequency = if self.max_count > 0 {
+            self.weights.frequency * (usage.count as f64).ln_1p() / (self.max_count as f64).ln_1p()
+        } else {
+            0.0
+        };
+
+        let directory = self.weights.directory * flag(usage.here);
+        let workspace = self.weights.workspace * flag(usage.workspace);
+
+        let failure = if usage.count > 0 {
+            self.weights.failure.clamp(0.0, 1.0) * usage.failures as f64 / usage.count as f64
+        } else {
+            0.0
+        };
+
+        let matched = match_score(query, &h.command);
+
+        Score {
+            total: matched * (recency + frequency + directory + workspace) * (1.0 - failure),
+            matched,
+            recency,
+            frequency,
+            directory,
+            workspace,
+            failure,
+        }
+    }
+}
+
+/// Rank results by their score, best first. Results that score the same keep their order.
+pub fn rank(query: &str, input: Vec<History>, scorer: &Scorer) -> Vec<(Score, History)> {
+    let mut scored: Vec<(Score, History)> = input
+        .into_iter()
+        .map(|h| (scorer.score(query, &h), h))
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total.total_cmp(&a.0.total));
+    scored
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use atuin_client::{
+        history::{CommandUsage, History},
+        settings::Frecency,
+    };
+    use time::OffsetDateTime;
+
+    use super::{Scorer, rank};
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn history(command: &str, age: Duration) -> History {
+        History::capture()
+            .timestamp(OffsetDateTime::now_utc() - age)
+            .command(command)
+            .cwd("/")
+            .build()
+            .into()
+    }
+
+    fn usage(count: i64, here: i64, failures: i64) -> CommandUsage {
+        CommandUsage {
+            count,
+            here,
+            workspace: here,
+            failures,
+        }
+    }
+
+    fn ranked(usage: &HashMap<String, CommandUsage>, input: Vec<History>) -> Vec<String> {
+        let weights = Frecency::default();
+        let scorer = Scorer::new(&weights, 7 * DAY, usage, OffsetDateTime::now_utc());
+
+        rank("", input, &scorer)
+            .into_iter()
+            .map(|(_, h)| h.command)
+            .collect()
+    }
+
+    #[test]
+    fn recency_halves_every_half_life() {
+        let weights = Frecency::default();
+        let usage = HashMap::new();
+        let scorer = Scorer::new(&weights, 7 * DAY, &usage, OffsetDateTime::now_utc());
+
+        let score = scorer.score("", &history("ls", 14 * DAY));
+        assert!((score.recency - 0.25).abs() < 0.001);
+    }
+
+    #[test]
+    fn frequent_beats_recent() {
+        let usage = HashMap::from([
+            ("cargo build".to_string(), usage(50, 0, 0)),
+            ("vim notes.md".to_string(), usage(1, 0, 0)),
+        ]);
+
+        assert_eq!(
+            ranked(
+                &usage,
+                vec![
+                    history("vim notes.md", DAY),
+                    history("cargo build", 2 * DAY)
+                ]
+            ),
+            ["cargo build", "vim notes.md"]
+        );
+    }
+
+    #[test]
+    fn here_beats_elsewhere_and_failures_sink() {
+        let usage = HashMap::from([
+            ("make".to_string(), usage(10, 0, 0)),
+            ("make test".to_string(), usage(10, 5, 0)),
+            ("make deploy".to_string(), usage(10, 5, 10)),
+        ]);
+
+        assert_eq!(
+            ranked(
+                &usage,
+                vec![
+                    history("make deploy", DAY),
+                    history("make", DAY),
+                    history("make test", DAY)
+                ]
+            ),
+            ["make test", "make", "make deploy"]
+        );
+    }
+}
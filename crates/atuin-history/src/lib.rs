@@ -1,2 +1,4 @@
+pub mod frecency;
+pub mod predict;
 pub mod sort;
 pub mod stats;
@@ -0,0 +1,98 @@
+use atuin_client::history::CommandUsage;
+
+/// A command that might be run next, with how strongly history points to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+    pub command: String,
+    pub score: f64,
+
+    /// How many times it followed
+    pub count: i64,
+}
+
+/// Rank the commands that have followed the current one, most likely first. A command that
+/// followed it where we are now counts for more than one that followed it elsewhere, and one
+/// that failed for less.
+#[allow(clippy::cast_precision_loss)]
+pub fn predict(following: Vec<(String, CommandUsage)>) -> Vec<Prediction> {
+    let mut predictions: Vec<Prediction> = following
+        .into_iter()
+        .filter(|(_, usage)| usage.count > 0)
+        .map(|(command, usage)| {
+            let weight = (usage.count + 2 * usage.here + usage.workspace) as f64;
+            let failed = usage.failures as f64 / usage.count as f64;
+
+            Prediction {
+                command,
+                score: weight * (1.0 - 0.5 * failed),
+                count: usage.count,
+            }
+        })
+        .collect();
+
+    predictions.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.count.cmp(&a.count))
+            .then_with(|| a.command.cmp(&b.command))
+    });
+
+    predictions
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::history::CommandUsage;
+
+    use super::predict;
+
+    fn following(command: &str, count: i64, here: i64, failures: i64) -> (String, CommandUsage) {
+        (
+            command.to_string(),
+            CommandUsage {
+                count,
+                here,
+                workspace: 0,
+                failures,
+            },
+        )
+    }
+
+    fn commands(following: Vec<(String, CommandUsage)>) -> Vec<String> {
+        predict(following).into_iter().map(|p| p.command).collect()
+    }
+
+    #[test]
+    fn most_often_first() {
+        assert_eq!(
+            commands(vec![
+                following("git stash", 2, 0, 0),
+                following("git commit -v", 9, 0, 0),
+                following("git diff --staged", 4, 0, 0),
+            ]),
+            ["git commit -v", "git diff --staged", "git stash"]
+        );
+    }
+
+    #[test]
+    fn here_counts_for_more() {
+        assert_eq!(
+            commands(vec![
+                following("cargo test", 5, 0, 0),
+                following("make test", 3, 3, 0),
+            ]),
+            ["make test", "cargo test"]
+        );
+    }
+
+    #[test]
+    fn failures_count_for_less() {
+        assert_eq!(
+            commands(vec![
+                following("make deploy", 4, 0, 4),
+                following("make test", 3, 0, 0),
+            ]),
+            ["make test", "make deploy"]
+        );
+    }
+}
@@ -2,6 +2,19 @@ use atuin_client::history::History;
 
 type ScoredHistory = (f64, History);
 
+/// How well a command matches the query, from 1 (it doesn't contain it as is) to 2 (it starts
+/// with it)
+pub fn match_score(query: &str, command: &str) -> f64 {
+    // If history is _prefixed_ with the query, score it more highly
+    if command.starts_with(query) {
+        2.0
+    } else if command.contains(query) {
+        1.75
+    } else {
+        1.0
+    }
+}
+
 // Fuzzy search already comes sorted by minspan
 // This sorting should be applicable to all search modes, and solve the more "obvious" issues
 // first.
@@ -14,14 +27,7 @@ pub fn sort(query: &str, input: Vec<History>) -> Vec<History> {
     let mut scored = input
         .into_iter()
         .map(|h| {
-            // If history is _prefixed_ with the query, score it more highly
-            let score = if h.command.starts_with(query) {
-                2.0
-            } else if h.command.contains(query) {
-                1.75
-            } else {
-                1.0
-            };
+            let score = match_score(query, &h.command);
 
             // calculate how long ago the history was, in seconds
             let now = time::OffsetDateTime::now_utc().unix_timestamp();
@@ -4,6 +4,7 @@ use eyre::{Result, bail};
 
 use atuin_client::record::sqlite_store::SqliteStore;
 use atuin_client::record::{encryption::PASETO_V4, store::Store};
+use atuin_client::team;
 use atuin_common::record::{Host, HostId, Record, RecordId, RecordIdx};
 use entry::KvEntry;
 use record::{KV_TAG, KV_VERSION, KvRecord};
@@ -19,6 +20,9 @@ pub struct KvStore {
     pub kv_db: Database,
     pub host_id: HostId,
     pub encryption_key: [u8; 32],
+
+    /// The tag our records are stored under, which differs for a team's store
+    pub tag: String,
 }
 
 impl KvStore {
@@ -33,9 +37,17 @@ impl KvStore {
             kv_db,
             host_id,
             encryption_key,
+            tag: KV_TAG.to_string(),
         }
     }
 
+    /// Read and write a team's entries instead of our own. The kv database should be the team's
+    /// too - building the store replaces everything in it.
+    pub fn with_team(mut self, team: &str) -> Self {
+        self.tag = team::tag(KV_TAG, team);
+        self
+    }
+
     pub async fn set(&self, namespace: &str, key: &str, value: &str) -> Result<()> {
         let kv_record = KvRecord::builder()
             .namespace(namespace.to_string())
@@ -86,14 +98,14 @@ impl KvStore {
         let bytes = record.serialize()?;
         let idx = self
             .record_store
-            .last(self.host_id, KV_TAG)
+            .last(self.host_id, &self.tag)
             .await?
             .map_or(0, |p| p.idx + 1);
 
         let record = Record::builder()
             .host(Host::new(self.host_id))
             .version(KV_VERSION.to_string())
-            .tag(KV_TAG.to_string())
+            .tag(self.tag.clone())
             .idx(idx)
             .data(bytes)
             .build();
@@ -108,7 +120,7 @@ impl KvStore {
     }
 
     pub async fn build(&self) -> Result<()> {
-        let mut tagged = self.record_store.all_tagged(KV_TAG).await?;
+        let mut tagged = self.record_store.all_tagged(&self.tag).await?;
         tagged.reverse();
 
         let cached = self.kv_db.list(None).await?;
@@ -208,4 +220,32 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_team_kv_store() -> Result<()> {
+        let store = setup().await?;
+        let team_db = Database::new("sqlite::memory:", 1.0).await.unwrap();
+        let team = KvStore::new(
+            store.record_store.clone(),
+            team_db,
+            store.host_id,
+            store.encryption_key,
+        )
+        .with_team("ops");
+
+        store.set("test", "key", "mine").await?;
+        team.set("test", "key", "shared").await?;
+
+        assert_eq!(store.record_store.all_tagged(KV_TAG).await?.len(), 1);
+        assert_eq!(store.record_store.all_tagged("kv@ops").await?.len(), 1);
+
+        // Building either store leaves the other alone
+        store.build().await?;
+        team.build().await?;
+
+        assert_eq!(store.get("test", "key").await?, Some("mine".to_string()));
+        assert_eq!(team.get("test", "key").await?, Some("shared".to_string()));
+
+        Ok(())
+    }
 }
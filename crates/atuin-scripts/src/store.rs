@@ -2,6 +2,7 @@ use eyre::{Result, bail};
 
 use atuin_client::record::sqlite_store::SqliteStore;
 use atuin_client::record::{encryption::PASETO_V4, store::Store};
+use atuin_client::team;
 use atuin_common::record::{Host, HostId, Record, RecordId, RecordIdx};
 use record::ScriptRecord;
 use script::{SCRIPT_TAG, SCRIPT_VERSION, Script};
@@ -16,6 +17,9 @@ pub struct ScriptStore {
     pub store: SqliteStore,
     pub host_id: HostId,
     pub encryption_key: [u8; 32],
+
+    /// The tag our records are stored under, which differs for a team's store
+    pub tag: String,
 }
 
 impl ScriptStore {
@@ -24,21 +28,29 @@ impl ScriptStore {
             store,
             host_id,
             encryption_key,
+            tag: SCRIPT_TAG.to_string(),
         }
     }
 
+    /// Read and write a team's scripts instead of our own, which should be built into a database
+    /// of the team's
+    pub fn with_team(mut self, team: &str) -> Self {
+        self.tag = team::tag(SCRIPT_TAG, team);
+        self
+    }
+
     async fn push_record(&self, record: ScriptRecord) -> Result<(RecordId, RecordIdx)> {
         let bytes = record.serialize()?;
         let idx = self
             .store
-            .last(self.host_id, SCRIPT_TAG)
+            .last(self.host_id, &self.tag)
             .await?
             .map_or(0, |p| p.idx + 1);
 
         let record = Record::builder()
             .host(Host::new(self.host_id))
             .version(SCRIPT_VERSION.to_string())
-            .tag(SCRIPT_TAG.to_string())
+            .tag(self.tag.clone())
             .idx(idx)
             .data(bytes)
             .build();
@@ -71,7 +83,7 @@ impl ScriptStore {
     }
 
     pub async fn scripts(&self) -> Result<Vec<ScriptRecord>> {
-        let records = self.store.all_tagged(SCRIPT_TAG).await?;
+        let records = self.store.all_tagged(&self.tag).await?;
         let mut ret = Vec::with_capacity(records.len());
 
         for record in records.into_iter() {
@@ -11,7 +11,10 @@ use std::{
 
 use self::{
     calendar::{TimePeriod, TimePeriodInfo},
-    models::{History, NewHistory, NewSession, NewUser, Session, User},
+    models::{
+        ApiToken, History, Invite, NewApiToken, NewHistory, NewInvite, NewSession, NewUser,
+        NewWebhookDelivery, RecordUsage, Session, SessionInfo, Team, User, WebhookDelivery,
+    },
 };
 use async_trait::async_trait;
 use atuin_common::record::{EncryptedData, HostId, Record, RecordIdx, RecordStatus};
@@ -45,6 +48,7 @@ pub type DbResult<T> = Result<T, DbError>;
 pub enum DbType {
     Postgres,
     Sqlite,
+    S3,
     Unknown,
 }
 
@@ -59,6 +63,8 @@ impl DbSettings {
             DbType::Postgres
         } else if self.db_uri.starts_with("sqlite://") {
             DbType::Sqlite
+        } else if self.db_uri.starts_with("s3://") {
+            DbType::S3
         } else {
             DbType::Unknown
         }
@@ -68,7 +74,7 @@ impl DbSettings {
 // Do our best to redact passwords so they're not logged in the event of an error.
 impl Debug for DbSettings {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.db_type() == DbType::Postgres {
+        if matches!(self.db_type(), DbType::Postgres | DbType::S3) {
             let redacted_uri = url::Url::parse(&self.db_uri)
                 .map(|mut url| {
                     let _ = url.set_password(Some("****"));
@@ -94,6 +100,43 @@ pub trait Database: Sized + Clone + Send + Sync + 'static {
     async fn get_session_user(&self, token: &str) -> DbResult<User>;
     async fn add_session(&self, session: &NewSession) -> DbResult<()>;
 
+    /// Every session the user has, oldest first
+    async fn list_sessions(&self, user: &User) -> DbResult<Vec<SessionInfo>>;
+
+    /// Revoke one of the user's sessions. NotFound if it isn't theirs.
+    async fn delete_session(&self, user: &User, id: i64) -> DbResult<()>;
+
+    /// Note that a session was just used. This happens on every request, so implementations may
+    /// skip the write if it was used very recently.
+    async fn touch_session(&self, token: &str) -> DbResult<()>;
+
+    /// Returns the id of the new token
+    async fn add_api_token(&self, token: &NewApiToken) -> DbResult<i64>;
+    async fn get_api_token(&self, token_hash: &str) -> DbResult<ApiToken>;
+    async fn get_api_token_user(&self, token_hash: &str) -> DbResult<User>;
+    async fn list_api_tokens(&self, user: &User) -> DbResult<Vec<ApiToken>>;
+
+    /// Revoke one of the user's API tokens. NotFound if it isn't theirs.
+    async fn delete_api_token(&self, user: &User, id: i64) -> DbResult<()>;
+
+    /// Create the team, the user that owns its records, and make `member` its first member, all
+    /// in one transaction. Returns the id of the new team, or None if the name is taken.
+    async fn add_team(&self, name: &str, owner: &NewUser, member: i64) -> DbResult<Option<i64>>;
+    async fn get_team(&self, name: &str) -> DbResult<Team>;
+
+    /// A team the user is a member of. NotFound if they aren't, or it doesn't exist.
+    async fn get_user_team(&self, user: &User, name: &str) -> DbResult<Team>;
+
+    /// Every team the user is a member of, by name
+    async fn list_teams(&self, user: &User) -> DbResult<Vec<Team>>;
+
+    /// Adding somebody who is already a member does nothing
+    async fn add_team_member(&self, team_id: i64, user_id: i64) -> DbResult<()>;
+
+    /// NotFound if they weren't a member
+    async fn remove_team_member(&self, team_id: i64, user_id: i64) -> DbResult<()>;
+    async fn list_team_members(&self, team_id: i64) -> DbResult<Vec<User>>;
+
     async fn get_user(&self, username: &str) -> DbResult<User>;
     async fn get_user_session(&self, u: &User) -> DbResult<Session>;
     async fn add_user(&self, user: &NewUser) -> DbResult<i64>;
@@ -104,6 +147,36 @@ pub trait Database: Sized + Clone + Send + Sync + 'static {
 
     async fn update_user_password(&self, u: &User) -> DbResult<()>;
 
+    /// Every user, in the order they registered
+    async fn list_users(&self) -> DbResult<Vec<User>>;
+
+    /// A disabled user keeps their data, but can't log in or use any existing session
+    async fn set_user_disabled(&self, id: i64, disabled: bool) -> DbResult<()>;
+
+    /// Log the user out everywhere. Returns the number of sessions deleted.
+    async fn delete_user_sessions(&self, id: i64) -> DbResult<u64>;
+
+    /// Issue a new password reset token, replacing any the user already had
+    async fn password_reset_token(&self, id: i64, valid_for: Duration) -> DbResult<String>;
+
+    /// Consume a password reset token, returning the user it was issued to. Unknown and expired
+    /// tokens are NotFound.
+    async fn use_password_reset_token(&self, username: &str, token: &str) -> DbResult<User>;
+
+    /// The user linked to an identity at an OpenID Connect provider
+    async fn get_identity_user(&self, issuer: &str, subject: &str) -> DbResult<User>;
+    async fn add_user_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DbResult<()>;
+
+    async fn add_invite(&self, invite: &NewInvite) -> DbResult<()>;
+    async fn list_invites(&self) -> DbResult<Vec<Invite>>;
+    async fn delete_invite(&self, code: &str) -> DbResult<()>;
+
+    /// Use up one of an invite's uses. Unknown, expired and used up invites are NotFound.
+    async fn use_invite(&self, code: &str) -> DbResult<()>;
+
+    /// Give back a use taken by use_invite, if the registration it was for failed
+    async fn release_invite(&self, code: &str) -> DbResult<()>;
+
     async fn total_history(&self) -> DbResult<i64>;
     async fn count_history(&self, user: &User) -> DbResult<i64>;
     async fn count_history_cached(&self, user: &User) -> DbResult<i64>;
@@ -126,6 +199,35 @@ pub trait Database: Sized + Clone + Send + Sync + 'static {
     // Return the tail record ID for each store, so (HostID, Tag, TailRecordID)
     async fn status(&self, user: &User) -> DbResult<RecordStatus>;
 
+    async fn record_usage(&self, user: &User) -> DbResult<RecordUsage>;
+
+    /// Usage of every user, by user id. Users who have stored nothing may be missing.
+    async fn list_record_usage(&self) -> DbResult<HashMap<i64, RecordUsage>>;
+
+    /// Delete every record received before the given time, for all users. The tail of each store
+    /// is always kept - otherwise clients would think we had lost the store, and upload it again.
+    async fn delete_records_before(&self, before: OffsetDateTime) -> DbResult<u64>;
+
+    async fn add_webhook_deliveries(&self, deliveries: &[NewWebhookDelivery]) -> DbResult<()>;
+
+    /// Deliveries that are due to be attempted, oldest first
+    async fn due_webhook_deliveries(
+        &self,
+        now: OffsetDateTime,
+        limit: i64,
+    ) -> DbResult<Vec<WebhookDelivery>>;
+
+    /// Record a failed attempt, and when to try again
+    async fn retry_webhook_delivery(
+        &self,
+        id: i64,
+        next_attempt: OffsetDateTime,
+        error: &str,
+    ) -> DbResult<()>;
+
+    /// The delivery succeeded, or we have given up on it
+    async fn delete_webhook_delivery(&self, id: i64) -> DbResult<()>;
+
     async fn count_history_range(&self, user: &User, range: Range<OffsetDateTime>)
     -> DbResult<i64>;
 
@@ -33,6 +33,7 @@ pub struct User {
     pub email: String,
     pub password: String,
     pub verified: Option<OffsetDateTime>,
+    pub disabled: Option<OffsetDateTime>,
 }
 
 pub struct Session {
@@ -50,4 +51,83 @@ pub struct NewUser {
 pub struct NewSession {
     pub user_id: i64,
     pub token: String,
+
+    /// What the client calls itself, usually its hostname
+    pub name: Option<String>,
+}
+
+/// A session as shown to its user. Sessions from before we tracked them have no creation time.
+pub struct SessionInfo {
+    pub id: i64,
+    pub name: Option<String>,
+    pub created: Option<OffsetDateTime>,
+    pub last_used: Option<OffsetDateTime>,
+}
+
+pub struct NewApiToken {
+    pub user_id: i64,
+    pub name: String,
+
+    /// Tokens are only stored hashed, and looked up by their hash
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+}
+
+/// A long-lived token, which can only do what its scopes allow
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created: OffsetDateTime,
+}
+
+/// A record store shared by its members. The records belong to `user_id`, a user that exists
+/// only to own them, and can never log in.
+pub struct Team {
+    pub id: i64,
+    pub name: String,
+    pub user_id: i64,
+}
+
+/// How much a user is keeping in the record store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordUsage {
+    pub records: u64,
+
+    /// Size of the encrypted record data, including the wrapped content encryption keys
+    pub bytes: u64,
+}
+
+pub struct NewWebhookDelivery {
+    pub url: String,
+    pub event: String,
+
+    /// The exact body we will POST, and sign
+    pub payload: String,
+}
+
+/// A webhook waiting to be delivered
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub url: String,
+    pub event: String,
+    pub payload: String,
+
+    /// How many times we have already tried, and failed, to deliver it
+    pub attempts: i32,
+}
+
+pub struct NewInvite {
+    pub code: String,
+    pub max_uses: i32,
+    pub expires: Option<OffsetDateTime>,
+}
+
+/// A code that lets someone register while registration is closed
+pub struct Invite {
+    pub code: String,
+    pub max_uses: i32,
+    pub uses: i32,
+    pub expires: Option<OffsetDateTime>,
 }
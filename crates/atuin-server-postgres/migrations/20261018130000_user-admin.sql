@@ -0,0 +1,9 @@
+-- Disabled users keep their data, but can neither log in nor use an existing session
+alter table users add disabled_at timestamp with time zone default null;
+
+create table password_reset_token(
+  id bigserial primary key,
+  user_id bigint unique references users(id),
+  token text not null,
+  valid_until timestamp with time zone not null
+);
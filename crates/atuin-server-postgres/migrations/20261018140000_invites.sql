@@ -0,0 +1,9 @@
+-- Invite codes, which let people register while open_registration is off
+create table invites(
+  id bigserial primary key,
+  code text not null unique,
+  max_uses integer not null,
+  uses integer not null default 0,
+  expires_at timestamp with time zone,
+  created_at timestamp with time zone not null default current_timestamp
+);
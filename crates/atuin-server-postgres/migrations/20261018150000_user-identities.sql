@@ -0,0 +1,10 @@
+-- Links a user to the subject an OpenID Connect provider knows them by
+create table user_identities(
+  id bigserial primary key,
+  user_id bigint not null references users(id),
+  issuer text not null,
+  subject text not null,
+  created_at timestamp with time zone not null default current_timestamp,
+
+  unique(issuer, subject)
+);
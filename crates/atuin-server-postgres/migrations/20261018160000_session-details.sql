@@ -0,0 +1,6 @@
+-- Enough to recognise a session when listing them. Older sessions have no created_at.
+alter table sessions add name text;
+alter table sessions add created_at timestamp with time zone;
+alter table sessions add last_used_at timestamp with time zone;
+
+create index sessions_user_id on sessions(user_id);
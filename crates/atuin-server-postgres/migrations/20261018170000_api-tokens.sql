@@ -0,0 +1,12 @@
+-- Long-lived tokens for automation, limited to the scopes they were created with. Only a hash of
+-- each token is kept, so the table is no use to anyone who gets hold of it.
+create table api_tokens(
+  id bigserial primary key,
+  user_id bigint not null,
+  name text not null,
+  token_hash text not null unique, -- hex encoded sha256
+  scopes text not null, -- space separated
+  created_at timestamp with time zone not null default current_timestamp
+);
+
+create index api_tokens_user_id on api_tokens(user_id);
@@ -0,0 +1,17 @@
+-- A team owns a record store of its own, through a user that can never log in
+create table teams(
+  id bigserial primary key,
+  name text not null unique,
+  user_id bigint not null unique,
+  created_at timestamp with time zone not null default current_timestamp
+);
+
+create table team_members(
+  team_id bigint not null,
+  user_id bigint not null,
+  created_at timestamp with time zone not null default current_timestamp,
+
+  unique(team_id, user_id)
+);
+
+create index team_members_user_id on team_members(user_id);
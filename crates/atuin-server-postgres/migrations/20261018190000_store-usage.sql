@@ -0,0 +1,37 @@
+-- Keep a running total of each user's record store, so checking a quota doesn't have to count
+-- the whole store on every upload
+create table store_usage(
+  user_id bigint primary key,
+  records bigint not null default 0,
+  bytes bigint not null default 0
+);
+
+insert into store_usage(user_id, records, bytes)
+select user_id, count(*), coalesce(sum(length(data) + length(cek)), 0)
+from store
+group by user_id;
+
+create or replace function user_store_usage()
+returns trigger as
+$func$
+begin
+	if (TG_OP='INSERT') then
+		insert into store_usage(user_id, records, bytes)
+		values (new.user_id, 1, length(new.data) + length(new.cek))
+		on conflict(user_id) do update
+		set records = store_usage.records + 1, bytes = store_usage.bytes + excluded.bytes;
+	elsif (TG_OP='DELETE') then
+		update store_usage
+		set records = records - 1, bytes = bytes - (length(old.data) + length(old.cek))
+		where user_id = old.user_id;
+	end if;
+
+	return null;
+end;
+$func$
+language plpgsql volatile;
+
+create trigger tg_user_store_usage
+	after insert or delete on store
+	for each row
+	execute procedure user_store_usage();
@@ -1,6 +1,6 @@
 use ::sqlx::{FromRow, Result};
 use atuin_common::record::{EncryptedData, Host, Record};
-use atuin_server_database::models::{History, Session, User};
+use atuin_server_database::models::{ApiToken, History, Session, User};
 use sqlx::{Row, postgres::PgRow};
 use time::PrimitiveDateTime;
 
@@ -8,6 +8,7 @@ pub struct DbUser(pub User);
 pub struct DbSession(pub Session);
 pub struct DbHistory(pub History);
 pub struct DbRecord(pub Record<EncryptedData>);
+pub struct DbApiToken(pub ApiToken);
 
 impl<'a> FromRow<'a, PgRow> for DbUser {
     fn from_row(row: &'a PgRow) -> Result<Self> {
@@ -17,6 +18,7 @@ impl<'a> FromRow<'a, PgRow> for DbUser {
             email: row.try_get("email")?,
             password: row.try_get("password")?,
             verified: row.try_get("verified_at")?,
+            disabled: row.try_get("disabled_at")?,
         }))
     }
 }
@@ -31,6 +33,20 @@ impl<'a> ::sqlx::FromRow<'a, PgRow> for DbSession {
     }
 }
 
+impl<'a> ::sqlx::FromRow<'a, PgRow> for DbApiToken {
+    fn from_row(row: &'a PgRow) -> ::sqlx::Result<Self> {
+        let scopes: String = row.try_get("scopes")?;
+
+        Ok(Self(ApiToken {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            name: row.try_get("name")?,
+            scopes: scopes.split_whitespace().map(String::from).collect(),
+            created: row.try_get("created_at")?,
+        }))
+    }
+}
+
 impl<'a> ::sqlx::FromRow<'a, PgRow> for DbHistory {
     fn from_row(row: &'a PgRow) -> ::sqlx::Result<Self> {
         Ok(Self(History {
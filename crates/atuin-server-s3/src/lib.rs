@@ -0,0 +1,722 @@
+#![forbid(unsafe_code)]
+
+// Records live in an S3-compatible bucket, everything else in sqlite.
+//
+// Each (user, host, tag) store is split into chunks of CHUNK_SIZE records, one object per chunk:
+//
+//   <prefix>/records/<user id>/<host id>/<tag>/<chunk>.json
+//
+// Object storage can't append or cheaply list the tail of a store, so sqlite's store_idx_cache
+// doubles as our index - it holds the head idx of every store, which is all `status` needs. The
+// store_usage table keeps count of what each user stores, for quotas.
+
+use std::{
+    collections::{BTreeMap, HashMap, btree_map::Entry, hash_map},
+    ops::Range,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use atuin_common::record::{EncryptedData, HostId, Record, RecordIdx, RecordStatus};
+use atuin_server_database::{
+    Database, DbError, DbResult, DbSettings,
+    models::{
+        ApiToken, History, Invite, NewApiToken, NewHistory, NewInvite, NewSession, NewUser,
+        NewWebhookDelivery, RecordUsage, Session, SessionInfo, Team, User, WebhookDelivery,
+    },
+};
+use atuin_server_sqlite::Sqlite;
+use futures_util::{StreamExt, TryStreamExt};
+use object_store::{ObjectStore, aws::AmazonS3Builder, path::Path};
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+pub use settings::S3Settings;
+
+mod settings;
+
+pub const CHUNK_SIZE: u64 = 1000;
+
+type UserLocks = Arc<std::sync::Mutex<HashMap<i64, Arc<Mutex<()>>>>>;
+
+#[derive(Clone)]
+pub struct S3 {
+    users: Sqlite,
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+
+    // Writing a chunk is read-modify-write, so only one upload per user at a time. These locks
+    // only hold within this process: nothing stops another server sharing the bucket from
+    // writing the same chunk, so there must only be one.
+    locks: UserLocks,
+}
+
+// Counted the same way as the sql backends, rather than by the size of the chunks
+fn record_size(record: &Record<EncryptedData>) -> u64 {
+    (record.data.data.len() + record.data.content_encryption_key.len()) as u64
+}
+
+fn fix_error(error: object_store::Error) -> DbError {
+    match error {
+        object_store::Error::NotFound { .. } => DbError::NotFound,
+        error => DbError::Other(error.into()),
+    }
+}
+
+impl S3 {
+    /// Use an already configured object store. Handy for testing against something that is not
+    /// really S3.
+    pub fn with_store(users: Sqlite, store: Arc<dyn ObjectStore>, prefix: Path) -> Self {
+        Self {
+            users,
+            store,
+            prefix,
+            locks: Arc::default(),
+        }
+    }
+
+    fn user_prefix(&self, user: &User) -> Path {
+        self.prefix.child("records").child(user.id.to_string())
+    }
+
+    fn chunk_path(&self, user: &User, host: HostId, tag: &str, chunk: u64) -> Path {
+        self.user_prefix(user)
+            .child(host.0.as_simple().to_string())
+            .child(tag)
+            .child(format!("{chunk:010}.json"))
+    }
+
+    fn user_lock(&self, user: &User) -> Arc<Mutex<()>> {
+        self.locks
+            .lock()
+            .expect("user lock map poisoned")
+            .entry(user.id)
+            .or_default()
+            .clone()
+    }
+
+    async fn read_chunk(&self, path: &Path) -> DbResult<Vec<Record<EncryptedData>>> {
+        let data = match self.store.get(path).await {
+            Ok(res) => res.bytes().await.map_err(fix_error)?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(Vec::new()),
+            Err(e) => return Err(fix_error(e)),
+        };
+
+        serde_json::from_slice(&data).map_err(|e| DbError::Other(e.into()))
+    }
+
+    /// The user's usage, counting every chunk if it hasn't been counted before. Stores uploaded
+    /// before the count was kept have no usage recorded yet. Call with the user's lock held.
+    async fn usage(&self, user: &User) -> DbResult<RecordUsage> {
+        if let Some(usage) = self.users.cached_usage(user).await? {
+            return Ok(usage);
+        }
+
+        let paths = self
+            .store
+            .list(Some(&self.user_prefix(user)))
+            .map_ok(|meta| meta.location)
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(fix_error)?;
+
+        let mut usage = RecordUsage::default();
+
+        for path in paths {
+            for record in self.read_chunk(&path).await? {
+                usage.records += 1;
+                usage.bytes += record_size(&record);
+            }
+        }
+
+        self.users.add_usage(user, usage).await?;
+
+        Ok(usage)
+    }
+
+    async fn write_chunk(&self, path: &Path, records: &[Record<EncryptedData>]) -> DbResult<()> {
+        let data = serde_json::to_vec(records).map_err(|e| DbError::Other(e.into()))?;
+
+        self.store.put(path, data.into()).await.map_err(fix_error)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Database for S3 {
+    async fn new(settings: &DbSettings) -> DbResult<Self> {
+        let s3 = S3Settings::from_uri(&settings.db_uri).map_err(DbError::Other)?;
+
+        let users = Sqlite::new(&DbSettings {
+            db_uri: s3.users_uri.clone(),
+        })
+        .await?;
+
+        let mut builder = AmazonS3Builder::from_env().with_bucket_name(&s3.bucket);
+
+        if let Some(endpoint) = &s3.endpoint {
+            builder = builder
+                .with_allow_http(endpoint.starts_with("http://"))
+                .with_endpoint(endpoint);
+        }
+
+        if let Some(region) = &s3.region {
+            builder = builder.with_region(region);
+        }
+
+        if let Some(key) = &s3.access_key_id {
+            builder = builder.with_access_key_id(key);
+        }
+
+        if let Some(secret) = &s3.secret_access_key {
+            builder = builder.with_secret_access_key(secret);
+        }
+
+        let store = builder.build().map_err(fix_error)?;
+
+        Ok(Self::with_store(users, Arc::new(store), s3.prefix))
+    }
+
+    async fn get_session(&self, token: &str) -> DbResult<Session> {
+        self.users.get_session(token).await
+    }
+
+    async fn get_session_user(&self, token: &str) -> DbResult<User> {
+        self.users.get_session_user(token).await
+    }
+
+    async fn add_session(&self, session: &NewSession) -> DbResult<()> {
+        self.users.add_session(session).await
+    }
+
+    async fn list_sessions(&self, user: &User) -> DbResult<Vec<SessionInfo>> {
+        self.users.list_sessions(user).await
+    }
+
+    async fn delete_session(&self, user: &User, id: i64) -> DbResult<()> {
+        self.users.delete_session(user, id).await
+    }
+
+    async fn touch_session(&self, token: &str) -> DbResult<()> {
+        self.users.touch_session(token).await
+    }
+
+    async fn add_api_token(&self, token: &NewApiToken) -> DbResult<i64> {
+        self.users.add_api_token(token).await
+    }
+
+    async fn get_api_token(&self, token_hash: &str) -> DbResult<ApiToken> {
+        self.users.get_api_token(token_hash).await
+    }
+
+    async fn get_api_token_user(&self, token_hash: &str) -> DbResult<User> {
+        self.users.get_api_token_user(token_hash).await
+    }
+
+    async fn list_api_tokens(&self, user: &User) -> DbResult<Vec<ApiToken>> {
+        self.users.list_api_tokens(user).await
+    }
+
+    async fn delete_api_token(&self, user: &User, id: i64) -> DbResult<()> {
+        self.users.delete_api_token(user, id).await
+    }
+
+    async fn add_team(&self, name: &str, owner: &NewUser, member: i64) -> DbResult<Option<i64>> {
+        self.users.add_team(name, owner, member).await
+    }
+
+    async fn get_team(&self, name: &str) -> DbResult<Team> {
+        self.users.get_team(name).await
+    }
+
+    async fn get_user_team(&self, user: &User, name: &str) -> DbResult<Team> {
+        self.users.get_user_team(user, name).await
+    }
+
+    async fn list_teams(&self, user: &User) -> DbResult<Vec<Team>> {
+        self.users.list_teams(user).await
+    }
+
+    async fn add_team_member(&self, team_id: i64, user_id: i64) -> DbResult<()> {
+        self.users.add_team_member(team_id, user_id).await
+    }
+
+    async fn remove_team_member(&self, team_id: i64, user_id: i64) -> DbResult<()> {
+        self.users.remove_team_member(team_id, user_id).await
+    }
+
+    async fn list_team_members(&self, team_id: i64) -> DbResult<Vec<User>> {
+        self.users.list_team_members(team_id).await
+    }
+
+    async fn get_user(&self, username: &str) -> DbResult<User> {
+        self.users.get_user(username).await
+    }
+
+    async fn get_user_session(&self, u: &User) -> DbResult<Session> {
+        self.users.get_user_session(u).await
+    }
+
+    async fn add_user(&self, user: &NewUser) -> DbResult<i64> {
+        self.users.add_user(user).await
+    }
+
+    async fn user_verified(&self, id: i64) -> DbResult<bool> {
+        self.users.user_verified(id).await
+    }
+
+    async fn verify_user(&self, id: i64) -> DbResult<()> {
+        self.users.verify_user(id).await
+    }
+
+    async fn user_verification_token(&self, id: i64) -> DbResult<String> {
+        self.users.user_verification_token(id).await
+    }
+
+    async fn update_user_password(&self, u: &User) -> DbResult<()> {
+        self.users.update_user_password(u).await
+    }
+
+    async fn list_users(&self) -> DbResult<Vec<User>> {
+        self.users.list_users().await
+    }
+
+    async fn set_user_disabled(&self, id: i64, disabled: bool) -> DbResult<()> {
+        self.users.set_user_disabled(id, disabled).await
+    }
+
+    async fn delete_user_sessions(&self, id: i64) -> DbResult<u64> {
+        self.users.delete_user_sessions(id).await
+    }
+
+    async fn password_reset_token(&self, id: i64, valid_for: time::Duration) -> DbResult<String> {
+        self.users.password_reset_token(id, valid_for).await
+    }
+
+    async fn use_password_reset_token(&self, username: &str, token: &str) -> DbResult<User> {
+        self.users.use_password_reset_token(username, token).await
+    }
+
+    async fn get_identity_user(&self, issuer: &str, subject: &str) -> DbResult<User> {
+        self.users.get_identity_user(issuer, subject).await
+    }
+
+    async fn add_user_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DbResult<()> {
+        self.users.add_user_identity(user_id, issuer, subject).await
+    }
+
+    async fn add_invite(&self, invite: &NewInvite) -> DbResult<()> {
+        self.users.add_invite(invite).await
+    }
+
+    async fn list_invites(&self) -> DbResult<Vec<Invite>> {
+        self.users.list_invites().await
+    }
+
+    async fn delete_invite(&self, code: &str) -> DbResult<()> {
+        self.users.delete_invite(code).await
+    }
+
+    async fn use_invite(&self, code: &str) -> DbResult<()> {
+        self.users.use_invite(code).await
+    }
+
+    async fn release_invite(&self, code: &str) -> DbResult<()> {
+        self.users.release_invite(code).await
+    }
+
+    async fn total_history(&self) -> DbResult<i64> {
+        self.users.total_history().await
+    }
+
+    async fn count_history(&self, user: &User) -> DbResult<i64> {
+        self.users.count_history(user).await
+    }
+
+    async fn count_history_cached(&self, user: &User) -> DbResult<i64> {
+        self.users.count_history_cached(user).await
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_user(&self, u: &User) -> DbResult<()> {
+        self.delete_store(u).await?;
+        self.users.delete_user(u).await
+    }
+
+    async fn delete_history(&self, user: &User, id: String) -> DbResult<()> {
+        self.users.delete_history(user, id).await
+    }
+
+    async fn deleted_history(&self, user: &User) -> DbResult<Vec<String>> {
+        self.users.deleted_history(user).await
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_store(&self, user: &User) -> DbResult<()> {
+        let lock = self.user_lock(user);
+        let _guard = lock.lock().await;
+
+        let prefix = self.user_prefix(user);
+        let locations = self.store.list(Some(&prefix)).map_ok(|meta| meta.location);
+
+        self.store
+            .delete_stream(locations.boxed())
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(fix_error)?;
+
+        self.users.delete_idx_cache(user).await?;
+        self.users.delete_usage(user).await
+    }
+
+    #[instrument(skip_all)]
+    async fn add_records(&self, user: &User, records: &[Record<EncryptedData>]) -> DbResult<()> {
+        let lock = self.user_lock(user);
+        let _guard = lock.lock().await;
+
+        // Make sure what's already stored is counted before adding to it
+        self.usage(user).await?;
+
+        let mut chunks: BTreeMap<(HostId, &str, u64), Vec<&Record<EncryptedData>>> =
+            BTreeMap::new();
+
+        for i in records {
+            chunks
+                .entry((i.host.id, i.tag.as_str(), i.idx / CHUNK_SIZE))
+                .or_default()
+                .push(i);
+        }
+
+        let mut heads = HashMap::<(HostId, String), RecordIdx>::new();
+        let mut added = RecordUsage::default();
+
+        for ((host, tag, chunk), records) in chunks {
+            let path = self.chunk_path(user, host, tag, chunk);
+
+            let mut existing: BTreeMap<RecordIdx, Record<EncryptedData>> = self
+                .read_chunk(&path)
+                .await?
+                .into_iter()
+                .map(|r| (r.idx, r))
+                .collect();
+
+            for record in records {
+                // Same as the sql backends - on conflict, do nothing
+                if let Entry::Vacant(entry) = existing.entry(record.idx) {
+                    added.records += 1;
+                    added.bytes += record_size(record);
+                    entry.insert(record.clone());
+                }
+            }
+
+            let records = existing.into_values().collect::<Vec<_>>();
+            self.write_chunk(&path, &records).await?;
+
+            if let Some(last) = records.last() {
+                let head = heads.entry((host, tag.to_string())).or_default();
+                *head = (*head).max(last.idx);
+            }
+        }
+
+        // Only once the records are safely stored can the index move forward
+        self.users.update_idx_cache(user, &heads).await?;
+        self.users.add_usage(user, added).await
+    }
+
+    #[instrument(skip_all)]
+    async fn next_records(
+        &self,
+        user: &User,
+        host: HostId,
+        tag: String,
+        start: Option<RecordIdx>,
+        count: u64,
+    ) -> DbResult<Vec<Record<EncryptedData>>> {
+        tracing::debug!("{:?} - {:?} - {:?}", host, tag, start);
+        let start = start.unwrap_or(0);
+
+        let mut ret = Vec::new();
+        let mut chunk = start / CHUNK_SIZE;
+
+        while (ret.len() as u64) < count {
+            let records = self
+                .read_chunk(&self.chunk_path(user, host, &tag, chunk))
+                .await?;
+
+            if records.is_empty() {
+                break;
+            }
+
+            let remaining = count as usize - ret.len();
+            ret.extend(
+                records
+                    .into_iter()
+                    .filter(|r| r.idx >= start)
+                    .take(remaining),
+            );
+
+            chunk += 1;
+        }
+
+        Ok(ret)
+    }
+
+    async fn status(&self, user: &User) -> DbResult<RecordStatus> {
+        self.users.cached_status(user).await
+    }
+
+    #[instrument(skip_all)]
+    async fn record_usage(&self, user: &User) -> DbResult<RecordUsage> {
+        if let Some(usage) = self.users.cached_usage(user).await? {
+            return Ok(usage);
+        }
+
+        let lock = self.user_lock(user);
+        let _guard = lock.lock().await;
+
+        self.usage(user).await
+    }
+
+    async fn list_record_usage(&self) -> DbResult<HashMap<i64, RecordUsage>> {
+        let mut usage = self.users.list_record_usage().await?;
+
+        // Count anyone whose store is older than the count
+        for user in self.users.list_users().await? {
+            if let hash_map::Entry::Vacant(entry) = usage.entry(user.id) {
+                entry.insert(self.record_usage(&user).await?);
+            }
+        }
+
+        Ok(usage)
+    }
+
+    async fn add_webhook_deliveries(&self, deliveries: &[NewWebhookDelivery]) -> DbResult<()> {
+        self.users.add_webhook_deliveries(deliveries).await
+    }
+
+    async fn due_webhook_deliveries(
+        &self,
+        now: OffsetDateTime,
+        limit: i64,
+    ) -> DbResult<Vec<WebhookDelivery>> {
+        self.users.due_webhook_deliveries(now, limit).await
+    }
+
+    async fn retry_webhook_delivery(
+        &self,
+        id: i64,
+        next_attempt: OffsetDateTime,
+        error: &str,
+    ) -> DbResult<()> {
+        self.users
+            .retry_webhook_delivery(id, next_attempt, error)
+            .await
+    }
+
+    async fn delete_webhook_delivery(&self, id: i64) -> DbResult<()> {
+        self.users.delete_webhook_delivery(id).await
+    }
+
+    async fn delete_records_before(&self, _before: OffsetDateTime) -> DbResult<u64> {
+        // Reading a store relies on every chunk before its head existing
+        tracing::warn!("record retention is not supported by the s3 backend, skipping");
+
+        Ok(0)
+    }
+
+    async fn count_history_range(
+        &self,
+        user: &User,
+        range: Range<OffsetDateTime>,
+    ) -> DbResult<i64> {
+        self.users.count_history_range(user, range).await
+    }
+
+    async fn list_history(
+        &self,
+        user: &User,
+        created_after: OffsetDateTime,
+        since: OffsetDateTime,
+        host: &str,
+        page_size: i64,
+    ) -> DbResult<Vec<History>> {
+        self.users
+            .list_history(user, created_after, since, host, page_size)
+            .await
+    }
+
+    async fn add_history(&self, history: &[NewHistory]) -> DbResult<()> {
+        self.users.add_history(history).await
+    }
+
+    async fn oldest_history(&self, user: &User) -> DbResult<History> {
+        self.users.oldest_history(user).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use atuin_common::record::{self, EncryptedData, Host, HostId, Record};
+    use atuin_server_database::{
+        Database, DbSettings,
+        models::{NewUser, RecordUsage, User},
+    };
+    use atuin_server_sqlite::Sqlite;
+    use object_store::{memory::InMemory, path::Path};
+
+    use super::{CHUNK_SIZE, S3, record_size};
+
+    // An in memory object store stands in for the bucket
+    async fn test_db(dir: &tempfile::TempDir) -> (S3, User) {
+        let users = Sqlite::new(&DbSettings {
+            db_uri: format!("sqlite://{}", dir.path().join("users.db").display()),
+        })
+        .await
+        .unwrap();
+
+        let db = S3::with_store(users, Arc::new(InMemory::new()), Path::from("atuin"));
+
+        db.add_user(&NewUser {
+            username: "ellie".into(),
+            email: "ellie@example.com".into(),
+            password: "hunter2".into(),
+        })
+        .await
+        .unwrap();
+
+        let user = db.get_user("ellie").await.unwrap();
+
+        (db, user)
+    }
+
+    fn test_records(n: u64) -> Vec<Record<EncryptedData>> {
+        record::test_records(&Host::new(HostId(atuin_common::utils::uuid_v7())), n)
+    }
+
+    #[tokio::test]
+    async fn add_and_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, user) = test_db(&dir).await;
+        let records = test_records(CHUNK_SIZE + 50);
+
+        for page in records.chunks(100) {
+            db.add_records(&user, page).await.unwrap();
+        }
+
+        let status = db.status(&user).await.unwrap();
+
+        assert_eq!(
+            status.get(records[0].host.id, "history".into()),
+            Some(CHUNK_SIZE + 49)
+        );
+    }
+
+    #[tokio::test]
+    async fn next_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, user) = test_db(&dir).await;
+        let records = test_records(CHUNK_SIZE + 50);
+        let host = records[0].host.id;
+
+        db.add_records(&user, &records).await.unwrap();
+
+        let page = db
+            .next_records(&user, host, "history".into(), Some(CHUNK_SIZE - 10), 100)
+            .await
+            .unwrap();
+
+        assert_eq!(page, records[(CHUNK_SIZE - 10) as usize..].to_vec());
+
+        let page = db
+            .next_records(&user, host, "history".into(), None, 10)
+            .await
+            .unwrap();
+
+        assert_eq!(page, records[..10].to_vec());
+
+        let page = db
+            .next_records(&user, host, "kv".into(), None, 10)
+            .await
+            .unwrap();
+
+        assert!(page.is_empty());
+    }
+
+    #[tokio::test]
+    async fn duplicates_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, user) = test_db(&dir).await;
+        let records = test_records(10);
+
+        db.add_records(&user, &records).await.unwrap();
+
+        let mut changed = records[5].clone();
+        changed.data.data = "changed".into();
+        db.add_records(&user, &[changed]).await.unwrap();
+
+        let page = db
+            .next_records(&user, records[0].host.id, "history".into(), None, 100)
+            .await
+            .unwrap();
+
+        assert_eq!(page, records);
+    }
+
+    #[tokio::test]
+    async fn delete_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, user) = test_db(&dir).await;
+        let records = test_records(10);
+
+        db.add_records(&user, &records).await.unwrap();
+        db.delete_store(&user).await.unwrap();
+
+        assert!(db.status(&user).await.unwrap().hosts.is_empty());
+
+        let page = db
+            .next_records(&user, records[0].host.id, "history".into(), None, 100)
+            .await
+            .unwrap();
+
+        assert!(page.is_empty());
+    }
+
+    #[tokio::test]
+    async fn record_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, user) = test_db(&dir).await;
+        let records = test_records(CHUNK_SIZE + 50);
+
+        let bytes = records.iter().map(record_size).sum();
+        let expected = RecordUsage {
+            records: CHUNK_SIZE + 50,
+            bytes,
+        };
+
+        db.add_records(&user, &records).await.unwrap();
+        assert_eq!(db.record_usage(&user).await.unwrap(), expected);
+
+        let all = db.list_record_usage().await.unwrap();
+        assert_eq!(all.get(&user.id), Some(&expected));
+
+        // Records already stored aren't counted twice
+        db.add_records(&user, &records[..10]).await.unwrap();
+        assert_eq!(db.record_usage(&user).await.unwrap(), expected);
+
+        // Stores from before usage was counted are counted from the chunks
+        db.users.delete_usage(&user).await.unwrap();
+        let all = db.list_record_usage().await.unwrap();
+        assert_eq!(all.get(&user.id), Some(&expected));
+        assert_eq!(db.record_usage(&user).await.unwrap(), expected);
+
+        db.delete_store(&user).await.unwrap();
+        assert_eq!(
+            db.record_usage(&user).await.unwrap(),
+            RecordUsage::default()
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use eyre::{Result, bail, eyre};
+use object_store::path::Path;
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+/// Everything we need to know to talk to the bucket, parsed from the db_uri. It looks like
+///
+///   s3://[access_key_id:secret_access_key@]bucket[/prefix]?users=sqlite:///path/to/users.db
+///
+/// with the optional query parameters `endpoint` (for anything that is not AWS itself, eg MinIO)
+/// and `region`. Credentials not given in the URI are read from the usual AWS_* environment
+/// variables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Settings {
+    pub bucket: String,
+    pub prefix: Path,
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+
+    /// Users, sessions and the record index live in sqlite
+    pub users_uri: String,
+}
+
+impl S3Settings {
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let url = Url::parse(uri)?;
+
+        if url.scheme() != "s3" {
+            bail!("s3 db_uri must start with s3://");
+        }
+
+        let bucket = url
+            .host_str()
+            .filter(|b| !b.is_empty())
+            .ok_or_else(|| eyre!("s3 db_uri must include a bucket name"))?
+            .to_string();
+
+        let prefix = Path::from(url.path().trim_matches('/'));
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.into_owned())
+        };
+
+        let users_uri = param("users").ok_or_else(|| {
+            eyre!(
+                "s3 db_uri must include a sqlite database for users, eg ?users=sqlite:///atuin.db"
+            )
+        })?;
+
+        let decode =
+            |s: &str| -> Result<String> { Ok(percent_decode_str(s).decode_utf8()?.into_owned()) };
+
+        let access_key_id = match url.username() {
+            "" => None,
+            user => Some(decode(user)?),
+        };
+
+        let secret_access_key = url.password().map(decode).transpose()?;
+
+        Ok(Self {
+            bucket,
+            prefix,
+            endpoint: param("endpoint"),
+            region: param("region"),
+            access_key_id,
+            secret_access_key,
+            users_uri,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object_store::path::Path;
+
+    use super::S3Settings;
+
+    #[test]
+    fn parse_minimal() {
+        let settings =
+            S3Settings::from_uri("s3://atuin?users=sqlite:///var/lib/atuin/users.db").unwrap();
+
+        assert_eq!(settings.bucket, "atuin");
+        assert_eq!(settings.prefix, Path::from(""));
+        assert_eq!(settings.users_uri, "sqlite:///var/lib/atuin/users.db");
+        assert_eq!(settings.endpoint, None);
+        assert_eq!(settings.access_key_id, None);
+    }
+
+    #[test]
+    fn parse_full() {
+        let settings = S3Settings::from_uri(
+            "s3://key:se%2Fcret@atuin/records/prod?users=sqlite:///users.db&endpoint=http://localhost:9000&region=eu-west-1",
+        )
+        .unwrap();
+
+        assert_eq!(settings.bucket, "atuin");
+        assert_eq!(settings.prefix, Path::from("records/prod"));
+        assert_eq!(settings.endpoint.as_deref(), Some("http://localhost:9000"));
+        assert_eq!(settings.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(settings.access_key_id.as_deref(), Some("key"));
+        assert_eq!(settings.secret_access_key.as_deref(), Some("se/cret"));
+    }
+
+    #[test]
+    fn users_db_required() {
+        assert!(S3Settings::from_uri("s3://atuin").is_err());
+    }
+}
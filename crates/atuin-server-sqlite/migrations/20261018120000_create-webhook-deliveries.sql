@@ -0,0 +1,13 @@
+-- Webhooks waiting to be delivered. Rows are deleted once delivered, or once we give up.
+create table webhook_deliveries (
+  id integer primary key autoincrement,
+  url text not null,
+  event text not null,
+  payload text not null,            -- the exact body we POST, and sign
+  attempts integer not null default 0,
+  last_error text,
+  next_attempt_at integer not null, -- unix seconds, so it compares sanely
+  created_at timestamp not null default current_timestamp
+);
+
+create index webhook_deliveries_next_attempt on webhook_deliveries(next_attempt_at);
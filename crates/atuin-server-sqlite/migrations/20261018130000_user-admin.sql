@@ -0,0 +1,9 @@
+-- Disabled users keep their data, but can neither log in nor use an existing session
+alter table users add disabled_at timestamp with time zone default null;
+
+create table password_reset_token(
+  id integer primary key autoincrement,
+  user_id bigint unique references users(id),
+  token text not null,
+  valid_until integer not null -- unix seconds, so it compares sanely
+);
@@ -0,0 +1,9 @@
+-- Invite codes, which let people register while open_registration is off
+create table invites(
+  id integer primary key autoincrement,
+  code text not null unique,
+  max_uses integer not null,
+  uses integer not null default 0,
+  expires_at integer, -- unix seconds, so it compares sanely
+  created_at timestamp not null default current_timestamp
+);
@@ -0,0 +1,10 @@
+-- Links a user to the subject an OpenID Connect provider knows them by
+create table user_identities(
+  id integer primary key autoincrement,
+  user_id bigint not null references users(id),
+  issuer text not null,
+  subject text not null,
+  created_at timestamp not null default current_timestamp,
+
+  unique(issuer, subject)
+);
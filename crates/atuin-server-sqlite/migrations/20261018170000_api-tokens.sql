@@ -0,0 +1,12 @@
+-- Long-lived tokens for automation, limited to the scopes they were created with. Only a hash of
+-- each token is kept, so the table is no use to anyone who gets hold of it.
+create table api_tokens(
+  id integer primary key autoincrement,
+  user_id integer not null,
+  name text not null,
+  token_hash text not null unique, -- hex encoded sha256
+  scopes text not null, -- space separated
+  created_at integer not null -- unix seconds
+);
+
+create index api_tokens_user_id on api_tokens(user_id);
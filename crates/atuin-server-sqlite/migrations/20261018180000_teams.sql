@@ -0,0 +1,17 @@
+-- A team owns a record store of its own, through a user that can never log in
+create table teams(
+  id integer primary key autoincrement,
+  name text not null unique,
+  user_id integer not null unique,
+  created_at integer not null -- unix seconds
+);
+
+create table team_members(
+  team_id integer not null,
+  user_id integer not null,
+  created_at integer not null, -- unix seconds
+
+  unique(team_id, user_id)
+);
+
+create index team_members_user_id on team_members(user_id);
@@ -0,0 +1,27 @@
+-- Keep a running total of each user's record store, so checking a quota doesn't have to count
+-- the whole store on every upload
+create table store_usage(
+  user_id integer primary key,
+  records integer not null default 0,
+  bytes integer not null default 0
+);
+
+insert into store_usage(user_id, records, bytes)
+select user_id, count(*), coalesce(sum(length(data) + length(cek)), 0)
+from store
+group by user_id;
+
+create trigger store_usage_insert after insert on store
+begin
+  insert into store_usage(user_id, records, bytes)
+  values (new.user_id, 1, length(new.data) + length(new.cek))
+  on conflict(user_id) do update
+  set records = records + 1, bytes = bytes + excluded.bytes;
+end;
+
+create trigger store_usage_delete after delete on store
+begin
+  update store_usage
+  set records = records - 1, bytes = bytes - (length(old.data) + length(old.cek))
+  where user_id = old.user_id;
+end;
@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use async_trait::async_trait;
 use atuin_common::{
@@ -7,7 +7,10 @@ use atuin_common::{
 };
 use atuin_server_database::{
     Database, DbError, DbResult, DbSettings,
-    models::{History, NewHistory, NewSession, NewUser, Session, User},
+    models::{
+        ApiToken, History, Invite, NewApiToken, NewHistory, NewInvite, NewSession, NewUser,
+        NewWebhookDelivery, RecordUsage, Session, SessionInfo, Team, User, WebhookDelivery,
+    },
 };
 use futures_util::TryStreamExt;
 use sqlx::{
@@ -17,7 +20,7 @@ use sqlx::{
 };
 use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
 use tracing::instrument;
-use wrappers::{DbHistory, DbRecord, DbSession, DbUser};
+use wrappers::{DbApiToken, DbHistory, DbRecord, DbSession, DbUser};
 
 mod wrappers;
 
@@ -26,6 +29,9 @@ pub struct Sqlite {
     pool: sqlx::Pool<sqlx::sqlite::Sqlite>,
 }
 
+// Don't write to the sessions table on every single request
+const SESSION_TOUCH_INTERVAL: time::Duration = time::Duration::minutes(1);
+
 fn fix_error(error: sqlx::Error) -> DbError {
     match error {
         sqlx::Error::RowNotFound => DbError::NotFound,
@@ -33,6 +39,118 @@ fn fix_error(error: sqlx::Error) -> DbError {
     }
 }
 
+// The idx cache is not used for records stored in sqlite itself, but backends that keep their
+// records elsewhere (eg object storage) use it as their index. They keep their usage counts here
+// too, where sqlite's own store has a trigger do it.
+impl Sqlite {
+    /// The status of a user's store, according to the idx cache
+    #[instrument(skip_all)]
+    pub async fn cached_status(&self, user: &User) -> DbResult<RecordStatus> {
+        let res: Vec<(Uuid, String, i64)> =
+            sqlx::query_as("select host, tag, idx from store_idx_cache where user_id = $1")
+                .bind(user.id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(fix_error)?;
+
+        let mut status = RecordStatus::new();
+
+        for i in res {
+            status.set_raw(HostId(i.0), i.1, i.2 as u64);
+        }
+
+        Ok(status)
+    }
+
+    /// Move the cached head of each (host, tag) forward. It is never moved backwards.
+    #[instrument(skip_all)]
+    pub async fn update_idx_cache(
+        &self,
+        user: &User,
+        heads: &HashMap<(HostId, String), RecordIdx>,
+    ) -> DbResult<()> {
+        let mut tx = self.pool.begin().await.map_err(fix_error)?;
+
+        for ((host, tag), idx) in heads {
+            sqlx::query(
+                "insert into store_idx_cache
+                    (user_id, host, tag, idx)
+                values ($1, $2, $3, $4)
+                on conflict(user_id, host, tag) do update set idx = max(store_idx_cache.idx, $4)
+                ",
+            )
+            .bind(user.id)
+            .bind(host.0)
+            .bind(tag)
+            .bind(*idx as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(fix_error)?;
+        }
+
+        tx.commit().await.map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    pub async fn delete_idx_cache(&self, user: &User) -> DbResult<()> {
+        sqlx::query("delete from store_idx_cache where user_id = $1")
+            .bind(user.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    /// The usage counted for a user, if anything has been counted yet
+    #[instrument(skip_all)]
+    pub async fn cached_usage(&self, user: &User) -> DbResult<Option<RecordUsage>> {
+        let usage: Option<(i64, i64)> =
+            sqlx::query_as("select records, bytes from store_usage where user_id = $1")
+                .bind(user.id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(fix_error)?;
+
+        Ok(usage.map(|(records, bytes)| RecordUsage {
+            records: records as u64,
+            bytes: bytes as u64,
+        }))
+    }
+
+    /// Add to the usage counted for a user
+    #[instrument(skip_all)]
+    pub async fn add_usage(&self, user: &User, added: RecordUsage) -> DbResult<()> {
+        sqlx::query(
+            "insert into store_usage (user_id, records, bytes)
+            values ($1, $2, $3)
+            on conflict(user_id) do update
+            set records = records + excluded.records, bytes = bytes + excluded.bytes",
+        )
+        .bind(user.id)
+        .bind(added.records as i64)
+        .bind(added.bytes as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    pub async fn delete_usage(&self, user: &User) -> DbResult<()> {
+        sqlx::query("delete from store_usage where user_id = $1")
+            .bind(user.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Database for Sqlite {
     async fn new(settings: &DbSettings) -> DbResult<Self> {
@@ -67,7 +185,7 @@ impl Database for Sqlite {
     #[instrument(skip_all)]
     async fn get_session_user(&self, token: &str) -> DbResult<User> {
         sqlx::query_as(
-            "select users.id, users.username, users.email, users.password, users.verified_at from users 
+            "select users.id, users.username, users.email, users.password, users.verified_at, users.disabled_at from users 
             inner join sessions 
             on users.id = sessions.user_id 
             and sessions.token = $1",
@@ -85,11 +203,276 @@ impl Database for Sqlite {
 
         sqlx::query(
             "insert into sessions
-                (user_id, token)
-            values($1, $2)",
+                (user_id, token, name, created_at)
+            values($1, $2, $3, $4)",
         )
         .bind(session.user_id)
         .bind(token)
+        .bind(&session.name)
+        .bind(OffsetDateTime::now_utc().unix_timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn list_sessions(&self, user: &User) -> DbResult<Vec<SessionInfo>> {
+        let res: Vec<(i64, Option<String>, Option<i64>, Option<i64>)> = sqlx::query_as(
+            "select id, name, created_at, last_used_at from sessions
+            where user_id = $1
+            order by id asc",
+        )
+        .bind(user.id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        let time = |t: Option<i64>| t.map(OffsetDateTime::from_unix_timestamp).transpose();
+
+        res.into_iter()
+            .map(|(id, name, created, last_used)| {
+                Ok(SessionInfo {
+                    id,
+                    name,
+                    created: time(created)?,
+                    last_used: time(last_used)?,
+                })
+            })
+            .collect()
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_session(&self, user: &User, id: i64) -> DbResult<()> {
+        let res = sqlx::query("delete from sessions where user_id = $1 and id = $2")
+            .bind(user.id)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn touch_session(&self, token: &str) -> DbResult<()> {
+        sqlx::query(
+            "update sessions set last_used_at = $2
+            where token = $1
+            and (last_used_at is null or last_used_at < $3)",
+        )
+        .bind(token)
+        .bind(OffsetDateTime::now_utc().unix_timestamp())
+        .bind((OffsetDateTime::now_utc() - SESSION_TOUCH_INTERVAL).unix_timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn add_api_token(&self, token: &NewApiToken) -> DbResult<i64> {
+        let res: (i64,) = sqlx::query_as(
+            "insert into api_tokens
+                (user_id, name, token_hash, scopes, created_at)
+            values($1, $2, $3, $4, $5)
+            returning id",
+        )
+        .bind(token.user_id)
+        .bind(&token.name)
+        .bind(&token.token_hash)
+        .bind(token.scopes.join(" "))
+        .bind(OffsetDateTime::now_utc().unix_timestamp())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(res.0)
+    }
+
+    #[instrument(skip_all)]
+    async fn get_api_token(&self, token_hash: &str) -> DbResult<ApiToken> {
+        sqlx::query_as(
+            "select id, user_id, name, scopes, created_at from api_tokens
+            where token_hash = $1",
+        )
+        .bind(token_hash)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(fix_error)
+        .map(|DbApiToken(token)| token)
+    }
+
+    #[instrument(skip_all)]
+    async fn get_api_token_user(&self, token_hash: &str) -> DbResult<User> {
+        sqlx::query_as(
+            "select users.id, users.username, users.email, users.password, users.verified_at, users.disabled_at from users
+            inner join api_tokens
+            on users.id = api_tokens.user_id
+            and api_tokens.token_hash = $1",
+        )
+        .bind(token_hash)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(fix_error)
+        .map(|DbUser(user)| user)
+    }
+
+    #[instrument(skip_all)]
+    async fn list_api_tokens(&self, user: &User) -> DbResult<Vec<ApiToken>> {
+        let res: Vec<DbApiToken> = sqlx::query_as(
+            "select id, user_id, name, scopes, created_at from api_tokens
+            where user_id = $1
+            order by id asc",
+        )
+        .bind(user.id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(res.into_iter().map(|DbApiToken(token)| token).collect())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_api_token(&self, user: &User, id: i64) -> DbResult<()> {
+        let res = sqlx::query("delete from api_tokens where user_id = $1 and id = $2")
+            .bind(user.id)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn add_team(&self, name: &str, owner: &NewUser, member: i64) -> DbResult<Option<i64>> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let mut tx = self.pool.begin().await.map_err(fix_error)?;
+
+        let owner: (i64,) = match sqlx::query_as(
+            "insert into users
+                (username, email, password)
+            values($1, $2, $3)
+            returning id",
+        )
+        .bind(&owner.username)
+        .bind(&owner.email)
+        .bind(&owner.password)
+        .fetch_one(&mut *tx)
+        .await
+        {
+            Ok(row) => row,
+            // The name is taken, by another team or a team that is being created right now
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Ok(None),
+            Err(e) => return Err(fix_error(e)),
+        };
+
+        let team: (i64,) = match sqlx::query_as(
+            "insert into teams
+                (name, user_id, created_at)
+            values($1, $2, $3)
+            returning id",
+        )
+        .bind(name)
+        .bind(owner.0)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await
+        {
+            Ok(row) => row,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Ok(None),
+            Err(e) => return Err(fix_error(e)),
+        };
+
+        sqlx::query(
+            "insert into team_members
+                (team_id, user_id, created_at)
+            values($1, $2, $3)",
+        )
+        .bind(team.0)
+        .bind(member)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(fix_error)?;
+
+        tx.commit().await.map_err(fix_error)?;
+
+        Ok(Some(team.0))
+    }
+
+    #[instrument(skip_all)]
+    async fn get_team(&self, name: &str) -> DbResult<Team> {
+        let (id, name, user_id) =
+            sqlx::query_as("select id, name, user_id from teams where name = $1")
+                .bind(name)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(fix_error)?;
+
+        Ok(Team { id, name, user_id })
+    }
+
+    #[instrument(skip_all)]
+    async fn get_user_team(&self, user: &User, name: &str) -> DbResult<Team> {
+        let (id, name, user_id) = sqlx::query_as(
+            "select teams.id, teams.name, teams.user_id from teams
+            inner join team_members
+            on teams.id = team_members.team_id
+            and team_members.user_id = $1
+            where teams.name = $2",
+        )
+        .bind(user.id)
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(Team { id, name, user_id })
+    }
+
+    #[instrument(skip_all)]
+    async fn list_teams(&self, user: &User) -> DbResult<Vec<Team>> {
+        let res: Vec<(i64, String, i64)> = sqlx::query_as(
+            "select teams.id, teams.name, teams.user_id from teams
+            inner join team_members
+            on teams.id = team_members.team_id
+            and team_members.user_id = $1
+            order by teams.name asc",
+        )
+        .bind(user.id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(res
+            .into_iter()
+            .map(|(id, name, user_id)| Team { id, name, user_id })
+            .collect())
+    }
+
+    #[instrument(skip_all)]
+    async fn add_team_member(&self, team_id: i64, user_id: i64) -> DbResult<()> {
+        sqlx::query(
+            "insert into team_members
+                (team_id, user_id, created_at)
+            values($1, $2, $3)
+            on conflict do nothing",
+        )
+        .bind(team_id)
+        .bind(user_id)
+        .bind(OffsetDateTime::now_utc().unix_timestamp())
         .execute(&self.pool)
         .await
         .map_err(fix_error)?;
@@ -97,10 +480,43 @@ impl Database for Sqlite {
         Ok(())
     }
 
+    #[instrument(skip_all)]
+    async fn remove_team_member(&self, team_id: i64, user_id: i64) -> DbResult<()> {
+        let res = sqlx::query("delete from team_members where team_id = $1 and user_id = $2")
+            .bind(team_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn list_team_members(&self, team_id: i64) -> DbResult<Vec<User>> {
+        let res: Vec<DbUser> = sqlx::query_as(
+            "select users.id, users.username, users.email, users.password, users.verified_at, users.disabled_at from users
+            inner join team_members
+            on users.id = team_members.user_id
+            and team_members.team_id = $1
+            order by users.username asc",
+        )
+        .bind(team_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(res.into_iter().map(|DbUser(user)| user).collect())
+    }
+
     #[instrument(skip_all)]
     async fn get_user(&self, username: &str) -> DbResult<User> {
         sqlx::query_as(
-            "select id, username, email, password, verified_at from users where username = $1",
+            "select id, username, email, password, verified_at, disabled_at from users where username = $1",
         )
         .bind(username)
         .fetch_one(&self.pool)
@@ -119,6 +535,203 @@ impl Database for Sqlite {
             .map(|DbSession(session)| session)
     }
 
+    #[instrument(skip_all)]
+    async fn list_users(&self) -> DbResult<Vec<User>> {
+        sqlx::query_as(
+            "select id, username, email, password, verified_at, disabled_at from users
+            order by id asc",
+        )
+        .fetch(&self.pool)
+        .map_ok(|DbUser(user)| user)
+        .try_collect()
+        .await
+        .map_err(fix_error)
+    }
+
+    #[instrument(skip_all)]
+    async fn set_user_disabled(&self, id: i64, disabled: bool) -> DbResult<()> {
+        let disabled_at = disabled.then(OffsetDateTime::now_utc);
+
+        let res = sqlx::query("update users set disabled_at = $2 where id = $1")
+            .bind(id)
+            .bind(disabled_at)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_user_sessions(&self, id: i64) -> DbResult<u64> {
+        let res = sqlx::query("delete from sessions where user_id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(res.rows_affected())
+    }
+
+    #[instrument(skip_all)]
+    async fn password_reset_token(&self, id: i64, valid_for: time::Duration) -> DbResult<String> {
+        let token = crypto_random_string::<24>();
+
+        sqlx::query(
+            "insert into password_reset_token (user_id, token, valid_until) values ($1, $2, $3)
+            on conflict (user_id) do update set token = $2, valid_until = $3",
+        )
+        .bind(id)
+        .bind(&token)
+        .bind((OffsetDateTime::now_utc() + valid_for).unix_timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(token)
+    }
+
+    #[instrument(skip_all)]
+    async fn use_password_reset_token(&self, username: &str, token: &str) -> DbResult<User> {
+        let user = self.get_user(username).await?;
+
+        let res = sqlx::query(
+            "delete from password_reset_token
+            where user_id = $1 and token = $2 and valid_until > $3",
+        )
+        .bind(user.id)
+        .bind(token)
+        .bind(OffsetDateTime::now_utc().unix_timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::NotFound);
+        }
+
+        Ok(user)
+    }
+
+    #[instrument(skip_all)]
+    async fn get_identity_user(&self, issuer: &str, subject: &str) -> DbResult<User> {
+        sqlx::query_as(
+            "select users.id, users.username, users.email, users.password, users.verified_at, users.disabled_at from users
+            inner join user_identities
+            on users.id = user_identities.user_id
+            and user_identities.issuer = $1
+            and user_identities.subject = $2",
+        )
+        .bind(issuer)
+        .bind(subject)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(fix_error)
+        .map(|DbUser(user)| user)
+    }
+
+    #[instrument(skip_all)]
+    async fn add_user_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DbResult<()> {
+        sqlx::query("insert into user_identities (user_id, issuer, subject) values ($1, $2, $3)")
+            .bind(user_id)
+            .bind(issuer)
+            .bind(subject)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn add_invite(&self, invite: &NewInvite) -> DbResult<()> {
+        sqlx::query("insert into invites (code, max_uses, expires_at) values ($1, $2, $3)")
+            .bind(&invite.code)
+            .bind(invite.max_uses)
+            .bind(invite.expires.map(|e| e.unix_timestamp()))
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn list_invites(&self) -> DbResult<Vec<Invite>> {
+        let res: Vec<(String, i32, i32, Option<i64>)> = sqlx::query_as(
+            "select code, max_uses, uses, expires_at from invites
+            order by id asc",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        res.into_iter()
+            .map(|(code, max_uses, uses, expires)| {
+                Ok(Invite {
+                    code,
+                    max_uses,
+                    uses,
+                    expires: expires
+                        .map(OffsetDateTime::from_unix_timestamp)
+                        .transpose()?,
+                })
+            })
+            .collect()
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_invite(&self, code: &str) -> DbResult<()> {
+        let res = sqlx::query("delete from invites where code = $1")
+            .bind(code)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn use_invite(&self, code: &str) -> DbResult<()> {
+        // A single statement, so two people can't race for the last use
+        let res = sqlx::query(
+            "update invites set uses = uses + 1
+            where code = $1
+            and uses < max_uses
+            and (expires_at is null or expires_at > $2)",
+        )
+        .bind(code)
+        .bind(OffsetDateTime::now_utc().unix_timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn release_invite(&self, code: &str) -> DbResult<()> {
+        sqlx::query("update invites set uses = uses - 1 where code = $1 and uses > 0")
+            .bind(code)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(())
+    }
+
     #[instrument(skip_all)]
     async fn add_user(&self, user: &NewUser) -> DbResult<i64> {
         let email: &str = &user.email;
@@ -155,13 +768,12 @@ impl Database for Sqlite {
 
     #[instrument(skip_all)]
     async fn verify_user(&self, id: i64) -> DbResult<()> {
-        sqlx::query(
-            "update users set verified_at = (current_timestamp at time zone 'utc') where id=$1",
-        )
-        .bind(id)
-        .execute(&self.pool)
-        .await
-        .map_err(fix_error)?;
+        sqlx::query("update users set verified_at = $2 where id = $1")
+            .bind(id)
+            .bind(OffsetDateTime::now_utc())
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
 
         Ok(())
     }
@@ -273,6 +885,45 @@ impl Database for Sqlite {
             .await
             .map_err(fix_error)?;
 
+        sqlx::query("delete from password_reset_token where user_id = $1")
+            .bind(u.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        sqlx::query("delete from user_identities where user_id = $1")
+            .bind(u.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        sqlx::query("delete from api_tokens where user_id = $1")
+            .bind(u.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        sqlx::query("delete from team_members where user_id = $1")
+            .bind(u.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        // The user may be the one that owns a team's records
+        sqlx::query(
+            "delete from team_members where team_id in (select id from teams where user_id = $1)",
+        )
+        .bind(u.id)
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        sqlx::query("delete from teams where user_id = $1")
+            .bind(u.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
         sqlx::query("delete from users where id = $1")
             .bind(u.id)
             .execute(&self.pool)
@@ -445,6 +1096,141 @@ impl Database for Sqlite {
         Ok(status)
     }
 
+    #[instrument(skip_all)]
+    async fn record_usage(&self, user: &User) -> DbResult<RecordUsage> {
+        // Kept up to date by a trigger on the store, so this doesn't scan it
+        Ok(self.cached_usage(user).await?.unwrap_or_default())
+    }
+
+    #[instrument(skip_all)]
+    async fn list_record_usage(&self) -> DbResult<HashMap<i64, RecordUsage>> {
+        let usage: Vec<(i64, i64, i64)> =
+            sqlx::query_as("select user_id, records, bytes from store_usage")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(fix_error)?;
+
+        Ok(usage
+            .into_iter()
+            .map(|(user_id, records, bytes)| {
+                let usage = RecordUsage {
+                    records: records as u64,
+                    bytes: bytes as u64,
+                };
+
+                (user_id, usage)
+            })
+            .collect())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_records_before(&self, before: OffsetDateTime) -> DbResult<u64> {
+        // created_at is sqlite's current_timestamp, so compare it as a datetime rather than
+        // whatever sqlx would encode our time as
+        let res = sqlx::query(
+            "delete from store
+            where created_at < datetime($1, 'unixepoch')
+            and idx < (
+                select max(tail.idx) from store tail
+                where tail.user_id = store.user_id
+                and tail.host = store.host
+                and tail.tag = store.tag
+            )",
+        )
+        .bind(before.unix_timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(res.rows_affected())
+    }
+
+    #[instrument(skip_all)]
+    async fn add_webhook_deliveries(&self, deliveries: &[NewWebhookDelivery]) -> DbResult<()> {
+        let mut tx = self.pool.begin().await.map_err(fix_error)?;
+
+        for i in deliveries {
+            sqlx::query(
+                "insert into webhook_deliveries (url, event, payload, next_attempt_at)
+                values ($1, $2, $3, $4)",
+            )
+            .bind(&i.url)
+            .bind(&i.event)
+            .bind(&i.payload)
+            .bind(OffsetDateTime::now_utc().unix_timestamp())
+            .execute(&mut *tx)
+            .await
+            .map_err(fix_error)?;
+        }
+
+        tx.commit().await.map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn due_webhook_deliveries(
+        &self,
+        now: OffsetDateTime,
+        limit: i64,
+    ) -> DbResult<Vec<WebhookDelivery>> {
+        let res: Vec<(i64, String, String, String, i32)> = sqlx::query_as(
+            "select id, url, event, payload, attempts from webhook_deliveries
+            where next_attempt_at <= $1
+            order by id asc
+            limit $2",
+        )
+        .bind(now.unix_timestamp())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(res
+            .into_iter()
+            .map(|(id, url, event, payload, attempts)| WebhookDelivery {
+                id,
+                url,
+                event,
+                payload,
+                attempts,
+            })
+            .collect())
+    }
+
+    #[instrument(skip_all)]
+    async fn retry_webhook_delivery(
+        &self,
+        id: i64,
+        next_attempt: OffsetDateTime,
+        error: &str,
+    ) -> DbResult<()> {
+        sqlx::query(
+            "update webhook_deliveries
+            set attempts = attempts + 1, next_attempt_at = $2, last_error = $3
+            where id = $1",
+        )
+        .bind(id)
+        .bind(next_attempt.unix_timestamp())
+        .bind(error)
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_webhook_delivery(&self, id: i64) -> DbResult<()> {
+        sqlx::query("delete from webhook_deliveries where id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(())
+    }
+
     #[instrument(skip_all)]
     async fn count_history_range(
         &self,
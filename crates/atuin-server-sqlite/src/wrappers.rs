@@ -1,12 +1,14 @@
 use ::sqlx::{FromRow, Result};
 use atuin_common::record::{EncryptedData, Host, Record};
-use atuin_server_database::models::{History, Session, User};
+use atuin_server_database::models::{ApiToken, History, Session, User};
 use sqlx::{Row, sqlite::SqliteRow};
+use time::OffsetDateTime;
 
 pub struct DbUser(pub User);
 pub struct DbSession(pub Session);
 pub struct DbHistory(pub History);
 pub struct DbRecord(pub Record<EncryptedData>);
+pub struct DbApiToken(pub ApiToken);
 
 impl<'a> FromRow<'a, SqliteRow> for DbUser {
     fn from_row(row: &'a SqliteRow) -> Result<Self> {
@@ -16,6 +18,7 @@ impl<'a> FromRow<'a, SqliteRow> for DbUser {
             email: row.try_get("email")?,
             password: row.try_get("password")?,
             verified: row.try_get("verified_at")?,
+            disabled: row.try_get("disabled_at")?,
         }))
     }
 }
@@ -30,6 +33,22 @@ impl<'a> ::sqlx::FromRow<'a, SqliteRow> for DbSession {
     }
 }
 
+impl<'a> ::sqlx::FromRow<'a, SqliteRow> for DbApiToken {
+    fn from_row(row: &'a SqliteRow) -> ::sqlx::Result<Self> {
+        let scopes: String = row.try_get("scopes")?;
+        let created = OffsetDateTime::from_unix_timestamp(row.try_get("created_at")?)
+            .map_err(|e| ::sqlx::Error::Decode(Box::new(e)))?;
+
+        Ok(Self(ApiToken {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            name: row.try_get("name")?,
+            scopes: scopes.split_whitespace().map(String::from).collect(),
+            created,
+        }))
+    }
+}
+
 impl<'a> ::sqlx::FromRow<'a, SqliteRow> for DbHistory {
     fn from_row(row: &'a SqliteRow) -> ::sqlx::Result<Self> {
         Ok(Self(History {
@@ -13,16 +13,19 @@ use axum::{
 };
 use metrics::counter;
 
-use postmark::{Query, reqwest::PostmarkClient};
-
 use rand::rngs::OsRng;
 use tracing::{debug, error, info, instrument};
 
 use super::{ErrorResponse, ErrorResponseStatus, RespExt};
-use crate::router::{AppState, UserAuth};
+use crate::{
+    mail::Email,
+    router::{AppState, UserAuth},
+    settings::WebhookEvent,
+    webhooks::{self, WebhookUser},
+};
 use atuin_server_database::{
     Database, DbError,
-    models::{NewSession, NewUser},
+    models::{NewSession, NewUser, User},
 };
 
 use reqwest::header::CONTENT_TYPE;
@@ -85,17 +88,50 @@ pub async fn get<DB: Database>(
     }))
 }
 
-#[instrument(skip_all)]
-pub async fn register<DB: Database>(
-    state: State<AppState<DB>>,
-    Json(register): Json<RegisterRequest>,
-) -> Result<Json<RegisterResponse>, ErrorResponseStatus<'static>> {
-    if !state.settings.open_registration {
+/// Check registration is open, or the invite lets someone in anyway. Using an invite counts it,
+/// so give it back with `release_invite` if registration then fails.
+pub(crate) async fn check_registration<'a, DB: Database>(
+    state: &AppState<DB>,
+    invite: Option<&'a str>,
+) -> Result<Option<&'a str>, ErrorResponseStatus<'static>> {
+    // Closed servers still let in anyone holding a valid invite
+    if state.settings.open_registration {
+        return Ok(None);
+    }
+
+    let Some(invite) = invite else {
         return Err(
             ErrorResponse::reply("this server is not open for registrations")
                 .with_status(StatusCode::BAD_REQUEST),
         );
+    };
+
+    match state.database.use_invite(invite).await {
+        Ok(()) => Ok(Some(invite)),
+        Err(DbError::NotFound) => Err(ErrorResponse::reply("invalid or expired invite code")
+            .with_status(StatusCode::BAD_REQUEST)),
+        Err(DbError::Other(e)) => {
+            error!("failed to use invite: {}", e);
+            Err(ErrorResponse::reply("database error")
+                .with_status(StatusCode::INTERNAL_SERVER_ERROR))
+        }
     }
+}
+
+pub(crate) async fn release_invite<DB: Database>(db: &DB, invite: Option<&str>) {
+    if let Some(invite) = invite {
+        if let Err(e) = db.release_invite(invite).await {
+            error!("failed to release invite: {}", e);
+        }
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn register<DB: Database>(
+    state: State<AppState<DB>>,
+    Json(register): Json<RegisterRequest>,
+) -> Result<Json<RegisterResponse>, ErrorResponseStatus<'static>> {
+    check_password_login(&state.settings)?;
 
     for c in register.username.chars() {
         match c {
@@ -109,6 +145,10 @@ pub async fn register<DB: Database>(
         }
     }
 
+    let db = &state.0.database;
+
+    let invite = check_registration(&state, register.invite.as_deref()).await?;
+
     let hashed = hash_secret(&register.password);
 
     let new_user = NewUser {
@@ -117,11 +157,14 @@ pub async fn register<DB: Database>(
         password: hashed,
     };
 
-    let db = &state.0.database;
     let user_id = match db.add_user(&new_user).await {
         Ok(id) => id,
         Err(e) => {
             error!("failed to add user: {}", e);
+
+            // Don't burn someone's single-use invite on a taken username
+            release_invite(db, invite).await;
+
             return Err(
                 ErrorResponse::reply("failed to add user").with_status(StatusCode::BAD_REQUEST)
             );
@@ -134,8 +177,21 @@ pub async fn register<DB: Database>(
     let new_session = NewSession {
         user_id,
         token: (&token).into(),
+        name: register.device.clone(),
     };
 
+    webhooks::emit(
+        db,
+        &state.settings.webhooks,
+        WebhookEvent::UserRegistered,
+        WebhookUser {
+            id: user_id,
+            username: &register.username,
+        },
+        None,
+    )
+    .await;
+
     if let Some(url) = &state.settings.register_webhook_url {
         // Could probs be run on another thread, but it's ok atm
         send_register_hook(
@@ -175,6 +231,18 @@ pub async fn delete<DB: Database>(
 
     counter!("atuin_users_deleted", 1);
 
+    webhooks::emit(
+        db,
+        &state.settings.webhooks,
+        WebhookEvent::UserDeleted,
+        WebhookUser {
+            id: user.id,
+            username: &user.username,
+        },
+        None,
+    )
+    .await;
+
     Ok(Json(DeleteUserResponse {}))
 }
 
@@ -200,14 +268,10 @@ pub async fn send_verification<DB: Database>(
         }));
     }
 
-    // TODO: if we ever add another mail provider, can match on them all here.
-    let postmark_token = match settings.mail.postmark.token {
-        Some(token) => token,
-        _ => {
-            error!("Failed to verify email: got None for postmark token");
-            return Err(ErrorResponse::reply("mail not configured")
-                .with_status(StatusCode::INTERNAL_SERVER_ERROR));
-        }
+    let Some(mailer) = &state.0.mailer else {
+        error!("Failed to verify email: mail is not configured correctly");
+        return Err(ErrorResponse::reply("mail not configured")
+            .with_status(StatusCode::INTERNAL_SERVER_ERROR));
     };
 
     let db = &state.0.database;
@@ -219,23 +283,20 @@ pub async fn send_verification<DB: Database>(
 
     debug!("Generated verification token, emailing user");
 
-    let client = PostmarkClient::builder()
-        .base_url("https://api.postmarkapp.com/")
-        .server_token(postmark_token)
-        .build();
-
-    let req = postmark::api::email::SendEmailRequest::builder()
-        .from(settings.mail.verification.from)
-        .subject(settings.mail.verification.subject)
-        .to(user.email)
-        .body(postmark::api::Body::text(format!(
+    let email = Email {
+        from: settings.mail.verification.from,
+        to: user.email,
+        subject: settings.mail.verification.subject,
+        body: format!(
             "Please run the following command to finalize your Atuin account verification. It is valid for 15 minutes:\n\natuin account verify --token '{verification_token}'"
-        )))
-        .build();
+        ),
+    };
 
-    req.execute(&client)
-        .await
-        .expect("postmark email request failed");
+    if let Err(e) = mailer.send(&email).await {
+        error!("Failed to send verification email: {e}");
+        return Err(ErrorResponse::reply("failed to send verification email")
+            .with_status(StatusCode::INTERNAL_SERVER_ERROR));
+    }
 
     debug!("Email sent");
 
@@ -308,6 +369,19 @@ pub async fn change_password<DB: Database>(
         return Err(ErrorResponse::reply("failed to change user password")
             .with_status(StatusCode::INTERNAL_SERVER_ERROR));
     };
+
+    webhooks::emit(
+        db,
+        &state.settings.webhooks,
+        WebhookEvent::PasswordChanged,
+        WebhookUser {
+            id: user.id,
+            username: &user.username,
+        },
+        None,
+    )
+    .await;
+
     Ok(Json(ChangePasswordResponse {}))
 }
 
@@ -316,6 +390,8 @@ pub async fn login<DB: Database>(
     state: State<AppState<DB>>,
     login: Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>, ErrorResponseStatus<'static>> {
+    check_password_login(&state.settings)?;
+
     let db = &state.0.database;
     let user = match db.get_user(login.username.borrow()).await {
         Ok(u) => u,
@@ -330,19 +406,6 @@ pub async fn login<DB: Database>(
         }
     };
 
-    let session = match db.get_user_session(&user).await {
-        Ok(u) => u,
-        Err(DbError::NotFound) => {
-            debug!("user session not found for user id={}", user.id);
-            return Err(ErrorResponse::reply("user not found").with_status(StatusCode::NOT_FOUND));
-        }
-        Err(DbError::Other(err)) => {
-            error!("database error for user {}: {}", login.username, err);
-            return Err(ErrorResponse::reply("database error")
-                .with_status(StatusCode::INTERNAL_SERVER_ERROR));
-        }
-    };
-
     let verified = verify_str(user.password.as_str(), login.password.borrow());
 
     if !verified {
@@ -352,11 +415,109 @@ pub async fn login<DB: Database>(
         );
     }
 
+    if user.disabled.is_some() {
+        debug!(user = user.username, "login refused, account disabled");
+        return Err(ErrorResponse::reply("this account has been disabled")
+            .with_status(StatusCode::FORBIDDEN));
+    }
+
+    // Every login gets its own session, so they can be revoked one at a time
+    let session = new_session(db, &user, login.device.clone()).await?;
+
     debug!(user = user.username, "login success");
 
-    Ok(Json(LoginResponse {
-        session: session.token,
-    }))
+    Ok(Json(LoginResponse { session }))
+}
+
+/// Start a new session for the user, returning its token
+pub(crate) async fn new_session<DB: Database>(
+    db: &DB,
+    user: &User,
+    name: Option<String>,
+) -> Result<String, ErrorResponseStatus<'static>> {
+    // 24 bytes encoded as base64
+    let token = crypto_random_string::<24>();
+
+    let session = NewSession {
+        user_id: user.id,
+        token: (&token).into(),
+        name,
+    };
+
+    if let Err(e) = db.add_session(&session).await {
+        error!("failed to add session for user {}: {}", user.username, e);
+        return Err(
+            ErrorResponse::reply("database error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+        );
+    }
+
+    Ok(token)
+}
+
+/// Refuse password logins and registrations, when everyone should be using single sign-on
+fn check_password_login(
+    settings: &crate::settings::Settings,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    if settings.oidc.enable && !settings.oidc.password_login {
+        return Err(ErrorResponse::reply(
+            "password login is disabled on this server, log in with `atuin login --sso`",
+        )
+        .with_status(StatusCode::FORBIDDEN));
+    }
+
+    Ok(())
+}
+
+#[instrument(skip_all, fields(user.username = reset.username.as_str()))]
+pub async fn reset_password<DB: Database>(
+    state: State<AppState<DB>>,
+    Json(reset): Json<ResetPasswordRequest>,
+) -> Result<Json<ResetPasswordResponse>, ErrorResponseStatus<'static>> {
+    let db = &state.0.database;
+
+    let mut user = match db
+        .use_password_reset_token(&reset.username, &reset.token)
+        .await
+    {
+        Ok(user) => user,
+        Err(DbError::NotFound) => {
+            return Err(ErrorResponse::reply("invalid or expired reset token")
+                .with_status(StatusCode::UNAUTHORIZED));
+        }
+        Err(DbError::Other(e)) => {
+            error!("failed to check reset token: {}", e);
+            return Err(ErrorResponse::reply("database error")
+                .with_status(StatusCode::INTERNAL_SERVER_ERROR));
+        }
+    };
+
+    user.password = hash_secret(&reset.new_password);
+
+    if let Err(e) = db.update_user_password(&user).await {
+        error!("failed to reset user password: {}", e);
+
+        return Err(ErrorResponse::reply("failed to reset user password")
+            .with_status(StatusCode::INTERNAL_SERVER_ERROR));
+    };
+
+    // Whoever knew the old password may still hold a session
+    if let Err(e) = db.delete_user_sessions(user.id).await {
+        error!("failed to revoke sessions after password reset: {}", e);
+    }
+
+    webhooks::emit(
+        db,
+        &state.settings.webhooks,
+        WebhookEvent::PasswordChanged,
+        WebhookUser {
+            id: user.id,
+            username: &user.username,
+        },
+        Some("reset"),
+    )
+    .await;
+
+    Ok(Json(ResetPasswordResponse {}))
 }
 
 fn hash_secret(password: &str) -> String {
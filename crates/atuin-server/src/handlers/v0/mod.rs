@@ -1,3 +1,7 @@
 pub(crate) mod me;
+pub(crate) mod oidc;
 pub(crate) mod record;
+pub(crate) mod session;
 pub(crate) mod store;
+pub(crate) mod team;
+pub(crate) mod token;
@@ -0,0 +1,200 @@
+use axum::{Json, extract::State, http::StatusCode};
+use metrics::counter;
+use tracing::{debug, error, info, instrument};
+
+use crate::{
+    handlers::{
+        ErrorResponse, ErrorResponseStatus, RespExt,
+        user::{check_registration, new_session, release_invite},
+    },
+    oidc::{self, Identity, Poll, Provider},
+    router::AppState,
+    settings::WebhookEvent,
+    webhooks::{self, WebhookUser},
+};
+use atuin_common::{api::*, utils::crypto_random_string};
+use atuin_server_database::{
+    Database, DbError,
+    models::{NewUser, User},
+};
+
+fn provider<DB: Database>(state: &AppState<DB>) -> Result<&Provider, ErrorResponseStatus<'static>> {
+    state.oidc.as_deref().ok_or_else(|| {
+        ErrorResponse::reply("single sign-on is not enabled on this server")
+            .with_status(StatusCode::NOT_FOUND)
+    })
+}
+
+fn database_error(e: DbError) -> ErrorResponseStatus<'static> {
+    error!("database error: {e}");
+    ErrorResponse::reply("database error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[instrument(skip_all)]
+pub async fn device<DB: Database>(
+    state: State<AppState<DB>>,
+) -> Result<Json<OidcDeviceResponse>, ErrorResponseStatus<'static>> {
+    let provider = provider(&state)?;
+
+    let auth = provider.device_authorization().await.map_err(|e| {
+        error!("failed to start device authorization: {e}");
+        ErrorResponse::reply("could not reach the identity provider")
+            .with_status(StatusCode::BAD_GATEWAY)
+    })?;
+
+    Ok(Json(OidcDeviceResponse {
+        device_code: auth.device_code,
+        user_code: auth.user_code,
+        verification_uri: auth.verification_uri,
+        verification_uri_complete: auth.verification_uri_complete,
+        expires_in: auth.expires_in,
+        interval: auth.interval,
+    }))
+}
+
+#[instrument(skip_all)]
+pub async fn token<DB: Database>(
+    state: State<AppState<DB>>,
+    Json(req): Json<OidcTokenRequest>,
+) -> Result<Json<OidcTokenResponse>, ErrorResponseStatus<'static>> {
+    let provider = provider(&state)?;
+
+    let identity = match provider.poll(&req.device_code).await {
+        Ok(Poll::Pending) => return Ok(Json(OidcTokenResponse::Pending)),
+        Ok(Poll::SlowDown) => return Ok(Json(OidcTokenResponse::SlowDown)),
+        Ok(Poll::Denied(reason)) => {
+            debug!("device authorization denied: {reason}");
+            return Err(ErrorResponse {
+                reason: format!("login was not completed: {reason}").into(),
+            }
+            .with_status(StatusCode::UNAUTHORIZED));
+        }
+        Ok(Poll::Complete(identity)) => identity,
+        Err(e) => {
+            error!("failed to poll identity provider: {e}");
+            return Err(
+                ErrorResponse::reply("could not reach the identity provider")
+                    .with_status(StatusCode::BAD_GATEWAY),
+            );
+        }
+    };
+
+    let db = &state.database;
+
+    let user = match db
+        .get_identity_user(provider.issuer(), &identity.subject)
+        .await
+    {
+        Ok(user) => user,
+        Err(DbError::NotFound) if state.settings.oidc.register => {
+            register(&state, provider, &identity, req.invite.as_deref()).await?
+        }
+        Err(DbError::NotFound) => {
+            info!(subject = identity.subject, "unknown identity");
+            return Err(ErrorResponse::reply(
+                "there is no account for this identity, ask your admin to link one",
+            )
+            .with_status(StatusCode::FORBIDDEN));
+        }
+        Err(e) => return Err(database_error(e)),
+    };
+
+    if user.disabled.is_some() {
+        debug!(user = user.username, "login refused, account disabled");
+        return Err(ErrorResponse::reply("this account has been disabled")
+            .with_status(StatusCode::FORBIDDEN));
+    }
+
+    let session = new_session(db, &user, req.device).await?;
+
+    debug!(user = user.username, "oidc login success");
+
+    Ok(Json(OidcTokenResponse::Complete {
+        session,
+        username: user.username,
+    }))
+}
+
+/// Create an account for an identity we have not seen before. The same rules apply as to
+/// registering with a password, so closed servers need an invite.
+async fn register<DB: Database>(
+    state: &AppState<DB>,
+    provider: &Provider,
+    identity: &Identity,
+    invite: Option<&str>,
+) -> Result<User, ErrorResponseStatus<'static>> {
+    let db = &state.database;
+
+    let username = identity
+        .username
+        .as_deref()
+        .map(oidc::username)
+        .filter(|u| !u.is_empty())
+        .ok_or_else(|| {
+            ErrorResponse {
+                reason: format!(
+                    "the identity provider did not send a usable {} claim",
+                    state.settings.oidc.username_claim
+                )
+                .into(),
+            }
+            .with_status(StatusCode::BAD_REQUEST)
+        })?;
+
+    let Some(email) = identity.email.clone() else {
+        return Err(
+            ErrorResponse::reply("the identity provider did not send an email address")
+                .with_status(StatusCode::BAD_REQUEST),
+        );
+    };
+
+    let invite = check_registration(state, invite).await?;
+
+    let new_user = NewUser {
+        username: username.clone(),
+        email,
+
+        // Not a valid password hash, so password login can never succeed
+        password: format!("oidc:{}", crypto_random_string::<24>()),
+    };
+
+    let id = match db.add_user(&new_user).await {
+        Ok(id) => id,
+        Err(e) => {
+            info!("failed to add user {username} from oidc: {e}");
+            release_invite(db, invite).await;
+
+            return Err(ErrorResponse {
+            reason: format!(
+                "could not create user {username}, the username or email may be taken. Ask your admin to link your identity to your existing account"
+            )
+            .into(),
+        }
+            .with_status(StatusCode::CONFLICT));
+        }
+    };
+
+    db.add_user_identity(id, provider.issuer(), &identity.subject)
+        .await
+        .map_err(database_error)?;
+
+    if identity.email_verified {
+        db.verify_user(id).await.map_err(database_error)?;
+    }
+
+    counter!("atuin_users_registered", 1);
+
+    webhooks::emit(
+        db,
+        &state.settings.webhooks,
+        WebhookEvent::UserRegistered,
+        WebhookUser {
+            id,
+            username: &username,
+        },
+        Some("oidc"),
+    )
+    .await;
+
+    db.get_user(&username).await.map_err(database_error)
+}
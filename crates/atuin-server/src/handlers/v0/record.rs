@@ -5,19 +5,32 @@ use tracing::{error, instrument};
 
 use crate::{
     handlers::{ErrorResponse, ErrorResponseStatus, RespExt},
-    router::{AppState, UserAuth},
+    router::{AppState, Scoped, scope},
+    settings::WebhookEvent,
+    webhooks::{self, WebhookUser},
 };
-use atuin_server_database::Database;
+use atuin_server_database::{Database, models::User};
 
 use atuin_common::record::{EncryptedData, HostId, Record, RecordIdx, RecordStatus};
 
 #[instrument(skip_all, fields(user.id = user.id))]
 pub async fn post<DB: Database>(
-    UserAuth(user): UserAuth,
-    state: State<AppState<DB>>,
+    Scoped(user, _): Scoped<scope::WriteRecords>,
+    State(state): State<AppState<DB>>,
     Json(records): Json<Vec<Record<EncryptedData>>>,
 ) -> Result<(), ErrorResponseStatus<'static>> {
-    let State(AppState { database, settings }) = state;
+    add(&state, &user, &records).await
+}
+
+/// Add records to the store of `user`, which may be a team
+pub(crate) async fn add<DB: Database>(
+    state: &AppState<DB>,
+    user: &User,
+    records: &[Record<EncryptedData>],
+) -> Result<(), ErrorResponseStatus<'static>> {
+    let AppState {
+        database, settings, ..
+    } = state;
 
     tracing::debug!(
         count = records.len(),
@@ -40,7 +53,65 @@ pub async fn post<DB: Database>(
         );
     }
 
-    if let Err(e) = database.add_records(&user, &records).await {
+    if settings.quota.enabled() {
+        let usage = match database.record_usage(user).await {
+            Ok(usage) => usage,
+            Err(e) => {
+                error!("failed to get record usage: {}", e);
+
+                return Err(ErrorResponse::reply("failed to check record quota")
+                    .with_status(StatusCode::INTERNAL_SERVER_ERROR));
+            }
+        };
+
+        let records_after = usage.records + records.len() as u64;
+        let bytes_after = usage.bytes
+            + records
+                .iter()
+                .map(|r| (r.data.data.len() + r.data.content_encryption_key.len()) as u64)
+                .sum::<u64>();
+
+        let quota = &settings.quota;
+
+        let exceeded = if quota.max_records > 0 && records_after > quota.max_records {
+            Some(format!(
+                "record quota exceeded; storing {} of {} records allowed",
+                usage.records, quota.max_records
+            ))
+        } else if quota.max_bytes > 0 && bytes_after > quota.max_bytes {
+            Some(format!(
+                "storage quota exceeded; storing {} of {} bytes allowed",
+                usage.bytes, quota.max_bytes
+            ))
+        } else {
+            None
+        };
+
+        if let Some(reason) = exceeded {
+            counter!("atuin_record_quota_exceeded", 1);
+
+            tracing::info!(user = user.username, reason, "rejecting records over quota");
+
+            webhooks::emit(
+                database,
+                &settings.webhooks,
+                WebhookEvent::QuotaExceeded,
+                WebhookUser {
+                    id: user.id,
+                    username: &user.username,
+                },
+                Some(&reason),
+            )
+            .await;
+
+            return Err(ErrorResponse {
+                reason: reason.into(),
+            }
+            .with_status(StatusCode::INSUFFICIENT_STORAGE));
+        }
+    }
+
+    if let Err(e) = database.add_records(user, records).await {
         error!("failed to add record: {}", e);
 
         return Err(ErrorResponse::reply("failed to add record")
@@ -52,15 +123,17 @@ pub async fn post<DB: Database>(
 
 #[instrument(skip_all, fields(user.id = user.id))]
 pub async fn index<DB: Database>(
-    UserAuth(user): UserAuth,
+    Scoped(user, _): Scoped<scope::ReadRecords>,
     state: State<AppState<DB>>,
 ) -> Result<Json<RecordStatus>, ErrorResponseStatus<'static>> {
-    let State(AppState {
-        database,
-        settings: _,
-    }) = state;
+    status(&state.database, &user).await
+}
 
-    let record_index = match database.status(&user).await {
+pub(crate) async fn status<DB: Database>(
+    database: &DB,
+    user: &User,
+) -> Result<Json<RecordStatus>, ErrorResponseStatus<'static>> {
+    let record_index = match database.status(user).await {
         Ok(index) => index,
         Err(e) => {
             error!("failed to get record index: {}", e);
@@ -86,17 +159,19 @@ pub struct NextParams {
 #[instrument(skip_all, fields(user.id = user.id))]
 pub async fn next<DB: Database>(
     params: Query<NextParams>,
-    UserAuth(user): UserAuth,
+    Scoped(user, _): Scoped<scope::ReadRecords>,
     state: State<AppState<DB>>,
 ) -> Result<Json<Vec<Record<EncryptedData>>>, ErrorResponseStatus<'static>> {
-    let State(AppState {
-        database,
-        settings: _,
-    }) = state;
-    let params = params.0;
+    next_for(&state.database, &user, params.0).await
+}
 
+pub(crate) async fn next_for<DB: Database>(
+    database: &DB,
+    user: &User,
+    params: NextParams,
+) -> Result<Json<Vec<Record<EncryptedData>>>, ErrorResponseStatus<'static>> {
     let records = match database
-        .next_records(&user, params.host, params.tag, params.start, params.count)
+        .next_records(user, params.host, params.tag, params.start, params.count)
         .await
     {
         Ok(records) => records,
@@ -0,0 +1,61 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use metrics::counter;
+use tracing::{error, instrument};
+
+use crate::{
+    handlers::{ErrorResponse, ErrorResponseStatus, RespExt},
+    router::{AppState, SessionAuth, UserAuth},
+};
+use atuin_common::api::*;
+use atuin_server_database::{Database, DbError};
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn list<DB: Database>(
+    SessionAuth(user, current): SessionAuth,
+    state: State<AppState<DB>>,
+) -> Result<Json<SessionsResponse>, ErrorResponseStatus<'static>> {
+    let sessions = state.database.list_sessions(&user).await.map_err(|e| {
+        error!("failed to list sessions: {e}");
+        ErrorResponse::reply("failed to list sessions")
+            .with_status(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    let sessions = sessions
+        .into_iter()
+        .map(|s| SessionResponse {
+            id: s.id,
+            current: s.id == current.id,
+            name: s.name,
+            created_at: s.created,
+            last_used_at: s.last_used,
+        })
+        .collect();
+
+    Ok(Json(SessionsResponse { sessions }))
+}
+
+#[instrument(skip_all, fields(user.id = user.id, session.id = id))]
+pub async fn revoke<DB: Database>(
+    Path(id): Path<i64>,
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    match state.database.delete_session(&user, id).await {
+        Ok(()) => {
+            counter!("atuin_session_revoked", 1);
+            Ok(())
+        }
+        Err(DbError::NotFound) => {
+            Err(ErrorResponse::reply("session not found").with_status(StatusCode::NOT_FOUND))
+        }
+        Err(DbError::Other(e)) => {
+            error!("failed to revoke session: {e}");
+            Err(ErrorResponse::reply("failed to revoke session")
+                .with_status(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
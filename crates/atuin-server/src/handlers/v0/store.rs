@@ -6,6 +6,8 @@ use tracing::{error, instrument};
 use crate::{
     handlers::{ErrorResponse, ErrorResponseStatus, RespExt},
     router::{AppState, UserAuth},
+    settings::WebhookEvent,
+    webhooks::{self, WebhookUser},
 };
 use atuin_server_database::Database;
 
@@ -19,8 +21,7 @@ pub async fn delete<DB: Database>(
     state: State<AppState<DB>>,
 ) -> Result<(), ErrorResponseStatus<'static>> {
     let State(AppState {
-        database,
-        settings: _,
+        database, settings, ..
     }) = state;
 
     if let Err(e) = database.delete_store(&user).await {
@@ -33,5 +34,17 @@ pub async fn delete<DB: Database>(
 
     counter!("atuin_store_deleted", 1);
 
+    webhooks::emit(
+        &database,
+        &settings.webhooks,
+        WebhookEvent::StoreDeleted,
+        WebhookUser {
+            id: user.id,
+            username: &user.username,
+        },
+        None,
+    )
+    .await;
+
     Ok(())
 }
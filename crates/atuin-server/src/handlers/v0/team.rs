@@ -0,0 +1,249 @@
+// A team's records are stored under a user of their own, which is never logged in to. Members
+// read and write that user's store through the routes here. The server only checks membership -
+// members share the encryption key out of band, so it never sees their data.
+//
+// Membership is flat: any member can add or remove any other.
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use metrics::counter;
+use tracing::{error, info, instrument};
+
+use crate::{
+    handlers::{ErrorResponse, ErrorResponseStatus, RespExt, v0::record},
+    router::{AppState, Scoped, UserAuth, scope},
+};
+use atuin_common::{
+    api::*,
+    record::{EncryptedData, Record, RecordStatus},
+    utils::crypto_random_string,
+};
+use atuin_server_database::{
+    Database, DbError,
+    models::{NewUser, Team, User},
+};
+
+// The team's user is named `team:<name>`, and usernames are at most 32 characters
+const MAX_NAME_LEN: usize = 24;
+
+fn database_error(e: DbError) -> ErrorResponseStatus<'static> {
+    error!("database error: {e}");
+    ErrorResponse::reply("database error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn team_not_found() -> ErrorResponseStatus<'static> {
+    ErrorResponse::reply("team not found").with_status(StatusCode::NOT_FOUND)
+}
+
+/// The user that owns a team's records. Registration only allows alphanumerics and hyphens, so
+/// this can never clash with a real user.
+fn team_username(team: &str) -> String {
+    format!("team:{team}")
+}
+
+/// A team the user is a member of. Teams they aren't in are indistinguishable from teams that
+/// don't exist.
+async fn member_team<DB: Database>(
+    db: &DB,
+    user: &User,
+    team: &str,
+) -> Result<Team, ErrorResponseStatus<'static>> {
+    match db.get_user_team(user, team).await {
+        Ok(team) => Ok(team),
+        Err(DbError::NotFound) => Err(team_not_found()),
+        Err(e) => Err(database_error(e)),
+    }
+}
+
+async fn team_user<DB: Database>(
+    db: &DB,
+    user: &User,
+    team: &str,
+) -> Result<User, ErrorResponseStatus<'static>> {
+    let team = member_team(db, user, team).await?;
+
+    db.get_user(&team_username(&team.name))
+        .await
+        .map_err(database_error)
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn create<DB: Database>(
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+    Json(req): Json<CreateTeamRequest>,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    let valid = !req.name.is_empty()
+        && req.name.len() <= MAX_NAME_LEN
+        && req
+            .name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    if !valid {
+        return Err(ErrorResponse {
+            reason: format!(
+                "team names must be at most {MAX_NAME_LEN} lowercase letters, digits and hyphens"
+            )
+            .into(),
+        }
+        .with_status(StatusCode::BAD_REQUEST));
+    }
+
+    let db = &state.database;
+
+    let owner = NewUser {
+        username: team_username(&req.name),
+        email: team_username(&req.name),
+
+        // Not a valid password hash, so nobody can ever log in as the team
+        password: format!("team:{}", crypto_random_string::<24>()),
+    };
+
+    let team = db
+        .add_team(&req.name, &owner, user.id)
+        .await
+        .map_err(database_error)?;
+
+    if team.is_none() {
+        info!("team name {} is taken", req.name);
+
+        return Err(ErrorResponse {
+            reason: format!("the team name {} is taken", req.name).into(),
+        }
+        .with_status(StatusCode::CONFLICT));
+    }
+
+    counter!("atuin_team_created", 1);
+
+    Ok(())
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn list<DB: Database>(
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<Json<TeamsResponse>, ErrorResponseStatus<'static>> {
+    let teams = state
+        .database
+        .list_teams(&user)
+        .await
+        .map_err(database_error)?;
+
+    Ok(Json(TeamsResponse {
+        teams: teams.into_iter().map(|t| t.name).collect(),
+    }))
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn members<DB: Database>(
+    Path(team): Path<String>,
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<Json<TeamMembersResponse>, ErrorResponseStatus<'static>> {
+    let db = &state.database;
+    let team = member_team(db, &user, &team).await?;
+
+    let members = db
+        .list_team_members(team.id)
+        .await
+        .map_err(database_error)?;
+
+    Ok(Json(TeamMembersResponse {
+        members: members.into_iter().map(|u| u.username).collect(),
+    }))
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn add_member<DB: Database>(
+    Path(team): Path<String>,
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+    Json(req): Json<AddTeamMemberRequest>,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    let db = &state.database;
+    let team = member_team(db, &user, &team).await?;
+
+    let member = match db.get_user(&req.username).await {
+        Ok(member) if member.username != team_username(&team.name) => member,
+        Ok(_) | Err(DbError::NotFound) => {
+            return Err(ErrorResponse::reply("user not found").with_status(StatusCode::NOT_FOUND));
+        }
+        Err(e) => return Err(database_error(e)),
+    };
+
+    db.add_team_member(team.id, member.id)
+        .await
+        .map_err(database_error)?;
+
+    counter!("atuin_team_member_added", 1);
+
+    Ok(())
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn remove_member<DB: Database>(
+    Path((team, username)): Path<(String, String)>,
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    let db = &state.database;
+    let team = member_team(db, &user, &team).await?;
+
+    let not_member = || {
+        ErrorResponse::reply("user is not a member of this team").with_status(StatusCode::NOT_FOUND)
+    };
+
+    let member = match db.get_user(&username).await {
+        Ok(member) => member,
+        Err(DbError::NotFound) => return Err(not_member()),
+        Err(e) => return Err(database_error(e)),
+    };
+
+    match db.remove_team_member(team.id, member.id).await {
+        Ok(()) => {
+            counter!("atuin_team_member_removed", 1);
+            Ok(())
+        }
+        Err(DbError::NotFound) => Err(not_member()),
+        Err(e) => Err(database_error(e)),
+    }
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn record_post<DB: Database>(
+    Path(team): Path<String>,
+    Scoped(user, _): Scoped<scope::WriteRecords>,
+    State(state): State<AppState<DB>>,
+    Json(records): Json<Vec<Record<EncryptedData>>>,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    let owner = team_user(&state.database, &user, &team).await?;
+
+    record::add(&state, &owner, &records).await
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn record_index<DB: Database>(
+    Path(team): Path<String>,
+    Scoped(user, _): Scoped<scope::ReadRecords>,
+    state: State<AppState<DB>>,
+) -> Result<Json<RecordStatus>, ErrorResponseStatus<'static>> {
+    let owner = team_user(&state.database, &user, &team).await?;
+
+    record::status(&state.database, &owner).await
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn record_next<DB: Database>(
+    Path(team): Path<String>,
+    params: Query<record::NextParams>,
+    Scoped(user, _): Scoped<scope::ReadRecords>,
+    state: State<AppState<DB>>,
+) -> Result<Json<Vec<Record<EncryptedData>>>, ErrorResponseStatus<'static>> {
+    let owner = team_user(&state.database, &user, &team).await?;
+
+    record::next_for(&state.database, &owner, params.0).await
+}
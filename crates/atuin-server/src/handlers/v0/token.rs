@@ -0,0 +1,127 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use metrics::counter;
+use sha2::{Digest, Sha256};
+use tracing::{error, instrument, warn};
+
+use crate::{
+    handlers::{ErrorResponse, ErrorResponseStatus, RespExt},
+    router::{AppState, UserAuth},
+};
+use atuin_common::{api::*, utils::crypto_random_string};
+use atuin_server_database::{Database, DbError, models::NewApiToken};
+
+/// API tokens are told apart from session tokens by this prefix. It also makes them easy to
+/// spot, should one be committed somewhere it shouldn't be.
+pub const API_TOKEN_PREFIX: &str = "atuin_";
+
+/// Tokens are random enough that a plain hash is as good as a password hash, and much faster to
+/// check on every request
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn database_error(e: DbError) -> ErrorResponseStatus<'static> {
+    error!("database error: {e}");
+    ErrorResponse::reply("database error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Tokens can only be managed with a session, so a leaked token can't be used to mint more
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn create<DB: Database>(
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+    Json(req): Json<CreateTokenRequest>,
+) -> Result<Json<CreateTokenResponse>, ErrorResponseStatus<'static>> {
+    if req.name.trim().is_empty() {
+        return Err(
+            ErrorResponse::reply("tokens must have a name").with_status(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    if req.scopes.is_empty() {
+        return Err(ErrorResponse::reply("tokens must have at least one scope")
+            .with_status(StatusCode::BAD_REQUEST));
+    }
+
+    let token = format!("{API_TOKEN_PREFIX}{}", crypto_random_string::<24>());
+
+    let mut scopes = req
+        .scopes
+        .iter()
+        .map(|s| s.as_str().to_string())
+        .collect::<Vec<_>>();
+    scopes.sort();
+    scopes.dedup();
+
+    let new_token = NewApiToken {
+        user_id: user.id,
+        name: req.name,
+        token_hash: hash_token(&token),
+        scopes,
+    };
+
+    let id = state
+        .database
+        .add_api_token(&new_token)
+        .await
+        .map_err(database_error)?;
+
+    counter!("atuin_api_token_created", 1);
+
+    // The only time anyone sees the token
+    Ok(Json(CreateTokenResponse { id, token }))
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn list<DB: Database>(
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<Json<TokensResponse>, ErrorResponseStatus<'static>> {
+    let tokens = state
+        .database
+        .list_api_tokens(&user)
+        .await
+        .map_err(database_error)?;
+
+    let tokens = tokens
+        .into_iter()
+        .map(|t| TokenResponse {
+            id: t.id,
+            name: t.name,
+            scopes: t
+                .scopes
+                .iter()
+                .filter_map(|s| {
+                    s.parse()
+                        .inspect_err(|e| warn!("api token {} has a bad scope: {e}", t.id))
+                        .ok()
+                })
+                .collect(),
+            created_at: t.created,
+        })
+        .collect();
+
+    Ok(Json(TokensResponse { tokens }))
+}
+
+#[instrument(skip_all, fields(user.id = user.id, token.id = id))]
+pub async fn revoke<DB: Database>(
+    Path(id): Path<i64>,
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    match state.database.delete_api_token(&user, id).await {
+        Ok(()) => {
+            counter!("atuin_api_token_revoked", 1);
+            Ok(())
+        }
+        Err(DbError::NotFound) => {
+            Err(ErrorResponse::reply("token not found").with_status(StatusCode::NOT_FOUND))
+        }
+        Err(DbError::Other(e)) => Err(database_error(DbError::Other(e))),
+    }
+}
@@ -10,9 +10,13 @@ use axum_server::tls_rustls::RustlsConfig;
 use eyre::{Context, Result, eyre};
 
 mod handlers;
+mod mail;
 mod metrics;
+mod oidc;
+mod retention;
 mod router;
 mod utils;
+pub mod webhooks;
 
 pub use settings::Settings;
 pub use settings::example_config;
@@ -136,6 +140,15 @@ async fn make_router<Db: Database>(settings: Settings) -> Result<Router, eyre::E
     let db = Db::new(&settings.db_settings)
         .await
         .wrap_err_with(|| format!("failed to connect to db: {:?}", settings.db_settings))?;
+
+    if settings.retention.days > 0 {
+        tokio::spawn(retention::run(db.clone(), settings.retention.clone()));
+    }
+
+    if !settings.webhooks.endpoints.is_empty() {
+        tokio::spawn(webhooks::run(db.clone(), settings.webhooks.clone()));
+    }
+
     let r = router::router(db, settings);
     Ok(r)
 }
@@ -0,0 +1,51 @@
+// Mail is only used to verify accounts for now, but the server should not care how it gets sent.
+
+use async_trait::async_trait;
+use eyre::{Result, eyre};
+
+use crate::settings::{Mail, MailTransport};
+
+pub use file::FileMailer;
+pub use postmark::PostmarkMailer;
+pub use smtp::SmtpMailer;
+
+mod file;
+mod postmark;
+mod smtp;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<()>;
+}
+
+/// Build the mailer the settings ask for. Returns None if mail is disabled.
+pub fn from_settings(settings: &Mail) -> Result<Option<Box<dyn Mailer>>> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let mailer: Box<dyn Mailer> = match settings.transport {
+        MailTransport::Postmark => {
+            let token = settings
+                .postmark
+                .token
+                .clone()
+                .ok_or_else(|| eyre!("mail.postmark.token is not set"))?;
+
+            Box::new(PostmarkMailer::new(token))
+        }
+
+        MailTransport::Smtp => Box::new(SmtpMailer::new(&settings.smtp)?),
+        MailTransport::File => Box::new(FileMailer::new(settings.file.path.clone())),
+    };
+
+    Ok(Some(mailer))
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use eyre::Result;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::Mutex};
+
+use super::{Email, Mailer};
+
+/// Append every email to a file instead of sending it
+pub struct FileMailer {
+    path: PathBuf,
+
+    // Keep concurrent emails from interleaving
+    lock: Mutex<()>,
+}
+
+impl FileMailer {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        let _guard = self.lock.lock().await;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+
+        let entry = format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+            email.from, email.to, email.subject, email.body
+        );
+
+        file.write_all(entry.as_bytes()).await?;
+
+        // tokio finishes writes in the background - make sure it has by the time we return
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileMailer;
+    use crate::mail::{Email, Mailer};
+
+    #[tokio::test]
+    async fn appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mail.log");
+        let mailer = FileMailer::new(path.clone());
+
+        for to in ["ellie@example.com", "conrad@example.com"] {
+            mailer
+                .send(&Email {
+                    from: "atuin@example.com".into(),
+                    to: to.into(),
+                    subject: "Verify your account".into(),
+                    body: "atuin account verify --token 'abc'".into(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let contents = fs_err::read_to_string(path).unwrap();
+
+        assert_eq!(
+            contents,
+            "From: atuin@example.com\nTo: ellie@example.com\nSubject: Verify your account\n\natuin account verify --token 'abc'\n\n\
+             From: atuin@example.com\nTo: conrad@example.com\nSubject: Verify your account\n\natuin account verify --token 'abc'\n\n"
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use eyre::Result;
+use postmark::{
+    Query,
+    api::{Body, email::SendEmailRequest},
+    reqwest::PostmarkClient,
+};
+
+use super::{Email, Mailer};
+
+pub struct PostmarkMailer {
+    client: PostmarkClient,
+}
+
+impl PostmarkMailer {
+    pub fn new(token: String) -> Self {
+        let client = PostmarkClient::builder()
+            .base_url("https://api.postmarkapp.com/")
+            .server_token(token)
+            .build();
+
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Mailer for PostmarkMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        let req = SendEmailRequest::builder()
+            .from(email.from.clone())
+            .subject(email.subject.clone())
+            .to(email.to.clone())
+            .body(Body::text(email.body.clone()))
+            .build();
+
+        req.execute(&self.client).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use eyre::{Result, bail};
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, message::header::ContentType,
+    transport::smtp::authentication::Credentials,
+};
+
+use super::{Email, Mailer};
+use crate::settings::{Smtp, SmtpTls};
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    /// Configure the transport. Nothing connects to the relay until we send something.
+    pub fn new(settings: &Smtp) -> Result<Self> {
+        if settings.host.is_empty() {
+            bail!("mail.smtp.host is not set");
+        }
+
+        let mut builder = match settings.tls {
+            SmtpTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+            }
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?,
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            }
+        };
+
+        if let Some(port) = settings.port {
+            builder = builder.port(port);
+        }
+
+        match (&settings.username, &settings.password) {
+            (Some(username), Some(password)) => {
+                builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+            }
+            (None, None) => {}
+            _ => bail!("mail.smtp.username and mail.smtp.password must be set together"),
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        let message = Message::builder()
+            .from(email.from.parse()?)
+            .to(email.to.parse()?)
+            .subject(email.subject.clone())
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())?;
+
+        self.transport.send(message).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SmtpMailer;
+    use crate::settings::{Smtp, SmtpTls};
+
+    #[tokio::test]
+    async fn configure() {
+        for tls in [SmtpTls::Starttls, SmtpTls::Tls, SmtpTls::None] {
+            let settings = Smtp {
+                host: "smtp.example.com".into(),
+                port: Some(2525),
+                tls,
+                username: Some("atuin".into()),
+                password: Some("hunter2".into()),
+            };
+
+            assert!(SmtpMailer::new(&settings).is_ok(), "{tls:?}");
+        }
+    }
+
+    #[test]
+    fn credentials_go_together() {
+        let settings = Smtp {
+            host: "smtp.example.com".into(),
+            username: Some("atuin".into()),
+            ..Default::default()
+        };
+
+        assert!(SmtpMailer::new(&settings).is_err());
+        assert!(SmtpMailer::new(&Smtp::default()).is_err());
+    }
+}
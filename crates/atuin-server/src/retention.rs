@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use atuin_server_database::Database;
+use metrics::counter;
+use time::OffsetDateTime;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::settings::Retention;
+
+/// Periodically delete records older than the retention window. Runs until the server exits.
+pub async fn run<DB: Database>(database: DB, retention: Retention) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(retention.interval.max(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let window = time::Duration::days(retention.days as i64);
+
+    loop {
+        ticker.tick().await;
+
+        let before = OffsetDateTime::now_utc() - window;
+
+        match database.delete_records_before(before).await {
+            Ok(deleted) => {
+                counter!("atuin_record_expired", deleted);
+                info!(deleted, %before, "deleted expired records");
+            }
+            Err(e) => error!("failed to delete expired records: {}", e),
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use std::{marker::PhantomData, sync::Arc};
+
 use async_trait::async_trait;
-use atuin_common::api::{ATUIN_CARGO_VERSION, ATUIN_HEADER_VERSION, ErrorResponse};
+use atuin_common::api::{ATUIN_CARGO_VERSION, ATUIN_HEADER_VERSION, ErrorResponse, TokenScope};
 use axum::{
     Router,
     extract::{FromRequestParts, Request},
@@ -14,14 +16,87 @@ use tower_http::trace::TraceLayer;
 
 use super::handlers;
 use crate::{
-    handlers::{ErrorResponseStatus, RespExt},
-    metrics,
+    handlers::{
+        ErrorResponseStatus, RespExt,
+        v0::token::{API_TOKEN_PREFIX, hash_token},
+    },
+    mail::{self, Mailer},
+    metrics, oidc,
     settings::Settings,
 };
-use atuin_server_database::{Database, DbError, models::User};
+use atuin_server_database::{
+    Database, DbError,
+    models::{Session, User},
+};
 
 pub struct UserAuth(pub User);
 
+/// Like [`UserAuth`], but also gives the session the request was made with
+pub struct SessionAuth(pub User, pub Session);
+
+fn auth_token(req: &Parts) -> Result<&str, ErrorResponseStatus<'static>> {
+    let auth_header = req
+        .headers
+        .get(http::header::AUTHORIZATION)
+        .ok_or_else(|| {
+            ErrorResponse::reply("missing authorization header")
+                .with_status(http::StatusCode::BAD_REQUEST)
+        })?;
+    let auth_header = auth_header.to_str().map_err(|_| {
+        ErrorResponse::reply("invalid authorization header encoding")
+            .with_status(http::StatusCode::BAD_REQUEST)
+    })?;
+    let (typ, token) = auth_header.split_once(' ').ok_or_else(|| {
+        ErrorResponse::reply("invalid authorization header encoding")
+            .with_status(http::StatusCode::BAD_REQUEST)
+    })?;
+
+    if typ != "Token" {
+        return Err(
+            ErrorResponse::reply("invalid authorization header encoding")
+                .with_status(http::StatusCode::BAD_REQUEST),
+        );
+    }
+
+    Ok(token)
+}
+
+fn session_error(e: DbError) -> ErrorResponseStatus<'static> {
+    match e {
+        DbError::NotFound => {
+            ErrorResponse::reply("session not found").with_status(http::StatusCode::FORBIDDEN)
+        }
+        DbError::Other(e) => {
+            tracing::error!(error = ?e, "could not query user session");
+            ErrorResponse::reply("could not query user session")
+                .with_status(http::StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn session_user<DB: Database>(
+    state: &AppState<DB>,
+    token: &str,
+) -> Result<User, ErrorResponseStatus<'static>> {
+    let user = state
+        .database
+        .get_session_user(token)
+        .await
+        .map_err(session_error)?;
+
+    if user.disabled.is_some() {
+        return Err(ErrorResponse::reply("this account has been disabled")
+            .with_status(http::StatusCode::FORBIDDEN));
+    }
+
+    // Only used to show the user when each session was last active, so don't fail the request
+    if let Err(e) = state.database.touch_session(token).await {
+        tracing::warn!(error = ?e, "could not update session last used time");
+    }
+
+    Ok(user)
+}
+
 #[async_trait]
 impl<DB: Send + Sync> FromRequestParts<AppState<DB>> for UserAuth
 where
@@ -33,44 +108,128 @@ where
         req: &mut Parts,
         state: &AppState<DB>,
     ) -> Result<Self, Self::Rejection> {
-        let auth_header = req
-            .headers
-            .get(http::header::AUTHORIZATION)
-            .ok_or_else(|| {
-                ErrorResponse::reply("missing authorization header")
-                    .with_status(http::StatusCode::BAD_REQUEST)
-            })?;
-        let auth_header = auth_header.to_str().map_err(|_| {
-            ErrorResponse::reply("invalid authorization header encoding")
-                .with_status(http::StatusCode::BAD_REQUEST)
-        })?;
-        let (typ, token) = auth_header.split_once(' ').ok_or_else(|| {
-            ErrorResponse::reply("invalid authorization header encoding")
-                .with_status(http::StatusCode::BAD_REQUEST)
-        })?;
+        let token = auth_token(req)?;
+        let user = session_user(state, token).await?;
 
-        if typ != "Token" {
-            return Err(
-                ErrorResponse::reply("invalid authorization header encoding")
-                    .with_status(http::StatusCode::BAD_REQUEST),
-            );
-        }
+        Ok(UserAuth(user))
+    }
+}
 
-        let user = state
+#[async_trait]
+impl<DB: Send + Sync> FromRequestParts<AppState<DB>> for SessionAuth
+where
+    DB: Database,
+{
+    type Rejection = ErrorResponseStatus<'static>;
+
+    async fn from_request_parts(
+        req: &mut Parts,
+        state: &AppState<DB>,
+    ) -> Result<Self, Self::Rejection> {
+        let token = auth_token(req)?;
+        let user = session_user(state, token).await?;
+        let session = state
             .database
-            .get_session_user(token)
+            .get_session(token)
             .await
-            .map_err(|e| match e {
-                DbError::NotFound => ErrorResponse::reply("session not found")
-                    .with_status(http::StatusCode::FORBIDDEN),
-                DbError::Other(e) => {
-                    tracing::error!(error = ?e, "could not query user session");
-                    ErrorResponse::reply("could not query user session")
-                        .with_status(http::StatusCode::INTERNAL_SERVER_ERROR)
-                }
-            })?;
+            .map_err(session_error)?;
 
-        Ok(UserAuth(user))
+        Ok(SessionAuth(user, session))
+    }
+}
+
+/// The scopes a handler can require of an API token. See [`Scoped`].
+pub mod scope {
+    use atuin_common::api::TokenScope;
+
+    pub trait Scope {
+        const SCOPE: TokenScope;
+    }
+
+    pub struct ReadStatus;
+    pub struct ReadRecords;
+    pub struct WriteRecords;
+
+    impl Scope for ReadStatus {
+        const SCOPE: TokenScope = TokenScope::ReadStatus;
+    }
+
+    impl Scope for ReadRecords {
+        const SCOPE: TokenScope = TokenScope::ReadRecords;
+    }
+
+    impl Scope for WriteRecords {
+        const SCOPE: TokenScope = TokenScope::WriteRecords;
+    }
+}
+
+/// Like [`UserAuth`], but also accepts an API token, if it was created with the scope `S`
+pub struct Scoped<S>(pub User, pub PhantomData<S>);
+
+async fn api_token_user<DB: Database>(
+    state: &AppState<DB>,
+    token: &str,
+    scope: TokenScope,
+) -> Result<User, ErrorResponseStatus<'static>> {
+    let token_hash = hash_token(token);
+
+    let api_token = state
+        .database
+        .get_api_token(&token_hash)
+        .await
+        .map_err(|e| match e {
+            DbError::NotFound => {
+                ErrorResponse::reply("token not found").with_status(http::StatusCode::FORBIDDEN)
+            }
+            DbError::Other(e) => {
+                tracing::error!(error = ?e, "could not query api token");
+                ErrorResponse::reply("could not query api token")
+                    .with_status(http::StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        })?;
+
+    if !api_token.scopes.iter().any(|s| s == scope.as_str()) {
+        return Err(ErrorResponse {
+            reason: format!("this token does not have the {scope} scope").into(),
+        }
+        .with_status(http::StatusCode::FORBIDDEN));
+    }
+
+    let user = state
+        .database
+        .get_api_token_user(&token_hash)
+        .await
+        .map_err(session_error)?;
+
+    if user.disabled.is_some() {
+        return Err(ErrorResponse::reply("this account has been disabled")
+            .with_status(http::StatusCode::FORBIDDEN));
+    }
+
+    Ok(user)
+}
+
+#[async_trait]
+impl<DB: Send + Sync, S> FromRequestParts<AppState<DB>> for Scoped<S>
+where
+    DB: Database,
+    S: scope::Scope,
+{
+    type Rejection = ErrorResponseStatus<'static>;
+
+    async fn from_request_parts(
+        req: &mut Parts,
+        state: &AppState<DB>,
+    ) -> Result<Self, Self::Rejection> {
+        let token = auth_token(req)?;
+
+        let user = if token.starts_with(API_TOKEN_PREFIX) {
+            api_token_user(state, token, S::SCOPE).await?
+        } else {
+            session_user(state, token).await?
+        };
+
+        Ok(Scoped(user, PhantomData))
     }
 }
 
@@ -106,9 +265,28 @@ async fn semver(request: Request, next: Next) -> Response {
 pub struct AppState<DB: Database> {
     pub database: DB,
     pub settings: Settings,
+
+    /// Set if single sign-on is enabled
+    pub oidc: Option<Arc<oidc::Provider>>,
+
+    /// Set if mail is enabled and configured correctly
+    pub mailer: Option<Arc<dyn Mailer>>,
 }
 
 pub fn router<DB: Database>(database: DB, settings: Settings) -> Router {
+    let oidc = settings
+        .oidc
+        .enable
+        .then(|| Arc::new(oidc::Provider::new(settings.oidc.clone())));
+
+    let mailer = match mail::from_settings(&settings.mail) {
+        Ok(mailer) => mailer.map(Arc::from),
+        Err(e) => {
+            tracing::error!("mail is not configured correctly, no mail will be sent: {e}");
+            None
+        }
+    };
+
     let routes = Router::new()
         .route("/", get(handlers::index))
         .route("/healthz", get(handlers::health::health_check))
@@ -128,6 +306,28 @@ pub fn router<DB: Database>(database: DB, settings: Settings) -> Router {
         .route("/record/next", get(handlers::record::next))
         .route("/api/v0/me", get(handlers::v0::me::get))
         .route("/api/v0/account/verify", post(handlers::user::verify_user))
+        .route(
+            "/api/v0/account/password/reset",
+            post(handlers::user::reset_password),
+        )
+        .route(
+            "/api/v0/login/oidc/device",
+            post(handlers::v0::oidc::device),
+        )
+        .route("/api/v0/login/oidc/token", post(handlers::v0::oidc::token))
+        .route("/api/v0/account/sessions", get(handlers::v0::session::list))
+        .route(
+            "/api/v0/account/sessions/:id",
+            delete(handlers::v0::session::revoke),
+        )
+        .route(
+            "/api/v0/account/tokens",
+            get(handlers::v0::token::list).post(handlers::v0::token::create),
+        )
+        .route(
+            "/api/v0/account/tokens/:id",
+            delete(handlers::v0::token::revoke),
+        )
         .route(
             "/api/v0/account/send-verification",
             post(handlers::user::send_verification),
@@ -135,7 +335,27 @@ pub fn router<DB: Database>(database: DB, settings: Settings) -> Router {
         .route("/api/v0/record", post(handlers::v0::record::post))
         .route("/api/v0/record", get(handlers::v0::record::index))
         .route("/api/v0/record/next", get(handlers::v0::record::next))
-        .route("/api/v0/store", delete(handlers::v0::store::delete));
+        .route("/api/v0/store", delete(handlers::v0::store::delete))
+        .route(
+            "/api/v0/teams",
+            get(handlers::v0::team::list).post(handlers::v0::team::create),
+        )
+        .route(
+            "/api/v0/teams/:team/members",
+            get(handlers::v0::team::members).post(handlers::v0::team::add_member),
+        )
+        .route(
+            "/api/v0/teams/:team/members/:username",
+            delete(handlers::v0::team::remove_member),
+        )
+        .route(
+            "/api/v0/teams/:team/record",
+            get(handlers::v0::team::record_index).post(handlers::v0::team::record_post),
+        )
+        .route(
+            "/api/v0/teams/:team/record/next",
+            get(handlers::v0::team::record_next),
+        );
 
     let path = settings.path.as_str();
     if path.is_empty() {
@@ -144,7 +364,12 @@ pub fn router<DB: Database>(database: DB, settings: Settings) -> Router {
         Router::new().nest(path, routes)
     }
     .fallback(teapot)
-    .with_state(AppState { database, settings })
+    .with_state(AppState {
+        database,
+        settings,
+        oidc,
+        mailer,
+    })
     .layer(
         ServiceBuilder::new()
             .layer(axum::middleware::from_fn(clacks_overhead))
@@ -13,15 +13,68 @@ pub struct Mail {
     #[serde(alias = "enable")]
     pub enabled: bool,
 
+    /// How to send mail. Defaults to postmark, for existing configs.
+    #[serde(default)]
+    pub transport: MailTransport,
+
     /// Configuration for the postmark api client
     /// This is what we use for Atuin Cloud, the forum, etc.
     #[serde(default)]
     pub postmark: Postmark,
 
+    #[serde(default)]
+    pub smtp: Smtp,
+
+    #[serde(default)]
+    pub file: MailFile,
+
     #[serde(default)]
     pub verification: MailVerification,
 }
 
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    #[default]
+    Postmark,
+    Smtp,
+    File,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Connect in plain text, then upgrade with STARTTLS. Usually port 587.
+    #[default]
+    Starttls,
+
+    /// Connect with TLS from the start. Usually port 465.
+    Tls,
+
+    /// No encryption at all. Only for relays on a network you trust.
+    None,
+}
+
+#[derive(Default, Clone, Debug, Deserialize, Serialize)]
+pub struct Smtp {
+    pub host: String,
+
+    /// Defaults to the usual port for the chosen TLS mode
+    pub port: Option<u16>,
+
+    #[serde(default)]
+    pub tls: SmtpTls,
+
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Append mail to a file, rather than sending it. Handy for testing.
+#[derive(Default, Clone, Debug, Deserialize, Serialize)]
+pub struct MailFile {
+    pub path: PathBuf,
+}
+
 #[derive(Default, Clone, Debug, Deserialize, Serialize)]
 pub struct Postmark {
     #[serde(alias = "token")]
@@ -53,6 +106,151 @@ impl Default for Metrics {
     }
 }
 
+/// Limits on how much a single user may keep in the record store. 0 means no limit.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Quota {
+    pub max_records: u64,
+
+    /// Total size of a user's encrypted record data, in bytes
+    pub max_bytes: u64,
+}
+
+impl Quota {
+    pub fn enabled(&self) -> bool {
+        self.max_records > 0 || self.max_bytes > 0
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Retention {
+    /// Delete records that were received more than this many days ago. 0 keeps them forever.
+    pub days: u64,
+
+    /// How often to look for expired records, in seconds
+    pub interval: u64,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            days: 0,
+            interval: 60 * 60,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    UserRegistered,
+    UserDeleted,
+    PasswordChanged,
+    StoreDeleted,
+    QuotaExceeded,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UserRegistered => "user_registered",
+            Self::UserDeleted => "user_deleted",
+            Self::PasswordChanged => "password_changed",
+            Self::StoreDeleted => "store_deleted",
+            Self::QuotaExceeded => "quota_exceeded",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+
+    /// Sign every payload with HMAC-SHA256, using this secret
+    pub secret: Option<String>,
+
+    /// Only send these events. Empty sends everything.
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+}
+
+impl WebhookEndpoint {
+    pub fn wants(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Webhooks {
+    #[serde(default)]
+    pub endpoints: Vec<WebhookEndpoint>,
+
+    /// Give up on a delivery after this many attempts
+    pub max_attempts: u32,
+
+    /// Seconds to wait before retrying a failed delivery. Doubles with every attempt, up to an
+    /// hour.
+    pub retry_interval: u64,
+
+    /// How often to look for deliveries, in seconds
+    pub poll_interval: u64,
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            max_attempts: 10,
+            retry_interval: 30,
+            poll_interval: 5,
+        }
+    }
+}
+
+/// Single sign-on with an OpenID Connect provider, using the device authorization grant so that
+/// it works from a terminal
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Oidc {
+    #[serde(alias = "enabled")]
+    pub enable: bool,
+
+    /// The issuer URL. Endpoints are found with OpenID Connect discovery.
+    pub issuer: String,
+
+    pub client_id: String,
+    pub client_secret: Option<String>,
+
+    pub scopes: Vec<String>,
+
+    /// The claim to take a new user's username from
+    pub username_claim: String,
+
+    /// Create an account for identities we have not seen before. If false, an admin must link
+    /// them to an existing user.
+    pub register: bool,
+
+    /// Still allow logging in and registering with a password
+    pub password_login: bool,
+}
+
+impl Default for Oidc {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            issuer: String::new(),
+            client_id: String::new(),
+            client_secret: None,
+            scopes: vec![
+                String::from("openid"),
+                String::from("profile"),
+                String::from("email"),
+            ],
+            username_claim: String::from("preferred_username"),
+            register: true,
+            password_login: true,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Settings {
     pub host: String,
@@ -67,6 +265,10 @@ pub struct Settings {
     pub metrics: Metrics,
     pub tls: Tls,
     pub mail: Mail,
+    pub quota: Quota,
+    pub retention: Retention,
+    pub webhooks: Webhooks,
+    pub oidc: Oidc,
 
     /// Advertise a version that is not what we are _actually_ running
     /// Many clients compare their version with api.atuin.sh, and if they differ, notify the user
@@ -109,6 +311,20 @@ impl Settings {
             .set_default("tls.enable", false)?
             .set_default("tls.cert_path", "")?
             .set_default("tls.pkey_path", "")?
+            .set_default("quota.max_records", 0)?
+            .set_default("quota.max_bytes", 0)?
+            .set_default("retention.days", 0)?
+            .set_default("retention.interval", 60 * 60)?
+            .set_default("webhooks.max_attempts", 10)?
+            .set_default("webhooks.retry_interval", 30)?
+            .set_default("webhooks.poll_interval", 5)?
+            .set_default("oidc.enable", false)?
+            .set_default("oidc.issuer", "")?
+            .set_default("oidc.client_id", "")?
+            .set_default("oidc.scopes", vec!["openid", "profile", "email"])?
+            .set_default("oidc.username_claim", "preferred_username")?
+            .set_default("oidc.register", true)?
+            .set_default("oidc.password_login", true)?
             .add_source(
                 Environment::with_prefix("atuin")
                     .prefix_separator("_")
@@ -0,0 +1,246 @@
+// Webhooks are queued in the database as part of handling a request, and delivered by a
+// background worker. A delivery that fails is retried with exponential backoff, so endpoints see
+// every event at least once - unless they stay down for longer than max_attempts allows.
+//
+// Every request carries the event name in X-Atuin-Event, and, if the endpoint has a secret, an
+// HMAC-SHA256 of the body in X-Atuin-Signature-256 ("sha256=<hex>").
+
+use std::time::Duration;
+
+use atuin_server_database::{
+    Database,
+    models::{NewWebhookDelivery, WebhookDelivery},
+};
+use eyre::Result;
+use hmac::{Hmac, Mac};
+use metrics::counter;
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use sha2::Sha256;
+use time::OffsetDateTime;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, error, warn};
+
+use crate::settings::{WebhookEndpoint, WebhookEvent, Webhooks};
+
+pub const EVENT_HEADER: &str = "X-Atuin-Event";
+pub const SIGNATURE_HEADER: &str = "X-Atuin-Signature-256";
+
+const MAX_BACKOFF: u64 = 60 * 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookUser<'a> {
+    pub id: i64,
+    pub username: &'a str,
+}
+
+/// The body of every webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct Payload<'a> {
+    /// Unique per event, so receivers can ignore a delivery they have already seen
+    pub id: String,
+    pub event: WebhookEvent,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+
+    pub user: WebhookUser<'a>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'a str>,
+}
+
+/// Queue an event for every endpoint that wants it. Best effort - a failure to queue is logged,
+/// and never fails the request that caused it.
+pub async fn emit<DB: Database>(
+    database: &DB,
+    settings: &Webhooks,
+    event: WebhookEvent,
+    user: WebhookUser<'_>,
+    reason: Option<&str>,
+) {
+    let endpoints = settings
+        .endpoints
+        .iter()
+        .filter(|e| e.wants(event))
+        .collect::<Vec<_>>();
+
+    if endpoints.is_empty() {
+        return;
+    }
+
+    let payload = Payload {
+        id: atuin_common::utils::uuid_v7().as_simple().to_string(),
+        event,
+        timestamp: OffsetDateTime::now_utc(),
+        user,
+        reason,
+    };
+
+    let payload = match serde_json::to_string(&payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            error!("failed to serialize webhook payload: {e}");
+            return;
+        }
+    };
+
+    let deliveries = endpoints
+        .into_iter()
+        .map(|e| NewWebhookDelivery {
+            url: e.url.clone(),
+            event: event.as_str().to_string(),
+            payload: payload.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    if let Err(e) = database.add_webhook_deliveries(&deliveries).await {
+        error!("failed to queue {} webhook: {e}", event.as_str());
+    }
+}
+
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any size");
+    mac.update(body.as_bytes());
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// How long to wait before the next attempt, after `attempts` failures
+fn backoff(retry_interval: u64, attempts: u32) -> Duration {
+    let secs = retry_interval
+        .saturating_mul(2u64.saturating_pow(attempts.saturating_sub(1)))
+        .min(MAX_BACKOFF);
+
+    Duration::from_secs(secs)
+}
+
+async fn send(
+    client: &reqwest::Client,
+    endpoint: &WebhookEndpoint,
+    delivery: &WebhookDelivery,
+) -> Result<()> {
+    let mut req = client
+        .post(&endpoint.url)
+        .timeout(Duration::from_secs(10))
+        .header(CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &delivery.event);
+
+    if let Some(secret) = &endpoint.secret {
+        req = req.header(SIGNATURE_HEADER, sign(secret, &delivery.payload));
+    }
+
+    req.body(delivery.payload.clone())
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+async fn deliver_due<DB: Database>(
+    database: &DB,
+    settings: &Webhooks,
+    client: &reqwest::Client,
+) -> Result<()> {
+    let due = database
+        .due_webhook_deliveries(OffsetDateTime::now_utc(), 100)
+        .await?;
+
+    for delivery in due {
+        // The endpoint was removed from the config since this was queued
+        let Some(endpoint) = settings.endpoints.iter().find(|e| e.url == delivery.url) else {
+            warn!(url = delivery.url, "dropping webhook for unknown endpoint");
+            database.delete_webhook_delivery(delivery.id).await?;
+            continue;
+        };
+
+        match send(client, endpoint, &delivery).await {
+            Ok(()) => {
+                debug!(
+                    url = delivery.url,
+                    event = delivery.event,
+                    "webhook delivered"
+                );
+                counter!("atuin_webhook_delivered", 1);
+
+                database.delete_webhook_delivery(delivery.id).await?;
+            }
+
+            Err(e) => {
+                let attempts = delivery.attempts as u32 + 1;
+
+                if attempts >= settings.max_attempts {
+                    error!(
+                        url = delivery.url,
+                        event = delivery.event,
+                        "giving up on webhook after {attempts} attempts: {e}"
+                    );
+                    counter!("atuin_webhook_dropped", 1);
+
+                    database.delete_webhook_delivery(delivery.id).await?;
+                } else {
+                    let wait = backoff(settings.retry_interval, attempts);
+                    warn!(
+                        url = delivery.url,
+                        event = delivery.event,
+                        "webhook failed, retrying in {}s: {e}",
+                        wait.as_secs()
+                    );
+                    counter!("atuin_webhook_failed", 1);
+
+                    database
+                        .retry_webhook_delivery(
+                            delivery.id,
+                            OffsetDateTime::now_utc() + wait,
+                            &e.to_string(),
+                        )
+                        .await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Deliver queued webhooks until the server exits
+pub async fn run<DB: Database>(database: DB, settings: Webhooks) {
+    let client = reqwest::Client::new();
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(settings.poll_interval.max(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+
+        if let Err(e) = deliver_due(&database, &settings, &client).await {
+            error!("failed to deliver webhooks: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{backoff, sign};
+
+    #[test]
+    fn signature() {
+        assert_eq!(
+            sign("key", "The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_an_hour() {
+        assert_eq!(backoff(30, 1), Duration::from_secs(30));
+        assert_eq!(backoff(30, 2), Duration::from_secs(60));
+        assert_eq!(backoff(30, 3), Duration::from_secs(120));
+        assert_eq!(backoff(30, 20), Duration::from_secs(60 * 60));
+        assert_eq!(backoff(30, u32::MAX), Duration::from_secs(60 * 60));
+    }
+}
@@ -14,6 +14,9 @@ mod sync;
 #[cfg(feature = "sync")]
 mod account;
 
+#[cfg(feature = "sync")]
+mod team;
+
 #[cfg(feature = "daemon")]
 mod daemon;
 
@@ -29,6 +32,7 @@ mod scripts;
 mod search;
 mod stats;
 mod store;
+mod suggest;
 mod wrapped;
 
 #[derive(Subcommand, Debug)]
@@ -48,6 +52,9 @@ pub enum Cmd {
     /// Interactive history search
     Search(search::Cmd),
 
+    /// Suggest the command that usually comes next, or how to finish the one being typed
+    Suggest(suggest::Cmd),
+
     #[cfg(feature = "sync")]
     #[command(flatten)]
     Sync(sync::Cmd),
@@ -56,8 +63,12 @@ pub enum Cmd {
     #[cfg(feature = "sync")]
     Account(account::Cmd),
 
-    /// Get or set small key-value pairs
+    /// Share key-value pairs and scripts with a team
+    #[cfg(feature = "sync")]
     #[command(subcommand)]
+    Team(team::Cmd),
+
+    /// Get or set small key-value pairs
     Kv(kv::Cmd),
 
     /// Manage the atuin data store
@@ -69,7 +80,6 @@ pub enum Cmd {
     Dotfiles(dotfiles::Cmd),
 
     /// Manage your scripts with Atuin
-    #[command(subcommand)]
     Scripts(scripts::Cmd),
 
     /// Print Atuin's shell init script
@@ -134,6 +144,7 @@ impl Cmd {
         match self {
             Self::History(history) => return history.run(&settings).await,
             Self::Init(init) => return init.run(&settings).await,
+            Self::Suggest(suggest) => return suggest.run(&settings).await,
             Self::Doctor => return doctor::run(&settings).await,
             _ => {}
         }
@@ -158,6 +169,9 @@ impl Cmd {
             #[cfg(feature = "sync")]
             Self::Account(account) => account.run(settings, sqlite_store).await,
 
+            #[cfg(feature = "sync")]
+            Self::Team(team) => team.run(&settings, &sqlite_store).await,
+
             Self::Kv(kv) => kv.run(&settings, &sqlite_store).await,
 
             Self::Store(store) => store.run(&settings, &db, sqlite_store).await,
@@ -181,7 +195,7 @@ impl Cmd {
             #[cfg(feature = "daemon")]
             Self::Daemon => daemon::run(settings, sqlite_store, db).await,
 
-            Self::History(_) | Self::Init(_) | Self::Doctor => unreachable!(),
+            Self::History(_) | Self::Init(_) | Self::Doctor | Self::Suggest(_) => unreachable!(),
         }
     }
 }
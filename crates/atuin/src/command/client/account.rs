@@ -9,6 +9,9 @@ pub mod delete;
 pub mod login;
 pub mod logout;
 pub mod register;
+pub mod reset_password;
+pub mod sessions;
+pub mod token;
 pub mod verify;
 
 #[derive(Args, Debug)]
@@ -34,8 +37,19 @@ pub enum Commands {
     /// Change your password
     ChangePassword(change_password::Cmd),
 
+    /// Set a new password, using a reset token from your server admin
+    ResetPassword(reset_password::Cmd),
+
     /// Verify your account
     Verify(verify::Cmd),
+
+    /// List and revoke the machines logged in to your account
+    #[command(subcommand)]
+    Sessions(sessions::Cmd),
+
+    /// Manage API tokens, for machines that should only have limited access
+    #[command(subcommand)]
+    Token(token::Cmd),
 }
 
 impl Cmd {
@@ -46,7 +60,10 @@ impl Cmd {
             Commands::Logout => logout::run(&settings),
             Commands::Delete => delete::run(&settings).await,
             Commands::ChangePassword(c) => c.run(&settings).await,
+            Commands::ResetPassword(c) => c.run(&settings).await,
             Commands::Verify(c) => c.run(&settings).await,
+            Commands::Sessions(c) => c.run(&settings).await,
+            Commands::Token(c) => c.run(&settings).await,
         }
     }
 }
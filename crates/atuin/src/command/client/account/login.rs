@@ -6,7 +6,8 @@ use tokio::{fs::File, io::AsyncWriteExt};
 
 use atuin_client::{
     api_client,
-    encryption::{Key, decode_key, encode_key, load_key},
+    encryption::{Key, Keys, decode_key, encode_key, load_key},
+    login::sso,
     record::sqlite_store::SqliteStore,
     record::store::Store,
     settings::Settings,
@@ -22,6 +23,24 @@ pub struct Cmd {
     #[clap(long, short)]
     pub password: Option<String>,
 
+    /// A name for this session, shown by `atuin account sessions list`. Defaults to the hostname.
+    #[clap(long)]
+    pub device_name: Option<String>,
+
+    /// Log in with your sync server's single sign-on provider, instead of a password
+    #[clap(long, conflicts_with_all = ["username", "password"])]
+    pub sso: bool,
+
+    /// An invite code, for a first single sign-on login to a server that is not open for
+    /// registration
+    #[clap(long, requires = "sso")]
+    pub invite: Option<String>,
+
+    /// Use an API token from `atuin account token create`, instead of logging in. The machine
+    /// can only do what the token's scopes allow.
+    #[clap(long, conflicts_with_all = ["username", "password", "sso", "device_name"])]
+    pub api_token: Option<String>,
+
     /// The encryption key for your account
     #[clap(long, short)]
     pub key: Option<String>,
@@ -47,8 +66,12 @@ impl Cmd {
             );
         }
 
-        let username = or_user_input(self.username.clone(), "username");
-        let password = self.password.clone().unwrap_or_else(read_user_password);
+        let credentials = (!self.sso && self.api_token.is_none()).then(|| {
+            let username = or_user_input(self.username.clone(), "username");
+            let password = self.password.clone().unwrap_or_else(read_user_password);
+
+            (username, password)
+        });
 
         let key_path = settings.key_path.as_str();
         let key_path = PathBuf::from(key_path);
@@ -66,38 +89,7 @@ impl Cmd {
             "encryption key [blank to use existing key file]",
         );
 
-        // if provided, the key may be EITHER base64, or a bip mnemonic
-        // try to normalize on base64
-        let key = if key.is_empty() {
-            key
-        } else {
-            // try parse the key as a mnemonic...
-            match bip39::Mnemonic::from_phrase(&key, bip39::Language::English) {
-                Ok(mnemonic) => encode_key(Key::from_slice(mnemonic.entropy()))?,
-                Err(err) => {
-                    match err.downcast_ref::<bip39::ErrorKind>() {
-                        Some(err) => {
-                            match err {
-                                // assume they copied in the base64 key
-                                bip39::ErrorKind::InvalidWord => key,
-                                bip39::ErrorKind::InvalidChecksum => {
-                                    bail!("key mnemonic was not valid")
-                                }
-                                bip39::ErrorKind::InvalidKeysize(_)
-                                | bip39::ErrorKind::InvalidWordLength(_)
-                                | bip39::ErrorKind::InvalidEntropyLength(_, _) => {
-                                    bail!("key was not the correct length")
-                                }
-                            }
-                        }
-                        _ => {
-                            // unknown error. assume they copied the base64 key
-                            key
-                        }
-                    }
-                }
-            }
-        };
+        let key = normalize_key(key)?;
 
         // I've simplified this a little, but it could really do with a refactor
         // Annoyingly, it's also very important to get it correct
@@ -136,7 +128,11 @@ impl Cmd {
             if new_key != current_key {
                 println!("\nRe-encrypting local store with new key");
 
-                store.re_encrypt(&current_key, &new_key).await?;
+                // Tags with keys of their own are left alone
+                let keys = Keys::load(settings)?;
+                store
+                    .re_encrypt(&keys, &keys.clone().with_main(new_key))
+                    .await?;
 
                 println!("Writing new key");
                 let mut file = File::create(key_path).await?;
@@ -144,20 +140,101 @@ impl Cmd {
             }
         }
 
-        let session = api_client::login(
-            settings.sync_address.as_str(),
-            LoginRequest { username, password },
-        )
-        .await?;
+        let session = self.new_session(settings, credentials).await?;
 
         let session_path = settings.session_path.as_str();
         let mut file = File::create(session_path).await?;
-        file.write_all(session.session.as_bytes()).await?;
+        file.write_all(session.as_bytes()).await?;
 
         println!("Logged in!");
 
         Ok(())
     }
+
+    async fn new_session(
+        &self,
+        settings: &Settings,
+        credentials: Option<(String, String)>,
+    ) -> Result<String> {
+        if let Some(token) = &self.api_token {
+            return Ok(token.clone());
+        }
+
+        let Some((username, password)) = credentials else {
+            return sso_login(settings, self.device_name.clone(), self.invite.clone()).await;
+        };
+
+        let resp = api_client::login(
+            settings.sync_address.as_str(),
+            LoginRequest {
+                username,
+                password,
+                device: self.device_name.clone(),
+            },
+        )
+        .await?;
+
+        Ok(resp.session)
+    }
+}
+
+async fn sso_login(
+    settings: &Settings,
+    device_name: Option<String>,
+    invite: Option<String>,
+) -> Result<String> {
+    let (session, username) = sso(settings, device_name, invite, |device| {
+        println!();
+
+        if let Some(uri) = &device.verification_uri_complete {
+            println!("To log in, open {uri}");
+            println!("and check that it shows the code {}", device.user_code);
+        } else {
+            println!("To log in, open {}", device.verification_uri);
+            println!("and enter the code {}", device.user_code);
+        }
+
+        println!("\nWaiting for you to finish logging in...");
+    })
+    .await?;
+
+    println!("Logged in to the sync server as {username}");
+
+    Ok(session)
+}
+
+/// A key may be given EITHER as base64, or as a bip mnemonic. Normalize on base64.
+pub fn normalize_key(key: String) -> Result<String> {
+    if key.is_empty() {
+        return Ok(key);
+    }
+
+    // try parse the key as a mnemonic...
+    match bip39::Mnemonic::from_phrase(&key, bip39::Language::English) {
+        Ok(mnemonic) => encode_key(Key::from_slice(mnemonic.entropy())),
+        Err(err) => {
+            match err.downcast_ref::<bip39::ErrorKind>() {
+                Some(err) => {
+                    match err {
+                        // assume they copied in the base64 key
+                        bip39::ErrorKind::InvalidWord => Ok(key),
+                        bip39::ErrorKind::InvalidChecksum => {
+                            bail!("key mnemonic was not valid")
+                        }
+                        bip39::ErrorKind::InvalidKeysize(_)
+                        | bip39::ErrorKind::InvalidWordLength(_)
+                        | bip39::ErrorKind::InvalidEntropyLength(_, _) => {
+                            bail!("key was not the correct length")
+                        }
+                    }
+                }
+                _ => {
+                    // unknown error. assume they copied the base64 key
+                    Ok(key)
+                }
+            }
+        }
+    }
 }
 
 pub(super) fn or_user_input(value: Option<String>, name: &'static str) -> String {
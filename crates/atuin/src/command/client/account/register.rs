@@ -14,11 +14,22 @@ pub struct Cmd {
 
     #[clap(long, short)]
     pub email: Option<String>,
+
+    /// An invite code, for servers that are not open for registration
+    #[clap(long, short)]
+    pub invite: Option<String>,
 }
 
 impl Cmd {
     pub async fn run(self, settings: &Settings) -> Result<()> {
-        run(settings, self.username, self.email, self.password).await
+        run(
+            settings,
+            self.username,
+            self.email,
+            self.password,
+            self.invite,
+        )
+        .await
     }
 }
 
@@ -27,6 +38,7 @@ pub async fn run(
     username: Option<String>,
     email: Option<String>,
     password: Option<String>,
+    invite: Option<String>,
 ) -> Result<()> {
     use super::login::or_user_input;
     println!("Registering for an Atuin Sync account");
@@ -42,8 +54,14 @@ pub async fn run(
         bail!("please provide a password");
     }
 
-    let session =
-        api_client::register(settings.sync_address.as_str(), &username, &email, &password).await?;
+    let session = api_client::register(
+        settings.sync_address.as_str(),
+        &username,
+        &email,
+        &password,
+        invite.as_deref(),
+    )
+    .await?;
 
     let path = settings.session_path.as_str();
     let mut file = File::create(path).await?;
@@ -0,0 +1,51 @@
+use clap::Parser;
+use eyre::{Result, bail};
+
+use atuin_client::{api_client, settings::Settings};
+use atuin_common::api::ResetPasswordRequest;
+use rpassword::prompt_password;
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    #[clap(long, short)]
+    pub username: String,
+
+    /// The reset token your server admin gave you
+    #[clap(long, short)]
+    pub token: String,
+
+    #[clap(long, short)]
+    pub new_password: Option<String>,
+}
+
+impl Cmd {
+    pub async fn run(self, settings: &Settings) -> Result<()> {
+        let new_password = self.new_password.unwrap_or_else(|| {
+            prompt_password("Please enter the new password: ").expect("Failed to read from input")
+        });
+
+        if new_password.is_empty() {
+            bail!("please provide a new password");
+        }
+
+        api_client::reset_password(
+            &settings.sync_address,
+            ResetPasswordRequest {
+                username: self.username,
+                token: self.token,
+                new_password,
+            },
+        )
+        .await?;
+
+        println!("Account password successfully reset!");
+
+        if settings.logged_in() {
+            println!("Your sessions have been revoked, run 'atuin logout' and 'atuin login' again");
+        } else {
+            println!("Run 'atuin login' to log in with your new password");
+        }
+
+        Ok(())
+    }
+}
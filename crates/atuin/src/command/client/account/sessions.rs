@@ -0,0 +1,73 @@
+use clap::Subcommand;
+use eyre::Result;
+use time::{OffsetDateTime, macros::format_description};
+
+use atuin_client::{api_client, settings::Settings};
+
+static TIME_FMT: &[time::format_description::FormatItem<'static>] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]");
+
+#[derive(Subcommand, Debug)]
+#[command(infer_subcommands = true)]
+pub enum Cmd {
+    /// List the machines logged in to your account
+    List,
+
+    /// Log a machine out, so it can no longer sync. Does not change your password.
+    Revoke {
+        /// The session id, as shown by `atuin account sessions list`
+        id: i64,
+    },
+}
+
+fn time(at: Option<OffsetDateTime>) -> String {
+    at.and_then(|at| at.format(TIME_FMT).ok())
+        .unwrap_or_else(|| String::from("-"))
+}
+
+impl Cmd {
+    pub async fn run(self, settings: &Settings) -> Result<()> {
+        let client = api_client::Client::new(
+            &settings.sync_address,
+            settings.session_token()?.as_str(),
+            settings.network_connect_timeout,
+            settings.network_timeout,
+        )?;
+
+        match self {
+            Self::List => {
+                let sessions = client.sessions().await?.sessions;
+
+                println!(
+                    "{:>6}  {:<32} {:<16}  {:<16}",
+                    "id", "device", "created", "last used"
+                );
+
+                for session in sessions {
+                    let name = session.name.as_deref().unwrap_or("-");
+                    let current = if session.current {
+                        " (this machine)"
+                    } else {
+                        ""
+                    };
+
+                    println!(
+                        "{:>6}  {:<32} {:<16}  {:<16}{current}",
+                        session.id,
+                        name,
+                        time(session.created_at),
+                        time(session.last_used_at),
+                    );
+                }
+            }
+
+            Self::Revoke { id } => {
+                client.revoke_session(id).await?;
+
+                println!("Revoked session {id}");
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use clap::Subcommand;
+use eyre::Result;
+use time::macros::format_description;
+
+use atuin_client::{api_client, settings::Settings};
+use atuin_common::api::TokenScope;
+
+static TIME_FMT: &[time::format_description::FormatItem<'static>] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]");
+
+#[derive(Subcommand, Debug)]
+#[command(infer_subcommands = true)]
+pub enum Cmd {
+    /// Create a long-lived token, which can only do what its scopes allow. Log in with it using
+    /// `atuin login --api-token`.
+    Create {
+        /// What the token is for, so you can tell your tokens apart
+        name: String,
+
+        /// What the token may do: read-status, read-records or write-records. Repeat for more
+        /// than one.
+        #[arg(long = "scope", short, required = true)]
+        scopes: Vec<TokenScope>,
+    },
+
+    /// List your API tokens
+    List,
+
+    /// Revoke an API token, so it can no longer be used
+    Revoke {
+        /// The token id, as shown by `atuin account token list`
+        id: i64,
+    },
+}
+
+impl Cmd {
+    pub async fn run(self, settings: &Settings) -> Result<()> {
+        let client = api_client::Client::new(
+            &settings.sync_address,
+            settings.session_token()?.as_str(),
+            settings.network_connect_timeout,
+            settings.network_timeout,
+        )?;
+
+        match self {
+            Self::Create { name, scopes } => {
+                let resp = client.create_token(&name, scopes).await?;
+
+                println!("Created token {}. It will not be shown again:\n", resp.id);
+                println!("{}", resp.token);
+            }
+
+            Self::List => {
+                let tokens = client.tokens().await?.tokens;
+
+                println!("{:>6}  {:<24} {:<16}  scopes", "id", "name", "created");
+
+                for token in tokens {
+                    let scopes = token
+                        .scopes
+                        .iter()
+                        .map(TokenScope::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    println!(
+                        "{:>6}  {:<24} {:<16}  {scopes}",
+                        token.id,
+                        token.name,
+                        token.created_at.format(TIME_FMT)?,
+                    );
+                }
+            }
+
+            Self::Revoke { id } => {
+                client.revoke_token(id).await?;
+
+                println!("Revoked token {id}");
+            }
+        }
+
+        Ok(())
+    }
+}
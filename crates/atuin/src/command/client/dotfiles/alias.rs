@@ -3,7 +3,10 @@ use eyre::{Context, Result, eyre};
 
 use atuin_client::{encryption, record::sqlite_store::SqliteStore, settings::Settings};
 
-use atuin_dotfiles::{shell::Alias, store::AliasStore};
+use atuin_dotfiles::{
+    shell::Alias,
+    store::{AliasStore, CONFIG_SHELL_ALIAS_TAG},
+};
 
 #[derive(Subcommand, Debug)]
 #[command(infer_subcommands = true)]
@@ -99,7 +102,7 @@ impl Cmd {
             return Ok(());
         }
 
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
+        let encryption_key: [u8; 32] = encryption::load_tag_key(settings, CONFIG_SHELL_ALIAS_TAG)
             .context("could not load encryption key")?
             .into();
         let host_id = Settings::host_id().expect("failed to get host_id");
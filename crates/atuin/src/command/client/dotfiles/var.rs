@@ -3,7 +3,10 @@ use eyre::{Context, Result};
 
 use atuin_client::{encryption, record::sqlite_store::SqliteStore, settings::Settings};
 
-use atuin_dotfiles::{shell::Var, store::var::VarStore};
+use atuin_dotfiles::{
+    shell::Var,
+    store::var::{DOTFILES_VAR_TAG, VarStore},
+};
 
 #[derive(Subcommand, Debug)]
 #[command(infer_subcommands = true)]
@@ -80,7 +83,7 @@ impl Cmd {
             return Ok(());
         }
 
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
+        let encryption_key: [u8; 32] = encryption::load_tag_key(settings, DOTFILES_VAR_TAG)
             .context("could not load encryption key")?
             .into();
         let host_id = Settings::host_id().expect("failed to get host_id");
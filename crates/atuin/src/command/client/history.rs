@@ -13,7 +13,7 @@ use runtime_format::{FormatKey, FormatKeyError, ParseSegment, ParsedFmt};
 use atuin_client::{
     database::{Database, Sqlite, current_context},
     encryption,
-    history::{History, store::HistoryStore},
+    history::{HISTORY_TAG, History, store::HistoryStore},
     record::sqlite_store::SqliteStore,
     settings::{
         FilterMode::{Directory, Global, Session},
@@ -574,7 +574,7 @@ impl Cmd {
                 settings.timezone,
             );
         } else {
-            let encryption_key: [u8; 32] = encryption::load_key(settings)
+            let encryption_key: [u8; 32] = encryption::load_tag_key(settings, HISTORY_TAG)
                 .context("could not load encryption key")?
                 .into();
             let host_id = Settings::host_id().expect("failed to get host_id");
@@ -629,7 +629,7 @@ impl Cmd {
                 settings.timezone,
             );
         } else {
-            let encryption_key: [u8; 32] = encryption::load_key(settings)
+            let encryption_key: [u8; 32] = encryption::load_tag_key(settings, HISTORY_TAG)
                 .context("could not load encryption key")?
                 .into();
             let host_id = Settings::host_id().expect("failed to get host_id");
@@ -673,7 +673,7 @@ impl Cmd {
         let db = Sqlite::new(db_path, settings.local_timeout).await?;
         let store = SqliteStore::new(record_store_path, settings.local_timeout).await?;
 
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
+        let encryption_key: [u8; 32] = encryption::load_tag_key(settings, HISTORY_TAG)
             .context("could not load encryption key")?
             .into();
 
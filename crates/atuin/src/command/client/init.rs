@@ -1,7 +1,10 @@
 use std::path::PathBuf;
 
-use atuin_client::{encryption, record::sqlite_store::SqliteStore, settings::Settings};
-use atuin_dotfiles::store::{AliasStore, var::VarStore};
+use atuin_client::{encryption::Keys, record::sqlite_store::SqliteStore, settings::Settings};
+use atuin_dotfiles::store::{
+    AliasStore, CONFIG_SHELL_ALIAS_TAG,
+    var::{DOTFILES_VAR_TAG, VarStore},
+};
 use clap::{Parser, ValueEnum};
 use eyre::{Result, WrapErr};
 
@@ -21,6 +24,12 @@ pub struct Cmd {
     /// Disable the binding of the Up Arrow key to atuin
     #[clap(long)]
     disable_up_arrow: bool,
+
+    /// Show atuin's suggestion as you type, accepted with the Right Arrow (zsh). fish can't show
+    /// it as you type, so there the Right Arrow fills it in when fish has no suggestion of its
+    /// own. That needs fish 4.0 or later, and does nothing on older versions.
+    #[clap(long)]
+    autosuggest: bool,
 }
 
 #[derive(Clone, Copy, ValueEnum, Debug)]
@@ -86,13 +95,13 @@ $env.config = (
     fn static_init(&self) {
         match self.shell {
             Shell::Zsh => {
-                zsh::init_static(self.disable_up_arrow, self.disable_ctrl_r);
+                zsh::init_static(self.disable_up_arrow, self.disable_ctrl_r, self.autosuggest);
             }
             Shell::Bash => {
                 bash::init_static(self.disable_up_arrow, self.disable_ctrl_r);
             }
             Shell::Fish => {
-                fish::init_static(self.disable_up_arrow, self.disable_ctrl_r);
+                fish::init_static(self.disable_up_arrow, self.disable_ctrl_r, self.autosuggest);
             }
             Shell::Nu => {
                 self.init_nu();
@@ -107,13 +116,15 @@ $env.config = (
         let record_store_path = PathBuf::from(settings.record_store_path.as_str());
         let sqlite_store = SqliteStore::new(record_store_path, settings.local_timeout).await?;
 
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
-            .context("could not load encryption key")?
-            .into();
+        let keys = Keys::load(settings).context("could not load encryption keys")?;
         let host_id = Settings::host_id().expect("failed to get host_id");
 
-        let alias_store = AliasStore::new(sqlite_store.clone(), host_id, encryption_key);
-        let var_store = VarStore::new(sqlite_store.clone(), host_id, encryption_key);
+        let alias_store = AliasStore::new(
+            sqlite_store.clone(),
+            host_id,
+            *keys.get(CONFIG_SHELL_ALIAS_TAG),
+        );
+        let var_store = VarStore::new(sqlite_store.clone(), host_id, *keys.get(DOTFILES_VAR_TAG));
 
         match self.shell {
             Shell::Zsh => {
@@ -122,6 +133,7 @@ $env.config = (
                     var_store,
                     self.disable_up_arrow,
                     self.disable_ctrl_r,
+                    self.autosuggest,
                 )
                 .await?;
             }
@@ -140,6 +152,7 @@ $env.config = (
                     var_store,
                     self.disable_up_arrow,
                     self.disable_ctrl_r,
+                    self.autosuggest,
                 )
                 .await?;
             }
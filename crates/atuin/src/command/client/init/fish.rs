@@ -1,7 +1,7 @@
 use atuin_dotfiles::store::{AliasStore, var::VarStore};
 use eyre::Result;
 
-pub fn init_static(disable_up_arrow: bool, disable_ctrl_r: bool) {
+pub fn init_static(disable_up_arrow: bool, disable_ctrl_r: bool, autosuggest: bool) {
     let base = include_str!("../../../shell/atuin.fish");
 
     println!("{base}");
@@ -14,16 +14,29 @@ pub fn init_static(disable_up_arrow: bool, disable_ctrl_r: bool) {
         const BIND_UP_ARROW_INS: &str = r"bind -M insert -k up _atuin_bind_up
 bind -M insert \eOA _atuin_bind_up
 bind -M insert \e\[A _atuin_bind_up";
+        const BIND_RIGHT_ARROW: &str = r"bind right _atuin_suggest
+bind \eOC _atuin_suggest
+bind \e\[C _atuin_suggest";
+        const BIND_RIGHT_ARROW_INS: &str = r"bind -M insert right _atuin_suggest
+bind -M insert \eOC _atuin_suggest
+bind -M insert \e\[C _atuin_suggest";
 
-        let bind_up_arrow = match std::env::var("FISH_VERSION") {
-            Ok(ref version) if version.starts_with("4.") => r"bind up _atuin_bind_up",
-            Ok(_) => r"bind -k up _atuin_bind_up",
+        let fish_4 = std::env::var("FISH_VERSION")
+            .ok()
+            .map(|version| version.starts_with("4."));
+
+        // fish can only tell us whether it is showing a suggestion of its own from 4.0, and
+        // without that we would replace it
+        let autosuggest = autosuggest && fish_4 == Some(true);
+
+        let bind_up_arrow = match fish_4 {
+            Some(true) => r"bind up _atuin_bind_up",
+            Some(false) => r"bind -k up _atuin_bind_up",
 
             // do nothing - we can't panic or error as this could be in use in
             // non-fish pipelines
-            _ => "",
-        }
-        .to_string();
+            None => "",
+        };
 
         if !disable_ctrl_r {
             println!("{BIND_CTRL_R}");
@@ -35,6 +48,9 @@ bind \eOA _atuin_bind_up
 bind \e\[A _atuin_bind_up"
             );
         }
+        if autosuggest {
+            println!("{BIND_RIGHT_ARROW}");
+        }
 
         println!("if bind -M insert > /dev/null 2>&1");
         if !disable_ctrl_r {
@@ -43,6 +59,9 @@ bind \e\[A _atuin_bind_up"
         if !disable_up_arrow {
             println!("{BIND_UP_ARROW_INS}");
         }
+        if autosuggest {
+            println!("{BIND_RIGHT_ARROW_INS}");
+        }
         println!("end");
     }
 }
@@ -52,8 +71,9 @@ pub async fn init(
     vars: VarStore,
     disable_up_arrow: bool,
     disable_ctrl_r: bool,
+    autosuggest: bool,
 ) -> Result<()> {
-    init_static(disable_up_arrow, disable_ctrl_r);
+    init_static(disable_up_arrow, disable_ctrl_r, autosuggest);
 
     let aliases = atuin_dotfiles::shell::fish::alias_config(&aliases).await;
     let vars = atuin_dotfiles::shell::fish::var_config(&vars).await;
@@ -1,11 +1,19 @@
 use atuin_dotfiles::store::{AliasStore, var::VarStore};
 use eyre::Result;
 
-pub fn init_static(disable_up_arrow: bool, disable_ctrl_r: bool) {
+pub fn init_static(disable_up_arrow: bool, disable_ctrl_r: bool, autosuggest: bool) {
     let base = include_str!("../../../shell/atuin.zsh");
 
     println!("{base}");
 
+    if autosuggest {
+        const AUTOSUGGEST: &str = r"autoload -U add-zle-hook-widget
+add-zle-hook-widget line-pre-redraw atuin-suggest
+add-zle-hook-widget line-finish atuin-suggest-clear";
+
+        println!("{AUTOSUGGEST}");
+    }
+
     if std::env::var("ATUIN_NOBIND").is_err() {
         const BIND_CTRL_R: &str = r"bindkey -M emacs '^r' atuin-search
 bindkey -M viins '^r' atuin-search-viins
@@ -19,12 +27,20 @@ bindkey -M vicmd '^[OA' atuin-up-search-vicmd
 bindkey -M viins '^[OA' atuin-up-search-viins
 bindkey -M vicmd 'k' atuin-up-search-vicmd";
 
+        const BIND_RIGHT_ARROW: &str = r"bindkey -M emacs '^[[C' atuin-suggest-accept
+bindkey -M viins '^[[C' atuin-suggest-accept
+bindkey -M emacs '^[OC' atuin-suggest-accept
+bindkey -M viins '^[OC' atuin-suggest-accept";
+
         if !disable_ctrl_r {
             println!("{BIND_CTRL_R}");
         }
         if !disable_up_arrow {
             println!("{BIND_UP_ARROW}");
         }
+        if autosuggest {
+            println!("{BIND_RIGHT_ARROW}");
+        }
     }
 }
 
@@ -33,8 +49,9 @@ pub async fn init(
     vars: VarStore,
     disable_up_arrow: bool,
     disable_ctrl_r: bool,
+    autosuggest: bool,
 ) -> Result<()> {
-    init_static(disable_up_arrow, disable_ctrl_r);
+    init_static(disable_up_arrow, disable_ctrl_r, autosuggest);
 
     let aliases = atuin_dotfiles::shell::zsh::alias_config(&aliases).await;
     let vars = atuin_dotfiles::shell::zsh::var_config(&vars).await;
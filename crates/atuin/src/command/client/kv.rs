@@ -1,12 +1,22 @@
-use clap::Subcommand;
+use clap::{Args, Subcommand};
 use eyre::{Context, Result, eyre};
 
-use atuin_client::{encryption, record::sqlite_store::SqliteStore, settings::Settings};
-use atuin_kv::store::KvStore;
+use atuin_client::{encryption, record::sqlite_store::SqliteStore, settings::Settings, team};
+use atuin_kv::store::{KvStore, record::KV_TAG};
+
+#[derive(Args, Debug)]
+pub struct Cmd {
+    /// Use the pairs shared with a team, rather than your own
+    #[arg(long, global = true)]
+    team: Option<String>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
 
 #[derive(Subcommand, Debug)]
 #[command(infer_subcommands = true)]
-pub enum Cmd {
+pub enum Commands {
     /// Set a key-value pair
     Set {
         /// Key to set
@@ -61,17 +71,29 @@ pub enum Cmd {
 
 impl Cmd {
     pub async fn run(&self, settings: &Settings, store: &SqliteStore) -> Result<()> {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
-            .context("could not load encryption key")?
-            .into();
-
         let host_id = Settings::host_id().expect("failed to get host_id");
 
-        let kv_db = atuin_kv::database::Database::new(settings.kv.db_path.clone(), 1.0).await?;
-        let kv_store = KvStore::new(store.clone(), kv_db, host_id, encryption_key);
+        let kv_store = if let Some(name) = &self.team {
+            team::validate(name)?;
+
+            let encryption_key: [u8; 32] =
+                encryption::load_tag_key(settings, &team::tag(KV_TAG, name))?.into();
+            let kv_db =
+                atuin_kv::database::Database::new(team::db_path(&settings.kv.db_path, name), 1.0)
+                    .await?;
+
+            KvStore::new(store.clone(), kv_db, host_id, encryption_key).with_team(name)
+        } else {
+            let encryption_key: [u8; 32] = encryption::load_tag_key(settings, KV_TAG)
+                .context("could not load encryption key")?
+                .into();
+            let kv_db = atuin_kv::database::Database::new(settings.kv.db_path.clone(), 1.0).await?;
+
+            KvStore::new(store.clone(), kv_db, host_id, encryption_key)
+        };
 
-        match self {
-            Self::Set {
+        match &self.command {
+            Commands::Set {
                 key,
                 value,
                 namespace,
@@ -83,9 +105,9 @@ impl Cmd {
                 kv_store.set(namespace, key, value).await
             }
 
-            Self::Delete { keys, namespace } => kv_store.delete(namespace, keys).await,
+            Commands::Delete { keys, namespace } => kv_store.delete(namespace, keys).await,
 
-            Self::Get { key, namespace } => {
+            Commands::Get { key, namespace } => {
                 let kv = kv_store.get(namespace, key).await?;
 
                 if let Some(val) = kv {
@@ -95,7 +117,7 @@ impl Cmd {
                 Ok(())
             }
 
-            Self::List {
+            Commands::List {
                 namespace,
                 all_namespaces,
             } => {
@@ -116,7 +138,7 @@ impl Cmd {
                 Ok(())
             }
 
-            Self::Rebuild {} => kv_store.build().await,
+            Commands::Rebuild => kv_store.build().await,
         }
     }
 }
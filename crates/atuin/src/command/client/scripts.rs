@@ -7,14 +7,19 @@ use std::path::PathBuf;
 use atuin_scripts::execution::template_script;
 use atuin_scripts::{
     execution::{build_executable_script, execute_script_interactive, template_variables},
-    store::{ScriptStore, script::Script},
+    store::{
+        ScriptStore,
+        script::{SCRIPT_TAG, Script},
+    },
 };
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use eyre::OptionExt;
 use eyre::{Result, bail};
 use tempfile::NamedTempFile;
 
-use atuin_client::{database::Database, record::sqlite_store::SqliteStore, settings::Settings};
+use atuin_client::{
+    database::Database, encryption, record::sqlite_store::SqliteStore, settings::Settings, team,
+};
 use tracing::debug;
 
 #[derive(Parser, Debug)]
@@ -105,9 +110,19 @@ pub struct Delete {
     pub force: bool,
 }
 
+#[derive(Args, Debug)]
+pub struct Cmd {
+    /// Use the scripts shared with a team, rather than your own
+    #[arg(long, global = true)]
+    team: Option<String>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
 #[derive(Subcommand, Debug)]
 #[command(infer_subcommands = true)]
-pub enum Cmd {
+pub enum Commands {
     New(NewScript),
     Run(Run),
     #[command(alias = "ls")]
@@ -567,22 +582,36 @@ impl Cmd {
         history_db: &impl Database,
     ) -> Result<()> {
         let host_id = Settings::host_id().expect("failed to get host_id");
-        let encryption_key: [u8; 32] = atuin_client::encryption::load_key(settings)?.into();
 
-        let script_store = ScriptStore::new(store, host_id, encryption_key);
-        let script_db =
-            atuin_scripts::database::Database::new(settings.scripts.db_path.clone(), 1.0).await?;
+        let (script_store, db_path) = if let Some(name) = &self.team {
+            team::validate(name)?;
+
+            let encryption_key: [u8; 32] =
+                encryption::load_tag_key(settings, &team::tag(SCRIPT_TAG, name))?.into();
+            let store = ScriptStore::new(store, host_id, encryption_key).with_team(name);
 
-        match self {
-            Self::New(new_script) => {
+            (store, team::db_path(&settings.scripts.db_path, name))
+        } else {
+            let encryption_key: [u8; 32] = encryption::load_tag_key(settings, SCRIPT_TAG)?.into();
+            let store = ScriptStore::new(store, host_id, encryption_key);
+
+            (store, settings.scripts.db_path.clone())
+        };
+
+        let script_db = atuin_scripts::database::Database::new(db_path, 1.0).await?;
+
+        match self.command {
+            Commands::New(new_script) => {
                 Self::handle_new_script(settings, new_script, script_store, script_db, history_db)
                     .await
             }
-            Self::Run(run) => Self::handle_run(settings, run, script_db).await,
-            Self::List(list) => Self::handle_list(settings, list, script_db).await,
-            Self::Get(get) => Self::handle_get(settings, get, script_db).await,
-            Self::Edit(edit) => Self::handle_edit(settings, edit, script_store, script_db).await,
-            Self::Delete(delete) => {
+            Commands::Run(run) => Self::handle_run(settings, run, script_db).await,
+            Commands::List(list) => Self::handle_list(settings, list, script_db).await,
+            Commands::Get(get) => Self::handle_get(settings, get, script_db).await,
+            Commands::Edit(edit) => {
+                Self::handle_edit(settings, edit, script_store, script_db).await
+            }
+            Commands::Delete(delete) => {
                 Self::handle_delete(settings, delete, script_store, script_db).await
             }
         }
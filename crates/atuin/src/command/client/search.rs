@@ -6,22 +6,31 @@ use eyre::Result;
 
 use atuin_client::{
     database::Database,
-    database::{OptFilters, current_context},
+    database::{Context, OptFilters, current_context},
     encryption,
-    history::{History, store::HistoryStore},
+    history::{HISTORY_TAG, History, store::HistoryStore},
     record::sqlite_store::SqliteStore,
     settings::{FilterMode, KeymapMode, SearchMode, Settings, Timezone},
     theme::Theme,
 };
 
+use atuin_history::frecency::{self, Score, Scorer};
+use itertools::Itertools as _;
+use time::OffsetDateTime;
+
 use super::history::ListMode;
 
 mod cursor;
+mod details;
+mod directories;
 mod duration;
 mod engines;
+mod groups;
 mod history_list;
 mod inspector;
 mod interactive;
+mod keymap;
+mod syntax;
 
 pub use duration::format_duration_into;
 
@@ -102,6 +111,10 @@ pub struct Cmd {
     #[arg(long)]
     delete_it_all: bool,
 
+    /// Delete without showing what matches and asking for confirmation first
+    #[arg(long)]
+    force: bool,
+
     /// Reverse the order of results, oldest first
     #[arg(long, short)]
     reverse: bool,
@@ -131,6 +144,15 @@ pub struct Cmd {
     /// Include duplicate commands in the output (non-interactive only)
     #[arg(long)]
     include_duplicates: bool,
+
+    /// Rank the results by frecency, and show how each one scored (non-interactive only)
+    #[arg(long, conflicts_with = "interactive")]
+    explain_score: bool,
+
+    /// Print the keybindings of the interactive search, in the format of the [keymap] config
+    /// sections, and exit
+    #[arg(long)]
+    print_keymap: bool,
 }
 
 impl Cmd {
@@ -145,6 +167,11 @@ impl Cmd {
         store: SqliteStore,
         theme: &Theme,
     ) -> Result<()> {
+        if self.print_keymap {
+            print!("{}", keymap::Keymaps::new(settings)?);
+            return Ok(());
+        }
+
         let query = self.query.map_or_else(
             || {
                 std::env::var("ATUIN_QUERY").map_or_else(
@@ -206,7 +233,7 @@ impl Cmd {
         };
         settings.keymap_mode_shell = self.keymap_mode;
 
-        let encryption_key: [u8; 32] = encryption::load_key(settings)?.into();
+        let encryption_key: [u8; 32] = encryption::load_tag_key(settings, HISTORY_TAG)?.into();
 
         let host_id = Settings::host_id().expect("failed to get host_id");
         let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
@@ -232,34 +259,56 @@ impl Cmd {
                 include_duplicates: self.include_duplicates,
             };
 
-            let mut entries =
-                run_non_interactive(settings, opt_filter.clone(), &query, &db).await?;
+            if self.delete || self.delete_it_all {
+                // Every copy of a command has to go, not just the most recent
+                let opt_filter = OptFilters {
+                    include_duplicates: true,
+                    ..opt_filter
+                };
+                let entries = run_non_interactive(settings, opt_filter, &query, &db).await?;
 
-            if entries.is_empty() {
-                std::process::exit(1)
-            }
+                if entries.is_empty() {
+                    std::process::exit(1)
+                }
+
+                if !self.force && std::io::stdin().is_terminal() && !confirm_delete(&entries)? {
+                    eprintln!("Deletion cancelled");
+                    return Ok(());
+                }
 
-            // if we aren't deleting, print it all
-            if self.delete || self.delete_it_all {
-                // delete it
                 // it only took me _years_ to add this
                 // sorry
-                while !entries.is_empty() {
-                    for entry in &entries {
-                        eprintln!("deleting {}", entry.id);
-
-                        if settings.sync.records {
-                            let (id, _) = history_store.delete(entry.id.clone()).await?;
-                            history_store.incremental_build(&db, &[id]).await?;
-                        } else {
-                            db.delete(entry.clone()).await?;
-                        }
+                if settings.sync.records {
+                    let ids: Vec<_> = entries.iter().map(|entry| entry.id.clone()).collect();
+                    history_store.delete_many(&db, &ids).await?;
+                } else {
+                    for entry in entries {
+                        db.delete(entry).await?;
                     }
-
-                    entries =
-                        run_non_interactive(settings, opt_filter.clone(), &query, &db).await?;
                 }
             } else {
+                let (scores, entries): (Vec<Score>, Vec<History>) =
+                    if settings.frecency.enabled || self.explain_score {
+                        run_ranked(settings, opt_filter, &query, &db)
+                            .await?
+                            .into_iter()
+                            .unzip()
+                    } else {
+                        (
+                            Vec::new(),
+                            run_non_interactive(settings, opt_filter, &query, &db).await?,
+                        )
+                    };
+
+                if entries.is_empty() {
+                    std::process::exit(1)
+                }
+
+                if self.explain_score {
+                    explain(&scores, &entries);
+                    return Ok(());
+                }
+
                 let format = match self.format {
                     None => Some(settings.history_format.as_str()),
                     _ => self.format.as_deref(),
@@ -319,3 +368,119 @@ async fn run_non_interactive(
 
     Ok(results)
 }
+
+/// Search as `run_non_interactive` does, but rank everything that matches before taking the page
+/// that was asked for
+async fn run_ranked(
+    settings: &Settings,
+    filter_options: OptFilters,
+    query: &[String],
+    db: &impl Database,
+) -> Result<Vec<(Score, History)>> {
+    let (offset, limit, reverse) = (
+        filter_options.offset,
+        filter_options.limit,
+        filter_options.reverse,
+    );
+    let filter_options = OptFilters {
+        offset: None,
+        limit: None,
+        reverse: false,
+        ..filter_options
+    };
+
+    let results = run_non_interactive(settings, filter_options, query, db).await?;
+    let mut ranked: Vec<_> = rank(settings, db, &current_context(), &query.join(" "), results)
+        .await?
+        .into_iter()
+        .skip(offset.and_then(|o| usize::try_from(o).ok()).unwrap_or(0))
+        .take(
+            limit
+                .and_then(|l| usize::try_from(l).ok())
+                .unwrap_or(usize::MAX),
+        )
+        .collect();
+
+    if reverse {
+        ranked.reverse();
+    }
+
+    Ok(ranked)
+}
+
+/// Rank results by frecency, best first, each with its score
+async fn rank(
+    settings: &Settings,
+    db: &dyn Database,
+    context: &Context,
+    query: &str,
+    results: Vec<History>,
+) -> Result<Vec<(Score, History)>> {
+    let commands: Vec<String> = results.iter().map(|h| h.command.clone()).unique().collect();
+    let usage = db.usage(&commands, context).await?;
+
+    let scorer = Scorer::new(
+        &settings.frecency,
+        settings.frecency.half_life()?,
+        &usage,
+        OffsetDateTime::now_utc(),
+    );
+
+    Ok(frecency::rank(query, results, &scorer))
+}
+
+/// Print each result with how it scored. Like the list, the best comes last.
+fn explain(scores: &[Score], entries: &[History]) {
+    for (score, entry) in scores.iter().zip(entries).rev() {
+        println!("{score}  {}", entry.command.escape_control());
+    }
+}
+
+/// The hosts the entries were run on, with how many of them each has, most first
+fn hosts(entries: &[History]) -> Vec<(&str, usize)> {
+    let mut hosts: Vec<(&str, usize)> = Vec::new();
+
+    for entry in entries {
+        match hosts.iter_mut().find(|(host, _)| *host == entry.hostname) {
+            Some((_, count)) => *count += 1,
+            None => hosts.push((&entry.hostname, 1)),
+        }
+    }
+
+    hosts.sort_by(|a, b| b.1.cmp(&a.1));
+    hosts
+}
+
+/// Show what is about to be deleted, and ask whether to go ahead
+fn confirm_delete(entries: &[History]) -> Result<bool> {
+    const PREVIEW: usize = 10;
+
+    let hosts = hosts(entries);
+
+    eprintln!(
+        "{} entries match, from {} host{}:",
+        entries.len(),
+        hosts.len(),
+        if hosts.len() == 1 { "" } else { "s" }
+    );
+    for (host, count) in &hosts {
+        eprintln!("  {count:>6}  {host}");
+    }
+
+    eprintln!();
+    for entry in entries.iter().take(PREVIEW) {
+        eprintln!("  {}", entry.command.escape_control());
+    }
+    if entries.len() > PREVIEW {
+        eprintln!("  ... and {} more", entries.len() - PREVIEW);
+    }
+
+    eprintln!();
+    eprint!("Delete them all? [y/N] ");
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
+}
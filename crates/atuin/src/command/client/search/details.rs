@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use atuin_client::{history::History, settings::Timezone};
+use atuin_common::shell::Shell;
+use ratatui::{
+    Frame,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Padding, Paragraph},
+};
+use time::macros::format_description;
+
+use super::duration::format_duration;
+use super::syntax;
+
+use super::super::theme::{Meaning, Theme};
+
+/// The pane next to the results: the selected command in full, what it runs, and where and how
+/// it ran
+pub fn draw(
+    f: &mut Frame<'_>,
+    area: Rect,
+    history: Option<&History>,
+    shell: Option<Shell>,
+    theme: &Theme,
+    tz: Timezone,
+) {
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title("Details")
+        .padding(Padding::horizontal(1));
+    let width = block.inner(area).width.into();
+
+    let Some(history) = history else {
+        f.render_widget(block, area);
+        return;
+    };
+
+    let mut lines = syntax::lines(&history.command, shell, width, theme);
+    lines.push(Line::default());
+
+    let field = |name: &'static str, value: String, meaning: Meaning| {
+        Line::from(vec![
+            Span::styled(format!("{name:<10}"), theme.as_style(Meaning::Annotation)),
+            Span::styled(value, theme.as_style(meaning)),
+        ])
+    };
+
+    if let Some(shell) = shell {
+        let programs = syntax::programs(&history.command, shell);
+        if !programs.is_empty() {
+            lines.push(field("Runs", programs.join(", "), Meaning::Important));
+        }
+    }
+
+    let (host, user) = history.hostname.split_once(':').unwrap_or(("", ""));
+    let duration = Duration::from_nanos(u64::try_from(history.duration).unwrap_or(0));
+    let time = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+
+    lines.extend([
+        field("Directory", history.cwd.clone(), Meaning::Base),
+        field(
+            "Exit",
+            history.exit.to_string(),
+            if history.success() {
+                Meaning::AlertInfo
+            } else {
+                Meaning::AlertError
+            },
+        ),
+        field("Duration", format_duration(duration), Meaning::Base),
+        field("Host", host.to_string(), Meaning::Base),
+        field("User", user.to_string(), Meaning::Base),
+        field(
+            "Time",
+            history
+                .timestamp
+                .to_offset(tz.0)
+                .format(time)
+                .unwrap_or_default(),
+            Meaning::Base,
+        ),
+    ]);
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
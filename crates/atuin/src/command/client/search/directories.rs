@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Padding},
+};
+
+use super::super::theme::{Meaning, Theme};
+
+/// Every directory in history as a tree, with how many commands were run in and under each
+#[derive(Debug, Default)]
+pub struct Tree {
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+
+    /// The nodes that are shown, in order - those whose parents are all expanded
+    visible: Vec<usize>,
+    state: ListState,
+
+    /// How many rows were drawn last time, to page by
+    height: usize,
+}
+
+#[derive(Debug)]
+struct Node {
+    name: String,
+    path: String,
+    depth: usize,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    expanded: bool,
+
+    /// Commands run in this directory
+    count: i64,
+
+    /// Commands run in this directory, or anywhere under it
+    total: i64,
+}
+
+/// The components of a path, each with the path up to and including it. An absolute path starts
+/// with the root, "/".
+fn components(path: &str) -> Vec<(&str, &str)> {
+    let mut components = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in path.char_indices() {
+        if c != '/' && c != '\\' {
+            continue;
+        }
+
+        if i == 0 {
+            components.push(("/", "/"));
+        } else if i > start {
+            components.push((&path[start..i], &path[..i]));
+        }
+
+        start = i + 1;
+    }
+
+    if start < path.len() {
+        components.push((&path[start..], path));
+    }
+
+    components
+}
+
+impl Tree {
+    /// Build the tree from directories and their command counts, opened up to show the
+    /// directory we are in
+    pub fn new(directories: Vec<(String, i64)>, cwd: &str) -> Self {
+        let mut tree = Self::default();
+        let mut index: HashMap<String, usize> = HashMap::new();
+
+        for (dir, count) in directories {
+            let mut parent = None;
+
+            for (name, path) in components(&dir) {
+                let node = if let Some(&node) = index.get(path) {
+                    node
+                } else {
+                    let node = tree.nodes.len();
+                    tree.nodes.push(Node {
+                        name: name.to_string(),
+                        path: path.to_string(),
+                        depth: parent.map_or(0, |p: usize| tree.nodes[p].depth + 1),
+                        parent,
+                        children: Vec::new(),
+                        expanded: false,
+                        count: 0,
+                        total: 0,
+                    });
+                    index.insert(path.to_string(), node);
+
+                    match parent {
+                        Some(p) => tree.nodes[p].children.push(node),
+                        None => tree.roots.push(node),
+                    }
+
+                    node
+                };
+
+                tree.nodes[node].total += count;
+                parent = Some(node);
+            }
+
+            if let Some(node) = parent {
+                tree.nodes[node].count += count;
+            }
+        }
+
+        let by_name = |nodes: &[Node], children: &mut Vec<usize>| {
+            children.sort_by(|a, b| nodes[*a].name.cmp(&nodes[*b].name));
+        };
+        for i in 0..tree.nodes.len() {
+            let mut children = std::mem::take(&mut tree.nodes[i].children);
+            by_name(&tree.nodes, &mut children);
+            tree.nodes[i].children = children;
+        }
+        let mut roots = std::mem::take(&mut tree.roots);
+        by_name(&tree.nodes, &mut roots);
+        tree.roots = roots;
+
+        for &root in &tree.roots {
+            tree.nodes[root].expanded = true;
+        }
+
+        // Open the way to where we are, and start there
+        let here = components(cwd)
+            .into_iter()
+            .map_while(|(_, path)| index.get(path).copied())
+            .inspect(|&node| tree.nodes[node].expanded = true)
+            .last();
+
+        if let Some(here) = here {
+            tree.nodes[here].expanded = false;
+        }
+
+        tree.update_visible();
+        tree.state
+            .select(Some(here.map_or(0, |here| tree.position(here))));
+
+        tree
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn update_visible(&mut self) {
+        fn walk(nodes: &[Node], children: &[usize], visible: &mut Vec<usize>) {
+            for &child in children {
+                visible.push(child);
+                if nodes[child].expanded {
+                    walk(nodes, &nodes[child].children, visible);
+                }
+            }
+        }
+
+        self.visible.clear();
+        walk(&self.nodes, &self.roots, &mut self.visible);
+    }
+
+    /// Where a node is in the visible list
+    fn position(&self, node: usize) -> usize {
+        self.visible.iter().position(|&n| n == node).unwrap_or(0)
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.state
+            .selected()
+            .and_then(|i| self.visible.get(i).copied())
+    }
+
+    /// The directory that is selected
+    pub fn selected_path(&self) -> Option<&str> {
+        self.selected().map(|node| self.nodes[node].path.as_str())
+    }
+
+    pub fn up(&mut self, n: usize) {
+        let i = self.state.selected().unwrap_or(0);
+        self.state.select(Some(i.saturating_sub(n)));
+    }
+
+    pub fn down(&mut self, n: usize) {
+        let i = self.state.selected().unwrap_or(0) + n;
+        self.state
+            .select(Some(i.min(self.visible.len().saturating_sub(1))));
+    }
+
+    pub fn page_up(&mut self) {
+        self.up(self.height.max(1));
+    }
+
+    pub fn page_down(&mut self) {
+        self.down(self.height.max(1));
+    }
+
+    /// Open the selected directory, or if it already is, go into it
+    pub fn expand(&mut self) {
+        let Some(node) = self.selected() else {
+            return;
+        };
+
+        if self.nodes[node].children.is_empty() {
+            return;
+        }
+
+        if self.nodes[node].expanded {
+            self.down(1);
+        } else {
+            self.nodes[node].expanded = true;
+            self.update_visible();
+        }
+    }
+
+    /// Close the selected directory, or if it already is, go to its parent
+    pub fn collapse(&mut self) {
+        let Some(node) = self.selected() else {
+            return;
+        };
+
+        if self.nodes[node].expanded && !self.nodes[node].children.is_empty() {
+            self.nodes[node].expanded = false;
+            self.update_visible();
+        } else if let Some(parent) = self.nodes[node].parent {
+            self.state.select(Some(self.position(parent)));
+        }
+    }
+}
+
+pub fn draw(f: &mut Frame<'_>, area: Rect, tree: &mut Tree, theme: &Theme) {
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .title("Directories")
+        .padding(Padding::horizontal(1));
+
+    tree.height = block.inner(area).height.into();
+
+    let width = tree
+        .visible
+        .iter()
+        .map(|&node| tree.nodes[node].depth * 2 + tree.nodes[node].name.len())
+        .max()
+        .unwrap_or(0);
+
+    let items: Vec<ListItem> = tree
+        .visible
+        .iter()
+        .map(|&node| {
+            let node = &tree.nodes[node];
+
+            let marker = match (node.children.is_empty(), node.expanded) {
+                (true, _) => "  ",
+                (false, true) => "▾ ",
+                (false, false) => "▸ ",
+            };
+            let name = format!("{}{}", "  ".repeat(node.depth), node.name);
+
+            let mut spans = vec![
+                Span::styled(marker, theme.as_style(Meaning::Annotation)),
+                Span::styled(format!("{name:width$}  "), theme.as_style(Meaning::Base)),
+                Span::styled(
+                    format!("{:>6}", node.total),
+                    theme.as_style(Meaning::Important),
+                ),
+            ];
+            if node.count != node.total && node.count > 0 {
+                spans.push(Span::styled(
+                    format!("  ({} here)", node.count),
+                    theme.as_style(Meaning::Annotation),
+                ));
+            }
+
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    f.render_stateful_widget(list, area, &mut tree.state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tree, components};
+
+    fn tree(cwd: &str) -> Tree {
+        Tree::new(
+            vec![
+                ("/home/ellie".to_string(), 3),
+                ("/home/ellie/src/atuin".to_string(), 5),
+                ("/home/ellie/src/blog".to_string(), 2),
+                ("/tmp".to_string(), 1),
+            ],
+            cwd,
+        )
+    }
+
+    fn visible(tree: &Tree) -> Vec<&str> {
+        tree.visible
+            .iter()
+            .map(|&node| tree.nodes[node].path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn splits_paths() {
+        assert_eq!(
+            components("/home/ellie"),
+            [("/", "/"), ("home", "/home"), ("ellie", "/home/ellie")]
+        );
+        assert_eq!(
+            components(r"C:\Users\ellie"),
+            [
+                ("C:", "C:"),
+                ("Users", r"C:\Users"),
+                ("ellie", r"C:\Users\ellie")
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_commands_under_each_directory() {
+        let tree = tree("/");
+        let node = |path: &str| {
+            tree.nodes
+                .iter()
+                .find(|n| n.path == path)
+                .map(|n| (n.count, n.total))
+        };
+
+        assert_eq!(node("/"), Some((0, 11)));
+        assert_eq!(node("/home/ellie"), Some((3, 10)));
+        assert_eq!(node("/home/ellie/src"), Some((0, 7)));
+        assert_eq!(node("/tmp"), Some((1, 1)));
+    }
+
+    #[test]
+    fn starts_where_we_are() {
+        let mut tree = tree("/home/ellie/src");
+
+        assert_eq!(
+            visible(&tree),
+            ["/", "/home", "/home/ellie", "/home/ellie/src", "/tmp"]
+        );
+        assert_eq!(tree.selected_path(), Some("/home/ellie/src"));
+
+        tree.expand();
+        assert_eq!(
+            visible(&tree),
+            [
+                "/",
+                "/home",
+                "/home/ellie",
+                "/home/ellie/src",
+                "/home/ellie/src/atuin",
+                "/home/ellie/src/blog",
+                "/tmp"
+            ]
+        );
+
+        tree.expand();
+        assert_eq!(tree.selected_path(), Some("/home/ellie/src/atuin"));
+
+        // to the parent, then closing it
+        tree.collapse();
+        assert_eq!(tree.selected_path(), Some("/home/ellie/src"));
+        tree.collapse();
+        assert_eq!(
+            visible(&tree),
+            ["/", "/home", "/home/ellie", "/home/ellie/src", "/tmp"]
+        );
+    }
+}
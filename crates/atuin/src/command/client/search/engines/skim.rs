@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{MAIN_SEPARATOR, Path};
 
 use async_trait::async_trait;
 use atuin_client::{database::Database, history::History, settings::FilterMode};
@@ -47,6 +47,12 @@ impl SearchEngine for Search {
     }
 }
 
+/// Whether `cwd` is `dir` or anywhere under it, as the workspace filter matches in the database
+fn in_dir_tree(cwd: &str, dir: &str) -> bool {
+    cwd.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(MAIN_SEPARATOR))
+}
+
 async fn fuzzy_search(
     engine: &SkimMatcherV2,
     state: &SearchState,
@@ -85,7 +91,8 @@ async fn fuzzy_search(
                     .contains(&context.session.as_bytes()) => {}
             // we aggregate directory by ':' separating them
             FilterMode::Directory if history.cwd.split(':').contains(&context.cwd.as_str()) => {}
-            FilterMode::Workspace if history.cwd.split(':').contains(&git_root) => {}
+            FilterMode::Workspace
+                if history.cwd.split(':').any(|cwd| in_dir_tree(cwd, git_root)) => {}
             _ => continue,
         }
         #[allow(clippy::cast_lossless, clippy::cast_precision_loss)]
@@ -0,0 +1,503 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use atuin_client::history::{CommandStats, History};
+use atuin_common::utils::Escapable as _;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Rect},
+    style::{Modifier, Style},
+    text::Span,
+    widgets::{Block, Borders, Cell, Padding, Row, Table, TableState},
+};
+use time::OffsetDateTime;
+use unicode_width::UnicodeWidthStr;
+
+use super::duration::format_duration;
+
+use super::super::theme::{Meaning, Theme};
+
+/// How many runs of a command are listed when it is opened
+pub const RUNS: i64 = 50;
+
+/// The results summed up by command, with the latest runs of those that are opened
+#[derive(Debug, Default)]
+pub struct Groups {
+    commands: Vec<Group>,
+
+    /// The rows that are shown, in order - the groups, and the runs of those that are expanded
+    visible: Vec<Entry>,
+    state: TableState,
+
+    /// How many rows were drawn last time, to page by
+    height: usize,
+}
+
+#[derive(Debug)]
+struct Group {
+    stats: CommandStats,
+
+    /// Where the command is in the results
+    index: usize,
+
+    /// The latest runs, newest first. Fetched when it is first opened.
+    runs: Option<Vec<History>>,
+    expanded: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    Group(usize),
+    Run(usize, usize),
+
+    /// The runs of a group that are not listed
+    More(usize),
+}
+
+impl Entry {
+    const fn group(self) -> usize {
+        match self {
+            Self::Group(group) | Self::Run(group, _) | Self::More(group) => group,
+        }
+    }
+}
+
+impl Group {
+    fn runs(&self) -> &[History] {
+        self.runs.as_deref().unwrap_or_default()
+    }
+
+    fn median(&self) -> Option<Duration> {
+        self.stats
+            .median_duration
+            .map(|d| Duration::from_nanos(u64::try_from(d).unwrap_or(0)))
+    }
+
+    /// The hosts it was run on most, and how many others
+    fn host_summary(&self) -> String {
+        let mut hosts: Vec<String> = self
+            .stats
+            .hosts
+            .iter()
+            .take(2)
+            .map(|(host, _)| host.clone())
+            .collect();
+        if self.stats.hosts.len() > 2 {
+            hosts.push(format!("+{}", self.stats.hosts.len() - 2));
+        }
+
+        hosts.join(", ")
+    }
+
+    /// The share of finished runs that succeeded
+    #[allow(clippy::cast_precision_loss)]
+    fn success_rate(&self) -> Option<f64> {
+        (self.stats.finished > 0).then(|| self.stats.succeeded as f64 / self.stats.finished as f64)
+    }
+}
+
+/// A result the stats are missing for, as it was deleted since, is at least one run of itself
+fn stats_of(h: &History) -> CommandStats {
+    let finished = h.duration != -1;
+
+    CommandStats {
+        command: h.command.clone(),
+        count: 1,
+        finished: i64::from(finished),
+        succeeded: i64::from(finished && h.success()),
+        last_run: h.timestamp,
+        median_duration: finished.then_some(h.duration),
+        hosts: vec![(
+            h.hostname.split(':').next().unwrap_or_default().to_string(),
+            1,
+        )],
+    }
+}
+
+impl Groups {
+    /// A group for each command in the results, keeping their order
+    pub fn new(results: &[History], stats: Vec<CommandStats>) -> Self {
+        let mut stats: HashMap<String, CommandStats> = stats
+            .into_iter()
+            .map(|stats| (stats.command.clone(), stats))
+            .collect();
+        let mut seen = HashSet::new();
+
+        let commands = results
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| seen.insert(h.command.as_str()))
+            .map(|(index, h)| Group {
+                stats: stats.remove(&h.command).unwrap_or_else(|| stats_of(h)),
+                index,
+                runs: None,
+                expanded: false,
+            })
+            .collect();
+
+        let mut groups = Self {
+            commands,
+            ..Self::default()
+        };
+        groups.update_visible();
+        groups.state.select(Some(0));
+
+        groups
+    }
+
+    /// A group that has been opened but whose runs haven't been fetched yet, and its command
+    pub fn runs_needed(&self) -> Option<(usize, String)> {
+        self.commands
+            .iter()
+            .position(|group| group.expanded && group.runs.is_none())
+            .map(|i| (i, self.commands[i].stats.command.clone()))
+    }
+
+    pub fn set_runs(&mut self, group: usize, runs: Vec<History>) {
+        if let Some(group) = self.commands.get_mut(group) {
+            group.runs = Some(runs);
+            self.update_visible();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    fn update_visible(&mut self) {
+        self.visible.clear();
+
+        for (i, group) in self.commands.iter().enumerate() {
+            self.visible.push(Entry::Group(i));
+
+            if group.expanded && group.runs.is_some() {
+                let runs = group.runs().len();
+                self.visible.extend((0..runs).map(|run| Entry::Run(i, run)));
+                if usize::try_from(group.stats.count).unwrap_or(0) > runs {
+                    self.visible.push(Entry::More(i));
+                }
+            }
+        }
+    }
+
+    fn selected(&self) -> Option<Entry> {
+        self.state
+            .selected()
+            .and_then(|i| self.visible.get(i).copied())
+    }
+
+    /// Where the selected command, or the command of the selected run, is in the results
+    pub fn selected_result(&self) -> Option<usize> {
+        self.selected()
+            .map(|entry| self.commands[entry.group()].index)
+    }
+
+    /// The command that is selected, or whose run is
+    pub fn selected_command(&self) -> Option<&str> {
+        self.selected()
+            .map(|entry| self.commands[entry.group()].stats.command.as_str())
+    }
+
+    pub fn up(&mut self, n: usize) {
+        let i = self.state.selected().unwrap_or(0);
+        self.state.select(Some(i.saturating_sub(n)));
+    }
+
+    pub fn down(&mut self, n: usize) {
+        let i = self.state.selected().unwrap_or(0) + n;
+        self.state
+            .select(Some(i.min(self.visible.len().saturating_sub(1))));
+    }
+
+    pub fn page_up(&mut self) {
+        self.up(self.height.max(1));
+    }
+
+    pub fn page_down(&mut self) {
+        self.down(self.height.max(1));
+    }
+
+    /// List the runs of the selected command, or if they already are, go to the first
+    pub fn expand(&mut self) {
+        let Some(Entry::Group(group)) = self.selected() else {
+            return;
+        };
+
+        if self.commands[group].expanded {
+            self.down(1);
+        } else {
+            self.commands[group].expanded = true;
+            self.update_visible();
+        }
+    }
+
+    /// Hide the runs of the selected command, or from one of its runs, go back to it
+    pub fn collapse(&mut self) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+        let group = entry.group();
+
+        if entry == Entry::Group(group) {
+            if self.commands[group].expanded {
+                self.commands[group].expanded = false;
+                self.update_visible();
+            }
+        } else {
+            let position = self.visible.iter().position(|&e| e == Entry::Group(group));
+            self.state.select(position);
+        }
+    }
+}
+
+fn ago(now: OffsetDateTime, timestamp: OffsetDateTime) -> String {
+    let since = (now - timestamp).try_into().unwrap_or_default();
+    format!("{} ago", format_duration(since))
+}
+
+fn group_row<'a>(group: &'a Group, now: OffsetDateTime, theme: &Theme) -> Row<'a> {
+    let marker = if group.expanded { "▾" } else { "▸" };
+
+    let success = group.success_rate().map_or_else(
+        || Cell::from(""),
+        |rate| {
+            let meaning = if group.stats.succeeded == group.stats.finished {
+                Meaning::AlertInfo
+            } else if group.stats.succeeded == 0 {
+                Meaning::AlertError
+            } else {
+                Meaning::AlertWarn
+            };
+            Cell::from(Span::styled(
+                format!("{:>7.0}%", rate * 100.0),
+                theme.as_style(meaning),
+            ))
+        },
+    );
+
+    Row::new(vec![
+        Cell::from(Span::styled(marker, theme.as_style(Meaning::Annotation))),
+        Cell::from(Span::styled(
+            format!("{:>6}", group.stats.count),
+            theme.as_style(Meaning::Important),
+        )),
+        success,
+        Cell::from(group.median().map(format_duration).unwrap_or_default()),
+        Cell::from(Span::styled(
+            ago(now, group.stats.last_run),
+            theme.as_style(Meaning::Guidance),
+        )),
+        Cell::from(group.stats.command.escape_control().to_string()),
+        Cell::from(Span::styled(
+            group.host_summary(),
+            theme.as_style(Meaning::Annotation),
+        )),
+    ])
+}
+
+fn run_row<'a>(h: &'a History, now: OffsetDateTime, theme: &Theme) -> Row<'a> {
+    let (exit, meaning) = if h.duration == -1 {
+        ("running".to_string(), Meaning::Annotation)
+    } else if h.success() {
+        (format!("exit {}", h.exit), Meaning::AlertInfo)
+    } else {
+        (format!("exit {}", h.exit), Meaning::AlertError)
+    };
+    let duration = Duration::from_nanos(u64::try_from(h.duration).unwrap_or(0));
+
+    Row::new(vec![
+        Cell::from(""),
+        Cell::from(""),
+        Cell::from(Span::styled(format!("{exit:>8}"), theme.as_style(meaning))),
+        Cell::from(if h.duration == -1 {
+            String::new()
+        } else {
+            format_duration(duration)
+        }),
+        Cell::from(Span::styled(
+            ago(now, h.timestamp),
+            theme.as_style(Meaning::Guidance),
+        )),
+        Cell::from(Span::styled(
+            format!("  in {}", h.cwd),
+            theme.as_style(Meaning::Base),
+        )),
+        Cell::from(Span::styled(
+            h.hostname.split(':').next().unwrap_or_default(),
+            theme.as_style(Meaning::Annotation),
+        )),
+    ])
+}
+
+pub fn draw(
+    f: &mut Frame<'_>,
+    area: Rect,
+    groups: &mut Groups,
+    now: OffsetDateTime,
+    theme: &Theme,
+) {
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .title("Commands")
+        .padding(Padding::horizontal(1));
+
+    // less the header
+    groups.height = usize::from(block.inner(area).height).saturating_sub(1);
+
+    let rows: Vec<Row> = groups
+        .visible
+        .iter()
+        .map(|&entry| match entry {
+            Entry::Group(group) => group_row(&groups.commands[group], now, theme),
+            Entry::Run(group, run) => run_row(&groups.commands[group].runs()[run], now, theme),
+            Entry::More(group) => Row::new(vec![
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(Span::styled(
+                    format!(
+                        "  ... and {} more",
+                        usize::try_from(groups.commands[group].stats.count)
+                            .unwrap_or(0)
+                            .saturating_sub(groups.commands[group].runs().len())
+                    ),
+                    theme.as_style(Meaning::Annotation),
+                )),
+            ]),
+        })
+        .collect();
+
+    // as wide as the widest list of hosts, leaving the rest to the commands
+    let hosts_width = groups
+        .commands
+        .iter()
+        .map(|group| group.host_summary().width())
+        .max()
+        .unwrap_or(0)
+        .clamp("Hosts".len(), 24);
+
+    let header = Row::new(vec![
+        "", "Runs", "Success", "Median", "Last run", "Command", "Hosts",
+    ])
+    .style(Style::from(theme.as_style(Meaning::Annotation)).add_modifier(Modifier::BOLD));
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(1),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Fill(1),
+            Constraint::Length(u16::try_from(hosts_width).unwrap_or(24)),
+        ],
+    )
+    .header(header)
+    .block(block)
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    f.render_stateful_widget(table, area, &mut groups.state);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use atuin_client::history::{CommandStats, History};
+    use time::OffsetDateTime;
+
+    use super::{Entry, Groups};
+
+    fn history(command: &str, host: &str, exit: i64, seconds: u64) -> History {
+        let mut h: History = History::capture()
+            .timestamp(OffsetDateTime::now_utc())
+            .command(command)
+            .cwd("/")
+            .build()
+            .into();
+        h.hostname = format!("{host}:ellie");
+        h.exit = exit;
+        h.duration = i64::try_from(Duration::from_secs(seconds).as_nanos()).unwrap();
+        h
+    }
+
+    fn groups() -> Groups {
+        let results = vec![
+            history("cargo test", "laptop", 0, 1),
+            history("ls", "laptop", 0, 1),
+            history("cargo test", "laptop", 0, 1),
+        ];
+        let stats = vec![CommandStats {
+            command: "cargo test".to_string(),
+            count: 60,
+            finished: 60,
+            succeeded: 50,
+            last_run: OffsetDateTime::now_utc(),
+            median_duration: Some(1),
+            hosts: vec![("laptop".to_string(), 60)],
+        }];
+
+        Groups::new(&results, stats)
+    }
+
+    #[test]
+    fn one_group_per_command() {
+        let groups = groups();
+
+        assert_eq!(groups.commands.len(), 2);
+        assert_eq!(groups.commands[0].stats.count, 60);
+
+        // there are no stats for ls, so it stands for itself
+        let ls = &groups.commands[1];
+        assert_eq!((ls.index, ls.stats.count), (1, 1));
+        assert_eq!(ls.success_rate(), Some(1.0));
+        assert_eq!(ls.host_summary(), "laptop");
+    }
+
+    #[test]
+    fn opens_to_show_runs() {
+        let mut groups = groups();
+        assert_eq!(groups.visible, [Entry::Group(0), Entry::Group(1)]);
+        assert_eq!(groups.runs_needed(), None);
+
+        // the runs are fetched once it is open
+        groups.expand();
+        assert_eq!(groups.visible, [Entry::Group(0), Entry::Group(1)]);
+        assert_eq!(groups.runs_needed(), Some((0, "cargo test".to_string())));
+
+        groups.set_runs(
+            0,
+            vec![
+                history("cargo test", "laptop", 0, 30),
+                history("cargo test", "server", 101, 10),
+            ],
+        );
+        assert_eq!(groups.runs_needed(), None);
+        assert_eq!(
+            groups.visible,
+            [
+                Entry::Group(0),
+                Entry::Run(0, 0),
+                Entry::Run(0, 1),
+                Entry::More(0),
+                Entry::Group(1)
+            ]
+        );
+
+        // into the runs, which still accept their command, then back out and closed
+        groups.expand();
+        groups.down(1);
+        assert_eq!(groups.selected(), Some(Entry::Run(0, 1)));
+        assert_eq!(groups.selected_result(), Some(0));
+
+        groups.collapse();
+        assert_eq!(groups.selected(), Some(Entry::Group(0)));
+        groups.collapse();
+        assert_eq!(groups.visible, [Entry::Group(0), Entry::Group(1)]);
+    }
+}
@@ -2,11 +2,12 @@ use std::time::Duration;
 
 use super::duration::format_duration;
 use super::engines::SearchEngine;
+use super::syntax;
 use atuin_client::{
     history::History,
     theme::{Meaning, Theme},
 };
-use atuin_common::utils::Escapable as _;
+use atuin_common::{shell::Shell, utils::Escapable as _};
 use itertools::Itertools;
 use ratatui::{
     buffer::Buffer,
@@ -39,6 +40,8 @@ pub struct HistoryList<'a> {
     indicator: &'a str,
     theme: &'a Theme,
     history_highlighter: HistoryHighlighter<'a>,
+    marked: &'a [History],
+    syntax: Option<Shell>,
 }
 
 #[derive(Default)]
@@ -93,10 +96,11 @@ impl StatefulWidget for HistoryList<'_> {
             indicator: self.indicator,
             theme: self.theme,
             history_highlighter: self.history_highlighter,
+            syntax: self.syntax,
         };
 
         for item in self.history.iter().skip(state.offset).take(end - start) {
-            s.index();
+            s.index(self.marked.iter().any(|h| h.id == item.id));
             s.duration(item);
             s.time(item);
             s.command(item);
@@ -127,9 +131,23 @@ impl<'a> HistoryList<'a> {
             indicator,
             theme,
             history_highlighter,
+            marked: &[],
+            syntax: None,
         }
     }
 
+    /// Entries marked for a batch action, which are flagged in the list
+    pub fn marked(mut self, marked: &'a [History]) -> Self {
+        self.marked = marked;
+        self
+    }
+
+    /// Color commands by their parts, as the given shell would split them
+    pub fn syntax(mut self, shell: Option<Shell>) -> Self {
+        self.syntax = shell;
+        self
+    }
+
     pub fn block(mut self, block: Block<'a>) -> Self {
         self.block = Some(block);
         self
@@ -162,6 +180,7 @@ struct DrawState<'a> {
     indicator: &'a str,
     theme: &'a Theme,
     history_highlighter: HistoryHighlighter<'a>,
+    syntax: Option<Shell>,
 }
 
 // longest line prefix I could come up with
@@ -171,7 +190,7 @@ static SPACES: &str = "                ";
 static _ASSERT: () = assert!(SPACES.len() == PREFIX_LENGTH as usize);
 
 impl DrawState<'_> {
-    fn index(&mut self) {
+    fn index(&mut self, marked: bool) {
         // these encode the slices of `" > "`, `" {n} "`, or `"   "` in a compact form.
         // Yes, this is a hack, but it makes me feel happy
         static SLICES: &str = " > 1 2 3 4 5 6 7 8 9   ";
@@ -184,7 +203,14 @@ impl DrawState<'_> {
         } else {
             &SLICES[i..i + 3]
         };
-        self.draw(prompt, Style::default());
+
+        match prompt.strip_prefix(' ') {
+            Some(rest) if marked => {
+                self.draw("+", self.theme.as_style(Meaning::Important).into());
+                self.draw(rest, Style::default());
+            }
+            _ => self.draw(prompt, Style::default()),
+        }
     }
 
     fn duration(&mut self, h: &History) {
@@ -230,13 +256,18 @@ impl DrawState<'_> {
             style.attributes.set(style::Attribute::Bold);
         }
 
-        let highlight_indices = self.history_highlighter.get_highlight_indices(
-            h.command
-                .escape_control()
-                .split_ascii_whitespace()
-                .join(" ")
-                .as_str(),
-        );
+        let command = h
+            .command
+            .escape_control()
+            .split_ascii_whitespace()
+            .join(" ");
+        let highlight_indices = self.history_highlighter.get_highlight_indices(&command);
+
+        // the selected row keeps its own color, so that it stands out
+        let kinds = self
+            .syntax
+            .filter(|_| !row_highlighted)
+            .map(|shell| syntax::char_kinds(&command, shell));
 
         let mut pos = 0;
         for section in h.command.escape_control().split_ascii_whitespace() {
@@ -247,7 +278,10 @@ impl DrawState<'_> {
                     // of the list
                     return;
                 }
-                let mut style = style;
+                let mut style = kinds
+                    .as_ref()
+                    .and_then(|kinds| kinds.get(pos))
+                    .map_or(style, |kind| self.theme.as_style(kind.meaning()));
                 if highlight_indices.contains(&pos) {
                     if row_highlighted {
                         // if the row is highlighted bold is not enough as the whole row is bold
@@ -2,22 +2,24 @@ use std::time::Duration;
 use time::macros::format_description;
 
 use atuin_client::{
-    history::{History, HistoryStats},
-    settings::{Settings, Timezone},
+    history::{History, HistoryId, HistoryStats},
+    settings::Timezone,
 };
 use ratatui::{
     Frame,
-    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
     layout::Rect,
     prelude::{Constraint, Direction, Layout},
-    style::Style,
-    widgets::{Bar, BarChart, BarGroup, Block, Borders, Padding, Paragraph, Row, Table},
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, List, ListItem, ListState, Padding, Paragraph,
+        Row, Table,
+    },
 };
 
 use super::duration::format_duration;
 
 use super::super::theme::{Meaning, Theme};
-use super::interactive::{InputAction, State};
 
 #[allow(clippy::cast_sign_loss)]
 fn u64_or_zero(num: i64) -> u64 {
@@ -253,14 +255,126 @@ fn draw_stats_charts(f: &mut Frame<'_>, parent: Rect, stats: &HistoryStats, them
     f.render_widget(duration_over_time, layout[2]);
 }
 
+/// Every command of the inspected entry's session, shown instead of the stats
+#[derive(Debug, Default)]
+pub struct Timeline {
+    pub state: ListState,
+
+    /// How many rows were drawn last time, to page by
+    pub height: u16,
+
+    /// The entry the timeline was opened on. When it changes, we start again from there.
+    entry: Option<HistoryId>,
+}
+
+fn draw_timeline(
+    f: &mut Frame<'_>,
+    parent: Rect,
+    history: &History,
+    stats: &HistoryStats,
+    timeline: &mut Timeline,
+    theme: &Theme,
+    tz: Timezone,
+) {
+    let session = &stats.session;
+
+    if timeline.entry.as_ref() != Some(&history.id) {
+        timeline.entry = Some(history.id.clone());
+        timeline
+            .state
+            .select(session.iter().position(|h| h.id == history.id));
+    }
+
+    let time = format_description!("[hour]:[minute]:[second]");
+    let date = format_description!("[year]-[month]-[day] [hour]:[minute]");
+
+    let mut cwd = None;
+    let items: Vec<ListItem> = session
+        .iter()
+        .map(|h| {
+            let mut lines = Vec::new();
+
+            // Only show the directory when it changes, like a shell prompt would
+            if cwd != Some(&h.cwd) {
+                lines.push(Line::styled(
+                    format!("{:>24}{}", "", h.cwd),
+                    theme.as_style(Meaning::Annotation),
+                ));
+                cwd = Some(&h.cwd);
+            }
+
+            let exit = if h.exit == 0 {
+                theme.as_style(Meaning::AlertInfo)
+            } else {
+                theme.as_style(Meaning::AlertError)
+            };
+            let mut command = Style::from(theme.as_style(Meaning::Base));
+            if h.id == history.id {
+                command = command.add_modifier(Modifier::BOLD);
+            }
+
+            lines.push(Line::from(vec![
+                Span::styled(
+                    h.timestamp.to_offset(tz.0).format(time).unwrap_or_default(),
+                    theme.as_style(Meaning::Annotation),
+                ),
+                Span::raw(format!(
+                    " {:>8} ",
+                    format_duration(Duration::from_nanos(u64_or_zero(h.duration)))
+                )),
+                Span::styled(format!("{:>4}  ", h.exit), exit),
+                Span::styled(h.command.lines().next().unwrap_or_default(), command),
+            ]));
+
+            ListItem::new(Text::from(lines))
+        })
+        .collect();
+
+    let title = match (session.first(), session.last()) {
+        (Some(first), Some(last)) => format!(
+            "Session - {} commands, {} to {}",
+            session.len(),
+            first
+                .timestamp
+                .to_offset(tz.0)
+                .format(date)
+                .unwrap_or_default(),
+            last.timestamp
+                .to_offset(tz.0)
+                .format(time)
+                .unwrap_or_default(),
+        ),
+        _ => "Session".to_string(),
+    };
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(theme.as_style(Meaning::Base))
+        .padding(Padding::horizontal(1));
+    timeline.height = block.inner(parent).height;
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    f.render_stateful_widget(list, parent, &mut timeline.state);
+}
+
 pub fn draw(
     f: &mut Frame<'_>,
     chunk: Rect,
     history: &History,
     stats: &HistoryStats,
+    timeline: Option<&mut Timeline>,
     theme: &Theme,
     tz: Timezone,
 ) {
+    if let Some(timeline) = timeline {
+        draw_timeline(f, chunk, history, stats, timeline, theme, tz);
+        return;
+    }
+
     let vert_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Ratio(1, 5), Constraint::Ratio(4, 5)])
@@ -275,19 +389,3 @@ pub fn draw(
     draw_stats_table(f, stats_layout[0], history, tz, stats, theme);
     draw_stats_charts(f, stats_layout[1], stats, theme);
 }
-
-// I'm going to break this out more, but just starting to move things around before changing
-// structure and making it nicer.
-pub fn input(
-    _state: &mut State,
-    _settings: &Settings,
-    selected: usize,
-    input: &KeyEvent,
-) -> InputAction {
-    let ctrl = input.modifiers.contains(KeyModifiers::CONTROL);
-
-    match input.code {
-        KeyCode::Char('d') if ctrl => InputAction::Delete(selected),
-        _ => InputAction::Continue,
-    }
-}
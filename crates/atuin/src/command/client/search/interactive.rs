@@ -1,30 +1,46 @@
 use std::{
     io::{Write, stdout},
+    path::PathBuf,
     time::Duration,
 };
 
-use atuin_common::utils::{self, Escapable as _};
-use eyre::Result;
+use atuin_common::{
+    shell::Shell,
+    utils::{self, Escapable as _},
+};
+use atuin_history::predict::Prediction;
+use atuin_scripts::store::{
+    ScriptStore,
+    script::{SCRIPT_TAG, Script},
+};
+use eyre::{Result, bail};
 use futures_util::FutureExt;
+use itertools::Itertools;
 use semver::Version;
 use time::OffsetDateTime;
 use unicode_width::UnicodeWidthStr;
 
 use super::{
     cursor::Cursor,
+    directories::Tree,
     engines::{SearchEngine, SearchState},
+    groups::{Groups, RUNS},
     history_list::{HistoryList, ListState, PREFIX_LENGTH},
+    inspector::Timeline,
+    keymap::{Action, Keymaps},
+    syntax,
 };
 use atuin_client::{
-    database::{Database, current_context},
+    database::{Database, OptFilters, current_context},
+    encryption,
     history::{History, HistoryStats, store::HistoryStore},
     settings::{
         CursorStyle, ExitMode, FilterMode, KeymapMode, PreviewStrategy, SearchMode, Settings,
     },
 };
 
-use crate::command::client::search::history_list::HistoryHighlighter;
 use crate::command::client::theme::{Meaning, Theme};
+use crate::command::client::{search::history_list::HistoryHighlighter, suggest};
 use crate::{VERSION, command::client::search::engines};
 
 use ratatui::{
@@ -50,12 +66,21 @@ use ratatui::crossterm::event::{
     KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
 };
 
-const TAB_TITLES: [&str; 2] = ["Search", "Inspect"];
+const TAB_TITLES: [&str; 4] = ["Search", "Inspect", "Directories", "Commands"];
 
 pub enum InputAction {
     Accept(usize),
+    AcceptMarked,
     Copy(usize),
+    CopyMarked,
+    CopySession(Option<usize>),
     Delete(usize),
+    DeleteMarked,
+    PreviewDeleteMatching,
+    DeleteMatching,
+    Mark(usize),
+    MarkRange(usize),
+    SaveScript(String),
     ReturnOriginal,
     ReturnQuery,
     Continue,
@@ -72,7 +97,24 @@ pub struct State {
     results_len: usize,
     accept: bool,
     keymap_mode: KeymapMode,
+    keymaps: Keymaps,
     prefix: bool,
+    marked: Vec<History>,
+    script_name: Option<Cursor>,
+    pending_delete: Option<Vec<History>>,
+    /// How far the delete preview is scrolled, in lines
+    delete_scroll: usize,
+    directories: Option<Tree>,
+    /// The results grouped by command, made when the commands tab is opened
+    groups: Option<Groups>,
+    timeline: Option<Timeline>,
+    /// Where we were and the filter mode before browsing a directory, to go back to after
+    browsing: Option<(String, Option<PathBuf>, FilterMode)>,
+    /// What we predict will be run next. Made once, the first time there is no query.
+    predictions: Option<Vec<Prediction>>,
+    /// The shell to color commands for, when syntax highlighting is on
+    syntax: Option<Shell>,
+    status: Option<String>,
     current_cursor: Option<CursorStyle>,
     tab_index: usize,
 
@@ -92,21 +134,61 @@ impl State {
     async fn query_results(
         &mut self,
         db: &mut dyn Database,
-        smart_sort: bool,
+        settings: &Settings,
     ) -> Result<Vec<History>> {
         let results = self.engine.query(&self.search, db).await?;
 
         self.results_state.select(0);
         self.results_len = results.len();
+        self.groups = None;
 
-        if smart_sort {
-            Ok(atuin_history::sort::sort(
+        let results = if settings.frecency.enabled {
+            let ranked = super::rank(
+                settings,
+                db,
+                &self.search.context,
                 self.search.input.as_str(),
                 results,
-            ))
+            )
+            .await?;
+            ranked.into_iter().map(|(_, h)| h).collect()
+        } else if settings.smart_sort {
+            atuin_history::sort::sort(self.search.input.as_str(), results)
         } else {
-            Ok(results)
+            results
+        };
+
+        // the up key is for going back, not forward
+        if !settings.predict_next
+            || settings.shell_up_key_binding
+            || !self.search.input.as_str().is_empty()
+        {
+            return Ok(results);
+        }
+
+        // predicting looks through every session the last command was run in, which is too slow
+        // to do again on every change of filter or search mode
+        if self.predictions.is_none() {
+            let mut predictions = suggest::predict(db, &self.search.context, None).await?;
+            predictions.truncate(PREDICTIONS);
+            self.predictions = Some(predictions);
         }
+        let predictions = self.predictions.as_deref().unwrap_or_default();
+
+        // predictions too old to be in the results still go first, if the filter lets them
+        let missing: Vec<String> = predictions
+            .iter()
+            .filter(|p| !results.iter().any(|h| h.command == p.command))
+            .map(|p| p.command.clone())
+            .collect();
+        let latest = db
+            .latest_runs(&missing, self.search.filter_mode, &self.search.context)
+            .await?;
+
+        let results = predicted_first(results, latest, predictions);
+        self.results_len = results.len();
+
+        Ok(results)
     }
 
     fn handle_input<W>(
@@ -207,8 +289,17 @@ impl State {
             return InputAction::Continue;
         }
 
+        self.status = None;
+
+        if self.script_name.is_some() {
+            return self.handle_script_name_input(input);
+        }
+
+        if self.pending_delete.is_some() {
+            return self.handle_delete_confirm_input(input);
+        }
+
         let ctrl = input.modifiers.contains(KeyModifiers::CONTROL);
-        let esc_allow_exit = !(self.tab_index == 0 && self.keymap_mode == KeymapMode::VimInsert);
         let cursor_at_end_of_line =
             self.search.input.position() == UnicodeWidthStr::width(self.search.input.as_str());
         let cursor_at_start_of_line = self.search.input.position() == 0;
@@ -222,40 +313,65 @@ impl State {
             return InputAction::Continue;
         }
 
-        // core input handling, common for all tabs
-        let common: Option<InputAction> = match input.code {
-            KeyCode::Char('c' | 'g') if ctrl => Some(InputAction::ReturnOriginal),
-            KeyCode::Esc if esc_allow_exit => Some(Self::handle_key_exit(settings)),
-            KeyCode::Char('[') if ctrl && esc_allow_exit => Some(Self::handle_key_exit(settings)),
-            KeyCode::Tab => Some(InputAction::Accept(self.results_state.selected())),
-            KeyCode::Right if cursor_at_end_of_line && settings.keys.accept_past_line_end => {
-                Some(InputAction::Accept(self.results_state.selected()))
-            }
-            KeyCode::Left if cursor_at_start_of_line && settings.keys.exit_past_line_start => {
-                Some(Self::handle_key_exit(settings))
+        // these have settings of their own, and take precedence over the keymap. In the
+        // directories and commands tabs, left and right open and close instead.
+        let tree_tab = matches!(self.tab_index, 2 | 3);
+        match input.code {
+            KeyCode::Right
+                if cursor_at_end_of_line && settings.keys.accept_past_line_end && !tree_tab =>
+            {
+                self.prefix = false;
+                return self.accept_selection();
             }
-            KeyCode::Char('o') if ctrl => {
-                self.tab_index = (self.tab_index + 1) % TAB_TITLES.len();
-                Some(InputAction::Continue)
+            KeyCode::Left
+                if cursor_at_start_of_line && settings.keys.exit_past_line_start && !tree_tab =>
+            {
+                self.prefix = false;
+                return Self::handle_key_exit(settings);
             }
-            _ => None,
-        };
-
-        if let Some(ret) = common {
-            self.prefix = false;
-
-            return ret;
+            _ => {}
         }
 
-        // handle tab-specific input
-        let action = match self.tab_index {
-            0 => self.handle_search_input(settings, input),
+        // reset the state, will be set to true later if user really did change it
+        self.switched_search_mode = false;
 
-            1 => super::inspector::input(self, settings, self.results_state.selected(), input),
+        // keys pressed after the prefix take precedence over all others
+        // eg, if a user types ctrl-a d, delete the history
+        let prefixed = if self.prefix && self.tab_index == 0 {
+            self.keymaps.prefix.action(input)
+        } else {
+            None
+        };
 
+        let keymap = match self.tab_index {
+            0 => self.keymaps.for_mode(self.keymap_mode),
+            1 => &self.keymaps.inspector,
+            2 => &self.keymaps.directories,
+            3 => &self.keymaps.commands,
             _ => panic!("invalid tab index on input"),
         };
 
+        let action = match prefixed.or_else(|| keymap.action(input)) {
+            Some(action) if self.tab_index == 1 && self.timeline.is_some() => {
+                self.handle_timeline_action(settings, action)
+            }
+            Some(action) if self.tab_index == 2 => self.handle_directories_action(settings, action),
+            Some(action) if self.tab_index == 3 => self.handle_groups_action(settings, action),
+            Some(action) => self.handle_action(settings, action),
+
+            // characters nobody has bound are typed into the search, except in vim normal mode
+            None => match input.code {
+                KeyCode::Char(c)
+                    if self.tab_index == 0
+                        && (ctrl || self.keymap_mode != KeymapMode::VimNormal) =>
+                {
+                    self.search.input.insert(c);
+                    InputAction::Continue
+                }
+                _ => InputAction::Continue,
+            },
+        };
+
         self.prefix = false;
 
         action
@@ -290,176 +406,320 @@ impl State {
         if settings.enter_accept {
             self.accept = true;
         }
-        InputAction::Accept(self.results_state.selected())
+        self.accept_selection()
     }
 
-    #[allow(clippy::too_many_lines)]
-    #[allow(clippy::cognitive_complexity)]
-    fn handle_search_input(&mut self, settings: &Settings, input: &KeyEvent) -> InputAction {
+    /// The selected entry, or every marked entry if there are any
+    fn accept_selection(&self) -> InputAction {
+        if self.marked.is_empty() {
+            InputAction::Accept(self.results_state.selected())
+        } else {
+            InputAction::AcceptMarked
+        }
+    }
+
+    fn handle_script_name_input(&mut self, input: &KeyEvent) -> InputAction {
         let ctrl = input.modifiers.contains(KeyModifiers::CONTROL);
-        let alt = input.modifiers.contains(KeyModifiers::ALT);
+        let Some(name) = &mut self.script_name else {
+            return InputAction::Continue;
+        };
 
-        // Use Ctrl-n instead of Alt-n?
-        let modfr = if settings.ctrl_n_shortcuts { ctrl } else { alt };
+        match input.code {
+            KeyCode::Enter => {
+                let name = name.as_str().to_string();
+                if !name.is_empty() {
+                    self.script_name = None;
+                    return InputAction::SaveScript(name);
+                }
+            }
+            KeyCode::Esc => self.script_name = None,
+            KeyCode::Char('c' | 'g') if ctrl => self.script_name = None,
+            KeyCode::Backspace => {
+                name.back();
+            }
+            KeyCode::Left => {
+                name.left();
+            }
+            KeyCode::Right => name.right(),
+            // script names are used on the command line, so keep them to one word
+            KeyCode::Char(c) if !ctrl && !c.is_whitespace() => name.insert(c),
+            _ => {}
+        }
 
-        // reset the state, will be set to true later if user really did change it
-        self.switched_search_mode = false;
+        InputAction::Continue
+    }
 
-        // first up handle prefix mappings. these take precedence over all others
-        // eg, if a user types ctrl-a d, delete the history
-        if self.prefix {
-            // It'll be expanded.
-            #[allow(clippy::single_match)]
-            match input.code {
-                KeyCode::Char('d') => {
-                    return InputAction::Delete(self.results_state.selected());
-                }
-                KeyCode::Char('a') => {
-                    self.search.input.start();
-                    //  This prevents pressing ctrl-a twice while still in prefix mode
-                    self.prefix = false;
-                    return InputAction::Continue;
-                }
-                _ => {}
+    /// The arrow and page keys scroll the preview. Anything else but y cancels, so a stray key
+    /// never deletes.
+    fn handle_delete_confirm_input(&mut self, input: &KeyEvent) -> InputAction {
+        let page = self.results_state.max_entries().max(1);
+
+        match input.code {
+            KeyCode::Char('y') if input.modifiers.difference(KeyModifiers::SHIFT).is_empty() => {
+                return InputAction::DeleteMatching;
             }
+            KeyCode::Up => self.delete_scroll = self.delete_scroll.saturating_sub(1),
+            KeyCode::Down => self.scroll_delete_preview_down(1),
+            KeyCode::PageUp => self.delete_scroll = self.delete_scroll.saturating_sub(page),
+            KeyCode::PageDown => self.scroll_delete_preview_down(page),
+            _ => self.pending_delete = None,
         }
 
-        // handle keymap specific keybindings.
-        match self.keymap_mode {
-            KeymapMode::VimNormal => match input.code {
-                KeyCode::Char('?' | '/') if !ctrl => {
-                    self.search.input.clear();
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('j') if !ctrl => {
-                    return self.handle_search_down(settings, true);
-                }
-                KeyCode::Char('k') if !ctrl => {
-                    return self.handle_search_up(settings, true);
-                }
-                KeyCode::Char('h') if !ctrl => {
-                    self.search.input.left();
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('l') if !ctrl => {
-                    self.search.input.right();
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('a') if !ctrl => {
-                    self.search.input.right();
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('A') if !ctrl => {
-                    self.search.input.end();
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('i') if !ctrl => {
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('I') if !ctrl => {
-                    self.search.input.start();
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char(_) if !ctrl => {
-                    return InputAction::Continue;
+        InputAction::Continue
+    }
+
+    fn scroll_delete_preview_down(&mut self, lines: usize) {
+        let Some(pending) = &self.pending_delete else {
+            return;
+        };
+
+        // a line per host and a blank one come before the entries
+        let last = super::hosts(pending).len() + pending.len();
+        self.delete_scroll = (self.delete_scroll + lines).min(last);
+    }
+
+    fn toggle_mark(&mut self, results: &[History], index: usize) {
+        let Some(entry) = results.get(index) else {
+            return;
+        };
+
+        if let Some(i) = self.marked.iter().position(|h| h.id == entry.id) {
+            self.marked.remove(i);
+        } else {
+            self.marked.push(entry.clone());
+        }
+
+        // on to the next entry, so a run of them can be marked by pressing the key repeatedly
+        self.scroll_up(1);
+    }
+
+    /// Mark everything from the last entry marked, to this one
+    fn mark_range(&mut self, results: &[History], index: usize) {
+        let anchor = self
+            .marked
+            .last()
+            .and_then(|last| results.iter().position(|h| h.id == last.id));
+
+        let Some(anchor) = anchor else {
+            self.toggle_mark(results, index);
+            return;
+        };
+
+        let range = anchor.min(index)..=anchor.max(index);
+        for entry in results.get(range).unwrap_or_default() {
+            if !self.marked.iter().any(|h| h.id == entry.id) {
+                self.marked.push(entry.clone());
+            }
+        }
+
+        // the next range starts from here
+        if let Some(i) = results
+            .get(index)
+            .and_then(|entry| self.marked.iter().position(|h| h.id == entry.id))
+        {
+            let entry = self.marked.remove(i);
+            self.marked.push(entry);
+        }
+    }
+
+    /// The marked entries oldest first, the order they were run in. Or the selected entry, if
+    /// none are marked.
+    fn batch(&self, results: &[History]) -> Vec<History> {
+        if self.marked.is_empty() {
+            return results
+                .get(self.results_state.selected())
+                .cloned()
+                .into_iter()
+                .collect();
+        }
+
+        let mut marked = self.marked.clone();
+        marked.sort_by_key(|h| h.timestamp);
+        marked
+    }
+
+    /// With the session shown in the inspector, moving around moves through the session
+    fn handle_timeline_action(&mut self, settings: &Settings, action: Action) -> InputAction {
+        let Some(timeline) = &mut self.timeline else {
+            return self.handle_action(settings, action);
+        };
+
+        match action {
+            Action::Up | Action::SelectPrevious => timeline.state.select_previous(),
+            Action::Down | Action::SelectNext => timeline.state.select_next(),
+            Action::PageUp => timeline.state.scroll_up_by(timeline.height.max(1)),
+            Action::PageDown => timeline.state.scroll_down_by(timeline.height.max(1)),
+            Action::Copy => return InputAction::CopySession(timeline.state.selected()),
+            Action::CopySession => return InputAction::CopySession(None),
+            _ => return self.handle_action(settings, action),
+        }
+
+        InputAction::Continue
+    }
+
+    /// In the directories tab, moving around moves around the tree
+    fn handle_directories_action(&mut self, settings: &Settings, action: Action) -> InputAction {
+        let Some(tree) = &mut self.directories else {
+            return self.handle_action(settings, action);
+        };
+
+        match action {
+            Action::Up | Action::SelectPrevious => tree.up(1),
+            Action::Down | Action::SelectNext => tree.down(1),
+            Action::PageUp => tree.page_up(),
+            Action::PageDown => tree.page_down(),
+            Action::Expand => tree.expand(),
+            Action::Collapse => tree.collapse(),
+            Action::Accept => {
+                if let Some(path) = tree.selected_path().map(str::to_string) {
+                    self.browse(path);
                 }
-                _ => {}
-            },
-            KeymapMode::VimInsert => {
-                if input.code == KeyCode::Esc || (ctrl && input.code == KeyCode::Char('[')) {
-                    self.set_keymap_cursor(settings, "vim_normal");
-                    self.keymap_mode = KeymapMode::VimNormal;
+            }
+            _ => return self.handle_action(settings, action),
+        }
+
+        InputAction::Continue
+    }
+
+    /// In the commands tab, moving around moves through the commands and their runs, and
+    /// whichever is selected stands for its command
+    fn handle_groups_action(&mut self, settings: &Settings, action: Action) -> InputAction {
+        let Some(groups) = &mut self.groups else {
+            return self.handle_action(settings, action);
+        };
+
+        match action {
+            Action::Up | Action::SelectPrevious => groups.up(1),
+            Action::Down | Action::SelectNext => groups.down(1),
+            Action::PageUp => groups.page_up(),
+            Action::PageDown => groups.page_down(),
+            Action::Expand => groups.expand(),
+            Action::Collapse => groups.collapse(),
+            Action::Accept | Action::ReturnSelection | Action::Copy => {
+                let Some(index) = groups.selected_result() else {
                     return InputAction::Continue;
-                }
+                };
+
+                return match action {
+                    Action::Copy => InputAction::Copy(index),
+                    Action::Accept => {
+                        self.accept = settings.enter_accept;
+                        InputAction::Accept(index)
+                    }
+                    _ => InputAction::Accept(index),
+                };
             }
-            _ => {}
+            _ => return self.handle_action(settings, action),
         }
 
-        match input.code {
-            KeyCode::Enter => return self.handle_search_accept(settings),
-            KeyCode::Char('m') if ctrl => return self.handle_search_accept(settings),
-            KeyCode::Char('y') if ctrl => {
+        InputAction::Continue
+    }
+
+    /// Search the commands run in a directory, or anywhere under it. These are the directory and
+    /// workspace filters, with the directory standing in for where we are and the git root.
+    fn browse(&mut self, path: String) {
+        if self.browsing.is_none() {
+            self.browsing = Some((
+                self.search.context.cwd.clone(),
+                self.search.context.git_root.clone(),
+                self.search.filter_mode,
+            ));
+        }
+
+        self.search.context.git_root = Some(PathBuf::from(&path));
+        self.search.context.cwd = path;
+        self.search.filter_mode = FilterMode::Workspace;
+        self.tab_index = 0;
+    }
+
+    /// Go back to where we were before browsing a directory
+    fn stop_browsing(&mut self) {
+        if let Some((cwd, git_root, filter_mode)) = self.browsing.take() {
+            self.search.context.cwd = cwd;
+            self.search.context.git_root = git_root;
+            self.search.filter_mode = filter_mode;
+        }
+    }
+
+    fn set_keymap_mode(&mut self, settings: &Settings, mode: KeymapMode) {
+        let cursor = match mode {
+            KeymapMode::VimNormal => "vim_normal",
+            KeymapMode::VimInsert => "vim_insert",
+            KeymapMode::Emacs | KeymapMode::Auto => "emacs",
+        };
+
+        self.set_keymap_cursor(settings, cursor);
+        self.keymap_mode = mode;
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn handle_action(&mut self, settings: &Settings, action: Action) -> InputAction {
+        match action {
+            Action::ReturnOriginal => return InputAction::ReturnOriginal,
+            Action::Exit => return Self::handle_key_exit(settings),
+            Action::ReturnSelection => return self.accept_selection(),
+            Action::ToggleTab => {
+                self.tab_index = (self.tab_index + 1) % TAB_TITLES.len();
+
+                // the inspector still shows the directory's commands, the directories tab doesn't
+                if self.tab_index == 2 {
+                    self.stop_browsing();
+                }
+            }
+            Action::Accept => return self.handle_search_accept(settings),
+            Action::AcceptNth(n) => {
+                return InputAction::Accept(self.results_state.selected() + usize::from(n));
+            }
+            Action::Copy if self.marked.is_empty() => {
                 return InputAction::Copy(self.results_state.selected());
             }
-            KeyCode::Char(c @ '1'..='9') if modfr => {
-                return c.to_digit(10).map_or(InputAction::Continue, |c| {
-                    InputAction::Accept(self.results_state.selected() + c as usize)
-                });
+            Action::Copy => return InputAction::CopyMarked,
+            Action::Delete if self.marked.is_empty() => {
+                return InputAction::Delete(self.results_state.selected());
             }
-            KeyCode::Left if ctrl => self
-                .search
-                .input
-                .prev_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Char('b') if alt => self
-                .search
-                .input
-                .prev_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Left => {
-                self.search.input.left();
+            Action::Delete => return InputAction::DeleteMarked,
+            Action::DeleteMatching if self.search.input.as_str().trim().is_empty() => {
+                self.status = Some("Type a query first, to match what to delete".to_string());
             }
-            KeyCode::Char('b') if ctrl => {
+            Action::DeleteMatching => return InputAction::PreviewDeleteMatching,
+            Action::ToggleMark => return InputAction::Mark(self.results_state.selected()),
+            Action::MarkRange => return InputAction::MarkRange(self.results_state.selected()),
+            Action::SaveScript => self.script_name = Some(Cursor::from(String::new())),
+            Action::CursorLeft => {
                 self.search.input.left();
             }
-            KeyCode::Right if ctrl => self
+            Action::CursorRight => self.search.input.right(),
+            Action::CursorWordLeft => self
                 .search
                 .input
-                .next_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Char('f') if alt => self
+                .prev_word(&settings.word_chars, settings.word_jump_mode),
+            Action::CursorWordRight => self
                 .search
                 .input
                 .next_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Right => self.search.input.right(),
-            KeyCode::Char('f') if ctrl => self.search.input.right(),
-            KeyCode::Home => self.search.input.start(),
-            KeyCode::Char('a') if ctrl => self.search.input.start(),
-            KeyCode::Char('e') if ctrl => self.search.input.end(),
-            KeyCode::End => self.search.input.end(),
-            KeyCode::Backspace if ctrl => self
-                .search
-                .input
-                .remove_prev_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Backspace => {
-                self.search.input.back();
-            }
-            KeyCode::Char('h' | '?') if ctrl => {
-                // Depending on the terminal, [Backspace] can be transmitted as
-                // \x08 or \x7F.  Also, [Ctrl+Backspace] can be transmitted as
-                // \x08 or \x7F or \x1F.  On the other hand, [Ctrl+h] and
-                // [Ctrl+?] are also transmitted as \x08 or \x7F by the
-                // terminals.
-                //
-                // The crossterm library translates \x08 and \x7F to C-h and
-                // Backspace, respectively.  With the extended keyboard
-                // protocol enabled, crossterm can faithfully translate
-                // [Ctrl+h] and [Ctrl+?] to C-h and C-?.  There is no perfect
-                // solution, but we treat C-h and C-? the same as backspace to
-                // suppress quirks as much as possible.
+            Action::CursorStart => self.search.input.start(),
+            Action::CursorEnd => self.search.input.end(),
+            Action::DeleteCharBefore => {
                 self.search.input.back();
             }
-            KeyCode::Delete if ctrl => self
-                .search
-                .input
-                .remove_next_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Delete => {
+            Action::DeleteCharAfter => {
                 self.search.input.remove();
             }
-            KeyCode::Char('d') if ctrl => {
+            Action::DeleteCharOrExit => {
                 if self.search.input.as_str().is_empty() {
                     return InputAction::ReturnOriginal;
                 }
                 self.search.input.remove();
             }
-            KeyCode::Char('w') if ctrl => {
+            Action::DeleteWordBefore => self
+                .search
+                .input
+                .remove_prev_word(&settings.word_chars, settings.word_jump_mode),
+            Action::DeleteWordAfter => self
+                .search
+                .input
+                .remove_next_word(&settings.word_chars, settings.word_jump_mode),
+            Action::UnixWordRubout => {
                 // remove the first batch of whitespace
                 while matches!(self.search.input.back(), Some(c) if c.is_whitespace()) {}
                 while self.search.input.left() {
@@ -470,48 +730,57 @@ impl State {
                     self.search.input.remove();
                 }
             }
-            KeyCode::Char('u') if ctrl => self.search.input.clear(),
-            KeyCode::Char('r') if ctrl => self.search.rotate_filter_mode(settings, 1),
-            KeyCode::Char('s') if ctrl => {
+            Action::ClearLine => self.search.input.clear(),
+            Action::CycleFilterMode => {
+                self.stop_browsing();
+                self.search.rotate_filter_mode(settings, 1);
+            }
+            Action::CycleSearchMode => {
                 self.switched_search_mode = true;
                 self.search_mode = self.search_mode.next(settings);
                 self.engine = engines::engine(self.search_mode);
             }
-            KeyCode::Down => {
-                return self.handle_search_down(settings, true);
-            }
-            KeyCode::Up => {
-                return self.handle_search_up(settings, true);
-            }
-            KeyCode::Char('n' | 'j') if ctrl => {
-                return self.handle_search_down(settings, false);
-            }
-            KeyCode::Char('p' | 'k') if ctrl => {
-                return self.handle_search_up(settings, false);
-            }
-            KeyCode::Char('l') if ctrl => {
-                return InputAction::Redraw;
+            Action::Up => return self.handle_search_up(settings, true),
+            Action::Down => return self.handle_search_down(settings, true),
+            Action::SelectPrevious => return self.handle_search_up(settings, false),
+            Action::SelectNext => return self.handle_search_down(settings, false),
+            Action::PageUp | Action::PageDown => {
+                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
+
+                if (action == Action::PageUp) == settings.invert {
+                    self.scroll_down(scroll_len);
+                } else {
+                    self.scroll_up(scroll_len);
+                }
             }
-            KeyCode::Char(c) => {
-                self.search.input.insert(c);
+            Action::Redraw => return InputAction::Redraw,
+            Action::VimNormal => self.set_keymap_mode(settings, KeymapMode::VimNormal),
+            Action::VimInsert => self.set_keymap_mode(settings, KeymapMode::VimInsert),
+            Action::VimInsertAfter => {
+                self.search.input.right();
+                self.set_keymap_mode(settings, KeymapMode::VimInsert);
             }
-            KeyCode::PageDown if !settings.invert => {
-                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
-                self.scroll_down(scroll_len);
+            Action::VimInsertStart => {
+                self.search.input.start();
+                self.set_keymap_mode(settings, KeymapMode::VimInsert);
             }
-            KeyCode::PageDown if settings.invert => {
-                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
-                self.scroll_up(scroll_len);
+            Action::VimInsertEnd => {
+                self.search.input.end();
+                self.set_keymap_mode(settings, KeymapMode::VimInsert);
             }
-            KeyCode::PageUp if !settings.invert => {
-                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
-                self.scroll_up(scroll_len);
+            Action::VimSearch => {
+                self.search.input.clear();
+                self.set_keymap_mode(settings, KeymapMode::VimInsert);
             }
-            KeyCode::PageUp if settings.invert => {
-                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
-                self.scroll_down(scroll_len);
+            Action::ToggleSession => {
+                self.timeline = match self.timeline {
+                    Some(_) => None,
+                    None => Some(Timeline::default()),
+                };
             }
-            _ => {}
+            Action::CopySession => return InputAction::CopySession(None),
+            // these only mean something in the directories and commands tabs
+            Action::Expand | Action::Collapse => {}
         }
 
         InputAction::Continue
@@ -595,7 +864,7 @@ impl State {
             }) + border_size * 2
         } else if settings.show_preview && settings.preview.strategy == PreviewStrategy::Fixed {
             settings.max_preview_height + border_size * 2
-        } else if compact || tab_index == 1 {
+        } else if compact || tab_index != 0 {
             0
         } else {
             1
@@ -731,6 +1000,11 @@ impl State {
         };
 
         match self.tab_index {
+            0 if self.pending_delete.is_some() => {
+                let preview = self.build_delete_preview(style, theme);
+                f.render_widget(preview, results_list_chunk);
+            }
+
             0 => {
                 let history_highlighter = HistoryHighlighter {
                     engine: self.engine.as_ref(),
@@ -744,8 +1018,15 @@ impl State {
                     indicator.as_str(),
                     theme,
                     history_highlighter,
-                );
-                f.render_stateful_widget(results_list, results_list_chunk, &mut self.results_state);
+                )
+                .marked(&self.marked)
+                .syntax(self.syntax);
+
+                let (list_chunk, details_chunk) =
+                    Self::split_details(settings, compact, results_list_chunk);
+                f.render_stateful_widget(results_list, list_chunk, &mut self.results_state);
+
+                self.draw_details(f, details_chunk, results, settings, theme);
             }
 
             1 => {
@@ -766,6 +1047,7 @@ impl State {
                         results_list_chunk,
                         &results[self.results_state.selected()],
                         &stats.expect("Drawing inspector, but no stats"),
+                        self.timeline.as_mut(),
                         theme,
                         settings.timezone,
                     );
@@ -781,6 +1063,40 @@ impl State {
                 return;
             }
 
+            2 => {
+                match &mut self.directories {
+                    Some(tree) if !tree.is_empty() => {
+                        super::directories::draw(f, results_list_chunk, tree, theme);
+                    }
+                    _ => {
+                        let message = Paragraph::new("No directories in history")
+                            .block(
+                                Block::new()
+                                    .title(Title::from(" Info ".to_string()))
+                                    .title_alignment(Alignment::Center)
+                                    .borders(Borders::ALL)
+                                    .padding(Padding::vertical(2)),
+                            )
+                            .alignment(Alignment::Center);
+                        f.render_widget(message, results_list_chunk);
+                    }
+                }
+
+                let selected = self
+                    .directories
+                    .as_ref()
+                    .and_then(Tree::selected_path)
+                    .unwrap_or_default();
+                f.render_widget(Paragraph::new(format!(" {selected}")), input_chunk);
+
+                return;
+            }
+
+            3 => {
+                self.draw_groups(f, results_list_chunk, input_chunk, theme);
+                return;
+            }
+
             _ => {
                 panic!("invalid tab index");
             }
@@ -804,7 +1120,11 @@ impl State {
             );
             f.render_widget(preview, preview_chunk);
 
-            let extra_width = UnicodeWidthStr::width(self.search.input.substring());
+            let extra_width = UnicodeWidthStr::width(
+                self.script_name
+                    .as_ref()
+                    .map_or_else(|| self.search.input.substring(), Cursor::substring),
+            );
 
             let cursor_offset = if compact { 0 } else { 1 };
             f.set_cursor_position((
@@ -815,6 +1135,39 @@ impl State {
         }
     }
 
+    /// The commands tab: the results grouped by command, and the selected one in full below
+    fn draw_groups(&mut self, f: &mut Frame, area: Rect, input_chunk: Rect, theme: &Theme) {
+        let now = (self.now)();
+
+        match &mut self.groups {
+            Some(groups) if !groups.is_empty() => {
+                super::groups::draw(f, area, groups, now, theme);
+            }
+            _ => {
+                let message = Paragraph::new("No results to group")
+                    .block(
+                        Block::new()
+                            .title(Title::from(" Info ".to_string()))
+                            .title_alignment(Alignment::Center)
+                            .borders(Borders::ALL)
+                            .padding(Padding::vertical(2)),
+                    )
+                    .alignment(Alignment::Center);
+                f.render_widget(message, area);
+            }
+        }
+
+        let selected = self
+            .groups
+            .as_ref()
+            .and_then(Groups::selected_command)
+            .unwrap_or_default();
+        f.render_widget(
+            Paragraph::new(format!(" {}", selected.escape_control())),
+            input_chunk,
+        );
+    }
+
     fn build_title(&self, theme: &Theme) -> Paragraph {
         let title = if self.update_needed.is_some() {
             let error_style: Style = theme.get_error().into();
@@ -834,41 +1187,155 @@ impl State {
 
     #[allow(clippy::unused_self)]
     fn build_help(&self, settings: &Settings, theme: &Theme) -> Paragraph {
-        match self.tab_index {
+        if let Some(pending) = &self.pending_delete {
+            return Paragraph::new(Line::from(vec![
+                Span::styled("<y>", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(
+                    ": delete {} entries, <up>/<down>: scroll, any other key: cancel",
+                    pending.len()
+                )),
+            ]))
+            .style(theme.as_style(Meaning::AlertError))
+            .alignment(Alignment::Center);
+        }
+
+        if let Some(status) = &self.status {
+            return Paragraph::new(status.as_str())
+                .style(theme.as_style(Meaning::Important))
+                .alignment(Alignment::Center);
+        }
+
+        let (keymap, hints) = match self.tab_index {
             // search
-            0 => Paragraph::new(Text::from(Line::from(vec![
-                Span::styled("<esc>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": exit"),
-                Span::raw(", "),
-                Span::styled("<tab>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": edit"),
-                Span::raw(", "),
-                Span::styled("<enter>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(if settings.enter_accept {
-                    ": run"
+            0 => (
+                self.keymaps.for_mode(self.keymap_mode),
+                vec![
+                    (Action::Exit, "exit"),
+                    (Action::ReturnSelection, "edit"),
+                    (
+                        Action::Accept,
+                        if settings.enter_accept { "run" } else { "edit" },
+                    ),
+                    (Action::ToggleTab, "inspect"),
+                ],
+            ),
+
+            1 => (
+                &self.keymaps.inspector,
+                if self.timeline.is_some() {
+                    vec![
+                        (Action::Exit, "exit"),
+                        (Action::ToggleSession, "stats"),
+                        (Action::Copy, "copy"),
+                        (Action::CopySession, "copy session"),
+                    ]
                 } else {
-                    ": edit"
-                }),
-                Span::raw(", "),
-                Span::styled("<ctrl-o>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": inspect"),
-            ]))),
-
-            1 => Paragraph::new(Text::from(Line::from(vec![
-                Span::styled("<esc>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": exit"),
-                Span::raw(", "),
-                Span::styled("<ctrl-o>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": search"),
-                Span::raw(", "),
-                Span::styled("<ctrl-d>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": delete"),
-            ]))),
+                    vec![
+                        (Action::Exit, "exit"),
+                        (Action::ToggleTab, "directories"),
+                        (Action::Delete, "delete"),
+                        (Action::ToggleSession, "session"),
+                    ]
+                },
+            ),
+
+            2 => (
+                &self.keymaps.directories,
+                vec![
+                    (Action::Exit, "exit"),
+                    (Action::ToggleTab, "commands"),
+                    (Action::Accept, "search here"),
+                    (Action::Expand, "open"),
+                ],
+            ),
+
+            3 => (
+                &self.keymaps.commands,
+                vec![
+                    (Action::Exit, "exit"),
+                    (Action::ToggleTab, "search"),
+                    (
+                        Action::Accept,
+                        if settings.enter_accept { "run" } else { "edit" },
+                    ),
+                    (Action::Expand, "runs"),
+                ],
+            ),
 
             _ => unreachable!("invalid tab index"),
+        };
+
+        // Only the first key of each action, and nothing for actions with no keys
+        let mut spans = Vec::new();
+        if self.tab_index == 0 && !self.marked.is_empty() {
+            spans.push(Span::styled(
+                format!("{} marked", self.marked.len()),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
         }
-        .style(theme.as_style(Meaning::Annotation))
-        .alignment(Alignment::Center)
+        for (action, label) in hints {
+            let Some(key) = keymap.hint(action) else {
+                continue;
+            };
+
+            if !spans.is_empty() {
+                spans.push(Span::raw(", "));
+            }
+            spans.push(Span::styled(
+                format!("<{key}>"),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            spans.push(Span::raw(format!(": {label}")));
+        }
+
+        Paragraph::new(Text::from(Line::from(spans)))
+            .style(theme.as_style(Meaning::Annotation))
+            .alignment(Alignment::Center)
+    }
+
+    /// What delete-matching is about to remove: how much from each host, then the entries
+    fn build_delete_preview(&self, style: StyleState, theme: &Theme) -> Paragraph {
+        let entries = self.pending_delete.as_deref().unwrap_or_default();
+        let hosts = super::hosts(entries);
+        let host_width = hosts.iter().map(|(host, _)| host.len()).max().unwrap_or(0);
+
+        let mut lines: Vec<Line> = hosts
+            .iter()
+            .map(|(host, count)| Line::from(format!("{count:>6}  {host}")))
+            .collect();
+        lines.push(Line::default());
+
+        lines.extend(entries.iter().map(|entry| {
+            let command = entry.command.lines().next().unwrap_or_default();
+
+            Line::from(vec![
+                Span::styled(
+                    format!("{:host_width$}  ", entry.hostname),
+                    theme.as_style(Meaning::Annotation),
+                ),
+                Span::raw(command.escape_control().to_string()),
+            ])
+        }));
+
+        let title = format!(
+            " Delete {} entries from {} host{} ",
+            entries.len(),
+            hosts.len(),
+            if hosts.len() == 1 { "" } else { "s" }
+        );
+        let block = if style.compact {
+            Block::new().title(title)
+        } else {
+            Block::new()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+        };
+
+        Paragraph::new(lines)
+            .style(theme.as_style(Meaning::Base))
+            .block(block.title_style(theme.as_style(Meaning::AlertError)))
+            .scroll((u16::try_from(self.delete_scroll).unwrap_or(u16::MAX), 0))
     }
 
     fn build_stats(&self, theme: &Theme) -> Paragraph {
@@ -880,6 +1347,40 @@ impl State {
         .alignment(Alignment::Right)
     }
 
+    /// Make room for the details pane next to the results, if it is on and fits
+    fn split_details(settings: &Settings, compact: bool, area: Rect) -> (Rect, Option<Rect>) {
+        if !settings.show_details || compact || area.width < 90 {
+            return (area, None);
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(3, 5), Constraint::Ratio(2, 5)])
+            .split(area);
+
+        (chunks[0], Some(chunks[1]))
+    }
+
+    fn draw_details(
+        &self,
+        f: &mut Frame,
+        area: Option<Rect>,
+        results: &[History],
+        settings: &Settings,
+        theme: &Theme,
+    ) {
+        if let Some(area) = area {
+            super::details::draw(
+                f,
+                area,
+                results.get(self.results_state.selected()),
+                self.syntax,
+                theme,
+                settings.timezone,
+            );
+        }
+    }
+
     fn build_results_list<'a>(
         style: StyleState,
         results: &'a [History],
@@ -928,7 +1429,10 @@ impl State {
         let mode_width = MAX_WIDTH - pref.len();
         // sanity check to ensure we don't exceed the layout limits
         debug_assert!(mode_width >= mode.len(), "mode name '{mode}' is too long!");
-        let input = format!("[{pref}{mode:^mode_width$}] {}", self.search.input.as_str(),);
+        let input = self.script_name.as_ref().map_or_else(
+            || format!("[{pref}{mode:^mode_width$}] {}", self.search.input.as_str()),
+            |name| format!("[{:^MAX_WIDTH$}] {}", "SCRIPT NAME", name.as_str()),
+        );
         let input = Paragraph::new(input);
         if style.compact {
             input
@@ -958,7 +1462,14 @@ impl State {
     ) -> Paragraph {
         let selected = self.results_state.selected();
         let command = if results.is_empty() {
-            String::new()
+            Text::default()
+        } else if self.syntax.is_some() {
+            Text::from(syntax::lines(
+                &results[selected].command,
+                self.syntax,
+                preview_width.into(),
+                theme,
+            ))
         } else {
             use itertools::Itertools as _;
             let s = &results[selected].command;
@@ -972,6 +1483,7 @@ impl State {
                         .map(|(a, b)| (&line[a..b]).escape_control().to_string())
                 })
                 .join("\n")
+                .into()
         };
 
         if compact {
@@ -987,6 +1499,30 @@ impl State {
     }
 }
 
+/// How many of the predicted commands go first
+const PREDICTIONS: usize = 10;
+
+/// Move the commands we predict will be run next to the top, most likely first. Those that aren't
+/// in the results are taken from `latest`, the last runs of the ones that aren't.
+fn predicted_first(
+    mut results: Vec<History>,
+    mut latest: Vec<History>,
+    predictions: &[Prediction],
+) -> Vec<History> {
+    let mut first = Vec::with_capacity(results.len() + latest.len());
+
+    for prediction in predictions {
+        if let Some(i) = results.iter().position(|h| h.command == prediction.command) {
+            first.push(results.remove(i));
+        } else if let Some(i) = latest.iter().position(|h| h.command == prediction.command) {
+            first.push(latest.remove(i));
+        }
+    }
+
+    first.extend(results);
+    first
+}
+
 struct Stdout {
     stdout: std::io::Stdout,
     inline_mode: bool,
@@ -1148,6 +1684,18 @@ pub async fn history(
             KeymapMode::Auto => KeymapMode::Emacs,
             value => value,
         },
+        keymaps: Keymaps::new(settings)?,
+        marked: Vec::new(),
+        script_name: None,
+        pending_delete: None,
+        delete_scroll: 0,
+        directories: None,
+        groups: None,
+        timeline: None,
+        browsing: None,
+        predictions: None,
+        syntax: settings.syntax_highlighting.then(Shell::from_env),
+        status: None,
         current_cursor: None,
         now: if settings.prefers_reduced_motion {
             let now = OffsetDateTime::now_utc();
@@ -1160,7 +1708,7 @@ pub async fn history(
 
     app.initialize_keymap_cursor(settings);
 
-    let mut results = app.query_results(&mut db, settings.smart_sort).await?;
+    let mut results = app.query_results(&mut db, settings).await?;
 
     if inline_height > 0 {
         terminal.clear()?;
@@ -1174,6 +1722,7 @@ pub async fn history(
         let initial_input = app.search.input.as_str().to_owned();
         let initial_filter_mode = app.search.filter_mode;
         let initial_search_mode = app.search_mode;
+        let initial_git_root = app.search.context.git_root.clone();
 
         let event_ready = tokio::task::spawn_blocking(|| event::poll(Duration::from_millis(250)));
 
@@ -1194,15 +1743,78 @@ pub async fn history(
                                 }
 
                                 let entry = results.remove(index);
+                                delete(settings, &db, history_store, entry).await?;
+
+                                app.directories = None;
+                                app.groups = None;
+                                app.tab_index  = 0;
+                            },
+                            InputAction::DeleteMarked => {
+                                let marked = std::mem::take(&mut app.marked);
+                                let ids: Vec<_> = marked.iter().map(|h| h.id.clone()).collect();
+                                results.retain(|h| !ids.contains(&h.id));
 
+                                // delete_many rebuilds once for the lot, rather than per entry
                                 if settings.sync.records {
-                                    let (id, _) = history_store.delete(entry.id).await?;
-                                    history_store.incremental_build(&db, &[id]).await?;
+                                    history_store.delete_many(&db, &ids).await?;
                                 } else {
-                                    db.delete(entry.clone()).await?;
+                                    for entry in marked {
+                                        db.delete(entry).await?;
+                                    }
                                 }
 
-                                app.tab_index  = 0;
+                                app.results_len = results.len();
+                                let selected = app.results_state.selected();
+                                app.results_state.select(selected.min(app.results_len.saturating_sub(1)));
+
+                                app.directories = None;
+                                app.groups = None;
+                                app.tab_index = 0;
+                            },
+                            InputAction::PreviewDeleteMatching => {
+                                let matches = matching(&db, &app.search).await?;
+
+                                if matches.is_empty() {
+                                    app.status = Some("Nothing matches, so there is nothing to delete".to_string());
+                                } else {
+                                    app.pending_delete = Some(matches);
+                                    app.delete_scroll = 0;
+                                }
+                            },
+                            InputAction::DeleteMatching => {
+                                let matches = app.pending_delete.take().unwrap_or_default();
+                                let ids: Vec<_> = matches.iter().map(|h| h.id.clone()).collect();
+
+                                if settings.sync.records {
+                                    history_store.delete_many(&db, &ids).await?;
+                                } else {
+                                    for entry in matches {
+                                        db.delete(entry).await?;
+                                    }
+                                }
+
+                                app.marked.retain(|h| !ids.contains(&h.id));
+                                app.history_count = db.history_count(false).await?;
+                                results = app.query_results(&mut db, settings).await?;
+                                app.directories = None;
+                                app.status = Some(format!("Deleted {} entries", ids.len()));
+                            },
+                            InputAction::Mark(index) => app.toggle_mark(&results, index),
+                            InputAction::MarkRange(index) => app.mark_range(&results, index),
+                            InputAction::SaveScript(name) => {
+                                let commands = app
+                                    .batch(&results)
+                                    .into_iter()
+                                    .map(|h| h.command)
+                                    .collect::<Vec<_>>();
+
+                                app.status = Some(match save_script(settings, history_store, &name, &commands).await {
+                                    Ok(()) => {
+                                        app.marked.clear();
+                                        format!("Saved {} commands as script {name}", commands.len())
+                                    }
+                                    Err(e) => format!("Could not save script: {e}"),
+                                });
                             },
                             InputAction::Redraw => {
                                 terminal.clear()?;
@@ -1227,11 +1839,36 @@ pub async fn history(
         if initial_input != app.search.input.as_str()
             || initial_filter_mode != app.search.filter_mode
             || initial_search_mode != app.search_mode
+            || initial_git_root != app.search.context.git_root
         {
-            results = app.query_results(&mut db, settings.smart_sort).await?;
+            results = app.query_results(&mut db, settings).await?;
+        }
+
+        if app.tab_index == 2 && app.directories.is_none() {
+            let directories = db.directories().await?;
+            app.directories = Some(Tree::new(directories, &app.search.context.cwd));
+        }
+
+        if app.tab_index == 3 && app.groups.is_none() {
+            let commands: Vec<String> =
+                results.iter().map(|h| h.command.clone()).unique().collect();
+            let stats = db
+                .command_stats(&commands, app.search.filter_mode, &app.search.context)
+                .await?;
+            app.groups = Some(Groups::new(&results, stats));
         }
 
-        stats = if app.tab_index == 0 {
+        // only the commands that are opened have their runs fetched
+        if let Some((group, command)) = app.groups.as_ref().and_then(Groups::runs_needed) {
+            let runs = db
+                .runs(&command, app.search.filter_mode, &app.search.context, RUNS)
+                .await?;
+            if let Some(groups) = &mut app.groups {
+                groups.set_runs(group, runs);
+            }
+        }
+
+        stats = if app.tab_index != 1 {
             None
         } else if !results.is_empty() {
             let selected = results[app.results_state.selected()].clone();
@@ -1249,19 +1886,19 @@ pub async fn history(
 
     match result {
         InputAction::Accept(index) if index < results.len() => {
-            let mut command = results.swap_remove(index).command;
-            if accept
-                && (utils::is_zsh() || utils::is_fish() || utils::is_bash() || utils::is_xonsh())
-            {
-                if is_command_chaining {
-                    command = String::from("__atuin_chain_command__:") + &command;
-                } else {
-                    command = String::from("__atuin_accept__:") + &command;
-                }
-            }
+            let command = results.swap_remove(index).command;
 
             // index is in bounds so we return that entry
-            Ok(command)
+            Ok(accept_command(command, accept, is_command_chaining))
+        }
+        InputAction::AcceptMarked => {
+            let command = app
+                .batch(&results)
+                .into_iter()
+                .map(|h| h.command)
+                .join(" && ");
+
+            Ok(accept_command(command, accept, is_command_chaining))
         }
         InputAction::ReturnOriginal => Ok(String::new()),
         InputAction::Copy(index) => {
@@ -1269,18 +1906,136 @@ pub async fn history(
             set_clipboard(cmd);
             Ok(String::new())
         }
+        InputAction::CopyMarked => {
+            let cmds = app
+                .batch(&results)
+                .into_iter()
+                .map(|h| h.command)
+                .join("\n");
+            set_clipboard(cmds);
+            Ok(String::new())
+        }
+        InputAction::CopySession(index) => {
+            let session = stats.map(|stats| stats.session).unwrap_or_default();
+            let cmds = match index {
+                Some(index) => session
+                    .into_iter()
+                    .nth(index)
+                    .map(|h| h.command)
+                    .unwrap_or_default(),
+                None => session.into_iter().map(|h| h.command).join("\n"),
+            };
+            set_clipboard(cmds);
+            Ok(String::new())
+        }
         InputAction::ReturnQuery | InputAction::Accept(_) => {
             // Either:
             // * index == RETURN_QUERY, in which case we should return the input
             // * out of bounds -> usually implies no selected entry so we return the input
             Ok(app.search.input.into_inner())
         }
-        InputAction::Continue | InputAction::Redraw | InputAction::Delete(_) => {
+        InputAction::Continue
+        | InputAction::Redraw
+        | InputAction::Delete(_)
+        | InputAction::DeleteMarked
+        | InputAction::PreviewDeleteMatching
+        | InputAction::DeleteMatching
+        | InputAction::Mark(_)
+        | InputAction::MarkRange(_)
+        | InputAction::SaveScript(_) => {
             unreachable!("should have been handled!")
         }
     }
 }
 
+fn accept_command(command: String, accept: bool, is_command_chaining: bool) -> String {
+    if accept && (utils::is_zsh() || utils::is_fish() || utils::is_bash() || utils::is_xonsh()) {
+        if is_command_chaining {
+            return String::from("__atuin_chain_command__:") + &command;
+        }
+
+        return String::from("__atuin_accept__:") + &command;
+    }
+
+    command
+}
+
+async fn delete(
+    settings: &Settings,
+    db: &impl Database,
+    history_store: &HistoryStore,
+    entry: History,
+) -> Result<()> {
+    if settings.sync.records {
+        let (id, _) = history_store.delete(entry.id).await?;
+        history_store.incremental_build(db, &[id]).await?;
+    } else {
+        db.delete(entry).await?;
+    }
+
+    Ok(())
+}
+
+/// Everything that contains the query, on every host and including duplicates - a secret has to
+/// go wherever it was synced to. Always a full text match, whatever the search mode: a fuzzy one
+/// would sweep up unrelated commands that merely share the query's letters.
+async fn matching(db: &impl Database, search: &SearchState) -> Result<Vec<History>> {
+    let matches = db
+        .search(
+            SearchMode::FullText,
+            FilterMode::Global,
+            &search.context,
+            search.input.as_str(),
+            OptFilters {
+                include_duplicates: true,
+                ..OptFilters::default()
+            },
+        )
+        .await?;
+
+    Ok(matches)
+}
+
+/// Save commands as a new script, one per line
+async fn save_script(
+    settings: &Settings,
+    history_store: &HistoryStore,
+    name: &str,
+    commands: &[String],
+) -> Result<()> {
+    if commands.is_empty() {
+        bail!("nothing is selected");
+    }
+
+    let script_db =
+        atuin_scripts::database::Database::new(&settings.scripts.db_path, settings.local_timeout)
+            .await?;
+
+    if script_db.get_by_name(name).await?.is_some() {
+        bail!("there is already a script named {name}");
+    }
+
+    let encryption_key: [u8; 32] = encryption::load_tag_key(settings, SCRIPT_TAG)?.into();
+    let script_store = ScriptStore::new(
+        history_store.store.clone(),
+        history_store.host_id,
+        encryption_key,
+    );
+
+    let script = Script::builder()
+        .name(name.to_string())
+        .description(String::new())
+        .shebang(String::new())
+        .tags(vec![])
+        .script(commands.join("\n"))
+        .build();
+
+    script_store.create(script).await?;
+    script_store.build(script_db).await?;
+
+    Ok(())
+}
+
 // cli-clipboard only works on Windows, Mac, and Linux.
 
 #[cfg(all(
@@ -1302,17 +2057,20 @@ fn set_clipboard(_s: String) {}
 
 #[cfg(test)]
 mod tests {
-    use atuin_client::database::Context;
+    use atuin_client::database::{Context, Database, Sqlite};
     use atuin_client::history::History;
     use atuin_client::settings::{
         FilterMode, KeymapMode, Preview, PreviewStrategy, SearchMode, Settings,
     };
+    use atuin_history::predict::Prediction;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     use time::OffsetDateTime;
 
     use crate::command::client::search::engines::{self, SearchState};
     use crate::command::client::search::history_list::ListState;
+    use crate::command::client::search::keymap::{Action, Keymaps};
 
-    use super::State;
+    use super::{InputAction, State, predicted_first};
 
     #[test]
     #[allow(clippy::too_many_lines)]
@@ -1468,10 +2226,8 @@ mod tests {
         assert_eq!(settings_preview_fixed, 15 + border_space);
     }
 
-    // Test when there's no results, scrolling up or down doesn't underflow
-    #[test]
-    fn state_scroll_up_underflow() {
-        let mut state = State {
+    fn state() -> State {
+        State {
             history_count: 0,
             update_needed: None,
             results_state: ListState::default(),
@@ -1480,7 +2236,19 @@ mod tests {
             results_len: 0,
             accept: false,
             keymap_mode: KeymapMode::Auto,
+            keymaps: Keymaps::default(),
             prefix: false,
+            marked: Vec::new(),
+            script_name: None,
+            pending_delete: None,
+            delete_scroll: 0,
+            directories: None,
+            groups: None,
+            timeline: None,
+            browsing: None,
+            predictions: None,
+            syntax: None,
+            status: None,
             current_cursor: None,
             tab_index: 0,
             search: SearchState {
@@ -1496,9 +2264,202 @@ mod tests {
             },
             engine: engines::engine(SearchMode::Fuzzy),
             now: Box::new(OffsetDateTime::now_utc),
-        };
+        }
+    }
+
+    #[test]
+    fn browsing_is_undone() {
+        let settings = Settings::utc();
+        let mut state = state();
+        state.search.context.cwd = "/home/ellie".to_string();
+
+        state.browse("/srv/app".to_string());
+        state.browse("/srv/app/src".to_string());
+        assert_eq!(state.search.context.cwd, "/srv/app/src");
+        assert_eq!(state.search.filter_mode, FilterMode::Workspace);
+
+        state.handle_action(&settings, Action::ToggleTab);
+        assert_eq!(state.search.context.cwd, "/srv/app/src");
+
+        // back to the directories
+        state.handle_action(&settings, Action::ToggleTab);
+        assert_eq!(state.search.context.cwd, "/home/ellie");
+        assert_eq!(state.search.context.git_root, None);
+        assert_eq!(state.search.filter_mode, FilterMode::Directory);
+    }
+
+    #[tokio::test]
+    async fn browsing_with_skim() {
+        let settings = Settings::utc();
+        let db = Sqlite::new("sqlite::memory:", settings.local_timeout)
+            .await
+            .unwrap();
+
+        for cwd in ["/srv/app", "/srv/app/src", "/srv/application", "/srv"] {
+            let h: History = History::capture()
+                .timestamp(OffsetDateTime::now_utc())
+                .command(format!("ls {cwd}"))
+                .cwd(cwd)
+                .build()
+                .into();
+            db.save(&h).await.unwrap();
+        }
+
+        let mut state = state();
+        state.engine = engines::engine(SearchMode::Skim);
+        state.browse("/srv/app".to_string());
+
+        let mut db: Box<dyn Database> = Box::new(db);
+        let mut commands: Vec<String> = state
+            .engine
+            .full_query(&state.search, db.as_mut())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|h| h.command)
+            .collect();
+        commands.sort();
+
+        assert_eq!(commands, ["ls /srv/app", "ls /srv/app/src"]);
+    }
+
+    // Test when there's no results, scrolling up or down doesn't underflow
+    #[test]
+    fn state_scroll_up_underflow() {
+        let mut state = state();
 
         state.scroll_up(1);
         state.scroll_down(1);
     }
+
+    #[test]
+    fn marking_entries() {
+        let now = OffsetDateTime::now_utc();
+
+        // newest first, as the search returns them
+        let results: Vec<History> = (0..4)
+            .map(|i| {
+                History::capture()
+                    .timestamp(now - time::Duration::minutes(i))
+                    .command(format!("cmd{i}"))
+                    .cwd("/")
+                    .build()
+                    .into()
+            })
+            .collect();
+
+        let mut state = state();
+        state.results_len = results.len();
+
+        let commands = |state: &State| {
+            state
+                .batch(&results)
+                .into_iter()
+                .map(|h| h.command)
+                .collect::<Vec<_>>()
+        };
+
+        // nothing marked, so the selected entry
+        assert_eq!(commands(&state), ["cmd0"]);
+
+        // marking moves on to the next entry
+        state.toggle_mark(&results, 0);
+        assert_eq!(state.results_state.selected(), 1);
+
+        state.mark_range(&results, 2);
+        assert_eq!(commands(&state), ["cmd2", "cmd1", "cmd0"]);
+
+        state.toggle_mark(&results, 1);
+        assert_eq!(commands(&state), ["cmd2", "cmd0"]);
+    }
+
+    #[test]
+    fn predictions_go_first() {
+        let history = |command: &str| -> History {
+            History::capture()
+                .timestamp(OffsetDateTime::now_utc())
+                .command(command)
+                .cwd("/")
+                .build()
+                .into()
+        };
+        let results: Vec<History> = ["ls", "git add -p", "git status", "git commit -v"]
+            .into_iter()
+            .map(history)
+            .collect();
+
+        let predictions: Vec<Prediction> = ["git commit -v", "git push", "git status"]
+            .into_iter()
+            .map(|command| Prediction {
+                command: command.to_string(),
+                score: 1.0,
+                count: 1,
+            })
+            .collect();
+
+        let commands: Vec<String> = predicted_first(results.clone(), Vec::new(), &predictions)
+            .into_iter()
+            .map(|h| h.command)
+            .collect();
+
+        // a prediction that is nowhere to be found is left out
+        assert_eq!(
+            commands,
+            ["git commit -v", "git status", "ls", "git add -p"]
+        );
+
+        let latest = vec![history("git push")];
+        let commands: Vec<String> = predicted_first(results, latest, &predictions)
+            .into_iter()
+            .map(|h| h.command)
+            .collect();
+
+        assert_eq!(
+            commands,
+            [
+                "git commit -v",
+                "git push",
+                "git status",
+                "ls",
+                "git add -p"
+            ]
+        );
+    }
+
+    #[test]
+    fn delete_matching_needs_confirmation() {
+        let settings = Settings::utc();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+        let mut state = state();
+
+        // without a query, everything would match
+        state.handle_action(&settings, Action::DeleteMatching);
+        assert!(state.status.is_some());
+
+        state.search.input.insert('x');
+        assert!(matches!(
+            state.handle_action(&settings, Action::DeleteMatching),
+            InputAction::PreviewDeleteMatching
+        ));
+
+        // the arrows scroll the preview, any other key but y cancels
+        state.pending_delete = Some(Vec::new());
+        assert!(matches!(
+            state.handle_key_input(&settings, &KeyEvent::from(KeyCode::Down)),
+            InputAction::Continue
+        ));
+        assert!(state.pending_delete.is_some());
+        assert!(matches!(
+            state.handle_key_input(&settings, &key('n')),
+            InputAction::Continue
+        ));
+        assert!(state.pending_delete.is_none());
+
+        state.pending_delete = Some(Vec::new());
+        assert!(matches!(
+            state.handle_key_input(&settings, &key('y')),
+            InputAction::DeleteMatching
+        ));
+    }
 }
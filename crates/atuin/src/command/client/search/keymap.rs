@@ -0,0 +1,666 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use eyre::{Result, bail, eyre};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use atuin_client::settings::{KeymapMode, Settings};
+
+/// Everything a key can do in the interactive search
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    ReturnOriginal,
+    Exit,
+    ReturnSelection,
+    ToggleTab,
+    Accept,
+    AcceptNth(u8),
+    Copy,
+    Delete,
+    DeleteMatching,
+    ToggleMark,
+    MarkRange,
+    SaveScript,
+    CursorLeft,
+    CursorRight,
+    CursorWordLeft,
+    CursorWordRight,
+    CursorStart,
+    CursorEnd,
+    DeleteCharBefore,
+    DeleteCharAfter,
+    DeleteCharOrExit,
+    DeleteWordBefore,
+    DeleteWordAfter,
+    UnixWordRubout,
+    ClearLine,
+    CycleFilterMode,
+    CycleSearchMode,
+    Up,
+    Down,
+    SelectPrevious,
+    SelectNext,
+    PageUp,
+    PageDown,
+    Redraw,
+    VimNormal,
+    VimInsert,
+    VimInsertAfter,
+    VimInsertStart,
+    VimInsertEnd,
+    VimSearch,
+    Expand,
+    Collapse,
+    ToggleSession,
+    CopySession,
+}
+
+impl Action {
+    pub const ALL: [Self; 52] = [
+        Self::ReturnOriginal,
+        Self::Exit,
+        Self::ReturnSelection,
+        Self::ToggleTab,
+        Self::Accept,
+        Self::AcceptNth(1),
+        Self::AcceptNth(2),
+        Self::AcceptNth(3),
+        Self::AcceptNth(4),
+        Self::AcceptNth(5),
+        Self::AcceptNth(6),
+        Self::AcceptNth(7),
+        Self::AcceptNth(8),
+        Self::AcceptNth(9),
+        Self::Copy,
+        Self::Delete,
+        Self::DeleteMatching,
+        Self::ToggleMark,
+        Self::MarkRange,
+        Self::SaveScript,
+        Self::CursorLeft,
+        Self::CursorRight,
+        Self::CursorWordLeft,
+        Self::CursorWordRight,
+        Self::CursorStart,
+        Self::CursorEnd,
+        Self::DeleteCharBefore,
+        Self::DeleteCharAfter,
+        Self::DeleteCharOrExit,
+        Self::DeleteWordBefore,
+        Self::DeleteWordAfter,
+        Self::UnixWordRubout,
+        Self::ClearLine,
+        Self::CycleFilterMode,
+        Self::CycleSearchMode,
+        Self::Up,
+        Self::Down,
+        Self::SelectPrevious,
+        Self::SelectNext,
+        Self::PageUp,
+        Self::PageDown,
+        Self::Redraw,
+        Self::VimNormal,
+        Self::VimInsert,
+        Self::VimInsertAfter,
+        Self::VimInsertStart,
+        Self::VimInsertEnd,
+        Self::VimSearch,
+        Self::Expand,
+        Self::Collapse,
+        Self::ToggleSession,
+        Self::CopySession,
+    ];
+
+    pub fn name(self) -> String {
+        let name = match self {
+            Self::ReturnOriginal => "return-original",
+            Self::Exit => "exit",
+            Self::ReturnSelection => "return-selection",
+            Self::ToggleTab => "toggle-tab",
+            Self::Accept => "accept",
+            Self::AcceptNth(n) => return format!("accept-{n}"),
+            Self::Copy => "copy",
+            Self::Delete => "delete",
+            Self::DeleteMatching => "delete-matching",
+            Self::ToggleMark => "toggle-mark",
+            Self::MarkRange => "mark-range",
+            Self::SaveScript => "save-script",
+            Self::CursorLeft => "cursor-left",
+            Self::CursorRight => "cursor-right",
+            Self::CursorWordLeft => "cursor-word-left",
+            Self::CursorWordRight => "cursor-word-right",
+            Self::CursorStart => "cursor-start",
+            Self::CursorEnd => "cursor-end",
+            Self::DeleteCharBefore => "delete-char-before",
+            Self::DeleteCharAfter => "delete-char-after",
+            Self::DeleteCharOrExit => "delete-char-or-exit",
+            Self::DeleteWordBefore => "delete-word-before",
+            Self::DeleteWordAfter => "delete-word-after",
+            Self::UnixWordRubout => "unix-word-rubout",
+            Self::ClearLine => "clear-line",
+            Self::CycleFilterMode => "cycle-filter-mode",
+            Self::CycleSearchMode => "cycle-search-mode",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::SelectPrevious => "select-previous",
+            Self::SelectNext => "select-next",
+            Self::PageUp => "page-up",
+            Self::PageDown => "page-down",
+            Self::Redraw => "redraw",
+            Self::VimNormal => "vim-normal",
+            Self::VimInsert => "vim-insert",
+            Self::VimInsertAfter => "vim-insert-after",
+            Self::VimInsertStart => "vim-insert-start",
+            Self::VimInsertEnd => "vim-insert-end",
+            Self::VimSearch => "vim-search",
+            Self::Expand => "expand",
+            Self::Collapse => "collapse",
+            Self::ToggleSession => "toggle-session",
+            Self::CopySession => "copy-session",
+        };
+
+        name.to_string()
+    }
+}
+
+impl FromStr for Action {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| eyre!("unknown action {s:?}"))
+    }
+}
+
+/// A key, and whether ctrl or alt are held with it. Shift is part of the character itself, so
+/// `A` rather than `shift-a`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 15] = [
+    ("esc", KeyCode::Esc),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+];
+
+impl KeyChord {
+    fn from_event(event: &KeyEvent) -> Self {
+        Self {
+            code: event.code,
+            ctrl: event.modifiers.contains(KeyModifiers::CONTROL),
+            alt: event.modifiers.contains(KeyModifiers::ALT),
+        }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut chord = Self {
+            code: KeyCode::Null,
+            ctrl: false,
+            alt: false,
+        };
+
+        // A lone "-" is the minus key, so only strip modifiers with something after them
+        let mut key = s;
+        loop {
+            if let Some(rest) = key.strip_prefix("ctrl-").filter(|r| !r.is_empty()) {
+                chord.ctrl = true;
+                key = rest;
+            } else if let Some(rest) = key.strip_prefix("alt-").filter(|r| !r.is_empty()) {
+                chord.alt = true;
+                key = rest;
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = key.chars();
+        chord.code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ if key == "space" => KeyCode::Char(' '),
+            _ => {
+                if let Some((_, code)) = NAMED_KEYS.iter().find(|(name, _)| *name == key) {
+                    *code
+                } else if let Some(n) = key.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    KeyCode::F(n)
+                } else {
+                    bail!(
+                        "{s:?} is not a key. Try something like \"ctrl-r\", \"alt-f\" or \"pageup\""
+                    )
+                }
+            }
+        };
+
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "ctrl-")?;
+        }
+        if self.alt {
+            write!(f, "alt-")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => {
+                let name = NAMED_KEYS
+                    .iter()
+                    .find(|(_, c)| *c == code)
+                    .map_or("?", |(name, _)| name);
+                write!(f, "{name}")
+            }
+        }
+    }
+}
+
+/// The keys of one mode of the interactive search
+#[derive(Clone, Debug, Default)]
+pub struct Keymap {
+    keys: Vec<(Action, Vec<KeyChord>)>,
+    actions: HashMap<KeyChord, Action>,
+}
+
+impl Keymap {
+    fn new(
+        name: &str,
+        defaults: Vec<(Action, Vec<KeyChord>)>,
+        changes: &HashMap<String, Vec<String>>,
+    ) -> Result<Self> {
+        let mut keys = defaults;
+
+        for (action, chords) in changes {
+            let action = action
+                .parse::<Action>()
+                .map_err(|e| eyre!("{e} in [keymap.{name}]"))?;
+            let chords = chords
+                .iter()
+                .map(|c| c.parse())
+                .collect::<Result<Vec<KeyChord>>>()
+                .map_err(|e| eyre!("{e}, in [keymap.{name}]"))?;
+
+            // Replaces the defaults, so an empty list unbinds the action
+            if let Some((_, keys)) = keys.iter_mut().find(|(a, _)| *a == action) {
+                *keys = chords;
+            } else {
+                keys.push((action, chords));
+            }
+        }
+
+        // Same order as the actions are listed in, whatever order the config has them in
+        keys.sort_by_key(|(action, _)| Action::ALL.iter().position(|a| a == action));
+
+        let mut actions = HashMap::new();
+        for (action, chords) in &keys {
+            for chord in chords {
+                if let Some(other) = actions.insert(*chord, *action) {
+                    if other != *action {
+                        bail!(
+                            "{chord} is bound to both {} and {} in [keymap.{name}]. Bind one of them to another key",
+                            other.name(),
+                            action.name()
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(Self { keys, actions })
+    }
+
+    /// The action bound to a key. Alt is ignored if the key with it is not bound, and so is ctrl
+    /// for keys other than characters - ctrl-up is up, but ctrl-x is not x.
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(event);
+        let plain = KeyChord {
+            alt: false,
+            ctrl: chord.ctrl && matches!(chord.code, KeyCode::Char(_)),
+            ..chord
+        };
+
+        self.actions
+            .get(&chord)
+            .or_else(|| self.actions.get(&plain))
+            .copied()
+    }
+
+    /// The first key bound to an action, to show in the help
+    pub fn hint(&self, action: Action) -> Option<KeyChord> {
+        self.keys
+            .iter()
+            .find(|(a, _)| *a == action)
+            .and_then(|(_, chords)| chords.first())
+            .copied()
+    }
+}
+
+/// The keys of every mode
+#[derive(Clone, Debug, Default)]
+pub struct Keymaps {
+    pub emacs: Keymap,
+    pub vim_normal: Keymap,
+    pub vim_insert: Keymap,
+    pub inspector: Keymap,
+    pub directories: Keymap,
+    pub commands: Keymap,
+    pub prefix: Keymap,
+}
+
+struct Defaults(Vec<(Action, Vec<KeyChord>)>);
+
+impl Defaults {
+    fn bind(mut self, action: Action, chords: &[&str]) -> Self {
+        let chords = chords
+            .iter()
+            .map(|c| c.parse().expect("invalid default key"));
+
+        if let Some((_, keys)) = self.0.iter_mut().find(|(a, _)| *a == action) {
+            keys.extend(chords);
+        } else {
+            self.0.push((action, chords.collect()));
+        }
+
+        self
+    }
+
+    fn common(exit: bool) -> Self {
+        Self(Vec::new())
+            .bind(Action::ReturnOriginal, &["ctrl-c", "ctrl-g"])
+            .bind(Action::Exit, if exit { &["esc", "ctrl-["] } else { &[] })
+            .bind(Action::ReturnSelection, &["tab"])
+            .bind(Action::ToggleTab, &["ctrl-o"])
+    }
+
+    fn search(self, settings: &Settings) -> Self {
+        let mut defaults = self
+            .bind(Action::Accept, &["enter", "ctrl-m"])
+            .bind(Action::Copy, &["ctrl-y"])
+            .bind(Action::ToggleMark, &["ctrl-space"])
+            .bind(Action::MarkRange, &["alt-space"])
+            .bind(Action::SaveScript, &["alt-s"]);
+
+        let modifier = if settings.ctrl_n_shortcuts {
+            "ctrl"
+        } else {
+            "alt"
+        };
+        for n in 1..=9 {
+            defaults = defaults.bind(Action::AcceptNth(n), &[&format!("{modifier}-{n}")]);
+        }
+
+        defaults
+            .bind(Action::CursorWordLeft, &["ctrl-left", "alt-b"])
+            .bind(Action::CursorLeft, &["left", "ctrl-b"])
+            .bind(Action::CursorWordRight, &["ctrl-right", "alt-f"])
+            .bind(Action::CursorRight, &["right", "ctrl-f"])
+            .bind(Action::CursorStart, &["home", "ctrl-a"])
+            .bind(Action::CursorEnd, &["end", "ctrl-e"])
+            .bind(Action::DeleteWordBefore, &["ctrl-backspace"])
+            // Depending on the terminal, backspace can arrive as ctrl-h or ctrl-?, and so
+            // can ctrl-backspace. There is no perfect answer, so treat them all as backspace.
+            .bind(Action::DeleteCharBefore, &["backspace", "ctrl-h", "ctrl-?"])
+            .bind(Action::DeleteWordAfter, &["ctrl-delete"])
+            .bind(Action::DeleteCharAfter, &["delete"])
+            .bind(Action::DeleteCharOrExit, &["ctrl-d"])
+            .bind(Action::UnixWordRubout, &["ctrl-w"])
+            .bind(Action::ClearLine, &["ctrl-u"])
+            .bind(Action::CycleFilterMode, &["ctrl-r"])
+            .bind(Action::CycleSearchMode, &["ctrl-s"])
+            .bind(Action::Down, &["down"])
+            .bind(Action::Up, &["up"])
+            .bind(Action::SelectNext, &["ctrl-n", "ctrl-j"])
+            .bind(Action::SelectPrevious, &["ctrl-p", "ctrl-k"])
+            .bind(Action::Redraw, &["ctrl-l"])
+            .bind(Action::PageDown, &["pagedown"])
+            .bind(Action::PageUp, &["pageup"])
+    }
+
+    fn without_alt_chars(mut self) -> Self {
+        for (_, chords) in &mut self.0 {
+            chords.retain(|c| !(c.alt && matches!(c.code, KeyCode::Char(_))));
+        }
+
+        self
+    }
+}
+
+impl Keymaps {
+    pub fn new(settings: &Settings) -> Result<Self> {
+        let changes = &settings.keymap;
+
+        let emacs = Defaults::common(true).search(settings);
+
+        let vim_insert = Defaults::common(false)
+            .search(settings)
+            .bind(Action::VimNormal, &["esc", "ctrl-["]);
+
+        // In normal mode alt with a character does nothing, as the character alone would
+        let vim_normal = Defaults::common(true)
+            .search(settings)
+            .without_alt_chars()
+            .bind(Action::VimSearch, &["?", "/"])
+            .bind(Action::Down, &["j"])
+            .bind(Action::Up, &["k"])
+            .bind(Action::CursorLeft, &["h"])
+            .bind(Action::CursorRight, &["l"])
+            .bind(Action::VimInsertAfter, &["a"])
+            .bind(Action::VimInsertEnd, &["A"])
+            .bind(Action::VimInsert, &["i"])
+            .bind(Action::VimInsertStart, &["I"])
+            .bind(Action::ToggleMark, &["v"])
+            .bind(Action::MarkRange, &["V"])
+            .bind(Action::SaveScript, &["s"]);
+
+        let inspector = Defaults::common(true)
+            .bind(Action::Delete, &["ctrl-d"])
+            .bind(Action::ToggleSession, &["s"])
+            // move through the results, or the session when it is shown
+            .bind(Action::SelectPrevious, &["up", "k"])
+            .bind(Action::SelectNext, &["down", "j"])
+            .bind(Action::PageUp, &["pageup"])
+            .bind(Action::PageDown, &["pagedown"])
+            .bind(Action::Copy, &["ctrl-y"])
+            .bind(Action::CopySession, &["alt-y"]);
+
+        let directories = Defaults::common(true)
+            .bind(Action::Accept, &["enter"])
+            .bind(Action::Up, &["up", "ctrl-p", "k"])
+            .bind(Action::Down, &["down", "ctrl-n", "j"])
+            .bind(Action::PageUp, &["pageup"])
+            .bind(Action::PageDown, &["pagedown"])
+            .bind(Action::Expand, &["right", "l"])
+            .bind(Action::Collapse, &["left", "h"]);
+
+        let commands = Defaults::common(true)
+            .bind(Action::Accept, &["enter"])
+            .bind(Action::Copy, &["ctrl-y"])
+            .bind(Action::Up, &["up", "ctrl-p", "k"])
+            .bind(Action::Down, &["down", "ctrl-n", "j"])
+            .bind(Action::PageUp, &["pageup"])
+            .bind(Action::PageDown, &["pagedown"])
+            .bind(Action::Expand, &["right", "l"])
+            .bind(Action::Collapse, &["left", "h"]);
+
+        let prefix = Defaults(Vec::new())
+            .bind(Action::Delete, &["d"])
+            .bind(Action::DeleteMatching, &["D"])
+            .bind(Action::CursorStart, &["a"]);
+
+        Ok(Self {
+            emacs: Keymap::new("emacs", emacs.0, &changes.emacs)?,
+            vim_normal: Keymap::new("vim_normal", vim_normal.0, &changes.vim_normal)?,
+            vim_insert: Keymap::new("vim_insert", vim_insert.0, &changes.vim_insert)?,
+            inspector: Keymap::new("inspector", inspector.0, &changes.inspector)?,
+            directories: Keymap::new("directories", directories.0, &changes.directories)?,
+            commands: Keymap::new("commands", commands.0, &changes.commands)?,
+            prefix: Keymap::new("prefix", prefix.0, &changes.prefix)?,
+        })
+    }
+
+    pub fn for_mode(&self, mode: KeymapMode) -> &Keymap {
+        match mode {
+            KeymapMode::Emacs | KeymapMode::Auto => &self.emacs,
+            KeymapMode::VimNormal => &self.vim_normal,
+            KeymapMode::VimInsert => &self.vim_insert,
+        }
+    }
+}
+
+/// In the format of the config file, to copy from
+impl fmt::Display for Keymaps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keymaps = [
+            ("emacs", &self.emacs),
+            ("vim_normal", &self.vim_normal),
+            ("vim_insert", &self.vim_insert),
+            ("inspector", &self.inspector),
+            ("directories", &self.directories),
+            ("commands", &self.commands),
+            ("prefix", &self.prefix),
+        ];
+
+        for (i, (name, keymap)) in keymaps.into_iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[keymap.{name}]")?;
+
+            for (action, chords) in keymap.keys.iter().filter(|(_, c)| !c.is_empty()) {
+                let chords = chords
+                    .iter()
+                    .map(|c| format!("{:?}", c.to_string()))
+                    .collect::<Vec<_>>();
+
+                writeln!(f, "{} = [{}]", action.name(), chords.join(", "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use atuin_client::settings::Settings;
+
+    use super::{Action, KeyChord, Keymaps};
+
+    #[test]
+    fn chords_round_trip() {
+        for chord in [
+            "ctrl-r",
+            "alt-f",
+            "ctrl-alt-x",
+            "A",
+            "-",
+            "ctrl--",
+            "space",
+            "f5",
+        ] {
+            let parsed = chord.parse::<KeyChord>().unwrap();
+            assert_eq!(parsed.to_string(), chord);
+        }
+
+        assert!("ctrl-nope".parse::<KeyChord>().is_err());
+        assert!("".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn actions_round_trip() {
+        for action in Action::ALL {
+            assert_eq!(action.name().parse::<Action>().unwrap(), action);
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let keymaps = Keymaps::new(&Settings::utc()).unwrap();
+
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(keymaps.emacs.action(&ctrl_r), Some(Action::CycleFilterMode));
+
+        // ctrl is ignored for keys that aren't characters, but not for characters
+        let ctrl_up = KeyEvent::new(KeyCode::Up, KeyModifiers::CONTROL);
+        assert_eq!(keymaps.emacs.action(&ctrl_up), Some(Action::Up));
+        let ctrl_x = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL);
+        assert_eq!(keymaps.emacs.action(&ctrl_x), None);
+
+        let alt_j = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::ALT);
+        assert_eq!(keymaps.vim_normal.action(&alt_j), Some(Action::Down));
+
+        // alt-s saves a script everywhere else, s does in normal mode
+        let s = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE);
+        assert_eq!(keymaps.vim_normal.action(&s), Some(Action::SaveScript));
+    }
+
+    #[test]
+    fn changes_replace_defaults() {
+        let mut settings = Settings::utc();
+        settings.keymap.emacs = HashMap::from([
+            (String::from("cycle-filter-mode"), vec![String::from("f2")]),
+            (String::from("redraw"), vec![]),
+        ]);
+
+        let keymaps = Keymaps::new(&settings).unwrap();
+
+        let f2 = KeyEvent::new(KeyCode::F(2), KeyModifiers::NONE);
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        let ctrl_l = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+
+        assert_eq!(keymaps.emacs.action(&f2), Some(Action::CycleFilterMode));
+        assert_eq!(keymaps.emacs.action(&ctrl_r), None);
+        assert_eq!(keymaps.emacs.action(&ctrl_l), None);
+
+        // Other modes are untouched
+        assert_eq!(
+            keymaps.vim_insert.action(&ctrl_r),
+            Some(Action::CycleFilterMode)
+        );
+    }
+
+    #[test]
+    fn conflicts_are_errors() {
+        let mut settings = Settings::utc();
+        settings.keymap.emacs = HashMap::from([(
+            String::from("cycle-search-mode"),
+            vec![String::from("ctrl-r")],
+        )]);
+
+        let err = Keymaps::new(&settings).unwrap_err().to_string();
+        assert!(err.contains("cycle-filter-mode"), "{err}");
+        assert!(err.contains("cycle-search-mode"), "{err}");
+
+        settings.keymap.emacs =
+            HashMap::from([(String::from("frobnicate"), vec![String::from("f1")])]);
+        assert!(Keymaps::new(&settings).is_err());
+    }
+}
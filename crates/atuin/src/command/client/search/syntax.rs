@@ -0,0 +1,424 @@
+use std::{iter::Peekable, str::CharIndices};
+
+use atuin_common::{shell::Shell, utils::Escapable as _};
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+
+use super::super::theme::{Meaning, Theme};
+
+/// What a part of a command is, to color it by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Command,
+    Argument,
+    Flag,
+    String,
+    Variable,
+    Operator,
+    Comment,
+    Space,
+}
+
+impl Kind {
+    pub fn meaning(self) -> Meaning {
+        match self {
+            Self::Command => Meaning::Important,
+            Self::Flag => Meaning::Guidance,
+            Self::String => Meaning::AlertInfo,
+            Self::Variable => Meaning::AlertWarn,
+            Self::Operator => Meaning::Annotation,
+            Self::Comment => Meaning::Muted,
+            Self::Argument | Self::Space => Meaning::Base,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: Kind,
+    pub text: &'a str,
+}
+
+/// The little of each shell's syntax we need to tell the parts of a command apart
+struct Syntax {
+    /// Escapes the next character, outside of single quotes
+    escape: Option<char>,
+
+    /// Words after which comes another command, rather than arguments
+    keywords: &'static [&'static str],
+}
+
+const POSIX_KEYWORDS: &[&str] = &[
+    "!", "builtin", "command", "do", "doas", "elif", "else", "exec", "if", "nohup", "sudo", "then",
+    "time", "until", "while",
+];
+
+const FISH_KEYWORDS: &[&str] = &[
+    "and", "begin", "builtin", "command", "doas", "else", "exec", "if", "not", "or", "sudo",
+    "time", "while",
+];
+
+impl Syntax {
+    fn of(shell: Shell) -> Self {
+        match shell {
+            Shell::Fish => Self {
+                escape: Some('\\'),
+                keywords: FISH_KEYWORDS,
+            },
+            Shell::Powershell => Self {
+                escape: Some('`'),
+                keywords: &["if", "while", "foreach"],
+            },
+            Shell::Nu => Self {
+                escape: None,
+                keywords: &["if", "else", "do"],
+            },
+            Shell::Sh | Shell::Bash | Shell::Zsh | Shell::Xonsh | Shell::Unknown => Self {
+                escape: Some('\\'),
+                keywords: POSIX_KEYWORDS,
+            },
+        }
+    }
+}
+
+fn is_operator(c: char) -> bool {
+    matches!(c, '|' | '&' | ';' | '<' | '>' | '(' | ')')
+}
+
+/// `NAME=value`, which sets a variable for the command that follows
+fn is_assignment(word: &str) -> bool {
+    let Some((name, _)) = word.split_once('=') else {
+        return false;
+    };
+
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+struct Tokenizer<'a> {
+    command: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    syntax: Syntax,
+    tokens: Vec<Token<'a>>,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn end(&mut self) -> usize {
+        self.chars.peek().map_or(self.command.len(), |&(i, _)| i)
+    }
+
+    fn push(&mut self, kind: Kind, start: usize) {
+        let end = self.end();
+        if end > start {
+            self.tokens.push(Token {
+                kind,
+                text: &self.command[start..end],
+            });
+        }
+    }
+
+    fn skip_while(&mut self, f: impl Fn(char) -> bool) {
+        while self.chars.next_if(|&(_, c)| f(c)).is_some() {}
+    }
+
+    /// A quoted string, up to and including the closing quote
+    fn string(&mut self, quote: char) {
+        self.chars.next();
+
+        while let Some((_, c)) = self.chars.next() {
+            if c == quote {
+                break;
+            }
+            if Some(c) == self.syntax.escape && quote == '"' {
+                self.chars.next();
+            }
+        }
+    }
+
+    /// `$name`, `${name}` or `$(command)`
+    fn variable(&mut self) {
+        self.chars.next();
+
+        let close = match self.chars.peek() {
+            Some((_, '{')) => '}',
+            Some((_, '(')) => ')',
+            Some((_, c)) if c.is_ascii_alphanumeric() || *c == '_' => {
+                self.skip_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                return;
+            }
+            Some((_, '?' | '!' | '#' | '@' | '*' | '$' | '-')) => {
+                self.chars.next();
+                return;
+            }
+            _ => return,
+        };
+
+        let open = if close == '}' { '{' } else { '(' };
+        let mut depth = 0;
+        for (_, c) in self.chars.by_ref() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// A word, which may be made of several parts, as in `--name="$USER"`. Returns the kind of
+    /// its unquoted parts.
+    fn word(&mut self, start: usize, command_next: bool) -> Kind {
+        let rest = &self.command[start..];
+        let word_end = rest
+            .find(|c: char| c.is_whitespace() || is_operator(c))
+            .unwrap_or(rest.len());
+
+        let kind = if command_next && is_assignment(&rest[..word_end]) {
+            Kind::Variable
+        } else if command_next {
+            Kind::Command
+        } else if rest.starts_with('-') {
+            Kind::Flag
+        } else {
+            Kind::Argument
+        };
+
+        let mut part = start;
+        while let Some(&(i, c)) = self.chars.peek() {
+            if c.is_whitespace() || is_operator(c) {
+                break;
+            }
+
+            match c {
+                '\'' | '"' => {
+                    self.push(kind, part);
+                    self.string(c);
+                    self.push(Kind::String, i);
+                    part = self.end();
+                }
+                '$' => {
+                    self.push(kind, part);
+                    self.variable();
+                    self.push(Kind::Variable, i);
+                    part = self.end();
+                }
+                c if Some(c) == self.syntax.escape => {
+                    self.chars.next();
+                    self.chars.next();
+                }
+                _ => {
+                    self.chars.next();
+                }
+            }
+        }
+        self.push(kind, part);
+
+        kind
+    }
+
+    fn run(mut self) -> Vec<Token<'a>> {
+        let mut command_next = true;
+
+        while let Some(&(start, c)) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.skip_while(char::is_whitespace);
+                self.push(Kind::Space, start);
+
+                if self.command[start..self.end()].contains('\n') {
+                    command_next = true;
+                }
+            } else if c == '#' {
+                self.skip_while(|c| c != '\n');
+                self.push(Kind::Comment, start);
+            } else if is_operator(c) {
+                self.skip_while(is_operator);
+                self.push(Kind::Operator, start);
+
+                // after a redirect comes a file, after anything else another command
+                let operator = &self.command[start..self.end()];
+                command_next = !operator.contains(['<', '>']);
+            } else {
+                let kind = self.word(start, command_next);
+                let word = &self.command[start..self.end()];
+
+                command_next = match kind {
+                    Kind::Variable => true,
+                    Kind::Command => self.syntax.keywords.contains(&word),
+                    _ => false,
+                };
+            }
+        }
+
+        self.tokens
+    }
+}
+
+/// Split a command into its parts, as the shell would. Joined back together, the tokens are the
+/// command as it was.
+pub fn tokenize(command: &str, shell: Shell) -> Vec<Token<'_>> {
+    Tokenizer {
+        command,
+        chars: command.char_indices().peekable(),
+        syntax: Syntax::of(shell),
+        tokens: Vec::new(),
+    }
+    .run()
+}
+
+/// The kind of each character of a command, in order
+pub fn char_kinds(command: &str, shell: Shell) -> Vec<Kind> {
+    tokenize(command, shell)
+        .into_iter()
+        .flat_map(|token| token.text.chars().map(move |_| token.kind))
+        .collect()
+}
+
+/// The programs a command runs, in order
+pub fn programs(command: &str, shell: Shell) -> Vec<&str> {
+    let mut programs = Vec::new();
+
+    for token in tokenize(command, shell) {
+        if token.kind == Kind::Command && !programs.contains(&token.text) {
+            programs.push(token.text);
+        }
+    }
+
+    programs
+}
+
+/// A command wrapped to a width, and colored by its parts if we know the shell
+pub fn lines(
+    command: &str,
+    shell: Option<Shell>,
+    width: usize,
+    theme: &Theme,
+) -> Vec<Line<'static>> {
+    let width = width.max(1);
+    let tokens = shell.map_or_else(
+        || {
+            vec![Token {
+                kind: Kind::Argument,
+                text: command,
+            }]
+        },
+        |shell| tokenize(command, shell),
+    );
+
+    let mut lines = vec![Line::default()];
+    let mut used = 0;
+
+    for token in tokens {
+        let style: Style = theme.as_style(token.kind.meaning()).into();
+
+        for (i, mut piece) in token.text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Line::default());
+                used = 0;
+            }
+
+            while !piece.is_empty() {
+                if used == width {
+                    lines.push(Line::default());
+                    used = 0;
+                }
+
+                let split = piece
+                    .char_indices()
+                    .nth(width - used)
+                    .map_or(piece.len(), |(i, _)| i);
+                let (head, tail) = piece.split_at(split);
+
+                used += head.chars().count();
+                if let Some(line) = lines.last_mut() {
+                    line.push_span(Span::styled(head.escape_control().to_string(), style));
+                }
+                piece = tail;
+            }
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_common::shell::Shell;
+
+    use super::{Kind, programs, tokenize};
+
+    fn kinds(command: &str, shell: Shell) -> Vec<(Kind, &str)> {
+        tokenize(command, shell)
+            .into_iter()
+            .filter(|t| t.kind != Kind::Space)
+            .map(|t| (t.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn tokens_join_back_into_the_command() {
+        for command in [
+            "ls -la",
+            "FOO=bar cargo test -- --nocapture 2>&1 | tee out.log",
+            r#"echo "it's $HOME" 'and \' this' \"quoted"#,
+            "for f in *.rs; do\n  rustfmt $f # tidy\ndone",
+            "echo ${unterminated",
+            "echo 'unterminated",
+            "é ü → $(date +%s)",
+        ] {
+            let joined: String = tokenize(command, Shell::Bash)
+                .into_iter()
+                .map(|t| t.text)
+                .collect();
+            assert_eq!(joined, command);
+        }
+    }
+
+    #[test]
+    fn tells_the_parts_apart() {
+        assert_eq!(
+            kinds(
+                r#"FOO=bar sudo git commit -m "fix $THING" > /dev/null && echo done # ok"#,
+                Shell::Zsh
+            ),
+            [
+                (Kind::Variable, "FOO=bar"),
+                (Kind::Command, "sudo"),
+                (Kind::Command, "git"),
+                (Kind::Argument, "commit"),
+                (Kind::Flag, "-m"),
+                (Kind::String, r#""fix $THING""#),
+                (Kind::Operator, ">"),
+                (Kind::Argument, "/dev/null"),
+                (Kind::Operator, "&&"),
+                (Kind::Command, "echo"),
+                (Kind::Argument, "done"),
+                (Kind::Comment, "# ok"),
+            ]
+        );
+
+        assert_eq!(
+            kinds("ls --path=$(pwd)/x", Shell::Bash)[1..],
+            [
+                (Kind::Flag, "--path="),
+                (Kind::Variable, "$(pwd)"),
+                (Kind::Flag, "/x")
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_the_shell() {
+        assert_eq!(
+            programs("make; and make install; or echo failed", Shell::Fish),
+            ["make", "and", "or", "echo"]
+        );
+        assert_eq!(
+            programs("make; and make install", Shell::Bash),
+            ["make", "and"]
+        );
+    }
+}
@@ -15,6 +15,9 @@ mod push;
 #[cfg(feature = "sync")]
 mod pull;
 
+#[cfg(feature = "sync")]
+mod repair;
+
 mod purge;
 mod rebuild;
 mod rekey;
@@ -45,6 +48,11 @@ pub enum Cmd {
     /// Pull records from the remote sync server (one way sync)
     #[cfg(feature = "sync")]
     Pull(pull::Pull),
+
+    /// Fix a local store that has diverged from the remote, eg because this machine was cloned
+    /// from another. Moves the local records onto a new host ID and syncs again.
+    #[cfg(feature = "sync")]
+    Repair(repair::Repair),
 }
 
 impl Cmd {
@@ -66,6 +74,9 @@ impl Cmd {
 
             #[cfg(feature = "sync")]
             Self::Pull(pull) => pull.run(settings, store, database).await,
+
+            #[cfg(feature = "sync")]
+            Self::Repair(repair) => repair.run(settings, store, database).await,
         }
     }
 
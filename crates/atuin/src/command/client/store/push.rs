@@ -4,9 +4,8 @@ use eyre::Result;
 use uuid::Uuid;
 
 use atuin_client::{
-    api_client::Client,
     record::sync::Operation,
-    record::{sqlite_store::SqliteStore, sync},
+    record::{sqlite_store::SqliteStore, sync, transport},
     settings::Settings,
 };
 
@@ -35,16 +34,14 @@ impl Push {
             println!("Forcing remote store overwrite!");
             println!("Clearing remote store");
 
-            let client = Client::new(
-                &settings.sync_address,
-                settings.session_token()?.as_str(),
-                settings.network_connect_timeout,
+            let transport = transport::from_settings_with_timeout(
+                settings,
                 settings.network_timeout * 10, // we may be deleting a lot of data... so up the
                                                // timeout
             )
             .expect("failed to create client");
 
-            client.delete_store().await?;
+            transport.delete_store().await?;
         }
 
         // We can actually just use the existing diff/etc to push
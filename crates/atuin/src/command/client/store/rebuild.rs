@@ -1,11 +1,17 @@
-use atuin_dotfiles::store::{AliasStore, var::VarStore};
-use atuin_scripts::store::ScriptStore;
+use atuin_dotfiles::store::{
+    AliasStore, CONFIG_SHELL_ALIAS_TAG,
+    var::{DOTFILES_VAR_TAG, VarStore},
+};
+use atuin_scripts::store::{ScriptStore, script::SCRIPT_TAG};
 use clap::Args;
 use eyre::{Result, bail};
 
 use atuin_client::{
-    database::Database, encryption, history::store::HistoryStore,
-    record::sqlite_store::SqliteStore, settings::Settings,
+    database::Database,
+    encryption::{self, Keys},
+    history::{HISTORY_TAG, store::HistoryStore},
+    record::sqlite_store::SqliteStore,
+    settings::Settings,
 };
 
 #[derive(Args, Debug)]
@@ -50,7 +56,7 @@ impl Rebuild {
         store: SqliteStore,
         database: &dyn Database,
     ) -> Result<()> {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)?.into();
+        let encryption_key: [u8; 32] = encryption::load_tag_key(settings, HISTORY_TAG)?.into();
 
         let host_id = Settings::host_id().expect("failed to get host_id");
         let history_store = HistoryStore::new(store, host_id, encryption_key);
@@ -61,12 +67,13 @@ impl Rebuild {
     }
 
     async fn rebuild_dotfiles(&self, settings: &Settings, store: SqliteStore) -> Result<()> {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)?.into();
+        let keys = Keys::load(settings)?;
 
         let host_id = Settings::host_id().expect("failed to get host_id");
 
-        let alias_store = AliasStore::new(store.clone(), host_id, encryption_key);
-        let var_store = VarStore::new(store.clone(), host_id, encryption_key);
+        let alias_store =
+            AliasStore::new(store.clone(), host_id, *keys.get(CONFIG_SHELL_ALIAS_TAG));
+        let var_store = VarStore::new(store.clone(), host_id, *keys.get(DOTFILES_VAR_TAG));
 
         alias_store.build().await?;
         var_store.build().await?;
@@ -75,7 +82,7 @@ impl Rebuild {
     }
 
     async fn rebuild_scripts(&self, settings: &Settings, store: SqliteStore) -> Result<()> {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)?.into();
+        let encryption_key: [u8; 32] = encryption::load_tag_key(settings, SCRIPT_TAG)?.into();
         let host_id = Settings::host_id().expect("failed to get host_id");
         let script_store = ScriptStore::new(store, host_id, encryption_key);
         let database =
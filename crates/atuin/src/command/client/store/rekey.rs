@@ -1,64 +1,66 @@
 use clap::Args;
-use eyre::{Result, bail};
+use eyre::Result;
 use tokio::{fs::File, io::AsyncWriteExt};
 
 use atuin_client::{
-    encryption::{Key, decode_key, encode_key, generate_encoded_key, load_key},
+    encryption::{Keys, decode_key, generate_encoded_key, has_tag_key, save_tag_key},
     record::sqlite_store::SqliteStore,
     record::store::Store,
     settings::Settings,
 };
 
+use crate::command::client::account::login::normalize_key;
+
 #[derive(Args, Debug)]
 pub struct Rekey {
     /// The new key to use for encryption. Omit for a randomly-generated key
     key: Option<String>,
+
+    /// Only change the key for records with this tag, giving the tag a key of its own if it
+    /// doesn't have one yet. Share it with `atuin key --tag`.
+    #[arg(long)]
+    tag: Option<String>,
 }
 
 impl Rekey {
     pub async fn run(&self, settings: &Settings, store: SqliteStore) -> Result<()> {
+        // Check the tag before we rewrite anything
+        if let Some(tag) = &self.tag {
+            if has_tag_key(settings, tag)? {
+                println!("Changing the key for {tag} records");
+            } else {
+                println!("Giving {tag} records a key of their own");
+            }
+        }
+
         let key = if let Some(key) = self.key.clone() {
             println!("Re-encrypting store with specified key");
 
-            match bip39::Mnemonic::from_phrase(&key, bip39::Language::English) {
-                Ok(mnemonic) => encode_key(Key::from_slice(mnemonic.entropy()))?,
-                Err(err) => {
-                    match err.downcast_ref::<bip39::ErrorKind>() {
-                        Some(err) => {
-                            match err {
-                                // assume they copied in the base64 key
-                                bip39::ErrorKind::InvalidWord => key,
-                                bip39::ErrorKind::InvalidChecksum => {
-                                    bail!("key mnemonic was not valid")
-                                }
-                                bip39::ErrorKind::InvalidKeysize(_)
-                                | bip39::ErrorKind::InvalidWordLength(_)
-                                | bip39::ErrorKind::InvalidEntropyLength(_, _) => {
-                                    bail!("key was not the correct length")
-                                }
-                            }
-                        }
-                        _ => {
-                            // unknown error. assume they copied the base64 key
-                            key
-                        }
-                    }
-                }
-            }
+            normalize_key(key)?
         } else {
             println!("Re-encrypting store with freshly-generated key");
             let (_, encoded) = generate_encoded_key()?;
             encoded
         };
 
-        let current_key: [u8; 32] = load_key(settings)?.into();
+        let keys = Keys::load(settings)?;
         let new_key: [u8; 32] = decode_key(key.clone())?.into();
 
-        store.re_encrypt(&current_key, &new_key).await?;
+        let new_keys = self.tag.as_ref().map_or_else(
+            || keys.clone().with_main(new_key),
+            |tag| keys.clone().with_tag(tag, new_key),
+        );
+
+        store.re_encrypt(&keys, &new_keys).await?;
 
         println!("Store rewritten. Saving new key");
-        let mut file = File::create(settings.key_path.clone()).await?;
-        file.write_all(key.as_bytes()).await?;
+
+        if let Some(tag) = &self.tag {
+            save_tag_key(settings, tag, &key)?;
+        } else {
+            let mut file = File::create(settings.key_path.clone()).await?;
+            file.write_all(key.as_bytes()).await?;
+        }
 
         Ok(())
     }
@@ -0,0 +1,138 @@
+use clap::Args;
+use eyre::Result;
+
+use atuin_client::{
+    database::Database,
+    encryption::Keys,
+    record::{
+        encryption::PASETO_V4,
+        sqlite_store::SqliteStore,
+        store::Store,
+        sync::{self, Divergence},
+        transport,
+    },
+    settings::Settings,
+};
+use atuin_common::{
+    record::{Host, HostId, Record, RecordId},
+    utils::uuid_v7,
+};
+
+#[derive(Args, Debug)]
+pub struct Repair {}
+
+impl Repair {
+    pub async fn run(
+        &self,
+        settings: &Settings,
+        store: SqliteStore,
+        db: &dyn Database,
+    ) -> Result<()> {
+        let transport = transport::from_settings(settings)?;
+
+        let local = store.status().await?;
+        let remote = transport.record_status().await?;
+
+        let divergences =
+            sync::find_divergence(&store, transport.as_ref(), &local, &remote, None).await?;
+
+        if divergences.is_empty() {
+            println!("The local store agrees with the remote, nothing to repair");
+            return Ok(());
+        }
+
+        let keys = Keys::load(settings)?;
+        let host_id = Settings::host_id().expect("failed to get host_id");
+
+        // Another machine is writing records as this host. It doesn't matter which of us was
+        // first - we move our side of the story onto a new host, and leave the old one to them.
+        let new_host = divergences
+            .iter()
+            .any(|d| d.host == host_id)
+            .then(|| HostId(uuid_v7()));
+
+        for divergence in divergences {
+            println!("Diverged: {divergence}");
+
+            match new_host {
+                Some(new_host) if divergence.host == host_id => {
+                    let forked =
+                        fork(&store, keys.get(&divergence.tag), &divergence, new_host).await?;
+
+                    println!(
+                        "\tmoved {forked} records to new host {}",
+                        new_host.0.as_simple()
+                    );
+                }
+
+                // We have a bad copy of another host's records. Drop it, and download them again.
+                _ => {
+                    let deleted = store
+                        .truncate(divergence.host, &divergence.tag, divergence.idx)
+                        .await?;
+
+                    println!("\tdiscarded {deleted} local records, they will be downloaded again");
+                }
+            }
+        }
+
+        if let Some(new_host) = new_host {
+            Settings::set_host_id(new_host)?;
+
+            println!("This machine now has host ID {}", new_host.0.as_simple());
+            println!("If the daemon is running, restart it to pick up the change");
+        }
+
+        println!("Syncing repaired store");
+
+        let (uploaded, downloaded) = sync::sync(settings, &store).await?;
+
+        crate::sync::build(settings, &store, db, Some(&downloaded)).await?;
+
+        println!("{uploaded}/{} up/down to record store", downloaded.len());
+
+        Ok(())
+    }
+}
+
+/// Move the local records from the point of divergence onto a new host, as a fresh chain
+async fn fork(
+    store: &SqliteStore,
+    key: &[u8; 32],
+    divergence: &Divergence,
+    new_host: HostId,
+) -> Result<usize> {
+    let records = store
+        .next(
+            divergence.host,
+            &divergence.tag,
+            divergence.idx,
+            i64::MAX as u64,
+        )
+        .await?;
+
+    // The host and idx are part of the additional data, so every record must be re-encrypted.
+    // New IDs too, so we never clash with records the other machine may have uploaded.
+    let forked = records
+        .into_iter()
+        .enumerate()
+        .map(|(idx, record)| {
+            let record = record.decrypt::<PASETO_V4>(key)?;
+
+            Ok(Record {
+                id: RecordId(uuid_v7()),
+                idx: idx as u64,
+                host: Host::new(new_host),
+                ..record
+            }
+            .encrypt::<PASETO_V4>(key))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // in one go, so a crash can't leave the records on neither host, or on both
+    store
+        .replace_tail(divergence.host, &divergence.tag, divergence.idx, &forked)
+        .await?;
+
+    Ok(forked.len())
+}
@@ -2,7 +2,7 @@ use clap::Args;
 use eyre::Result;
 
 use atuin_client::{
-    encryption::load_key,
+    encryption::Keys,
     record::{sqlite_store::SqliteStore, store::Store},
     settings::Settings,
 };
@@ -12,11 +12,11 @@ pub struct Verify {}
 
 impl Verify {
     pub async fn run(&self, settings: &Settings, store: SqliteStore) -> Result<()> {
-        println!("Verifying local store can be decrypted with the current key");
+        println!("Verifying local store can be decrypted with the current keys");
 
-        let key = load_key(settings)?;
+        let keys = Keys::load(settings)?;
 
-        match store.verify(&key.into()).await {
+        match store.verify(&keys).await {
             Ok(()) => println!("Local store encryption verified OK"),
             Err(e) => println!("Failed to verify local store encryption: {e:?}"),
         }
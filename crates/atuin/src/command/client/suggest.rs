@@ -0,0 +1,264 @@
+use std::{
+    io::{IsTerminal as _, stdout},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use eyre::Result;
+use time::OffsetDateTime;
+
+use atuin_client::{
+    database::{Context, Database, OptFilters, Sqlite, current_context},
+    settings::{FilterMode, SearchMode, Settings},
+};
+use atuin_common::utils::Escapable as _;
+use atuin_history::{
+    frecency::{self, Scorer},
+    predict::{self, Prediction},
+};
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Suggest what usually follows this command, rather than the last one run in this session
+    #[arg(long, conflicts_with = "prefix")]
+    after: Option<String>,
+
+    /// Complete this partly typed command, as the shell shows while typing. It may start with a
+    /// hyphen, so pass it as `--prefix=<PREFIX>`
+    #[arg(long, allow_hyphen_values = true)]
+    prefix: Option<String>,
+
+    /// How many suggestions to print, most likely first
+    #[arg(long, short, default_value = "1")]
+    limit: usize,
+}
+
+impl Cmd {
+    pub async fn run(&self, settings: &Settings) -> Result<()> {
+        // Suggestions are asked for as the user types, so open only the history database
+        let db_path = PathBuf::from(settings.db_path.as_str());
+        let db = Sqlite::new(db_path, settings.local_timeout).await?;
+
+        let context = current_context();
+        let suggestions = match self.prefix.as_deref() {
+            Some(prefix) => complete(&db, settings, &context, prefix, self.limit).await?,
+            None => predict(&db, &context, self.after.as_deref())
+                .await?
+                .into_iter()
+                .map(|p| p.command)
+                .collect(),
+        };
+
+        if suggestions.is_empty() {
+            std::process::exit(1)
+        }
+
+        for suggestion in suggestions.iter().take(self.limit) {
+            if stdout().is_terminal() {
+                println!("{}", suggestion.escape_control());
+            } else {
+                println!("{suggestion}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// How many commands starting with the prefix to rank. The most recent few are plenty, and
+/// keep this quick enough to run on every key press.
+const CANDIDATES: i64 = 20;
+
+/// Commands that complete what has been typed so far, best first, by how the recent commands that
+/// start the same way rank. This runs on every key press, so it leaves out what usually comes next
+/// in this session: working that out looks through every session the last command was run in,
+/// which takes far too long on a big history.
+pub async fn complete(
+    db: &dyn Database,
+    settings: &Settings,
+    context: &Context,
+    prefix: &str,
+    limit: usize,
+) -> Result<Vec<String>> {
+    let completes = |command: &str| {
+        command.len() > prefix.len() && command.starts_with(prefix) && !command.contains('\n')
+    };
+
+    if prefix.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // the prefix is matched with LIKE, which doesn't care for case and takes `_` and `%` as
+    // wildcards, so check again what comes back
+    let candidates: Vec<_> = db
+        .search(
+            SearchMode::Prefix,
+            FilterMode::Global,
+            context,
+            prefix,
+            // grouping by command would go through every match, newest first can stop early
+            OptFilters {
+                limit: Some(CANDIDATES),
+                include_duplicates: true,
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_iter()
+        .filter(|h| completes(&h.command))
+        .collect();
+
+    let commands: Vec<String> = candidates.iter().map(|h| h.command.clone()).collect();
+    let usage = db.usage(&commands, context).await?;
+    let scorer = Scorer::new(
+        &settings.frecency,
+        settings.frecency.half_life()?,
+        &usage,
+        OffsetDateTime::now_utc(),
+    );
+
+    let mut suggestions: Vec<String> = Vec::with_capacity(limit);
+    for (_, h) in frecency::rank(prefix, candidates, &scorer) {
+        if suggestions.len() >= limit {
+            break;
+        }
+        if !suggestions.contains(&h.command) {
+            suggestions.push(h.command);
+        }
+    }
+
+    Ok(suggestions)
+}
+
+/// What usually comes after a command in a session, most likely first. Without a command, this
+/// is what comes after the last one run in this session.
+pub async fn predict(
+    db: &dyn Database,
+    context: &Context,
+    after: Option<&str>,
+) -> Result<Vec<Prediction>> {
+    let after = match after {
+        Some(after) => after.to_string(),
+        None => match last_command(db, context).await? {
+            Some(last) => last,
+            None => return Ok(Vec::new()),
+        },
+    };
+
+    let following = db.following(&after, context).await?;
+    Ok(predict::predict(following))
+}
+
+/// The last command to finish in this session - not one that is still running, like whatever
+/// asked for a suggestion
+async fn last_command(db: &dyn Database, context: &Context) -> Result<Option<String>> {
+    let recent = db
+        .list(&[FilterMode::Session], context, Some(10), false, false)
+        .await?;
+
+    Ok(recent
+        .into_iter()
+        .find(|h| h.duration != -1)
+        .map(|h| h.command))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use atuin_client::{
+        database::{Context, Database, Sqlite},
+        history::History,
+        settings::Settings,
+    };
+    use time::OffsetDateTime;
+
+    use super::complete;
+
+    fn context() -> Context {
+        Context {
+            hostname: "booop".to_string(),
+            session: "beep boop".to_string(),
+            cwd: "/home/ellie".to_string(),
+            host_id: "test-host".to_string(),
+            git_root: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn complete_exact_prefix() {
+        let context = context();
+        let settings = Settings::utc();
+        let db = Sqlite::new("sqlite::memory:", settings.local_timeout)
+            .await
+            .unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        for command in [
+            "git commit -m 'fix'",
+            "GIT COMMIT --amend",
+            "git_commit.sh",
+            "git%commit",
+            "git commit -m 'one\ntwo'",
+            "git com",
+        ] {
+            let h: History = History::capture()
+                .timestamp(now)
+                .command(command)
+                .cwd("/home/ellie")
+                .build()
+                .into();
+            db.save(&h).await.unwrap();
+        }
+
+        // LIKE would take these as wildcards, and match any case
+        for (prefix, expected) in [
+            ("git com", vec!["git commit -m 'fix'"]),
+            ("git_com", vec!["git_commit.sh"]),
+            ("git%", vec!["git%commit"]),
+            ("GIT", vec!["GIT COMMIT --amend"]),
+            ("", vec![]),
+        ] {
+            let suggestions = complete(&db, &settings, &context, prefix, 10)
+                .await
+                .unwrap();
+            assert_eq!(suggestions, expected, "completing {prefix:?}");
+        }
+    }
+
+    // This runs on every key press, so it has to stay quick however much history there is
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "slow, run with --ignored to check completion stays quick on a big history"]
+    async fn complete_large_history() {
+        let context = context();
+        let settings = Settings::utc();
+        let db = Sqlite::new("sqlite::memory:", settings.local_timeout)
+            .await
+            .unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        let history: Vec<History> = (0..200_000)
+            .map(|i| {
+                History::capture()
+                    .timestamp(now - time::Duration::seconds(i))
+                    .command(format!("git commit -m 'change {}'", i % 5000))
+                    .cwd("/home/ellie")
+                    .build()
+                    .into()
+            })
+            .collect();
+        db.save_bulk(&history).await.unwrap();
+
+        for (prefix, expected) in [("git com", 1), ("nothing starts like this", 0)] {
+            let start = Instant::now();
+            let suggestions = complete(&db, &settings, &context, prefix, 1).await.unwrap();
+            let duration = start.elapsed();
+
+            assert_eq!(suggestions.len(), expected);
+            assert!(
+                duration < Duration::from_millis(500),
+                "completing {prefix:?} took {duration:?}"
+            );
+        }
+    }
+}
@@ -1,17 +1,28 @@
+use std::io::{self, IsTerminal};
+
 use clap::Subcommand;
-use eyre::{Result, WrapErr};
+use eyre::{Result, WrapErr, bail};
+use fs_err as fs;
+use rpassword::prompt_password;
 
 use atuin_client::{
     database::Database,
-    encryption,
-    history::store::HistoryStore,
-    record::{sqlite_store::SqliteStore, store::Store, sync},
+    encryption::{self, Keys, decode_key},
+    history::{HISTORY_TAG, store::HistoryStore},
+    record::{
+        sqlite_store::SqliteStore,
+        store::Store,
+        sync::{self, SyncError, check_key},
+        transport,
+    },
     settings::Settings,
 };
+use atuin_common::record::RecordId;
 
+mod key;
 mod status;
 
-use crate::command::client::account;
+use crate::command::client::account::{self, login::normalize_key};
 
 #[derive(Subcommand, Debug)]
 #[command(infer_subcommands = true)]
@@ -32,12 +43,8 @@ pub enum Cmd {
     /// Register with the configured server
     Register(account::register::Cmd),
 
-    /// Print the encryption key for transfer to another machine
-    Key {
-        /// Switch to base64 output of the key
-        #[arg(long)]
-        base64: bool,
-    },
+    /// Print the encryption key for transfer to another machine, or check a backup of it
+    Key(key::Cmd),
 
     /// Display the sync status
     Status,
@@ -56,20 +63,7 @@ impl Cmd {
             Self::Logout => account::logout::run(&settings),
             Self::Register(r) => r.run(&settings).await,
             Self::Status => status::run(&settings, db).await,
-            Self::Key { base64 } => {
-                use atuin_client::encryption::{encode_key, load_key};
-                let key = load_key(&settings).wrap_err("could not load encryption key")?;
-
-                if base64 {
-                    let encode = encode_key(&key).wrap_err("could not encode encryption key")?;
-                    println!("{encode}");
-                } else {
-                    let mnemonic = bip39::Mnemonic::from_entropy(&key, bip39::Language::English)
-                        .map_err(|_| eyre::eyre!("invalid key"))?;
-                    println!("{mnemonic}");
-                }
-                Ok(())
-            }
+            Self::Key(key) => key.run(&settings),
         }
     }
 }
@@ -81,14 +75,7 @@ async fn run(
     store: SqliteStore,
 ) -> Result<()> {
     if settings.sync.records {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
-            .context("could not load encryption key")?
-            .into();
-
-        let host_id = Settings::host_id().expect("failed to get host_id");
-        let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
-
-        let (uploaded, downloaded) = sync::sync(settings, &store).await?;
+        let (uploaded, downloaded) = sync_records(settings, &store).await?;
 
         crate::sync::build(settings, &store, db, Some(&downloaded)).await?;
 
@@ -104,6 +91,14 @@ async fn run(
             );
             println!("Running automatic history store init...");
 
+            // Loaded after syncing, as the key may have just been recovered
+            let encryption_key: [u8; 32] = encryption::load_tag_key(settings, HISTORY_TAG)
+                .context("could not load encryption key")?
+                .into();
+
+            let host_id = Settings::host_id().expect("failed to get host_id");
+            let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
+
             // Internally we use the global filter mode, so this context is ignored.
             // don't recurse or loop here.
             history_store.init_store(db).await?;
@@ -129,3 +124,61 @@ async fn run(
 
     Ok(())
 }
+
+async fn sync_records(settings: &Settings, store: &SqliteStore) -> Result<(i64, Vec<RecordId>)> {
+    match sync::sync(settings, store).await {
+        Err(SyncError::WrongKey) => {
+            recover_key(settings, store).await?;
+
+            println!("Syncing with the recovered key");
+            Ok(sync::sync(settings, store).await?)
+        }
+        res => Ok(res?),
+    }
+}
+
+/// Walk the user through replacing the key on this machine with the one their other machines use
+async fn recover_key(settings: &Settings, store: &SqliteStore) -> Result<()> {
+    println!("This machine's encryption key can't read anything your other machines have synced.");
+    println!("Usually this means it was logged in with a new key, rather than the one they use.");
+    println!("Nothing has been uploaded or downloaded.\n");
+
+    if !io::stdin().is_terminal() {
+        bail!(
+            "run `atuin key` on one of your other machines, then `atuin logout` and `atuin login --key <key>` here"
+        );
+    }
+
+    println!("Run `atuin key` on one of your other machines, and enter what it prints.");
+    println!("History recorded here will be re-encrypted to match. Leave it blank to give up.\n");
+
+    let key = normalize_key(prompt_password("Encryption key: ")?.trim().to_string())?;
+
+    if key.is_empty() {
+        bail!("not syncing, this machine's key does not match your other machines");
+    }
+
+    let new_key: [u8; 32] = decode_key(key.clone())
+        .wrap_err("that is neither a key mnemonic, nor base64")?
+        .into();
+
+    let keys = Keys::load(settings)?;
+    let new_keys = keys.clone().with_main(new_key);
+
+    let transport = transport::from_settings(settings)?;
+    let host_id = Settings::host_id().expect("failed to get host_id");
+
+    match check_key(store, transport.as_ref(), &new_keys, host_id).await {
+        Err(SyncError::WrongKey) => {
+            bail!("that key can't read your other machines' records either, nothing was changed")
+        }
+        res => res?,
+    }
+
+    println!("\nRe-encrypting local store with the recovered key");
+    store.re_encrypt(&keys, &new_keys).await?;
+
+    fs::write(&settings.key_path, key)?;
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use clap::{Args, Subcommand};
+use eyre::{Result, WrapErr, bail};
+use rpassword::prompt_password;
+
+use atuin_client::{
+    encryption::{Key, decode_key, encode_key, load_key, load_tag_key},
+    settings::Settings,
+};
+
+use crate::command::client::account::login::normalize_key;
+
+#[derive(Args, Debug)]
+pub struct Cmd {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Switch to base64 output of the key
+    #[arg(long)]
+    base64: bool,
+
+    /// Print the key for records with this tag, to share them without sharing the main key
+    #[arg(long)]
+    tag: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Check that a copy of the key you have backed up, as words or base64, matches the key on
+    /// this machine
+    Verify {
+        /// Check the key for records with this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+}
+
+fn load(settings: &Settings, tag: Option<&str>) -> Result<Key> {
+    tag.map_or_else(|| load_key(settings), |tag| load_tag_key(settings, tag))
+        .wrap_err("could not load encryption key")
+}
+
+impl Cmd {
+    pub fn run(self, settings: &Settings) -> Result<()> {
+        match self.command {
+            Some(Commands::Verify { tag }) => verify(settings, tag.as_deref()),
+
+            None => {
+                let key = load(settings, self.tag.as_deref())?;
+
+                if self.base64 {
+                    let encode = encode_key(&key).wrap_err("could not encode encryption key")?;
+                    println!("{encode}");
+                } else {
+                    let mnemonic = bip39::Mnemonic::from_entropy(&key, bip39::Language::English)
+                        .map_err(|_| eyre::eyre!("invalid key"))?;
+                    println!("{mnemonic}");
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn verify(settings: &Settings, tag: Option<&str>) -> Result<()> {
+    let key = load(settings, tag)?;
+
+    // Not echoed, so the key doesn't end up in a scrollback buffer
+    let backup = prompt_password("Enter your backed up key: ")?;
+    let backup = normalize_key(backup.trim().to_string())?;
+
+    if backup.is_empty() {
+        bail!("no key entered");
+    }
+
+    let backup = decode_key(backup).wrap_err("that is neither a key mnemonic, nor base64")?;
+
+    if backup != key {
+        bail!(
+            "that is NOT the key this machine uses. Run `atuin key` to see the right one, and back it up again"
+        );
+    }
+
+    println!("Your backup matches the key on this machine");
+
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use clap::Subcommand;
+use eyre::{Result, WrapErr, bail};
+use fs_err as fs;
+
+use atuin_client::{
+    api_client,
+    encryption::{self, decode_key, encode_key, generate_encoded_key},
+    record::{sqlite_store::SqliteStore, store::Store},
+    settings::Settings,
+    team,
+};
+
+#[derive(Subcommand, Debug)]
+#[command(infer_subcommands = true)]
+pub enum Cmd {
+    /// Create a team, with you as its only member. Prints the team's key, which you need to give
+    /// to everyone you add.
+    Create { name: String },
+
+    /// Start syncing a team you have been added to, with the key a member gave you
+    Join { name: String, key: String },
+
+    /// List the teams you are a member of
+    List,
+
+    /// List the members of a team
+    Members { name: String },
+
+    /// Add someone to a team. They also need the team's key, from `atuin team key`.
+    Add { name: String, username: String },
+
+    /// Remove someone from a team. They keep anything they have already synced.
+    Remove { name: String, username: String },
+
+    /// Print a team's key, to give to a new member
+    Key { name: String },
+
+    /// Leave a team, and remove everything it shared from this machine
+    Leave { name: String },
+}
+
+impl Cmd {
+    pub async fn run(self, settings: &Settings, store: &SqliteStore) -> Result<()> {
+        let client = api_client::Client::new(
+            &settings.sync_address,
+            settings.session_token()?.as_str(),
+            settings.network_connect_timeout,
+            settings.network_timeout,
+        )?;
+
+        match self {
+            Self::Create { name } => {
+                team::validate(&name)?;
+
+                client.create_team(&name).await?;
+
+                let (_, encoded) = generate_encoded_key()?;
+                encryption::save_team_key(settings, &name, &encoded)?;
+
+                println!("Created team {name}. Anyone you add will need its key:\n");
+                println!("atuin team join {name} '{encoded}'");
+            }
+
+            Self::Join { name, key } => {
+                team::validate(&name)?;
+                decode_key(key.clone()).wrap_err("that is not a valid team key")?;
+
+                let teams = client.teams().await?.teams;
+                if !teams.contains(&name) {
+                    bail!("you are not a member of {name}. Ask a member to add you first");
+                }
+
+                encryption::save_team_key(settings, &name, &key)?;
+
+                println!("Joined {name}. Run `atuin sync` to fetch what it shares");
+            }
+
+            Self::List => {
+                let joined = team::joined(settings)?;
+
+                for name in client.teams().await?.teams {
+                    if joined.contains(&name) {
+                        println!("{name}");
+                    } else {
+                        println!("{name} (not joined on this machine)");
+                    }
+                }
+            }
+
+            Self::Members { name } => {
+                for member in client.team_members(&name).await?.members {
+                    println!("{member}");
+                }
+            }
+
+            Self::Add { name, username } => {
+                client.add_team_member(&name, &username).await?;
+
+                println!(
+                    "Added {username} to {name}. Send them the team key, from `atuin team key {name}`"
+                );
+            }
+
+            Self::Remove { name, username } => {
+                client.remove_team_member(&name, &username).await?;
+
+                println!("Removed {username} from {name}");
+            }
+
+            Self::Key { name } => {
+                team::validate(&name)?;
+
+                let key = encryption::load_team_key(settings, &name)?;
+                println!("{}", encode_key(&key)?);
+            }
+
+            Self::Leave { name } => {
+                team::validate(&name)?;
+
+                let me = client.me().await?.username;
+                client.remove_team_member(&name, &me).await?;
+
+                let removed = forget(settings, store, &name).await?;
+
+                println!("Left {name}, and removed {removed} of its records from this machine");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Remove a team's key, records and databases from this machine
+async fn forget(settings: &Settings, store: &SqliteStore, name: &str) -> Result<u64> {
+    let mut removed = 0;
+
+    for (host, tags) in store.status().await?.hosts {
+        for tag in tags.keys() {
+            if team::team(tag) == Some(name) {
+                removed += store.truncate(host, tag, 0).await?;
+            }
+        }
+    }
+
+    // The databases hold everything the team shared, decrypted
+    for path in [
+        team::db_path(&settings.kv.db_path, name),
+        team::db_path(&settings.scripts.db_path, name),
+    ] {
+        if fs::metadata(&path).is_ok() {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    encryption::delete_team_key(settings, name)?;
+
+    Ok(removed)
+}
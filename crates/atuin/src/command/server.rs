@@ -2,6 +2,8 @@ use std::net::SocketAddr;
 
 use atuin_server_database::DbType;
 use atuin_server_postgres::Postgres;
+#[cfg(feature = "server-s3")]
+use atuin_server_s3::S3;
 use atuin_server_sqlite::Sqlite;
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
@@ -10,6 +12,8 @@ use eyre::{Context, Result, eyre};
 
 use atuin_server::{Settings, example_config, launch, launch_metrics_server};
 
+mod admin;
+
 #[derive(Parser, Debug)]
 #[clap(infer_subcommands = true)]
 pub enum Cmd {
@@ -26,6 +30,10 @@ pub enum Cmd {
 
     /// Print server example configuration
     DefaultConfig,
+
+    /// Manage users
+    #[command(subcommand)]
+    Admin(admin::Cmd),
 }
 
 impl Cmd {
@@ -55,15 +63,38 @@ impl Cmd {
                 match settings.db_settings.db_type() {
                     DbType::Postgres => launch::<Postgres>(settings, addr).await,
                     DbType::Sqlite => launch::<Sqlite>(settings, addr).await,
-                    DbType::Unknown => {
-                        Err(eyre!("db_uri must start with postgres:// or sqlite://"))
-                    }
+                    #[cfg(feature = "server-s3")]
+                    DbType::S3 => launch::<S3>(settings, addr).await,
+                    #[cfg(not(feature = "server-s3"))]
+                    DbType::S3 => Err(eyre!(
+                        "this build of atuin does not support s3 storage, rebuild with the server-s3 feature"
+                    )),
+                    DbType::Unknown => Err(eyre!(
+                        "db_uri must start with postgres://, sqlite:// or s3://"
+                    )),
                 }
             }
             Self::DefaultConfig => {
                 println!("{}", example_config());
                 Ok(())
             }
+            Self::Admin(admin) => {
+                let settings = Settings::new().wrap_err("could not load server settings")?;
+
+                match settings.db_settings.db_type() {
+                    DbType::Postgres => admin.run::<Postgres>(settings).await,
+                    DbType::Sqlite => admin.run::<Sqlite>(settings).await,
+                    #[cfg(feature = "server-s3")]
+                    DbType::S3 => admin.run::<S3>(settings).await,
+                    #[cfg(not(feature = "server-s3"))]
+                    DbType::S3 => Err(eyre!(
+                        "this build of atuin does not support s3 storage, rebuild with the server-s3 feature"
+                    )),
+                    DbType::Unknown => Err(eyre!(
+                        "db_uri must start with postgres://, sqlite:// or s3://"
+                    )),
+                }
+            }
         }
     }
 }
@@ -0,0 +1,310 @@
+use clap::Subcommand;
+use eyre::{Result, bail};
+use time::{Duration, OffsetDateTime, macros::format_description};
+
+use atuin_common::utils::crypto_random_string;
+use atuin_server::{
+    Settings,
+    settings::WebhookEvent,
+    webhooks::{self, WebhookUser},
+};
+use atuin_server_database::{
+    Database, DbError,
+    models::{NewInvite, User},
+};
+
+static DATE_FMT: &[time::format_description::FormatItem<'static>] =
+    format_description!("[year]-[month]-[day]");
+
+/// Manage the users of this server. These commands talk to the database directly, so run them
+/// with the same configuration as the server itself.
+#[derive(Subcommand, Debug)]
+#[command(infer_subcommands = true)]
+pub enum Cmd {
+    /// List every user, with how much they have stored
+    List,
+
+    /// Stop a user from logging in or syncing. Their data is kept.
+    Disable { username: String },
+
+    /// Allow a disabled user to log in and sync again
+    Enable { username: String },
+
+    /// Delete a user, and all of their synced data
+    Delete {
+        username: String,
+
+        /// Don't ask for confirmation
+        #[arg(long, short)]
+        force: bool,
+    },
+
+    /// Mark a user's email as verified, without sending them a token
+    Verify { username: String },
+
+    /// Log a user out of every machine
+    RevokeSessions { username: String },
+
+    /// Issue a token the user can set a new password with, via `atuin account reset-password`
+    ResetPassword {
+        username: String,
+
+        /// How many hours the token is valid for
+        #[arg(long, default_value_t = 24)]
+        valid_hours: i64,
+    },
+
+    /// Let an existing user log in with single sign-on, as the given subject at the configured
+    /// provider
+    LinkIdentity { username: String, subject: String },
+
+    /// Manage invite codes, which let people register while registration is closed
+    #[command(subcommand)]
+    Invite(InviteCmd),
+}
+
+#[derive(Subcommand, Debug)]
+#[command(infer_subcommands = true)]
+pub enum InviteCmd {
+    /// Create a new invite code
+    Create {
+        /// How many people can register with it
+        #[arg(long, default_value_t = 1)]
+        uses: i32,
+
+        /// How many days it is valid for. Never expires if not set.
+        #[arg(long)]
+        valid_days: Option<i64>,
+    },
+
+    /// List invite codes, and how much they have been used
+    List,
+
+    /// Revoke an invite code, so nobody else can use it
+    Revoke { code: String },
+}
+
+async fn get_user<DB: Database>(db: &DB, username: &str) -> Result<User> {
+    match db.get_user(username).await {
+        Ok(user) => Ok(user),
+        Err(DbError::NotFound) => bail!("user {username} not found"),
+        Err(DbError::Other(e)) => Err(e),
+    }
+}
+
+fn date(at: Option<OffsetDateTime>) -> String {
+    at.and_then(|at| at.format(DATE_FMT).ok())
+        .unwrap_or_else(|| String::from("-"))
+}
+
+async fn link_identity<DB: Database>(
+    db: &DB,
+    settings: &Settings,
+    username: &str,
+    subject: &str,
+) -> Result<()> {
+    if !settings.oidc.enable {
+        bail!("single sign-on is not enabled in the server config");
+    }
+
+    let issuer = settings.oidc.issuer.trim_end_matches('/');
+    let user = get_user(db, username).await?;
+
+    if let Ok(linked) = db.get_identity_user(issuer, subject).await {
+        bail!("{subject} is already linked to {}", linked.username);
+    }
+
+    db.add_user_identity(user.id, issuer, subject).await?;
+
+    println!("{username} can now log in as {subject} at {issuer}");
+
+    Ok(())
+}
+
+impl Cmd {
+    pub async fn run<DB: Database>(self, settings: Settings) -> Result<()> {
+        let db = DB::new(&settings.db_settings).await?;
+
+        match self {
+            Self::List => {
+                let users = db.list_users().await?;
+                let usage = db.list_record_usage().await?;
+
+                println!(
+                    "{:>6}  {:<24} {:<32} {:<10} {:<10} {:>10} {:>12}",
+                    "id", "username", "email", "verified", "disabled", "records", "bytes"
+                );
+
+                for user in &users {
+                    let usage = usage.get(&user.id).copied().unwrap_or_default();
+
+                    println!(
+                        "{:>6}  {:<24} {:<32} {:<10} {:<10} {:>10} {:>12}",
+                        user.id,
+                        user.username,
+                        user.email,
+                        date(user.verified),
+                        date(user.disabled),
+                        usage.records,
+                        usage.bytes
+                    );
+                }
+
+                println!("{} users", users.len());
+            }
+
+            Self::Disable { username } => {
+                let user = get_user(&db, &username).await?;
+                db.set_user_disabled(user.id, true).await?;
+
+                println!("Disabled {username}");
+            }
+
+            Self::Enable { username } => {
+                let user = get_user(&db, &username).await?;
+                db.set_user_disabled(user.id, false).await?;
+
+                println!("Enabled {username}");
+            }
+
+            Self::Delete { username, force } => {
+                let user = get_user(&db, &username).await?;
+
+                if !force {
+                    println!(
+                        "Are you sure you want to delete '{username}' and all of their data? [y/N]"
+                    );
+                    let mut input = String::new();
+                    std::io::stdin().read_line(&mut input)?;
+
+                    let input = input.trim().to_lowercase();
+                    if input != "y" && input != "yes" {
+                        println!("Deletion cancelled");
+                        return Ok(());
+                    }
+                }
+
+                db.delete_user(&user).await?;
+
+                webhooks::emit(
+                    &db,
+                    &settings.webhooks,
+                    WebhookEvent::UserDeleted,
+                    WebhookUser {
+                        id: user.id,
+                        username: &user.username,
+                    },
+                    Some("deleted by an admin"),
+                )
+                .await;
+
+                println!("Deleted {username}");
+            }
+
+            Self::Verify { username } => {
+                let user = get_user(&db, &username).await?;
+
+                if user.verified.is_some() {
+                    println!("{username} is already verified");
+                } else {
+                    db.verify_user(user.id).await?;
+                    println!("Verified {username}");
+                }
+            }
+
+            Self::RevokeSessions { username } => {
+                let user = get_user(&db, &username).await?;
+                let revoked = db.delete_user_sessions(user.id).await?;
+
+                println!("Revoked {revoked} sessions for {username}");
+            }
+
+            Self::ResetPassword {
+                username,
+                valid_hours,
+            } => {
+                if valid_hours <= 0 {
+                    bail!("--valid-hours must be positive");
+                }
+
+                let user = get_user(&db, &username).await?;
+                let token = db
+                    .password_reset_token(user.id, Duration::hours(valid_hours))
+                    .await?;
+
+                println!(
+                    "Reset token for {username}, valid for {valid_hours} hours. Send them this command:\n"
+                );
+                println!("atuin account reset-password --username '{username}' --token '{token}'");
+            }
+
+            Self::LinkIdentity { username, subject } => {
+                link_identity(&db, &settings, &username, &subject).await?;
+            }
+
+            Self::Invite(invite) => invite.run(&db).await?,
+        }
+
+        Ok(())
+    }
+}
+
+impl InviteCmd {
+    async fn run<DB: Database>(self, db: &DB) -> Result<()> {
+        match self {
+            Self::Create { uses, valid_days } => {
+                if uses <= 0 {
+                    bail!("--uses must be positive");
+                }
+
+                if valid_days.is_some_and(|days| days <= 0) {
+                    bail!("--valid-days must be positive");
+                }
+
+                let invite = NewInvite {
+                    code: crypto_random_string::<12>(),
+                    max_uses: uses,
+                    expires: valid_days
+                        .map(|days| OffsetDateTime::now_utc() + Duration::days(days)),
+                };
+
+                db.add_invite(&invite).await?;
+
+                println!(
+                    "Created invite {}, good for {uses} registrations",
+                    invite.code
+                );
+                println!("Register with: atuin register --invite '{}'", invite.code);
+            }
+
+            Self::List => {
+                let invites = db.list_invites().await?;
+
+                println!(
+                    "{:<20} {:>6} {:>6}  {:<10}",
+                    "code", "uses", "max", "expires"
+                );
+
+                for invite in &invites {
+                    println!(
+                        "{:<20} {:>6} {:>6}  {:<10}",
+                        invite.code,
+                        invite.uses,
+                        invite.max_uses,
+                        date(invite.expires)
+                    );
+                }
+
+                println!("{} invites", invites.len());
+            }
+
+            Self::Revoke { code } => match db.delete_invite(&code).await {
+                Ok(()) => println!("Revoked {code}"),
+                Err(DbError::NotFound) => bail!("invite {code} not found"),
+                Err(DbError::Other(e)) => return Err(e),
+            },
+        }
+
+        Ok(())
+    }
+}
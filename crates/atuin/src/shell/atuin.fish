@@ -73,3 +73,23 @@ function _atuin_bind_up
             up-or-search
     end
 end
+
+function _atuin_suggest
+    # fish has no way to show another suggestion than its own, so when it has none, fill in
+    # atuin's instead. Anywhere else, this is the Right Arrow as usual. Only bound on fish 4,
+    # as older versions can't say whether they are showing a suggestion.
+    set -l buffer (commandline -b | string collect)
+    if commandline --showing-suggestion 2>/dev/null
+        or commandline --paging-mode
+        or test -z "$buffer"
+        or test (commandline --cursor) -lt (string length -- "$buffer")
+        commandline -f forward-char
+        return
+    end
+
+    set -l suggestion (ATUIN_LOG=error atuin suggest --prefix="$buffer" 2>/dev/null | string collect)
+    if test -n "$suggestion"
+        commandline -r -- "$suggestion"
+        commandline -f end-of-line
+    end
+end
@@ -1,13 +1,20 @@
-use atuin_dotfiles::store::{AliasStore, var::VarStore};
-use atuin_scripts::store::ScriptStore;
+use atuin_dotfiles::store::{
+    AliasStore, CONFIG_SHELL_ALIAS_TAG,
+    var::{DOTFILES_VAR_TAG, VarStore},
+};
+use atuin_scripts::store::{ScriptStore, script::SCRIPT_TAG};
 use eyre::{Context, Result};
 
 use atuin_client::{
-    database::Database, history::store::HistoryStore, record::sqlite_store::SqliteStore,
+    database::Database,
+    encryption::Keys,
+    history::{HISTORY_TAG, store::HistoryStore},
+    record::sqlite_store::SqliteStore,
     settings::Settings,
+    team,
 };
-use atuin_common::record::RecordId;
-use atuin_kv::store::KvStore;
+use atuin_common::record::{HostId, RecordId};
+use atuin_kv::store::{KvStore, record::KV_TAG};
 
 // This is the only crate that ties together all other crates.
 // Therefore, it's the only crate where functions tying together all stores can live
@@ -21,9 +28,7 @@ pub async fn build(
     db: &dyn Database,
     downloaded: Option<&[RecordId]>,
 ) -> Result<()> {
-    let encryption_key: [u8; 32] = atuin_client::encryption::load_key(settings)
-        .context("could not load encryption key")?
-        .into();
+    let keys = Keys::load(settings).context("could not load encryption keys")?;
 
     let host_id = Settings::host_id().expect("failed to get host_id");
 
@@ -31,11 +36,11 @@ pub async fn build(
 
     let kv_db = atuin_kv::database::Database::new(settings.kv.db_path.clone(), 1.0).await?;
 
-    let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
-    let alias_store = AliasStore::new(store.clone(), host_id, encryption_key);
-    let var_store = VarStore::new(store.clone(), host_id, encryption_key);
-    let kv_store = KvStore::new(store.clone(), kv_db, host_id, encryption_key);
-    let script_store = ScriptStore::new(store.clone(), host_id, encryption_key);
+    let history_store = HistoryStore::new(store.clone(), host_id, *keys.get(HISTORY_TAG));
+    let alias_store = AliasStore::new(store.clone(), host_id, *keys.get(CONFIG_SHELL_ALIAS_TAG));
+    let var_store = VarStore::new(store.clone(), host_id, *keys.get(DOTFILES_VAR_TAG));
+    let kv_store = KvStore::new(store.clone(), kv_db, host_id, *keys.get(KV_TAG));
+    let script_store = ScriptStore::new(store.clone(), host_id, *keys.get(SCRIPT_TAG));
 
     history_store.incremental_build(db, downloaded).await?;
 
@@ -46,5 +51,42 @@ pub async fn build(
     let script_db =
         atuin_scripts::database::Database::new(settings.scripts.db_path.clone(), 1.0).await?;
     script_store.build(script_db).await?;
+
+    build_teams(settings, store, &keys, host_id).await?;
+
+    Ok(())
+}
+
+/// Rebuild the kv and script databases of every team we have joined
+async fn build_teams(
+    settings: &Settings,
+    store: &SqliteStore,
+    keys: &Keys,
+    host_id: HostId,
+) -> Result<()> {
+    for name in team::joined(settings)? {
+        let kv_db =
+            atuin_kv::database::Database::new(team::db_path(&settings.kv.db_path, &name), 1.0)
+                .await?;
+        let kv_key = *keys.get(&team::tag(KV_TAG, &name));
+
+        KvStore::new(store.clone(), kv_db, host_id, kv_key)
+            .with_team(&name)
+            .build()
+            .await?;
+
+        let script_db = atuin_scripts::database::Database::new(
+            team::db_path(&settings.scripts.db_path, &name),
+            1.0,
+        )
+        .await?;
+        let script_key = *keys.get(&team::tag(SCRIPT_TAG, &name));
+
+        ScriptStore::new(store.clone(), host_id, script_key)
+            .with_team(&name)
+            .build(script_db)
+            .await?;
+    }
+
     Ok(())
 }
@@ -1,5 +1,11 @@
-use atuin_common::{api::AddHistoryRequest, utils::uuid_v7};
-use time::OffsetDateTime;
+use atuin_common::{
+    api::AddHistoryRequest,
+    record::{Host, HostId, Record, test_records},
+    utils::uuid_v7,
+};
+use atuin_server_database::Database;
+use atuin_server_postgres::Postgres;
+use time::{Duration, OffsetDateTime};
 
 mod common;
 
@@ -43,3 +49,152 @@ async fn sync() {
     shutdown.send(()).unwrap();
     server.await.unwrap();
 }
+
+#[tokio::test]
+async fn record_quota() {
+    let path = format!("/{}", uuid_v7().as_simple());
+
+    let mut settings = common::server_settings(&path);
+    settings.quota.max_records = 3;
+
+    let (address, shutdown, server) = common::start_server_with(settings).await;
+
+    let client = common::register(&address).await;
+    let host = Host::new(HostId(uuid_v7()));
+    let records = test_records(&host, 4);
+
+    client.post_records(&records[..2]).await.unwrap();
+
+    let err = client.post_records(&records[2..]).await.unwrap_err();
+    assert!(
+        err.to_string().contains("record quota exceeded"),
+        "unexpected error: {err}"
+    );
+
+    // Nothing from the rejected batch was stored
+    let status = client.record_status().await.unwrap();
+    assert_eq!(status.get(host.id, "history".into()), Some(1));
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn record_retention() {
+    let path = format!("/{}", uuid_v7().as_simple());
+    let (address, shutdown, server) = common::start_server(&path).await;
+
+    let username = uuid_v7().as_simple().to_string();
+    let client = common::register_inner(&address, &username, "hunter2").await;
+    let host = Host::new(HostId(uuid_v7()));
+    let records = test_records(&host, 5);
+
+    client.post_records(&records).await.unwrap();
+
+    let db = Postgres::new(&common::server_settings(&path).db_settings)
+        .await
+        .unwrap();
+    let user = db.get_user(&username).await.unwrap();
+
+    // Everything has expired, but the tail of the store must stay put
+    db.delete_records_before(OffsetDateTime::now_utc() + Duration::minutes(1))
+        .await
+        .unwrap();
+
+    let status = client.record_status().await.unwrap();
+    assert_eq!(status.get(host.id, "history".into()), Some(4));
+
+    let remaining = db
+        .next_records(&user, host.id, "history".into(), None, 100)
+        .await
+        .unwrap();
+    assert_eq!(remaining, records[4..].to_vec());
+
+    // The usage counter follows deletes as well as inserts
+    let usage = db.record_usage(&user).await.unwrap();
+    assert_eq!(usage.records, 1);
+    assert_eq!(
+        usage.bytes,
+        (records[4].data.data.len() + records[4].data.content_encryption_key.len()) as u64
+    );
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn team_records() {
+    let path = format!("/{}", uuid_v7().as_simple());
+    let (address, shutdown, server) = common::start_server(&path).await;
+
+    let alice_name = uuid_v7().as_simple().to_string();
+    let bob_name = uuid_v7().as_simple().to_string();
+    let alice = common::register_inner(&address, &alice_name, "hunter2").await;
+    let bob = common::register_inner(&address, &bob_name, "hunter2").await;
+
+    // -- CREATE --
+
+    // team names are at most 24 characters
+    let team = uuid_v7().as_simple().to_string()[8..].to_string();
+    alice.create_team(&team).await.unwrap();
+
+    assert!(alice.create_team(&team).await.is_err());
+    assert!(alice.create_team("Not A Team").await.is_err());
+    assert_eq!(alice.teams().await.unwrap().teams, vec![team.clone()]);
+
+    // -- ONLY MEMBERS GET IN --
+
+    let alice_team = common::login(&address, alice_name.clone(), "hunter2".into())
+        .await
+        .with_team(&team);
+    let bob_team = common::login(&address, bob_name.clone(), "hunter2".into())
+        .await
+        .with_team(&team);
+
+    let host = Host::new(HostId(uuid_v7()));
+    let records = test_records(&host, 3)
+        .into_iter()
+        .map(|r| Record {
+            tag: format!("kv@{team}"),
+            ..r
+        })
+        .collect::<Vec<_>>();
+
+    alice_team.post_records(&records).await.unwrap();
+
+    assert!(bob_team.record_status().await.is_err());
+    assert!(bob_team.post_records(&records).await.is_err());
+    assert!(bob.team_members(&team).await.is_err());
+
+    // the team's records are kept apart from alice's own
+    assert!(alice.record_status().await.unwrap().hosts.is_empty());
+
+    // -- MEMBERSHIP --
+
+    alice.add_team_member(&team, &bob_name).await.unwrap();
+    assert!(alice.add_team_member(&team, "nobody").await.is_err());
+
+    let mut members = bob.team_members(&team).await.unwrap().members;
+    members.sort();
+    let mut expected = vec![alice_name.clone(), bob_name.clone()];
+    expected.sort();
+    assert_eq!(members, expected);
+
+    let status = bob_team.record_status().await.unwrap();
+    assert_eq!(status.get(host.id, format!("kv@{team}")), Some(2));
+
+    let fetched = bob_team
+        .next_records(host.id, format!("kv@{team}"), 0, 10)
+        .await
+        .unwrap();
+    assert_eq!(fetched, records);
+
+    // -- LEAVING --
+
+    bob.remove_team_member(&team, &bob_name).await.unwrap();
+    assert!(bob_team.record_status().await.is_err());
+    assert!(bob.teams().await.unwrap().teams.is_empty());
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}
@@ -1,4 +1,17 @@
-use atuin_common::utils::uuid_v7;
+use std::time::Duration;
+
+use atuin_client::api_client;
+use atuin_common::{
+    api::{LoginRequest, RegisterResponse, ResetPasswordRequest, TokenScope},
+    utils::uuid_v7,
+};
+use atuin_server::settings::{WebhookEndpoint, WebhookEvent};
+use atuin_server_database::{Database, models::NewInvite};
+use atuin_server_postgres::Postgres;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
 
 mod common;
 
@@ -68,6 +81,252 @@ async fn change_password() {
     server.await.unwrap();
 }
 
+#[tokio::test]
+async fn admin_actions() {
+    let path = format!("/{}", uuid_v7().as_simple());
+    let (address, shutdown, server) = common::start_server(&path).await;
+
+    let username = uuid_v7().as_simple().to_string();
+    let password = uuid_v7().as_simple().to_string();
+    let client = common::register_inner(&address, &username, &password).await;
+
+    let db = Postgres::new(&common::server_settings(&path).db_settings)
+        .await
+        .unwrap();
+    let user = db.get_user(&username).await.unwrap();
+
+    // -- DISABLE --
+
+    db.set_user_disabled(user.id, true).await.unwrap();
+
+    assert!(client.status().await.is_err());
+    assert!(
+        api_client::login(
+            &address,
+            atuin_common::api::LoginRequest {
+                username: username.clone(),
+                password: password.clone(),
+                device: None,
+            },
+        )
+        .await
+        .is_err()
+    );
+
+    db.set_user_disabled(user.id, false).await.unwrap();
+    assert_eq!(client.status().await.unwrap().username, username);
+
+    // -- REVOKE SESSIONS --
+
+    assert_eq!(db.delete_user_sessions(user.id).await.unwrap(), 1);
+    assert!(client.status().await.is_err());
+
+    // logging in again starts a new session
+    let client = common::login(&address, username.clone(), password.clone()).await;
+    assert_eq!(client.status().await.unwrap().username, username);
+
+    // -- RESET PASSWORD --
+
+    let token = db
+        .password_reset_token(user.id, time::Duration::hours(1))
+        .await
+        .unwrap();
+    let new_password = uuid_v7().as_simple().to_string();
+
+    let reset = |token: String| {
+        api_client::reset_password(
+            &address,
+            ResetPasswordRequest {
+                username: username.clone(),
+                token,
+                new_password: new_password.clone(),
+            },
+        )
+    };
+
+    assert!(reset(String::from("wrong")).await.is_err());
+    reset(token.clone()).await.unwrap();
+
+    // tokens only work once, and the reset logs the user out
+    assert!(reset(token).await.is_err());
+    assert!(client.status().await.is_err());
+
+    let client = common::login(&address, username.clone(), new_password.clone()).await;
+    assert_eq!(client.status().await.unwrap().username, username);
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn sessions() {
+    let path = format!("/{}", uuid_v7().as_simple());
+    let (address, shutdown, server) = common::start_server(&path).await;
+
+    let username = uuid_v7().as_simple().to_string();
+    let password = uuid_v7().as_simple().to_string();
+    let laptop = common::register_inner(&address, &username, &password).await;
+
+    let login = api_client::login(
+        &address,
+        LoginRequest {
+            username: username.clone(),
+            password: password.clone(),
+            device: Some(String::from("desktop")),
+        },
+    )
+    .await
+    .unwrap();
+    let desktop = api_client::Client::new(&address, &login.session, 5, 30).unwrap();
+
+    // -- LIST --
+
+    let sessions = desktop.sessions().await.unwrap().sessions;
+    assert_eq!(sessions.len(), 2);
+
+    let current = sessions.iter().find(|s| s.current).unwrap();
+    assert_eq!(current.name.as_deref(), Some("desktop"));
+    assert!(current.created_at.is_some());
+
+    // the desktop session was used to make this request
+    assert!(current.last_used_at.is_some());
+
+    let other = sessions.iter().find(|s| !s.current).unwrap();
+
+    // -- REVOKE --
+
+    desktop.revoke_session(other.id).await.unwrap();
+    assert!(laptop.status().await.is_err());
+    assert_eq!(desktop.status().await.unwrap().username, username);
+
+    // sessions can only be revoked once, and only by their owner
+    assert!(desktop.revoke_session(other.id).await.is_err());
+
+    let stranger = common::register(&address).await;
+    assert!(stranger.revoke_session(current.id).await.is_err());
+    assert_eq!(desktop.sessions().await.unwrap().sessions.len(), 1);
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn api_tokens() {
+    let path = format!("/{}", uuid_v7().as_simple());
+    let (address, shutdown, server) = common::start_server(&path).await;
+
+    let client = common::register(&address).await;
+    let username = client.me().await.unwrap().username;
+
+    // -- CREATE --
+
+    let read_only = client
+        .create_token("ci", vec![TokenScope::ReadStatus, TokenScope::ReadRecords])
+        .await
+        .unwrap();
+    let writer = client
+        .create_token("backup", vec![TokenScope::WriteRecords])
+        .await
+        .unwrap();
+
+    assert!(client.create_token("nothing", vec![]).await.is_err());
+
+    // only a hash of each token is kept
+    let db = Postgres::new(&common::server_settings(&path).db_settings)
+        .await
+        .unwrap();
+    assert!(db.get_api_token(&read_only.token).await.is_err());
+
+    // -- SCOPES ARE ENFORCED --
+
+    let ci = api_client::Client::new(&address, &read_only.token, 5, 30).unwrap();
+    assert_eq!(ci.me().await.unwrap().username, username);
+    ci.record_status().await.unwrap();
+    assert!(ci.post_records(&[]).await.is_err());
+
+    let backup = api_client::Client::new(&address, &writer.token, 5, 30).unwrap();
+    backup.post_records(&[]).await.unwrap();
+    assert!(backup.record_status().await.is_err());
+
+    // tokens can't manage the account, or mint more tokens
+    assert!(ci.status().await.is_err());
+    assert!(ci.sessions().await.is_err());
+    assert!(
+        ci.create_token("escalate", vec![TokenScope::WriteRecords])
+            .await
+            .is_err()
+    );
+
+    // -- LIST AND REVOKE --
+
+    let tokens = client.tokens().await.unwrap().tokens;
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0].name, "ci");
+    assert_eq!(
+        tokens[0].scopes,
+        vec![TokenScope::ReadRecords, TokenScope::ReadStatus]
+    );
+
+    client.revoke_token(read_only.id).await.unwrap();
+    assert!(ci.me().await.is_err());
+    assert!(client.revoke_token(read_only.id).await.is_err());
+    assert_eq!(client.tokens().await.unwrap().tokens.len(), 1);
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}
+
+async fn register_invited(address: &str, invite: Option<&str>) -> eyre::Result<RegisterResponse> {
+    let username = uuid_v7().as_simple().to_string();
+    let email = format!("{username}@example.com");
+
+    api_client::register(address, &username, &email, "password", invite).await
+}
+
+#[tokio::test]
+async fn invite_only_registration() {
+    let path = format!("/{}", uuid_v7().as_simple());
+
+    let mut settings = common::server_settings(&path);
+    settings.open_registration = false;
+
+    let db = Postgres::new(&settings.db_settings).await.unwrap();
+    let (address, shutdown, server) = common::start_server_with(settings).await;
+
+    let code = uuid_v7().as_simple().to_string();
+    db.add_invite(&NewInvite {
+        code: code.clone(),
+        max_uses: 2,
+        expires: None,
+    })
+    .await
+    .unwrap();
+
+    // registration is closed without an invite, or with a bad one
+    assert!(register_invited(&address, None).await.is_err());
+    assert!(register_invited(&address, Some("nope")).await.is_err());
+
+    // the invite is good for exactly two registrations
+    register_invited(&address, Some(&code)).await.unwrap();
+    register_invited(&address, Some(&code)).await.unwrap();
+    assert!(register_invited(&address, Some(&code)).await.is_err());
+
+    // expired invites don't work at all
+    let expired = uuid_v7().as_simple().to_string();
+    db.add_invite(&NewInvite {
+        code: expired.clone(),
+        max_uses: 1,
+        expires: Some(time::OffsetDateTime::now_utc() - time::Duration::minutes(1)),
+    })
+    .await
+    .unwrap();
+
+    assert!(register_invited(&address, Some(&expired)).await.is_err());
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}
+
 #[tokio::test]
 async fn multi_user_test() {
     let path = format!("/{}", uuid_v7().as_simple());
@@ -119,3 +378,76 @@ async fn multi_user_test() {
     shutdown.send(()).unwrap();
     server.await.unwrap();
 }
+
+// Accept a single HTTP request, reply 200, and return its headers and body
+async fn receive_one(listener: TcpListener) -> (String, String) {
+    let (mut socket, _) = listener.accept().await.unwrap();
+    let mut buf = Vec::new();
+
+    let (head, body_start) = loop {
+        let mut chunk = [0; 1024];
+        let n = socket.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break (String::from_utf8_lossy(&buf[..end]).to_lowercase(), end + 4);
+        }
+    };
+
+    let len = head
+        .lines()
+        .find_map(|l| l.strip_prefix("content-length:"))
+        .map(|l| l.trim().parse::<usize>().unwrap())
+        .unwrap_or(0);
+
+    while buf.len() < body_start + len {
+        let mut chunk = [0; 1024];
+        let n = socket.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    socket
+        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+        .await
+        .unwrap();
+
+    let body = String::from_utf8(buf[body_start..body_start + len].to_vec()).unwrap();
+
+    (head, body)
+}
+
+#[tokio::test]
+async fn registration_webhook() {
+    let receiver = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", receiver.local_addr().unwrap());
+    let received = tokio::spawn(receive_one(receiver));
+
+    let path = format!("/{}", uuid_v7().as_simple());
+    let mut settings = common::server_settings(&path);
+    settings.webhooks.poll_interval = 1;
+    settings.webhooks.endpoints = vec![WebhookEndpoint {
+        url,
+        secret: Some("hunter2".into()),
+        events: vec![WebhookEvent::UserRegistered],
+    }];
+
+    let (address, shutdown, server) = common::start_server_with(settings).await;
+
+    let username = uuid_v7().as_simple().to_string();
+    common::register_inner(&address, &username, "hunter2").await;
+
+    let (head, body) = tokio::time::timeout(Duration::from_secs(10), received)
+        .await
+        .expect("webhook was not delivered")
+        .unwrap();
+
+    assert!(head.contains("x-atuin-event: user_registered"), "{head}");
+    assert!(head.contains("x-atuin-signature-256: sha256="), "{head}");
+
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(payload["event"], "user_registered");
+    assert_eq!(payload["user"]["username"], username.as_str());
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}